@@ -0,0 +1,52 @@
+use algorithm::buf::{Binary, BinaryMut};
+use std::time::Instant;
+use wmhttp::Body;
+
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+const CHUNK_SIZE: usize = 16 * 1024;
+const CHUNK_NUM: usize = 256;
+
+async fn build_body() -> Body {
+    let (sender, body) = Body::channel(CHUNK_NUM);
+    for i in 0..CHUNK_NUM {
+        let _ = sender
+            .send((i + 1 == CHUNK_NUM, Binary::from(vec![1u8; CHUNK_SIZE])))
+            .await;
+    }
+    body
+}
+
+fn print_stats(name: &str, start: Instant, before: dhat::HeapStats) {
+    let after = dhat::HeapStats::get();
+    println!(
+        "{}: 耗时 {:?}, 分配次数 {}, 分配字节 {}",
+        name,
+        start.elapsed(),
+        after.total_blocks - before.total_blocks,
+        after.total_bytes - before.total_bytes
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let _profiler = dhat::Profiler::builder().testing().build();
+
+    // 读取到自动扩容的BinaryMut中
+    let mut body = build_body().await;
+    let before = dhat::HeapStats::get();
+    let start = Instant::now();
+    let mut buffer = BinaryMut::new();
+    body.read_all(&mut buffer).await;
+    print_stats("read_all", start, before);
+
+    // 读取到预先分配好的缓冲区中
+    let mut body = build_body().await;
+    let mut buf = vec![0u8; CHUNK_SIZE * CHUNK_NUM];
+    let before = dhat::HeapStats::get();
+    let start = Instant::now();
+    let size = body.read_into(&mut buf).await.unwrap();
+    print_stats("read_into", start, before);
+    assert_eq!(size, buf.len());
+}
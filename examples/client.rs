@@ -105,7 +105,7 @@ async fn test_https2() -> ProtResult<()> {
         .await
         .unwrap();
 
-    let mut recv = client.send(req.into_type()).await.unwrap();
+    let (mut recv, _) = client.send(req.into_type()).await.unwrap();
     while let Some(res) = recv.recv().await {
         let mut res = res?;
         res.body_mut().wait_all().await;
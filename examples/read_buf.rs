@@ -0,0 +1,115 @@
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use algorithm::buf::BinaryMut;
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf};
+use webparse::Response;
+use wmhttp::{Body, HttpTrait, ProtResult, RecvRequest, RecvResponse, Server};
+
+const BODY_SIZE: usize = 8 * 1024 * 1024;
+
+/// 统计读取次数, 每次读取对应一次系统调用
+struct CountIo {
+    io: DuplexStream,
+    reads: Arc<AtomicUsize>,
+}
+
+impl AsyncRead for CountIo {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let ret = Pin::new(&mut self.io).poll_read(cx, buf);
+        if ret.is_ready() {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+        }
+        ret
+    }
+}
+
+impl AsyncWrite for CountIo {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+struct Operate;
+
+#[async_trait]
+impl HttpTrait for Operate {
+    async fn operate(&mut self, mut req: RecvRequest) -> ProtResult<RecvResponse> {
+        let mut buffer = BinaryMut::new();
+        req.body_mut().read_all(&mut buffer).await;
+        let response = Response::builder()
+            .header("Connection", "close")
+            .body(Body::empty())
+            .unwrap();
+        Ok(response)
+    }
+}
+
+async fn run(read_buf_size: Option<usize>) -> usize {
+    let (mut client, server) = tokio::io::duplex(1024 * 1024);
+    let reads = Arc::new(AtomicUsize::new(0));
+    let io = CountIo {
+        io: server,
+        reads: reads.clone(),
+    };
+    let handle = tokio::spawn(async move {
+        let mut server = Server::new(io, None);
+        if let Some(size) = read_buf_size {
+            server.set_read_buf_size(size);
+        }
+        server.set_callback_http(Box::new(Operate));
+        let _ = server.incoming().await;
+    });
+
+    let head = format!(
+        "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+        BODY_SIZE
+    );
+    client.write_all(head.as_bytes()).await.unwrap();
+    client.write_all(&vec![1u8; BODY_SIZE]).await.unwrap();
+    let mut response = vec![];
+    let _ = client.read_to_end(&mut response).await;
+    let _ = handle.await;
+    reads.load(Ordering::Relaxed)
+}
+
+#[tokio::main]
+async fn main() {
+    for size in [None, Some(1024), Some(16 * 1024), Some(64 * 1024)] {
+        let start = Instant::now();
+        let reads = run(size).await;
+        println!(
+            "read_buf_size: {:?}, 读取次数 {}, 耗时 {:?}",
+            size.unwrap_or(16 * 1024),
+            reads,
+            start.elapsed()
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::{path::PathBuf, time::Instant};
+
+use async_trait::async_trait;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use webparse::Response;
+use wmhttp::{Body, HttpTrait, ProtResult, RecvRequest, RecvResponse, Server};
+
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+const FILE_SIZE: usize = 256 * 1024 * 1024;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+struct Operate {
+    path: PathBuf,
+}
+
+#[async_trait]
+impl HttpTrait for Operate {
+    async fn operate(&mut self, _req: RecvRequest) -> ProtResult<RecvResponse> {
+        let file = tokio::fs::File::open(&self.path).await?;
+        let response = Response::builder()
+            .header("Content-Length", FILE_SIZE.to_string())
+            .header("Connection", "close")
+            .body(Body::new_file(file, FILE_SIZE as u64))
+            .unwrap();
+        Ok(response)
+    }
+}
+
+async fn run(path: PathBuf, send_file: bool) -> usize {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        let (stream, addr) = listener.accept().await.unwrap();
+        let mut server = Server::new(stream, Some(addr));
+        server.set_send_file(send_file);
+        server.set_callback_http(Box::new(Operate { path }));
+        let _ = server.incoming().await;
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    // 逐块读取丢弃, 只统计传输的开销
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut total = 0;
+    loop {
+        match client.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => total += n,
+        }
+    }
+    let _ = handle.await;
+    total
+}
+
+/// 峰值内存无法重置, 每次运行只测试一种方式:
+/// cargo run --release --example send_file -- [buffer|sendfile]
+#[tokio::main]
+async fn main() {
+    let path = std::env::temp_dir().join("wmhttp_send_file_bench");
+    std::fs::write(&path, vec![1u8; FILE_SIZE]).unwrap();
+    let send_file = std::env::args().nth(1).as_deref() == Some("sendfile");
+
+    let _profiler = dhat::Profiler::builder().testing().build();
+    let start = Instant::now();
+    let total = run(path.clone(), send_file).await;
+    let elapsed = start.elapsed();
+    let stats = dhat::HeapStats::get();
+    println!(
+        "{}: 接收 {}字节, 耗时 {:?}, 吞吐 {:.1}MB/s, 分配次数 {}, 分配字节 {}, 峰值内存 {}",
+        if send_file { "sendfile" } else { "buffer" },
+        total,
+        elapsed,
+        FILE_SIZE as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64(),
+        stats.total_blocks,
+        stats.total_bytes,
+        stats.max_bytes
+    );
+    let _ = std::fs::remove_file(&path);
+}
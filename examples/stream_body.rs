@@ -0,0 +1,73 @@
+use std::time::Instant;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use webparse::Response;
+use wmhttp::{Body, HttpTrait, ProtResult, RecvRequest, RecvResponse, Server};
+
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+const BODY_SIZE: usize = 64 * 1024 * 1024;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+struct Operate;
+
+#[async_trait]
+impl HttpTrait for Operate {
+    async fn operate(&mut self, mut req: RecvRequest) -> ProtResult<RecvResponse> {
+        // 逐块读取丢弃, 只统计传输的开销
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        while req.body_mut().read_into(&mut buf).await? > 0 {}
+        let response = Response::builder()
+            .header("Connection", "close")
+            .body(Body::empty())
+            .unwrap();
+        Ok(response)
+    }
+}
+
+async fn run(stream_body: bool) {
+    let (mut client, server) = tokio::io::duplex(1024 * 1024);
+    let handle = tokio::spawn(async move {
+        let mut server = Server::new(server, None);
+        server.set_stream_body(stream_body);
+        server.set_callback_http(Box::new(Operate));
+        let _ = server.incoming().await;
+    });
+
+    let head = format!(
+        "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+        BODY_SIZE
+    );
+    client.write_all(head.as_bytes()).await.unwrap();
+    let chunk = vec![1u8; CHUNK_SIZE];
+    for _ in 0..BODY_SIZE / CHUNK_SIZE {
+        client.write_all(&chunk).await.unwrap();
+    }
+    let mut response = vec![];
+    let _ = client.read_to_end(&mut response).await;
+    let _ = handle.await;
+}
+
+/// 峰值内存无法重置, 每次运行只测试一种方式:
+/// cargo run --release --example stream_body -- [channel|stream]
+#[tokio::main]
+async fn main() {
+    let _profiler = dhat::Profiler::builder().testing().build();
+    let stream_body = std::env::args().nth(1).as_deref() == Some("stream");
+
+    let start = Instant::now();
+    run(stream_body).await;
+    let elapsed = start.elapsed();
+    let stats = dhat::HeapStats::get();
+    println!(
+        "{}: 耗时 {:?}, 吞吐 {:.1}MB/s, 分配次数 {}, 分配字节 {}, 峰值内存 {}",
+        if stream_body { "stream" } else { "channel" },
+        elapsed,
+        BODY_SIZE as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64(),
+        stats.total_blocks,
+        stats.total_bytes,
+        stats.max_bytes
+    );
+}
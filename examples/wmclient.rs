@@ -14,7 +14,7 @@ async fn test_http2() -> ProtResult<()> {
         http2_only(true)
         .connect().await.unwrap();
 
-    let (mut recv, _sender) = client.send2(req.into_type()).await?;
+    let (mut recv, _sender, _) = client.send2(req.into_type()).await?;
     let mut res = recv.recv().await.unwrap()?;
     res.body_mut().wait_all().await;
     println!("res = {}", res);
@@ -49,7 +49,7 @@ async fn test_https2() -> ProtResult<()> {
         .await
         .unwrap();
 
-    let mut recv = client.send(req.into_type()).await.unwrap();
+    let (mut recv, _) = client.send(req.into_type()).await.unwrap();
     while let Some(res) = recv.recv().await {
         let mut res = res?;
         res.body_mut().wait_all().await;
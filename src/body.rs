@@ -10,50 +10,259 @@
 // -----
 // Created Date: 2023/09/14 09:42:25
 
+use base64::{engine::general_purpose::STANDARD, Engine};
 use brotli::{CompressorWriter, Decompressor};
+use md5::Md5;
+use sha1::{digest::DynDigest, Digest, Sha1};
+use sha2::Sha256;
 use flate2::{
     write::{DeflateEncoder, GzEncoder},
     Compression, read::{GzDecoder, DeflateDecoder},
 };
 use tokio_util::sync::PollSemaphore;
+use zstd::stream::write::{Decoder as ZstdDecoder, Encoder as ZstdEncoder};
 
-use std::{fmt::Debug, io::{self, Error}, sync::Arc};
+use std::{fmt::Debug, io::{self, Error}, sync::{Arc, Mutex}};
 use std::{
+    collections::VecDeque,
     fmt::Display,
     io::{Read, Write},
     pin::Pin,
-    task::{ready, Context, Poll},
+    task::{ready, Context, Poll, Waker},
 };
+use tokio_stream::Stream;
 use tokio::{
     fs::File,
     io::{AsyncRead, AsyncReadExt, ReadBuf, AsyncSeekExt},
-    sync::{mpsc::Receiver, OwnedSemaphorePermit, Semaphore},
+    sync::{mpsc::{channel, Receiver, Sender}, OwnedSemaphorePermit, Semaphore},
 };
 use algorithm::buf::{Binary, BinaryMut, Bt, BtMut};
-use webparse::{Helper, Serialize, WebResult};
+use webparse::{HeaderMap, Helper, Serialize, WebResult};
 
-use crate::{Consts, ProtResult};
+use crate::{error::CompressError, Consts, ProtError, ProtResult, SseSender};
 
 use super::layer::RateLimitLayer;
 
 
-fn read_all_data<R: Read>(read_buf: &mut BinaryMut, read: &mut Box<R>) -> io::Result<usize> {
+/// lines按行读取时单行的默认最大长度
+const DEFAULT_MAX_LINE_SIZE: usize = 1024 * 1024;
+
+/// 向通道类型的Body写入数据, 数据为(是否结束, 数据)
+pub type BodySender = Sender<(bool, Binary)>;
+
+/// chunked包体结束后的trailer头, 由读取连接写入, 包体读取完毕后可获取
+pub(crate) type TrailerSlot = Arc<Mutex<Option<HeaderMap>>>;
+
+struct PipeInner {
+    buf: BinaryMut,
+    is_end: bool,
+    /// 结束标识已被读取端取走
+    is_finish: bool,
+    /// 读取端已释放
+    is_recv_closed: bool,
+    /// 写入端已释放
+    is_send_closed: bool,
+    recv_waker: Option<Waker>,
+    send_waker: Option<Waker>,
+}
+
+/// 创建流式包体的管道, 连接直接将包体数据写入共享的缓冲区, 读取端一次取走全部数据,
+/// 不经过固定容量的通道, 也不需为每个数据块分配内存
+pub(crate) fn body_pipe() -> (PipeSender, PipeReceiver) {
+    let inner = Arc::new(Mutex::new(PipeInner {
+        buf: BinaryMut::new(),
+        is_end: false,
+        is_finish: false,
+        is_recv_closed: false,
+        is_send_closed: false,
+        recv_waker: None,
+        send_waker: None,
+    }));
+    (
+        PipeSender {
+            inner: inner.clone(),
+        },
+        PipeReceiver { inner },
+    )
+}
+
+/// 管道的写入端, 由读取连接持有
+pub(crate) struct PipeSender {
+    inner: Arc<Mutex<PipeInner>>,
+}
+
+impl PipeSender {
+    /// 缓存的数据未超出limit时调用read写入数据, 否则等待读取端取走数据
+    pub fn poll_write<F>(
+        &self,
+        cx: &mut Context<'_>,
+        limit: usize,
+        read: F,
+    ) -> Poll<ProtResult<usize>>
+    where
+        F: FnOnce(&mut BinaryMut) -> ProtResult<usize>,
+    {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.is_recv_closed {
+            return Poll::Ready(Err(ProtError::channel_closed("http1 body")));
+        }
+        if inner.buf.remaining() >= limit {
+            inner.send_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let size = read(&mut inner.buf)?;
+        if size > 0 {
+            if let Some(waker) = inner.recv_waker.take() {
+                waker.wake();
+            }
+        }
+        Poll::Ready(Ok(size))
+    }
+
+    /// 包体已全部写入
+    pub fn set_end(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.is_end = true;
+        if let Some(waker) = inner.recv_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for PipeSender {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.is_send_closed = true;
+        if let Some(waker) = inner.recv_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// 管道的读取端, 由Body持有
+pub(crate) struct PipeReceiver {
+    inner: Arc<Mutex<PipeInner>>,
+}
+
+impl PipeReceiver {
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<(bool, Binary)>> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.is_finish {
+            return Poll::Ready(None);
+        }
+        if inner.buf.has_remaining() || inner.is_end {
+            let data = std::mem::replace(&mut inner.buf, BinaryMut::new()).freeze();
+            inner.is_finish = inner.is_end;
+            if let Some(waker) = inner.send_waker.take() {
+                waker.wake();
+            }
+            return Poll::Ready(Some((inner.is_end, data)));
+        }
+        // 写入端释放时包体未结束, 同通道关闭的处理
+        if inner.is_send_closed {
+            return Poll::Ready(None);
+        }
+        inner.recv_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for PipeReceiver {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.is_recv_closed = true;
+        if let Some(waker) = inner.send_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// 将src的数据转发到dst中, 通道容量有限, 下游写入慢时会暂停读取上游,
+/// 下游关闭时停止读取并返回错误, 成功返回转发的字节数
+pub async fn proxy_body(mut src: Body, dst: BodySender) -> ProtResult<u64> {
+    use tokio_stream::StreamExt;
+    let mut size = 0u64;
+    while let Some(bin) = src.next().await {
+        let bin = bin?;
+        size += bin.remaining() as u64;
+        if dst.send((false, bin)).await.is_err() {
+            log::trace!("转发包体时下游已关闭, 停止读取上游");
+            return Err(ProtError::channel_closed("proxy body"));
+        }
+    }
+    dst.send((true, Binary::new()))
+        .await
+        .map_err(|_| ProtError::channel_closed("proxy body"))?;
+    Ok(size)
+}
+
+/// 向dst写入一行数据并追加`\n`, 用于NDJSON等按行的流式数据, 如写入序列化后的JSON
+pub async fn send_line(dst: &BodySender, line: &[u8]) -> ProtResult<()> {
+    let mut data = Vec::with_capacity(line.len() + 1);
+    data.extend_from_slice(line);
+    data.push(b'\n');
+    dst.send((false, Binary::from(data)))
+        .await
+        .map_err(|_| ProtError::channel_closed("send line"))
+}
+
+/// 读取全部解压后的数据, 超出limit时立即停止, 防止少量的压缩数据解压出大量数据
+fn read_all_data<R: Read>(
+    read_buf: &mut BinaryMut,
+    read: &mut Box<R>,
+    limit: usize,
+) -> io::Result<usize> {
     let mut cache_buf = vec![0u8; 4096];
     let mut size = 0;
     loop {
         let s = read.read(&mut cache_buf)?;
         size += s;
         read_buf.put_slice(&cache_buf[..s]);
-        if s < cache_buf.len() {
+        if s < cache_buf.len() || size > limit {
             return Ok(size)
         }
     }
 }
 
-#[derive(Debug)]
+/// 宽松模式下数据已结束时的截断错误视为正常结束, 保留已解压的数据
+fn lenient_decompress(
+    result: io::Result<usize>,
+    method: &'static str,
+    lenient: bool,
+) -> io::Result<usize> {
+    match result {
+        Err(e) if lenient && e.kind() == io::ErrorKind::UnexpectedEof => {
+            log::warn!("{}数据不完整, 宽松模式下返回已解压的数据: {}", method, e);
+            Ok(0)
+        }
+        Err(e) => Err(decompress_error(method, e)),
+        Ok(s) => Ok(s),
+    }
+}
+
+/// 附带压缩方式的压缩错误, 转为ProtError后为ProtError::Compress
+fn compress_error(method: &'static str, e: io::Error) -> io::Error {
+    CompressError::new_io(method, false, e)
+}
+
+/// 附带压缩方式的解压错误, 转为ProtError后为ProtError::Decompress
+fn decompress_error(method: &'static str, e: io::Error) -> io::Error {
+    CompressError::new_io(method, true, e)
+}
+
 struct InnerReceiver {
     receiver: Option<Receiver<(bool, Binary)>>,
+    /// 直接读取连接缓冲区的管道
+    pipe: Option<PipeReceiver>,
     file: Option<Box<File>>,
+    /// 任意的数据源, 如解密的读取器或socket
+    reader: Option<Box<dyn AsyncRead + Send + Unpin>>,
+    /// 外部的数据流
+    stream: Option<Pin<Box<dyn Stream<Item = io::Result<Binary>> + Send>>>,
+    /// 预先准备好的数据块, 每次读取一块
+    chunks: Option<VecDeque<Binary>>,
+    /// 读取数据源时出现的错误
+    error: Option<io::Error>,
     cache_buf: Vec<u8>,
     /// 数据包大小
     data_size: u64,
@@ -63,6 +272,21 @@ struct InnerReceiver {
     end_pos: Option<u64>,
 }
 
+impl Debug for InnerReceiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InnerReceiver")
+            .field("receiver", &self.receiver)
+            .field("pipe", &self.pipe.is_some())
+            .field("file", &self.file)
+            .field("reader", &self.reader.is_some())
+            .field("stream", &self.stream.is_some())
+            .field("chunks", &self.chunks.as_ref().map(|c| c.len()))
+            .field("error", &self.error)
+            .field("data_size", &self.data_size)
+            .finish()
+    }
+}
+
 impl Drop for InnerReceiver {
     fn drop(&mut self) {
         if self.receiver.is_some() {
@@ -75,7 +299,12 @@ impl InnerReceiver {
     pub fn new() -> Self {
         Self {
             receiver: None,
+            pipe: None,
             file: None,
+            reader: None,
+            stream: None,
+            chunks: None,
+            error: None,
             cache_buf: vec![],
             data_size: u64::MAX,
             start_pos: None,
@@ -87,7 +316,12 @@ impl InnerReceiver {
         let vec = vec![0u8; 4096];
         Self {
             receiver: Some(receiver),
+            pipe: None,
             file: None,
+            reader: None,
+            stream: None,
+            chunks: None,
+            error: None,
             cache_buf: vec,
             data_size: u64::MAX,
             start_pos: None,
@@ -95,11 +329,32 @@ impl InnerReceiver {
         }
     }
     
+    pub fn new_pipe(pipe: PipeReceiver) -> Self {
+        Self {
+            receiver: None,
+            pipe: Some(pipe),
+            file: None,
+            reader: None,
+            stream: None,
+            chunks: None,
+            error: None,
+            cache_buf: vec![],
+            data_size: u64::MAX,
+            start_pos: None,
+            end_pos: None
+        }
+    }
+
     pub fn new_file(file: File, data_size: u64) -> Self {
         let vec = vec![0u8; 4096];
         Self {
             receiver: None,
+            pipe: None,
             file: Some(Box::new(file)),
+            reader: None,
+            stream: None,
+            chunks: None,
+            error: None,
             cache_buf: vec,
             data_size,
             start_pos: None,
@@ -107,6 +362,55 @@ impl InnerReceiver {
         }
     }
 
+    pub fn new_reader(reader: Box<dyn AsyncRead + Send + Unpin>, data_size: u64) -> Self {
+        let vec = vec![0u8; 4096];
+        Self {
+            receiver: None,
+            pipe: None,
+            file: None,
+            reader: Some(reader),
+            stream: None,
+            chunks: None,
+            error: None,
+            cache_buf: vec,
+            data_size,
+            start_pos: None,
+            end_pos: None
+        }
+    }
+
+    pub fn new_stream(stream: Pin<Box<dyn Stream<Item = io::Result<Binary>> + Send>>) -> Self {
+        Self {
+            receiver: None,
+            pipe: None,
+            file: None,
+            reader: None,
+            stream: Some(stream),
+            chunks: None,
+            error: None,
+            cache_buf: vec![],
+            data_size: u64::MAX,
+            start_pos: None,
+            end_pos: None
+        }
+    }
+
+    pub fn new_chunks(chunks: VecDeque<Binary>) -> Self {
+        Self {
+            receiver: None,
+            pipe: None,
+            file: None,
+            reader: None,
+            stream: None,
+            chunks: Some(chunks),
+            error: None,
+            cache_buf: vec![],
+            data_size: u64::MAX,
+            start_pos: None,
+            end_pos: None
+        }
+    }
+
     pub async fn set_start_end(&mut self, start_pos: u64, end_pos: u64) -> ProtResult<()> {
         assert!(end_pos >= start_pos, "结束位置必须大于起始位置");
         self.start_pos = Some(start_pos);
@@ -119,7 +423,20 @@ impl InnerReceiver {
     }
 
     pub fn is_none(&self) -> bool {
-        self.receiver.is_none() && self.file.is_none()
+        self.receiver.is_none()
+            && self.pipe.is_none()
+            && self.file.is_none()
+            && self.reader.is_none()
+            && self.stream.is_none()
+            && self.chunks.is_none()
+    }
+
+    /// 数据源出错时返回该错误
+    fn check_error(&self) -> io::Result<()> {
+        match &self.error {
+            Some(e) => Err(Error::new(e.kind(), e.to_string())),
+            None => Ok(()),
+        }
     }
 
     pub async fn recv(&mut self) -> Option<(bool, Binary)> {
@@ -142,6 +459,14 @@ impl InnerReceiver {
                 Err(_) => return None,
             };
         }
+
+        if self.pipe.is_some()
+            || self.reader.is_some()
+            || self.stream.is_some()
+            || self.chunks.is_some()
+        {
+            return std::future::poll_fn(|cx| self.poll_recv(cx)).await;
+        }
         None
     }
 
@@ -150,6 +475,10 @@ impl InnerReceiver {
             return receiver.poll_recv(cx);
         }
 
+        if let Some(pipe) = &mut self.pipe {
+            return pipe.poll_recv(cx);
+        }
+
         if let Some(file) = &mut self.file {
             let size = {
                 let mut buf = ReadBuf::new(&mut self.cache_buf);
@@ -176,6 +505,62 @@ impl InnerReceiver {
             )));
         }
 
+        if let Some(reader) = &mut self.reader {
+            let size = {
+                let mut buf = ReadBuf::new(&mut self.cache_buf);
+                match Pin::new(reader.as_mut()).poll_read(cx, &mut buf) {
+                    Poll::Pending => {
+                        return Poll::Pending;
+                    }
+                    Poll::Ready(Ok(_)) => buf.filled().len(),
+                    Poll::Ready(Err(e)) => {
+                        log::trace!("读取数据源时出错:{:?}", e);
+                        return Poll::Ready(None);
+                    }
+                }
+            };
+            // 读取器的数据可能分多次到达, 读到0字节或达到指定大小才结束
+            let read = std::cmp::min(self.data_size, size as u64) as usize;
+            if self.data_size != u64::MAX {
+                self.data_size -= read as u64;
+            }
+            let is_end = size == 0 || self.data_size == 0;
+            return Poll::Ready(Some((
+                is_end,
+                Binary::from(self.cache_buf[..read].to_vec()),
+            )));
+        }
+
+        if let Some(chunks) = &mut self.chunks {
+            let bin = match chunks.pop_front() {
+                Some(bin) => bin,
+                None => return Poll::Ready(None),
+            };
+            let is_end = chunks.is_empty();
+            // 剩余的数据块在下次读取, 需主动唤醒
+            if !is_end {
+                cx.waker().wake_by_ref();
+            }
+            return Poll::Ready(Some((is_end, bin)));
+        }
+
+        if let Some(stream) = &mut self.stream {
+            return match stream.as_mut().poll_next(cx) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(Some(Ok(bin))) => Poll::Ready(Some((false, bin))),
+                Poll::Ready(Some(Err(e))) => {
+                    log::trace!("读取数据流时出错:{:?}", e);
+                    self.error = Some(e);
+                    self.stream = None;
+                    Poll::Ready(None)
+                }
+                Poll::Ready(None) => {
+                    self.stream = None;
+                    Poll::Ready(Some((true, Binary::new())))
+                }
+            };
+        }
+
         return Poll::Ready(None);
     }
 }
@@ -184,6 +569,11 @@ struct InnerCompress {
     write_gz: Option<Box<GzEncoder<BinaryMut>>>,
     write_br: Option<Box<CompressorWriter<BinaryMut>>>,
     write_de: Option<Box<DeflateEncoder<BinaryMut>>>,
+    write_zstd: Option<Box<ZstdEncoder<'static, BinaryMut>>>,
+    /// 压缩等级, 按各压缩方式的范围截取, 未设置则使用默认值
+    level: Option<u32>,
+    /// brotli的质量及窗口大小, 优先于压缩等级
+    brotli: Option<(u32, u32)>,
 }
 
 impl Debug for InnerCompress {
@@ -191,6 +581,7 @@ impl Debug for InnerCompress {
         f.debug_struct("InnerCompress")
             .field("write_gz", &self.write_gz)
             .field("write_de", &self.write_de)
+            .field("write_zstd", &self.write_zstd.is_some())
             .finish()
     }
 }
@@ -201,12 +592,36 @@ impl InnerCompress {
             write_gz: None,
             write_br: None,
             write_de: None,
+            write_zstd: None,
+            level: None,
+            brotli: None,
+        }
+    }
+
+    /// gzip及deflate的压缩等级, 范围0-9
+    fn flate_level(&self) -> Compression {
+        match self.level {
+            Some(level) => Compression::new(level.min(9)),
+            None => Compression::default(),
+        }
+    }
+
+    /// brotli的质量(0-11)及窗口大小(10-24)
+    fn brotli_params(&self) -> (u32, u32) {
+        match self.brotli {
+            Some(params) => params,
+            None => (self.level.map(|l| l.min(11)).unwrap_or(11), 22),
         }
     }
 
+    /// zstd的压缩等级, 范围1-22, 0为默认等级
+    fn zstd_level(&self) -> i32 {
+        self.level.map(|l| l.min(22) as i32).unwrap_or(0)
+    }
+
     pub fn open_write_gz(&mut self) {
         if self.write_gz.is_none() {
-            self.write_gz = Some(Box::new(GzEncoder::new(BinaryMut::new(), Compression::default())) );
+            self.write_gz = Some(Box::new(GzEncoder::new(BinaryMut::new(), self.flate_level())));
         }
     }
 
@@ -214,16 +629,29 @@ impl InnerCompress {
         if self.write_de.is_none() {
             self.write_de = Some(Box::new(DeflateEncoder::new(
                 BinaryMut::new(),
-                Compression::default(),
+                self.flate_level(),
             )));
         }
     }
 
     pub fn open_write_br(&mut self) {
         if self.write_br.is_none() {
-            self.write_br = Some(Box::new(CompressorWriter::new(BinaryMut::new(), 4096, 11, 22)));
+            let (quality, lgwin) = self.brotli_params();
+            self.write_br = Some(Box::new(CompressorWriter::new(
+                BinaryMut::new(),
+                4096,
+                quality,
+                lgwin,
+            )));
         }
     }
+
+    pub fn open_write_zstd(&mut self) -> io::Result<()> {
+        if self.write_zstd.is_none() {
+            self.write_zstd = Some(Box::new(ZstdEncoder::new(BinaryMut::new(), self.zstd_level())?));
+        }
+        Ok(())
+    }
 }
 
 
@@ -231,6 +659,7 @@ struct InnerDecompress {
     reader_gz: Option<Box<GzDecoder<BinaryMut>>>,
     reader_br: Option<Box<Decompressor<BinaryMut>>>,
     reader_de: Option<Box<DeflateDecoder<BinaryMut>>>,
+    reader_zstd: Option<Box<ZstdDecoder<'static, BinaryMut>>>,
 }
 
 impl Debug for InnerDecompress {
@@ -238,6 +667,7 @@ impl Debug for InnerDecompress {
         f.debug_struct("InnerDecompress")
             .field("reader_gz", &self.reader_gz)
             .field("reader_de", &self.reader_de)
+            .field("reader_zstd", &self.reader_zstd.is_some())
             .finish()
     }
 }
@@ -249,6 +679,7 @@ impl InnerDecompress {
             reader_gz: None,
             reader_br: None,
             reader_de: None,
+            reader_zstd: None,
         }
     }
 
@@ -271,6 +702,83 @@ impl InnerDecompress {
             self.reader_br = Some(Box::new(Decompressor::new(BinaryMut::new(), 4096)));
         }
     }
+
+    pub fn open_reader_zstd(&mut self) -> io::Result<()> {
+        if self.reader_zstd.is_none() {
+            self.reader_zstd = Some(Box::new(ZstdDecoder::new(BinaryMut::new())?));
+        }
+        Ok(())
+    }
+}
+
+/// 可重放的包体缓存, 超出上限后不再缓存, 包体变为不可重放
+#[derive(Debug)]
+struct ReplayBuffer {
+    limit: usize,
+    data: BinaryMut,
+    /// 原始数据的压缩方式
+    compress_method: i8,
+    is_overflow: bool,
+    /// 已重放过的数据不再重复记录
+    is_complete: bool,
+}
+
+/// 包体摘要的算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    /// 从Digest/Content-Digest中的算法名解析, 不区分大小写
+    pub fn from_name(name: &str) -> Option<Self> {
+        match &*name.to_ascii_lowercase() {
+            "md5" => Some(Self::Md5),
+            "sha" | "sha-1" => Some(Self::Sha1),
+            "sha-256" => Some(Self::Sha256),
+            _ => None,
+        }
+    }
+
+    fn hasher(&self) -> Box<dyn DynDigest + Send + Sync> {
+        match self {
+            Self::Md5 => Box::new(Md5::new()),
+            Self::Sha1 => Box::new(Sha1::new()),
+            Self::Sha256 => Box::new(Sha256::new()),
+        }
+    }
+}
+
+struct BodyDigest {
+    hasher: Box<dyn DynDigest + Send + Sync>,
+    expected: Vec<u8>,
+    matched: Option<bool>,
+}
+
+impl BodyDigest {
+    fn new(algorithm: DigestAlgorithm, expected: &str) -> Option<Self> {
+        let hasher = algorithm.hasher();
+        // Content-Digest的值形如 :base64:
+        let expected = expected.trim().trim_matches(':');
+        let size = hasher.output_size();
+        let expected = if expected.len() == size * 2 {
+            (0..size)
+                .map(|i| u8::from_str_radix(&expected[i * 2..i * 2 + 2], 16).ok())
+                .collect::<Option<Vec<u8>>>()?
+        } else {
+            STANDARD.decode(expected).ok()?
+        };
+        if expected.len() != size {
+            return None;
+        }
+        Some(Self {
+            hasher,
+            expected,
+            matched: None,
+        })
+    }
 }
 
 pub struct Body {
@@ -289,6 +797,26 @@ pub struct Body {
     is_process_end: bool,
     max_read_buf: usize,
     rate_limit: Option<RateLimitLayer>,
+    /// 解压时容忍数据结尾被截断
+    lenient_decompress: bool,
+    replay: Option<ReplayBuffer>,
+    /// 接收到的原始数据(解压前), 开启后才记录
+    raw: Option<BinaryMut>,
+    /// 接收时校验的摘要, 开启后才计算
+    digest: Option<BodyDigest>,
+    trailers: TrailerSlot,
+    /// 输出编码已确定, 开始读取或重新指定后不可再修改
+    is_encoding_fixed: bool,
+    /// 单个包体解压后的最大大小
+    max_decompressed_size: usize,
+    /// 已解压的数据大小
+    decompressed_size: usize,
+    /// 解压后的数据超出限制
+    is_decompress_overflow: bool,
+    /// 接收数据时解压出错
+    decode_error: Option<io::Error>,
+    /// 处理数据时的错误, 已处理的数据读取完后返回一次
+    process_error: Option<webparse::WebError>,
 }
 
 impl Default for Body {
@@ -312,10 +840,28 @@ impl Default for Body {
             // 为了数据安全, 防止一次性全部读到内存, 限定默认大小为10M
             max_read_buf: 10_485_760,
             rate_limit: None,
+            lenient_decompress: false,
+            replay: None,
+            raw: None,
+            digest: None,
+            trailers: Default::default(),
+            is_encoding_fixed: false,
+            // 防止解压炸弹, 限定解压后的默认大小为64M
+            max_decompressed_size: 67_108_864,
+            decompressed_size: 0,
+            is_decompress_overflow: false,
+            decode_error: None,
+            process_error: None,
         }
     }
 }
 
+impl<B: Into<Binary>> FromIterator<B> for Body {
+    fn from_iter<I: IntoIterator<Item = B>>(iter: I) -> Self {
+        Body::from_chunks(iter.into_iter().map(|b| b.into()).collect())
+    }
+}
+
 impl Body {
     pub fn empty() -> Body {
         Default::default()
@@ -361,6 +907,41 @@ impl Body {
         }
     }
 
+    /// 直接从连接的缓冲区读取数据的Body
+    pub(crate) fn new_pipe(pipe: PipeReceiver, binary: BinaryMut, is_end: bool) -> Body {
+        Body {
+            receiver: InnerReceiver::new_pipe(pipe),
+            origin_buf: Some(binary),
+            is_end,
+            ..Default::default()
+        }
+    }
+
+    /// 创建通道类型的Body, buffer为通道中最多缓存的数据块数
+    pub fn channel(buffer: usize) -> (BodySender, Body) {
+        let (sender, receiver) = channel(buffer);
+        (sender, Body::new(receiver, BinaryMut::new(), false))
+    }
+
+    /// 包体结束后收到的trailer头, 需在包体读取完毕后获取
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        self.trailers.lock().unwrap().clone()
+    }
+
+    pub fn set_trailers(&mut self, trailers: HeaderMap) {
+        *self.trailers.lock().unwrap() = Some(trailers);
+    }
+
+    /// 创建Server-Sent Events的包体及写入事件的发送端
+    pub fn sse(buffer: usize) -> (SseSender, Body) {
+        let (sender, body) = Self::channel(buffer);
+        (SseSender::new(sender), body)
+    }
+
+    pub(crate) fn trailer_slot(&self) -> TrailerSlot {
+        self.trailers.clone()
+    }
+
     pub fn new_file(file: File, data_size: u64) -> Body {
         Body {
             receiver: InnerReceiver::new_file(file, data_size),
@@ -369,6 +950,51 @@ impl Body {
         }
     }
 
+    /// 从任意的AsyncRead读取数据, 已知大小时发送Content-Length,
+    /// 否则HTTP/1以chunked的方式流式发送
+    pub fn from_async_read<R: AsyncRead + Send + Unpin + 'static>(
+        reader: R,
+        data_size: Option<u64>,
+    ) -> Body {
+        Body {
+            receiver: InnerReceiver::new_reader(Box::new(reader), data_size.unwrap_or(u64::MAX)),
+            is_end: false,
+            ..Default::default()
+        }
+    }
+
+    /// 从外部的数据流读取数据, 数据流结束时包体结束,
+    /// 数据流返回错误时读取包体也将返回该错误
+    pub fn from_stream<S, B, E>(stream: S) -> Body
+    where
+        S: Stream<Item = Result<B, E>> + Send + 'static,
+        B: AsRef<[u8]> + 'static,
+        E: Into<io::Error> + 'static,
+    {
+        use tokio_stream::StreamExt;
+        let stream = stream.map(|v| match v {
+            Ok(b) => Ok(Binary::from(b.as_ref().to_vec())),
+            Err(e) => Err(e.into()),
+        });
+        Body {
+            receiver: InnerReceiver::new_stream(Box::pin(stream)),
+            is_end: false,
+            ..Default::default()
+        }
+    }
+
+    /// 由同步的数据块构建包体, 每块作为单独的DATA帧或chunk发送, 最后一块结束包体
+    fn from_chunks(chunks: VecDeque<Binary>) -> Body {
+        if chunks.is_empty() {
+            return Body::empty();
+        }
+        Body {
+            receiver: InnerReceiver::new_chunks(chunks),
+            is_end: false,
+            ..Default::default()
+        }
+    }
+
     pub fn new_text(text: String) -> Self {
         Body {
             origin_buf: Some(BinaryMut::from(text)),
@@ -376,6 +1002,233 @@ impl Body {
         }
     }
 
+    /// 将已完全缓存的包体按指定方式一次性压缩, 返回压缩后的数据,
+    /// 可据此设置准确的Content-Length, 避免使用chunked发送.
+    /// 包体内容替换为压缩后的数据, 发送时不再重复压缩
+    pub fn compress_to_buffer(&mut self, method: i8) -> ProtResult<Binary> {
+        if !self.is_end {
+            return Err(ProtError::Extension("body is not fully buffered"));
+        }
+        if self.origin_compress_method != Consts::COMPRESS_METHOD_NONE
+            && self.origin_compress_method != method
+        {
+            return Err(ProtError::Extension("body already compressed"));
+        }
+        // 压缩的原始数据已解压到读取缓存中, 两种数据不能混合
+        if self.origin_compress_method != Consts::COMPRESS_METHOD_NONE
+            && self.read_buf.as_ref().is_some_and(|read| read.remaining() > 0)
+        {
+            return Err(ProtError::Extension("body partially decoded"));
+        }
+        let mut raw = BinaryMut::new();
+        if let Some(origin) = self.origin_buf.take() {
+            raw.put_slice(origin.chunk());
+        }
+        if let Some(read) = self.read_buf.as_mut() {
+            raw.put_slice(read.chunk());
+            read.advance_all();
+        }
+        let compressed = if self.origin_compress_method == method {
+            raw.freeze()
+        } else {
+            let data = match method {
+                Consts::COMPRESS_METHOD_GZIP => {
+                    let mut gz = GzEncoder::new(Vec::new(), self.compress.flate_level());
+                    gz.write_all(raw.chunk())?;
+                    gz.finish()?
+                }
+                Consts::COMPRESS_METHOD_DEFLATE => {
+                    let mut de = DeflateEncoder::new(Vec::new(), self.compress.flate_level());
+                    de.write_all(raw.chunk())?;
+                    de.finish()?
+                }
+                Consts::COMPRESS_METHOD_BROTLI => {
+                    let (quality, lgwin) = self.compress.brotli_params();
+                    let mut br = CompressorWriter::new(Vec::new(), 4096, quality, lgwin);
+                    br.write_all(raw.chunk())?;
+                    br.flush()?;
+                    br.into_inner()
+                }
+                Consts::COMPRESS_METHOD_ZSTD => {
+                    let mut zstd = ZstdEncoder::new(Vec::new(), self.compress.zstd_level())?;
+                    zstd.write_all(raw.chunk())?;
+                    zstd.finish()?
+                }
+                _ => raw.chunk().to_vec(),
+            };
+            Binary::from(data)
+        };
+        self.origin_buf = Some(BinaryMut::from(compressed.chunk().to_vec()));
+        self.origin_compress_method = method;
+        self.now_compress_method = method;
+        Ok(compressed)
+    }
+
+    /// 复制完全缓存在内存中且未被读取的数据, 用于请求重试,
+    /// 流式或通道数据无法复制则返回None
+    pub fn try_clone(&self) -> Option<Body> {
+        if self.is_replayable() {
+            let replay = self.replay.as_ref().unwrap();
+            return Some(Body {
+                origin_buf: Some(replay.data.clone()),
+                origin_compress_method: replay.compress_method,
+                now_compress_method: self.now_compress_method,
+                is_chunked: self.is_chunked,
+                max_read_buf: self.max_read_buf,
+                lenient_decompress: self.lenient_decompress,
+                ..Default::default()
+            });
+        }
+        if !self.receiver.is_none() || self.is_process_end || self.read_buf.is_some() {
+            return None;
+        }
+        let origin_buf = self.origin_buf.as_ref()?;
+        Some(Body {
+            origin_buf: Some(origin_buf.clone()),
+            origin_compress_method: self.origin_compress_method,
+            now_compress_method: self.now_compress_method,
+            is_chunked: self.is_chunked,
+            max_read_buf: self.max_read_buf,
+            lenient_decompress: self.lenient_decompress,
+            ..Default::default()
+        })
+    }
+
+    /// 开启重放缓存, 读取过程中缓存不超过limit字节的原始数据,
+    /// 数据全部接收且未超出上限时可通过try_clone/rewind重放, 超出则退化为普通的流式读取.
+    /// 需在读取数据前设置, 否则返回false
+    pub fn set_replay_limit(&mut self, limit: usize) -> bool {
+        if self.read_buf.is_some() || self.is_process_end {
+            return false;
+        }
+        self.replay = Some(ReplayBuffer {
+            limit,
+            data: BinaryMut::new(),
+            compress_method: self.origin_compress_method,
+            is_overflow: false,
+            is_complete: false,
+        });
+        true
+    }
+
+    /// 是否可以重放, 需数据已全部接收且未超出重放上限
+    pub fn is_replayable(&self) -> bool {
+        match &self.replay {
+            Some(replay) => !replay.is_overflow && self.is_end && self.origin_buf.is_none(),
+            None => false,
+        }
+    }
+
+    /// 重置到包体的开始位置重新读取, 不可重放时返回false
+    pub fn rewind(&mut self) -> bool {
+        let mut body = match self.try_clone() {
+            Some(body) if self.is_replayable() => body,
+            _ => return false,
+        };
+        let mut replay = self.replay.take();
+        if let Some(replay) = &mut replay {
+            replay.is_complete = true;
+        }
+        body.replay = replay;
+        body.rate_limit = self.rate_limit.take();
+        *self = body;
+        true
+    }
+
+    fn record_replay(&mut self, data: &[u8]) {
+        if let Some(replay) = &mut self.replay {
+            if replay.is_overflow || replay.is_complete {
+                return;
+            }
+            if replay.data.remaining() + data.len() > replay.limit {
+                log::trace!("包体超出重放上限{}, 变为不可重放", replay.limit);
+                replay.is_overflow = true;
+                replay.data = BinaryMut::new();
+            } else {
+                replay.data.put_slice(data);
+            }
+        }
+    }
+
+    /// 开启原始数据记录, 保留解压前收到的原始字节, 如用于Webhook的签名校验.
+    /// 需在读取数据前设置, 否则返回false
+    pub fn set_capture_raw(&mut self, capture: bool) -> bool {
+        if self.read_buf.is_some() || self.is_process_end {
+            return false;
+        }
+        self.raw = if capture { Some(BinaryMut::new()) } else { None };
+        true
+    }
+
+    /// 获取收到的原始数据(解压前), 需开启记录且数据已全部接收
+    pub fn raw_bytes(&self) -> Option<Binary> {
+        if !self.is_end {
+            return None;
+        }
+        let raw = self.raw.as_ref()?;
+        Some(Binary::from(raw.chunk().to_vec()))
+    }
+
+    fn record_raw(&mut self, data: &[u8]) {
+        if let Some(raw) = &mut self.raw {
+            raw.put_slice(data);
+        }
+        if let Some(digest) = &mut self.digest {
+            digest.hasher.update(data);
+        }
+    }
+
+    /// 开启摘要校验, 边接收边计算原始数据(解压前)的摘要, 结束时与期望值比较.
+    /// 期望值支持base64或16进制, 需在读取数据前设置, 否则返回false
+    pub fn verify_digest(&mut self, algorithm: DigestAlgorithm, expected: &str) -> bool {
+        if self.read_buf.is_some() || self.is_process_end {
+            return false;
+        }
+        match BodyDigest::new(algorithm, expected) {
+            Some(digest) => {
+                self.digest = Some(digest);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 根据头信息中的Content-MD5, Digest或Content-Digest开启摘要校验,
+    /// 未声明或算法不支持时返回false
+    pub fn verify_digest_header(&mut self, header: &HeaderMap) -> bool {
+        if let Some(value) = header.get_str_value(&"Content-MD5") {
+            return self.verify_digest(DigestAlgorithm::Md5, &value);
+        }
+        for name in ["Content-Digest", "Digest"] {
+            let Some(value) = header.get_str_value(&name) else {
+                continue;
+            };
+            for item in value.split(',') {
+                let Some((algorithm, expected)) = item.split_once('=') else {
+                    continue;
+                };
+                if let Some(algorithm) = DigestAlgorithm::from_name(algorithm.trim()) {
+                    return self.verify_digest(algorithm, expected.trim());
+                }
+            }
+        }
+        false
+    }
+
+    /// 数据接收完毕时比较摘要, 不一致返回错误, 之后数据结束
+    fn check_digest(&mut self) -> io::Result<()> {
+        if let Some(digest) = &mut self.digest {
+            if digest.matched.is_none() {
+                let value = digest.hasher.finalize_reset();
+                digest.matched = Some(*value == *digest.expected);
+            }
+            if digest.matched == Some(false) {
+                log::trace!("包体摘要校验失败");
+                return Err(Error::new(io::ErrorKind::InvalidData, "body digest mismatch"));
+            }
+        }
+        Ok(())
+    }
 
     pub fn set_file(&mut self, file: String, data_size: u64) {
         let f = std::fs::File::open(file);
@@ -399,6 +1252,31 @@ impl Body {
         self.origin_buf = Some(BinaryMut::from(text));
     }
 
+    /// 是否为from_async_read创建的包体
+    pub fn is_reader(&self) -> bool {
+        self.receiver.reader.is_some()
+    }
+
+    /// 是否为未经压缩及chunk转换的纯文件数据, 可直接由文件写入socket
+    pub fn is_plain_file(&self) -> bool {
+        self.receiver.file.is_some()
+            && self.origin_buf.is_none()
+            && self.read_buf.is_none()
+            && self.rate_limit.is_none()
+            && !self.is_chunked
+            && self.get_now_compress() == Consts::COMPRESS_METHOD_NONE
+    }
+
+    /// 取出纯文件数据的文件及剩余大小, 非纯文件返回None
+    pub fn take_plain_file(&mut self) -> Option<(File, u64)> {
+        if !self.is_plain_file() {
+            return None;
+        }
+        let file = self.receiver.file.take()?;
+        self.is_end = true;
+        Some((*file, self.receiver.data_size))
+    }
+
     pub fn set_rate_limit(&mut self, rate: RateLimitLayer) {
         self.rate_limit = Some(rate);
     }
@@ -406,6 +1284,27 @@ impl Body {
     pub fn set_max_read_buf(&mut self, max_read_buf: usize) {
         self.max_read_buf = max_read_buf;
     }
+
+    /// 设置压缩等级, 在首次压缩前设置有效, 适用于所有压缩方式并按其范围截取:
+    /// gzip及deflate为0-9, brotli为0-11, zstd为1-22
+    pub fn set_compress_level(&mut self, level: u32) {
+        self.compress.level = Some(level);
+    }
+
+    /// 设置brotli的质量(0-11)及窗口大小(10-24), 超出范围则截取, 优先于set_compress_level
+    pub fn set_brotli_params(&mut self, quality: u32, lgwin: u32) {
+        self.compress.brotli = Some((quality.min(11), lgwin.clamp(10, 24)));
+    }
+
+    /// 设置单个包体解压后的最大大小, 超出时停止解压并返回错误, 默认64M
+    pub fn set_max_decompressed_size(&mut self, limit: usize) {
+        self.max_decompressed_size = limit;
+    }
+
+    /// 设置宽松解压模式, 数据结束时压缩数据被截断则返回已解压的数据而不报错, 默认严格
+    pub fn set_lenient_decompress(&mut self, lenient: bool) {
+        self.lenient_decompress = lenient;
+    }
     
     pub async fn set_start_end(&mut self, start_pos: u64, end_pos: u64) -> ProtResult<()> {
         self.receiver.set_start_end(start_pos, end_pos).await
@@ -464,6 +1363,11 @@ impl Body {
         self.now_compress_method = Consts::COMPRESS_METHOD_NONE;
     }
 
+    pub fn set_compress_zstd(&mut self) {
+        self.origin_compress_method = Consts::COMPRESS_METHOD_ZSTD;
+        self.now_compress_method = Consts::COMPRESS_METHOD_NONE;
+    }
+
     pub fn set_compress_origin_gzip(&mut self) {
         self.origin_compress_method = Consts::COMPRESS_METHOD_GZIP;
         self.now_compress_method = Consts::COMPRESS_METHOD_NONE;
@@ -479,6 +1383,11 @@ impl Body {
         self.now_compress_method = Consts::COMPRESS_METHOD_NONE;
     }
 
+    pub fn set_compress_origin_zstd(&mut self) {
+        self.origin_compress_method = Consts::COMPRESS_METHOD_ZSTD;
+        self.now_compress_method = Consts::COMPRESS_METHOD_NONE;
+    }
+
     pub fn set_origin_compress_method(&mut self, method: i8) -> i8 {
         self.origin_compress_method = method;
         self.origin_compress_method
@@ -489,6 +1398,17 @@ impl Body {
         self.get_now_compress()
     }
 
+    /// 在首次读取前重新指定输出的编码, 只可调用一次, 如代理根据下游的Accept-Encoding决定:
+    /// 与原始编码相同则原样透传, 为COMPRESS_METHOD_NONE则解压, 否则解压后重新压缩.
+    /// 发送时头中的Content-Encoding需与之一致
+    pub fn reconfigure_encoding(&mut self, method: i8) -> ProtResult<i8> {
+        if self.is_encoding_fixed {
+            return Err(ProtError::Extension("body encoding already fixed"));
+        }
+        self.is_encoding_fixed = true;
+        Ok(self.add_compress_method(method))
+    }
+
     pub fn is_chunked(&mut self) -> bool {
         self.is_chunked
     }
@@ -498,10 +1418,25 @@ impl Body {
     }
 
     pub fn cache_buffer(&mut self, buf: &[u8]) -> usize {
+        self.is_encoding_fixed = true;
+        self.record_replay(buf);
+        self.record_raw(buf);
         if self.read_buf.is_none() {
             self.read_buf = Some(BinaryMut::new());
         }
-        self.decode_read_data(buf).ok().unwrap_or(0)
+        match self.decode_read_data(buf) {
+            Ok(size) => size,
+            // 数据未结束时的截断错误表示还需要更多的数据
+            Err(e) if !self.is_end && e.kind() == io::ErrorKind::UnexpectedEof => 0,
+            Err(e) => {
+                // 超出解压限制的错误单独记录, 只保留第一个解压错误
+                if !self.is_decompress_overflow && self.decode_error.is_none() {
+                    log::trace!("包体解压出错: {}", e);
+                    self.decode_error = Some(e);
+                }
+                0
+            }
+        }
     }
 
     pub fn is_end(&self) -> bool {
@@ -542,6 +1477,55 @@ impl Body {
         return self.cache_body_data.remaining();
     }
 
+    /// 预计可读取的剩余数据大小, 需要压缩转换或通道数据未知时返回None
+    pub fn size_hint(&self) -> Option<u64> {
+        if self.get_now_compress() != Consts::COMPRESS_METHOD_NONE
+            || self.origin_compress_method != Consts::COMPRESS_METHOD_NONE
+        {
+            return None;
+        }
+        let mut size = self.cache_body_data.remaining() as u64;
+        if let Some(bin) = &self.origin_buf {
+            size += bin.remaining() as u64;
+        }
+        if let Some(bin) = &self.read_buf {
+            size += bin.remaining() as u64;
+        }
+        if self.receiver.file.is_some() || self.receiver.reader.is_some() {
+            if self.receiver.data_size == u64::MAX {
+                return None;
+            }
+            return Some(size + self.receiver.data_size);
+        }
+        if !self.is_end {
+            return None;
+        }
+        Some(size)
+    }
+
+    /// 读取数据到调用方提供的缓冲区中, 直到缓冲区写满或数据结束, 返回写入的大小,
+    /// 已知长度时可按size_hint预先分配缓冲区, 避免读取过程中的再次分配:
+    ///
+    /// ```no_run
+    /// # async fn read(mut body: wmhttp::Body) -> wmhttp::ProtResult<()> {
+    /// let mut buf = vec![0u8; body.size_hint().unwrap_or(4096) as usize];
+    /// let size = body.read_into(&mut buf).await?;
+    /// buf.truncate(size);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn read_into(&mut self, buf: &mut [u8]) -> ProtResult<usize> {
+        let mut size = 0;
+        while size < buf.len() {
+            let n = self.read(&mut buf[size..]).await?;
+            if n == 0 {
+                break;
+            }
+            size += n;
+        }
+        Ok(size)
+    }
+
     pub async fn wait_all(&mut self) -> Option<usize> {
         let _ = self.process_data(None);
         let mut size = 0;
@@ -557,23 +1541,84 @@ impl Body {
         Some(size)
     }
 
+    /// 转成数据流, 按到达顺序返回解压后的数据块, 数据结束返回None
+    pub fn into_stream(self) -> impl Stream<Item = ProtResult<Binary>> {
+        self
+    }
+
+    /// 转成按行分割的数据流, 每行到达即返回(不含行尾的`\n`或`\r\n`), 不缓存整个包体,
+    /// 最后一行没有换行符时在数据结束时返回, 单行最长1M
+    pub fn lines(self) -> impl Stream<Item = ProtResult<Binary>> {
+        self.lines_with_limit(DEFAULT_MAX_LINE_SIZE)
+    }
+
+    /// 同lines, 单行超过max_line_size时返回错误并结束, 避免没有换行符的包体无限缓存
+    pub fn lines_with_limit(self, max_line_size: usize) -> impl Stream<Item = ProtResult<Binary>> {
+        futures::stream::unfold(
+            (self, BinaryMut::new(), false),
+            move |(mut body, mut pending, mut is_end)| async move {
+                use tokio_stream::StreamExt;
+                loop {
+                    let pos = pending.chunk().iter().position(|c| *c == b'\n');
+                    if pos.unwrap_or(pending.remaining()) > max_line_size {
+                        pending.advance_all();
+                        let err = ProtError::Extension("line too long");
+                        return Some((Err(err), (body, pending, true)));
+                    }
+                    if let Some(pos) = pos {
+                        let mut line = pending.chunk()[..pos].to_vec();
+                        pending.advance(pos + 1);
+                        if line.last() == Some(&b'\r') {
+                            line.pop();
+                        }
+                        return Some((Ok(Binary::from(line)), (body, pending, is_end)));
+                    }
+                    if is_end {
+                        if pending.remaining() == 0 {
+                            return None;
+                        }
+                        let line = pending.chunk().to_vec();
+                        pending.advance_all();
+                        return Some((Ok(Binary::from(line)), (body, pending, is_end)));
+                    }
+                    match body.next().await {
+                        Some(Ok(bin)) => {
+                            pending.put_slice(bin.chunk());
+                        }
+                        Some(Err(e)) => {
+                            pending.advance_all();
+                            return Some((Err(e), (body, pending, true)));
+                        }
+                        None => is_end = true,
+                    }
+                }
+            },
+        )
+    }
+
     pub async fn read_all(&mut self, buffer: &mut BinaryMut) -> Option<usize> {
-        let _ = self.process_data(None);
+        if let Poll::Ready(Err(_)) = self.process_data(None) {
+            return None;
+        }
 
         if !self.is_end && !self.receiver.is_none() {
             while let Some(v) = self.receiver.recv().await {
-                self.cache_buffer(v.1.chunk());
                 self.is_end = v.0;
+                self.cache_buffer(v.1.chunk());
                 if self.is_end == true {
                     break;
                 }
             }
         }
-        let _ = self.process_data(None);
-        match self.read_data(buffer) {
-            Ok(s) => Some(s),
-            _ => None,
+        if let Poll::Ready(Err(_)) = self.process_data(None) {
+            return None;
+        }
+        let size = self.read_data(buffer).ok()?;
+        // 出错前已处理的数据读取完后才返回错误
+        if let Poll::Ready(Err(_)) = self.process_data(None) {
+            return None;
         }
+        Some(size)
     }
 
     fn inner_encode_write_data<B: Bt + BtMut>(
@@ -595,7 +1640,7 @@ impl Body {
                 if data.len() == 0 {
                     self.compress.open_write_gz();
                     let gz = self.compress.write_gz.take().unwrap();
-                    let value = gz.finish().unwrap();
+                    let value = gz.finish().map_err(|e| compress_error("gzip", e))?;
                     if value.remaining() > 0 {
                         Self::inner_encode_write_data(
                             &mut self.cache_body_data,
@@ -604,14 +1649,14 @@ impl Body {
                         )?;
                     }
                     if self.is_chunked {
-                        Helper::encode_chunk_data(&mut self.cache_body_data, data)
+                        self.encode_chunk_end()
                     } else {
                         Ok(0)
                     }
                 } else {
                     self.compress.open_write_gz();
                     let gz = self.compress.write_gz.as_mut().unwrap();
-                    gz.write_all(data).unwrap();
+                    gz.write_all(data).map_err(|e| compress_error("gzip", e))?;
                     // 每次写入，在尝试读取出数据
                     if gz.get_mut().remaining() > 0 {
                         let s = Self::inner_encode_write_data(
@@ -631,7 +1676,7 @@ impl Body {
                 if data.len() == 0 {
                     self.compress.open_write_de();
                     let de = self.compress.write_de.take().unwrap();
-                    let value = de.finish().unwrap();
+                    let value = de.finish().map_err(|e| compress_error("deflate", e))?;
                     if value.remaining() > 0 {
                         Self::inner_encode_write_data(
                             &mut self.cache_body_data,
@@ -640,14 +1685,14 @@ impl Body {
                         )?;
                     }
                     if self.is_chunked {
-                        Helper::encode_chunk_data(&mut self.cache_body_data, data)
+                        self.encode_chunk_end()
                     } else {
                         Ok(0)
                     }
                 } else {
                     self.compress.open_write_de();
                     let de = self.compress.write_de.as_mut().unwrap();
-                    de.write_all(data).unwrap();
+                    de.write_all(data).map_err(|e| compress_error("deflate", e))?;
                     // 每次写入，在尝试读取出数据
                     if de.get_mut().remaining() > 0 {
                         let s = Self::inner_encode_write_data(
@@ -667,7 +1712,7 @@ impl Body {
                 if data.len() == 0 {
                     self.compress.open_write_br();
                     let mut de = self.compress.write_br.take().unwrap();
-                    de.flush()?;
+                    de.flush().map_err(|e| compress_error("brotli", e))?;
                     let value = de.into_inner();
                     if value.remaining() > 0 {
                         Self::inner_encode_write_data(
@@ -677,14 +1722,14 @@ impl Body {
                         )?;
                     }
                     if self.is_chunked {
-                        Helper::encode_chunk_data(&mut self.cache_body_data, data)
+                        self.encode_chunk_end()
                     } else {
                         Ok(0)
                     }
                 } else {
                     self.compress.open_write_br();
                     let de = self.compress.write_br.as_mut().unwrap();
-                    de.write_all(data).unwrap();
+                    de.write_all(data).map_err(|e| compress_error("brotli", e))?;
                     // 每次写入，在尝试读取出数据
                     if de.get_mut().remaining() > 0 {
                         let s = Self::inner_encode_write_data(
@@ -699,10 +1744,60 @@ impl Body {
                     }
                 }
             }
+            Consts::COMPRESS_METHOD_ZSTD => {
+                self.compress.open_write_zstd().map_err(|e| compress_error("zstd", e))?;
+                // 数据结束，需要主动调用结束以导出全部结果
+                if data.len() == 0 {
+                    let zstd = self.compress.write_zstd.take().unwrap();
+                    let value = zstd.finish().map_err(|e| compress_error("zstd", e))?;
+                    if value.remaining() > 0 {
+                        Self::inner_encode_write_data(
+                            &mut self.cache_body_data,
+                            &value,
+                            self.is_chunked,
+                        )?;
+                    }
+                    if self.is_chunked {
+                        self.encode_chunk_end()
+                    } else {
+                        Ok(0)
+                    }
+                } else {
+                    let zstd = self.compress.write_zstd.as_mut().unwrap();
+                    zstd.write_all(data).map_err(|e| compress_error("zstd", e))?;
+                    // 每次写入，在尝试读取出数据
+                    if zstd.get_mut().remaining() > 0 {
+                        let s = Self::inner_encode_write_data(
+                            &mut self.cache_body_data,
+                            &zstd.get_mut().chunk(),
+                            self.is_chunked,
+                        );
+                        zstd.get_mut().clear();
+                        s
+                    } else {
+                        Ok(0)
+                    }
+                }
+            }
+            _ if data.is_empty() && self.is_chunked => self.encode_chunk_end(),
             _ => Self::inner_encode_write_data(&mut self.cache_body_data, data, self.is_chunked),
         }
     }
 
+    /// chunked包体的结束块, 有trailer头时写在结束块之后
+    fn encode_chunk_end(&mut self) -> std::io::Result<usize> {
+        let trailers = self.trailers.lock().unwrap().clone();
+        let Some(trailers) = trailers else {
+            return Helper::encode_chunk_data(&mut self.cache_body_data, &[]);
+        };
+        let mut data = String::from("0\r\n");
+        for (name, value) in trailers.iter() {
+            data += &format!("{}: {}\r\n", name, value);
+        }
+        data += "\r\n";
+        Ok(self.cache_body_data.put_slice(data.as_bytes()))
+    }
+
     pub fn poll_encode_write<B: Bt + BtMut>(
         &mut self,
         cx: &mut Context<'_>,
@@ -753,7 +1848,8 @@ impl Body {
                         rate.poll_call(bin.remaining() as u64)?;
                     }
                     has_change = true;
-                    if self.is_end {
+                    // 预先准备的数据块每次只读一块, 各自作为单独的帧发送
+                    if self.is_end || self.receiver.chunks.is_some() {
                         break;
                     }
                 }
@@ -783,31 +1879,70 @@ impl Body {
                 self.read_buf.as_mut().unwrap().put_slice(data);
                 return Ok(0)
             }
+            if self.is_decompress_overflow {
+                return Err(Self::decompress_overflow());
+            }
+            let limit = self.max_decompressed_size.saturating_sub(self.decompressed_size);
+            let lenient = self.lenient_decompress && self.is_end;
             // 数据结束前不做解压缩操作, 后续也不可读
             let size = match self.origin_compress_method {
                 Consts::COMPRESS_METHOD_GZIP => {
                     self.decompress.open_reader_gz();
                     let gz = self.decompress.reader_gz.as_mut().unwrap();
-                    gz.write_all(data)?;
-                    let s = read_all_data(self.read_buf.as_mut().unwrap(), gz)?;
-                    s
+                    gz.write_all(data).map_err(|e| decompress_error("gzip", e))?;
+                    let s = read_all_data(self.read_buf.as_mut().unwrap(), gz, limit);
+                    lenient_decompress(s, "gzip", lenient)?
                 },
                 Consts::COMPRESS_METHOD_DEFLATE => {
                     self.decompress.open_reader_de();
                     let de = self.decompress.reader_de.as_mut().unwrap();
-                    let s = read_all_data(self.read_buf.as_mut().unwrap(), de)?;
-                    s
+                    de.get_mut().put_slice(data);
+                    let s = read_all_data(self.read_buf.as_mut().unwrap(), de, limit);
+                    lenient_decompress(s, "deflate", lenient)?
                 },
                 Consts::COMPRESS_METHOD_BROTLI => {
                     self.decompress.open_reader_br();
                     let br = self.decompress.reader_br.as_mut().unwrap();
-                    let s = read_all_data(self.read_buf.as_mut().unwrap(), br)?;
-                    s
+                    br.get_mut().put_slice(data);
+                    let s = read_all_data(self.read_buf.as_mut().unwrap(), br, limit);
+                    lenient_decompress(s, "brotli", lenient)?
+                },
+                Consts::COMPRESS_METHOD_ZSTD => {
+                    self.decompress.open_reader_zstd().map_err(|e| decompress_error("zstd", e))?;
+                    let zstd = self.decompress.reader_zstd.as_mut().unwrap();
+                    // 分段写入压缩数据, 解压后的数据写入内部缓冲区, 超出限制时停止
+                    let read_buf = self.read_buf.as_mut().unwrap();
+                    let mut input = data;
+                    let mut size = 0;
+                    loop {
+                        let value = zstd.get_mut();
+                        size += value.remaining();
+                        read_buf.put_slice(value.chunk());
+                        value.clear();
+                        if size > limit || input.is_empty() {
+                            break;
+                        }
+                        let n = zstd.write(input).map_err(|e| decompress_error("zstd", e))?;
+                        input = &input[n..];
+                        if input.is_empty() {
+                            zstd.flush().map_err(|e| decompress_error("zstd", e))?;
+                        }
+                    }
+                    size
                 },
                 _ => {
                     return Err(Error::new(io::ErrorKind::Interrupted, "未知的压缩格式"));
                 }
             };
+            self.decompressed_size += size;
+            if self.decompressed_size > self.max_decompressed_size {
+                log::trace!("解压后的数据超出限制:{}", self.max_decompressed_size);
+                self.is_decompress_overflow = true;
+                if let Some(read_buf) = self.read_buf.as_mut() {
+                    read_buf.clear();
+                }
+                return Err(Self::decompress_overflow());
+            }
             if self.is_end {
                 self.origin_compress_method = Consts::COMPRESS_METHOD_NONE;
             }
@@ -818,17 +1953,44 @@ impl Body {
         Ok(data.len())
     }
 
+    fn decompress_overflow() -> io::Error {
+        Error::new(io::ErrorKind::InvalidData, "decompressed body too large")
+    }
+
+    /// 处理已接收的数据, 任一处出错时, 出错前已处理的数据读取完后返回一次错误, 之后数据结束
     pub fn process_data(&mut self, cx: Option<&mut Context<'_>>) -> Poll<webparse::WebResult<usize> > {
-        if self.is_process_end {
-            return Poll::Ready(Ok(0));
+        if self.process_error.is_none() && !self.is_process_end {
+            match self.inner_process_data(cx) {
+                Poll::Ready(Err(e)) => self.process_error = Some(e),
+                poll => return poll,
+            }
+        }
+        if self.process_error.is_some() {
+            if self.cache_body_data.remaining() > 0 {
+                return Poll::Ready(Ok(0));
+            }
+            self.is_process_end = true;
+            return Poll::Ready(Err(self.process_error.take().unwrap()));
+        }
+        Poll::Ready(Ok(0))
+    }
+
+    fn inner_process_data(&mut self, cx: Option<&mut Context<'_>>) -> Poll<webparse::WebResult<usize> > {
+        if self.is_decompress_overflow {
+            return Poll::Ready(Err(Self::decompress_overflow().into()));
         }
+        self.receiver.check_error()?;
+        self.is_encoding_fixed = true;
 
         if let Some(origin) = self.origin_buf.take() {
-            let _ = self.decode_read_data(origin.chunk())?;
+            self.cache_buffer(origin.chunk());
         }
 
         if let Some(cx) = cx {
-            ready!(self.inner_poll_read(cx)?);
+            // 解压出错后不再读取新的数据
+            if self.decode_error.is_none() {
+                ready!(self.inner_poll_read(cx)?);
+            }
         }
         
         if let Some(mut bin) = self.read_buf.take() {
@@ -839,7 +2001,15 @@ impl Body {
             self.read_buf = Some(bin);
             self.notify_some_read();
         }
+        if self.is_decompress_overflow {
+            return Poll::Ready(Err(Self::decompress_overflow().into()));
+        }
+        if let Some(e) = self.decode_error.take() {
+            return Poll::Ready(Err(e.into()));
+        }
+        self.receiver.check_error()?;
         if self.is_end {
+            self.check_digest()?;
             self.encode_write_data(&[])?;
         }
         self.is_process_end = self.is_end;
@@ -867,14 +2037,39 @@ impl AsyncRead for Body {
         buf: &mut tokio::io::ReadBuf<'_>,
     ) -> Poll<std::io::Result<()>> {
         
-        ready!(self.process_data(Some(cx)).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "process data error")))?;
+        // 缓存中已有数据时直接复制, 不足时再处理新到达的数据
+        if self.cache_body_data.remaining() < buf.remaining() {
+            ready!(self.process_data(Some(cx)).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "process data error")))?;
+        }
         let len = std::cmp::min(self.cache_body_data.remaining(), buf.remaining());
+        if len == 0 && !self.is_process_end && buf.remaining() > 0 {
+            // 数据未结束且暂无数据, 等待唤醒, 避免被当作EOF
+            return Poll::Pending;
+        }
         buf.put_slice(&self.cache_body_data.chunk()[..len]);
         self.cache_body_data.advance(len);
         return Poll::Ready(Ok(()));
     }
 }
 
+impl Stream for Body {
+    type Item = ProtResult<Binary>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.process_data(Some(cx));
+        if self.cache_body_data.remaining() > 0 {
+            let bin = self.read_now();
+            return Poll::Ready(Some(Ok(bin)));
+        }
+        match poll {
+            Poll::Ready(Err(e)) => Poll::Ready(Some(Err(e.into()))),
+            Poll::Ready(Ok(_)) if self.is_process_end => Poll::Ready(None),
+            // 未结束且本次没有新数据, 已注册唤醒, 等待下次数据到达
+            _ => Poll::Pending,
+        }
+    }
+}
+
 impl Serialize for Body {
     fn serialize<B: Bt + BtMut>(
         &mut self,
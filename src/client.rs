@@ -10,34 +10,46 @@
 // -----
 // Created Date: 2023/10/07 09:41:02
 
+use std::collections::HashMap;
 use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::http2::{self, ClientH2Connection};
-use crate::ws::{ClientWsConnection, WsHandshake, WsOption, WsTrait};
+use crate::ws::{
+    ClientWsConnection, DeflateConfig, DeflateContext, WsHandshake, WsOption, WsTrait,
+};
 use crate::{http1::ClientH1Connection, ProtError};
 use crate::{
-    Body, MaybeHttpsStream, Middleware, ProtResult, RecvRequest, RecvResponse, TimeoutLayer,
+    Body, ContentRange, HeaderHelper, HttpHelper, MaybeHttpsStream, Middleware, ProtResult,
+    RecvRequest, RecvResponse, TimeoutLayer, Timings,
 };
-use algorithm::buf::Binary;
+use algorithm::buf::{Binary, BinaryMut, Bt, BtMut};
 use base64::prelude::*;
 use futures::StreamExt;
 use rustls::{ClientConfig, RootCertStore};
-use tokio::net::ToSocketAddrs;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::{
+    mpsc::{channel, Receiver, Sender},
+    oneshot,
+};
 use tokio::{
-    io::{AsyncRead, AsyncWrite},
+    fs::OpenOptions,
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
     net::TcpStream,
 };
 use tokio_rustls::TlsConnector;
-use webparse::http2::frame::Settings;
+use webparse::http2::frame::{Settings, StreamIdentifier};
 use webparse::http2::{DEFAULT_INITIAL_WINDOW_SIZE, DEFAULT_MAX_FRAME_SIZE, HTTP2_MAGIC};
-use webparse::{ws::OwnedMessage, Request, Url, WebError};
+use webparse::{
+    ws::OwnedMessage, HeaderMap, HeaderName, HeaderValue, Request, Url, Version, WebError,
+};
 
 use super::middle::BaseMiddleware;
 use super::proxy::ProxyScheme;
+use super::resolver::{connect_happy_eyeballs, connect_in_order, GaiResolver, Resolve};
 
 pub struct Builder {
     inner: ClientOption,
@@ -60,6 +72,27 @@ impl Builder {
         self
     }
 
+    /// 固定以HTTP/1.0发送请求, 用于不能正确处理HTTP/1.1特性的旧服务端.
+    /// 不尝试升级HTTP/2, 未声明`Connection: keep-alive`时默认短连接, 包体完整缓存后以Content-Length发送
+    pub fn http_10(mut self) -> Self {
+        self.inner.http_10 = true;
+        self.inner.http2 = false;
+        self.inner.http2_only = false;
+        self
+    }
+
+    /// 自定义域名解析, 如指定路由或测试时将域名映射到本地地址
+    pub fn resolver(mut self, resolver: Arc<dyn Resolve>) -> Self {
+        self.inner.resolver = Some(resolver);
+        self
+    }
+
+    /// 解析出多个地址时, 交替IPv6及IPv4的地址并发竞争连接, 否则按顺序尝试
+    pub fn happy_eyeballs(mut self, happy_eyeballs: bool) -> Self {
+        self.inner.happy_eyeballs = happy_eyeballs;
+        self
+    }
+
     pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
         if self.inner.timeout.is_none() {
             self.inner.timeout = Some(TimeoutLayer::new());
@@ -92,6 +125,24 @@ impl Builder {
         self
     }
 
+    /// 已建立的连接超过该时长未读取到数据则断开, 与整体请求超时相互独立
+    pub fn read_idle_timeout(mut self, read_idle_timeout: Duration) -> Self {
+        if self.inner.timeout.is_none() {
+            self.inner.timeout = Some(TimeoutLayer::new());
+        }
+        self.inner.timeout.as_mut().unwrap().read_idle_timeout = Some(read_idle_timeout);
+        self
+    }
+
+    /// 已建立的连接超过该时长未能写出数据则断开
+    pub fn write_idle_timeout(mut self, write_idle_timeout: Duration) -> Self {
+        if self.inner.timeout.is_none() {
+            self.inner.timeout = Some(TimeoutLayer::new());
+        }
+        self.inner.timeout.as_mut().unwrap().write_idle_timeout = Some(write_idle_timeout);
+        self
+    }
+
     pub fn timeout(mut self, timeout: Duration) -> Self {
         if self.inner.timeout.is_none() {
             self.inner.timeout = Some(TimeoutLayer::new());
@@ -127,26 +178,102 @@ impl Builder {
         self.inner
     }
 
+    /// 添加默认的请求头, 单个请求中设置的同名头优先
+    pub fn default_header<N, V>(mut self, name: N, value: V) -> Self
+    where
+        HeaderName: From<N>,
+        HeaderValue: From<V>,
+    {
+        self.inner
+            .headers
+            .insert(HeaderName::from(name), HeaderValue::from(value));
+        self
+    }
+
+    /// 是否自动解压响应包体, 关闭后包体保留原始压缩数据及Content-Encoding
+    pub fn auto_decompress(mut self, auto_decompress: bool) -> Self {
+        self.inner.auto_decompress = auto_decompress;
+        self
+    }
+
+    /// 设置请求的User-Agent, 单个请求中设置的优先
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.inner.headers.insert("User-Agent", user_agent.to_string());
+        self
+    }
+
+    /// 设置HTTP/1每次从socket读取的缓冲区大小, 默认16KB
+    pub fn read_buf_size(mut self, read_buf_size: usize) -> Self {
+        self.inner.read_buf_size = Some(read_buf_size);
+        self
+    }
+
+    /// 连接的最长存活时间, 到达后不再发送新的请求, 等进行中的请求完成后关闭
+    pub fn max_connection_age(mut self, max_connection_age: Duration) -> Self {
+        self.inner.max_connection_age = Some(max_connection_age);
+        self
+    }
+
+    /// 到达最长存活时间后, 等待进行中的请求完成的时长, 超出则强制关闭
+    pub fn max_connection_age_grace(mut self, grace: Duration) -> Self {
+        self.inner.max_connection_age_grace = Some(grace);
+        self
+    }
+
     pub fn middle<M: Middleware + 'static>(mut self, middle: M) -> Self {
         self.inner.middles.push(Box::new(middle));
         self
     }
 
+    /// WebSocket握手时是否请求permessage-deflate压缩, 服务端同意后收发的消息均压缩, 默认关闭
+    pub fn enable_per_message_deflate(mut self, enable: bool) -> Self {
+        self.inner.ws_deflate = enable.then(DeflateConfig::new);
+        self
+    }
+
     pub async fn connect_by_stream(self, stream: TcpStream) -> ProtResult<Client> {
         Ok(Client::new(self.inner, MaybeHttpsStream::Http(stream)))
     }
 
-    async fn inner_connect<A: ToSocketAddrs>(&self, addr: A) -> ProtResult<TcpStream> {
+    async fn inner_connect(&self, url: &Url, timings: &mut Timings) -> ProtResult<TcpStream> {
         if self.inner.timeout.is_some() {
             // 获取是否配置了连接超时, 如果有连接超时那么指定timeout
             if let Some(connect) = &self.inner.timeout.as_ref().unwrap().connect_timeout {
-                match tokio::time::timeout(*connect, TcpStream::connect(addr)).await {
-                    Ok(v) => return Ok(v?),
+                match tokio::time::timeout(*connect, self.connect_url(url, timings)).await {
+                    Ok(v) => return v,
                     Err(_) => return Err(ProtError::connect_timeout("client")),
                 }
             }
         }
-        let tcp = TcpStream::connect(addr).await?;
+        self.connect_url(url, timings).await
+    }
+
+    async fn connect_url(&self, url: &Url, timings: &mut Timings) -> ProtResult<TcpStream> {
+        let host = url.domain.clone().unwrap_or_default();
+        let port = url.port.unwrap_or_else(|| {
+            if url.scheme.is_https() || url.scheme.is_wss() {
+                443
+            } else {
+                80
+            }
+        });
+        // IP地址无需解析
+        let ip = host.trim_matches(|c| c == '[' || c == ']').parse::<IpAddr>();
+        let addrs = if let Ok(ip) = ip {
+            vec![SocketAddr::new(ip, port)]
+        } else if let Some(resolver) = &self.inner.resolver {
+            resolver.resolve(&host, port).await?
+        } else {
+            GaiResolver.resolve(&host, port).await?
+        };
+        timings.dns_done = Some(Instant::now());
+        log::trace!("域名{}解析的地址:{:?}", host, addrs);
+        let tcp = if self.inner.happy_eyeballs {
+            connect_happy_eyeballs(addrs).await?
+        } else {
+            connect_in_order(addrs).await?
+        };
+        timings.connect_done = Some(Instant::now());
         Ok(tcp)
     }
 
@@ -154,15 +281,18 @@ impl Builder {
         self.connect_with_domain("").await
     }
 
-    pub async fn connect_with_domain(self, domain: &str) -> ProtResult<Client> {
+    pub async fn connect_with_domain(mut self, domain: &str) -> ProtResult<Client> {
         if self.inner.url.is_none() {
             return Err(ProtError::Extension("unknown connection url"));
         }
-        let url = self.inner.url.as_ref().unwrap();
+        let mut timings = Timings::new();
+        let url = self.inner.url.clone().unwrap();
         if self.inner.proxies.len() > 0 {
             for p in self.inner.proxies.iter() {
                 match p.connect(&url).await? {
                     Some(tcp) => {
+                        timings.connect_done = Some(Instant::now());
+                        self.inner.timings = timings;
                         if url.scheme.is_https() {
                             return self.connect_tls_by_stream_with_domain(tcp, domain).await;
                         } else {
@@ -182,6 +312,8 @@ impl Builder {
                 for p in proxies.iter() {
                     match p.connect(&url).await? {
                         Some(tcp) => {
+                            timings.connect_done = Some(Instant::now());
+                            self.inner.timings = timings;
                             if url.scheme.is_https() {
                                 return self.connect_tls_by_stream_with_domain(tcp, domain).await;
                             } else {
@@ -196,13 +328,12 @@ impl Builder {
                     }
                 }
             }
+            let stream = self.inner_connect(&url, &mut timings).await?;
+            self.inner.timings = timings;
             if url.scheme.is_https() {
-                let connect = url.get_connect_url();
-                let stream = self.inner_connect(&connect.unwrap()).await?;
                 self.connect_tls_by_stream_with_domain(stream, domain).await
             } else {
-                let tcp = self.inner_connect(url.get_connect_url().unwrap()).await?;
-                Ok(Client::new(self.inner, MaybeHttpsStream::Http(tcp)))
+                Ok(Client::new(self.inner, MaybeHttpsStream::Http(stream)))
             }
         }
     }
@@ -248,11 +379,15 @@ impl Builder {
         let domain = rustls::pki_types::ServerName::try_from(name)
             .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid dnsname"))?;
 
+        if self.inner.timings.start.is_none() {
+            self.inner.timings = Timings::new();
+        }
         let outbound = connector.connect(domain, stream).await?;
+        self.inner.timings.tls_done = Some(Instant::now());
         let aa = outbound.get_ref().1.alpn_protocol();
         if aa == Some(&ClientOption::H2_PROTOCOL) {
             self.inner.http2_only = true;
-        } else {
+        } else if !self.inner.http_10 {
             self.inner.http2 = true;
             self.inner.http2_only = false;
         }
@@ -263,15 +398,37 @@ impl Builder {
 pub struct ClientOption {
     http2_only: bool,
     http2: bool,
+    /// 是否固定以HTTP/1.0发送请求
+    http_10: bool,
     settings: Settings,
     url: Option<Url>,
     timeout: Option<TimeoutLayer>,
     proxies: Vec<ProxyScheme>,
     middles: Vec<Box<dyn Middleware>>,
+    /// 默认的请求头
+    headers: HeaderMap,
+    /// 是否自动解压响应包体
+    auto_decompress: bool,
+    /// HTTP/1每次从socket读取的缓冲区大小
+    read_buf_size: Option<usize>,
+    /// 自定义的域名解析, 默认使用系统解析
+    resolver: Option<Arc<dyn Resolve>>,
+    /// 是否以Happy Eyeballs的方式竞争连接IPv6及IPv4地址
+    happy_eyeballs: bool,
+    /// 建立连接时各阶段的时间点, 附加在首个响应上
+    timings: Timings,
+    /// 连接的最长存活时间
+    max_connection_age: Option<Duration>,
+    /// 到达最长存活时间后等待请求完成的时长
+    max_connection_age_grace: Option<Duration>,
+    /// WebSocket的permessage-deflate配置, 为None时不请求压缩
+    ws_deflate: Option<DeflateConfig>,
 }
 
 impl ClientOption {
     pub const H2_PROTOCOL: [u8; 2] = [104, 50];
+    /// 默认的User-Agent
+    pub const DEFAULT_USER_AGENT: &str = concat!("wmhttp/", env!("CARGO_PKG_VERSION"));
     pub fn get_alpn_protocol(&self) -> Vec<Vec<u8>> {
         let mut ret = vec![];
         if self.http2_only {
@@ -300,14 +457,26 @@ impl ClientOption {
 
 impl Default for ClientOption {
     fn default() -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert("User-Agent", ClientOption::DEFAULT_USER_AGENT);
         Self {
             http2_only: false,
             http2: true,
+            http_10: false,
             url: None,
             settings: Default::default(),
             timeout: None,
             proxies: vec![],
             middles: vec![Box::new(BaseMiddleware::new(true))],
+            headers,
+            auto_decompress: true,
+            read_buf_size: None,
+            resolver: None,
+            happy_eyeballs: false,
+            timings: Timings::default(),
+            max_connection_age: None,
+            max_connection_age_grace: None,
+            ws_deflate: None,
         }
     }
 }
@@ -322,6 +491,8 @@ pub struct Client<T = TcpStream> {
     ws: Option<ClientWsConnection<MaybeHttpsStream<T>>>,
     callback_ws: Option<Box<dyn WsTrait>>,
     proxy: Option<ProxyScheme>,
+    /// 连接建立的时间
+    start_time: Instant,
 }
 
 impl Client {
@@ -346,6 +517,7 @@ where
             ws: None,
             callback_ws: None,
             proxy: None,
+            start_time: Instant::now(),
         };
         if client.option.http2_only {
             let mut value = http2::Builder::new()
@@ -369,9 +541,37 @@ where
     ) -> ClientH1Connection<MaybeHttpsStream<T>> {
         let mut client = ClientH1Connection::new(stream);
         client.set_timeout_layer(self.option.timeout.clone());
+        if let Some(size) = self.option.read_buf_size {
+            client.set_read_buf_size(size);
+        }
         client
     }
 
+    /// 以客户端配置的地址及默认请求头构建请求, path可为相对路径
+    pub fn request(&self, method: &str, path: &str) -> RequestBuilder {
+        let url = match &self.option.url {
+            Some(base) if path.starts_with('/') => {
+                let scheme = if base.scheme.is_https() {
+                    "https"
+                } else {
+                    "http"
+                };
+                format!(
+                    "{}://{}{}",
+                    scheme,
+                    base.get_connect_url().unwrap_or_default(),
+                    path
+                )
+            }
+            _ => path.to_string(),
+        };
+        RequestBuilder {
+            method: method.to_string(),
+            url,
+            headers: self.option.headers.clone(),
+        }
+    }
+
     pub fn set_proxy(&mut self, proxy: ProxyScheme) {
         self.proxy = Some(proxy);
     }
@@ -403,21 +603,192 @@ where
         Ok((self.receiver.take().unwrap(), sender))
     }
 
-    async fn send_req(&mut self, mut req: RecvRequest) -> ProtResult<()> {
+    /// 发送请求, HTTP/2时返回分配的流id
+    async fn send_req(&mut self, mut req: RecvRequest) -> ProtResult<Option<StreamIdentifier>> {
         if let Some(proxy) = &self.proxy {
             proxy.fix_request(&mut req)?;
         }
+        if self.option.http_10 && self.http1.is_some() {
+            Self::downgrade_http10(&mut req).await?;
+        }
+        // 注入默认的请求头, 请求中已设置的不覆盖
+        for (name, value) in self.option.headers.iter() {
+            if req.headers().get_option_value(name).is_none() {
+                req.headers_mut().insert(name.clone(), value.clone());
+            }
+        }
         for i in 0usize..self.option.middles.len() {
             self.option.middles[i].process_request(&mut req).await?;
         }
         if let Some(h) = &mut self.http1 {
             h.send_request(req)?;
         } else if let Some(h) = &mut self.http2 {
-            h.send_request(req)?;
+            let stream_id = h.send_request(req)?;
+            h.wait_buffer_ready().await?;
+            return Ok(Some(stream_id));
+        }
+        Ok(None)
+    }
+
+    /// 将请求降级为HTTP/1.0, 包体在此完整读取, 由之后的头处理设置Content-Length,
+    /// 读取包体出错时返回该错误, 不发送截断的包体
+    async fn downgrade_http10(req: &mut RecvRequest) -> ProtResult<()> {
+        *req.version_mut() = Version::Http10;
+        req.headers_mut().remove(&HeaderName::TRANSFER_ENCODING);
+        if !req.body().is_end() {
+            let mut buf = BinaryMut::new();
+            while let Some(bin) = req.body_mut().next().await {
+                buf.put_slice(bin?.chunk());
+            }
+            *req.body_mut() = Body::new_binary(buf);
+        }
+        // HTTP/1.0默认短连接, 仅显式要求时保持连接
+        let keep_alive = req.headers().get_str_value(&"Connection").is_some_and(|v| {
+            HeaderHelper::split_list(&v)
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case("keep-alive"))
+        });
+        if !keep_alive {
+            req.headers_mut().insert("Connection", "close");
         }
         Ok(())
     }
 
+    fn process_response(&mut self, r: &mut RecvResponse) {
+        if !self.option.auto_decompress {
+            // 输出编码与原始编码一致, 包体不做解压
+            let method = HeaderHelper::get_compress_method(r.headers());
+            r.body_mut().add_compress_method(method);
+        }
+        // 连接阶段的耗时只记录在该连接的首个响应上
+        let connect = std::mem::take(&mut self.option.timings);
+        if connect.start.is_some() {
+            let mut timings = r.extensions_mut().remove::<Timings>().unwrap_or_default();
+            timings.merge_connect(&connect);
+            r.extensions_mut().insert(timings);
+        }
+    }
+
+    /// 下一次需要处理存活时间的时间点, 到达存活时间前为存活时间, 之后为强制关闭的时间
+    fn age_deadline(&self, is_aged: bool) -> Option<Instant> {
+        let mut deadline = self.start_time + self.option.max_connection_age?;
+        if is_aged {
+            deadline += self.option.max_connection_age_grace.unwrap_or_default();
+        }
+        Some(deadline)
+    }
+
+    async fn wait_deadline(deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// 转为可克隆的句柄, 由后台任务驱动连接, 多个任务可通过句柄并发发送请求.
+    /// 仅支持HTTP/2的连接, 每个请求分配独立的流
+    pub fn into_handle(self) -> ProtResult<ClientHandle> {
+        if self.http2.is_none() {
+            return Err(ProtError::Extension("client handle requires http2 connection"));
+        }
+        let (sender, receiver) = channel(100);
+        tokio::spawn(async move {
+            if let Err(e) = self.wait_handle(receiver).await {
+                log::trace!("客户端连接处理时发生错误: {:?}", e);
+            }
+        });
+        Ok(ClientHandle { sender })
+    }
+
+    async fn wait_handle(mut self, receiver: Receiver<HandleRequest>) -> ProtResult<()> {
+        async fn recv_handle(
+            receiver: &mut Option<Receiver<HandleRequest>>,
+        ) -> Option<HandleRequest> {
+            if let Some(r) = receiver {
+                r.recv().await
+            } else {
+                std::future::pending().await
+            }
+        }
+
+        fn close_pending(
+            pending: &mut HashMap<StreamIdentifier, oneshot::Sender<ProtResult<RecvResponse>>>,
+        ) {
+            for (_, callback) in pending.drain() {
+                let _ = callback.send(Err(ProtError::Extension("close by server")));
+            }
+        }
+
+        let metrics = self.http2.as_ref().unwrap().stream_metrics();
+        let mut receiver = Some(receiver);
+        let mut pending = HashMap::new();
+        let mut is_aged = false;
+        loop {
+            // 所有句柄已释放且没有进行中的流, 关闭连接
+            if receiver.is_none() && metrics.current() == 0 {
+                return Ok(());
+            }
+            let deadline = self.age_deadline(is_aged);
+            let h2 = self.http2.as_mut().unwrap();
+            let result = tokio::select! {
+                r = h2.incoming() => r,
+                _ = Self::wait_deadline(deadline) => {
+                    if is_aged {
+                        log::trace!("客户端连接超过最长存活时间的等待时长, 强制关闭");
+                        close_pending(&mut pending);
+                        return Ok(());
+                    }
+                    // 不再接收新的请求, 句柄关闭后由调用方重新建立连接
+                    log::trace!("客户端连接到达最长存活时间, 开始关闭");
+                    is_aged = true;
+                    receiver = None;
+                    continue;
+                }
+                req = recv_handle(&mut receiver) => {
+                    let Some((req, callback)) = req else {
+                        receiver = None;
+                        continue;
+                    };
+                    match self.send_req(req).await {
+                        Ok(stream_id) => {
+                            if let Some(stream_id) = stream_id {
+                                pending.insert(stream_id, callback);
+                            }
+                        }
+                        Err(e) => {
+                            let _ = callback.send(Err(e));
+                        }
+                    }
+                    continue;
+                }
+            };
+            match result {
+                Ok(Some(mut r)) => {
+                    let stream_id = r.extensions().get::<StreamIdentifier>().cloned();
+                    if let Some(callback) = stream_id.and_then(|id| pending.remove(&id)) {
+                        self.process_response(&mut r);
+                        let _ = callback.send(Ok(r));
+                    }
+                }
+                Err(e @ ProtError::StreamReset(..)) => {
+                    // 被重置的流已结束但未收到响应
+                    let reset = e.stream_reset_id();
+                    if let Some(callback) = reset.and_then(|id| pending.remove(&id)) {
+                        let _ = callback.send(Err(e));
+                    }
+                }
+                Ok(None) => {
+                    close_pending(&mut pending);
+                    return Ok(());
+                }
+                Err(e) => {
+                    close_pending(&mut pending);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
     pub fn middle<M: Middleware + 'static>(&mut self, middle: M) {
         self.option.middles.push(Box::new(middle));
     }
@@ -465,11 +836,23 @@ where
             }
         }
         let (mut ws_receiver, mut ws_option);
+        let mut is_aged = false;
         loop {
+            let deadline = self.age_deadline(is_aged);
             let v = tokio::select! {
                 r = http1_wait(&mut self.http1) => {
                     r
                 }
+                _ = Self::wait_deadline(deadline) => {
+                    if is_aged {
+                        log::trace!("客户端连接超过最长存活时间的等待时长, 强制关闭");
+                        return Ok(());
+                    }
+                    log::trace!("客户端连接到达最长存活时间, 不再发送新的请求");
+                    is_aged = true;
+                    self.req_receiver = None;
+                    continue;
+                }
                 r = http2_wait(&mut self.http2) => {
                     r
                 }
@@ -505,11 +888,15 @@ where
                         return Err(ProtError::ClientUpgradeHttp2(s));
                     }
                 }
+                // 流被重置时连接仍可用, 由调用方决定是否重试
+                Err(e @ ProtError::StreamReset(..)) => {
+                    self.sender.send(Err(e)).await?;
+                }
                 Err(e) => {
                     self.sender.send(Err(e)).await?;
                     return Ok(());
                 }
-                Ok(Some(r)) => {
+                Ok(Some(mut r)) => {
                     if r.status() == 101
                         && r.headers().is_contains(&"Connection", "Upgrade".as_bytes())
                     {
@@ -532,11 +919,20 @@ where
                                 return Err(ProtError::Extension("websocket callback is none"));
                             }
                             if self.http1.is_some() {
-                                self.ws = Some(self.http1.take().unwrap().into_ws());
+                                let deflate = self.accept_deflate(&r)?;
+                                let mut ws = self.http1.take().unwrap().into_ws();
+                                ws.set_deflate(deflate);
+                                self.ws = Some(ws);
                                 let (sender, receiver) = channel::<OwnedMessage>(10);
                                 let shake = WsHandshake::new(sender, None, r, None);
                                 ws_option =
                                     self.callback_ws.as_mut().unwrap().on_open(shake).await?;
+                                if let (Some(ws), Some(option)) = (&mut self.ws, &ws_option) {
+                                    ws.set_auto_pong(option.auto_pong);
+                                    if let Some(size) = option.max_message_size {
+                                        ws.set_max_message_size(size);
+                                    }
+                                }
                                 ws_receiver = receiver;
 
                                 if ws_option.is_some()
@@ -553,6 +949,7 @@ where
                             }
                         }
                     }
+                    self.process_response(&mut r);
                     self.sender.send(Ok(r)).await?;
                 }
             };
@@ -563,6 +960,20 @@ where
         Ok(())
     }
 
+    /// 解析服务端同意的permessage-deflate, 未请求却被启用或无法遵守时断开连接
+    fn accept_deflate(&self, r: &RecvResponse) -> ProtResult<Option<DeflateContext>> {
+        let Some(value) = r.headers().get_str_value(&"Sec-WebSocket-Extensions") else {
+            return Ok(None);
+        };
+        let params = self
+            .option
+            .ws_deflate
+            .as_ref()
+            .and_then(|config| config.accept(&value))
+            .ok_or(ProtError::Extension("invalid websocket extensions"))?;
+        Ok(Some(DeflateContext::new(params, false)))
+    }
+
     async fn inner_oper_ws(
         &mut self,
         mut receiver: Receiver<OwnedMessage>,
@@ -647,6 +1058,9 @@ where
         header.insert("Sec-WebSocket-Key", BASE64_STANDARD.encode(&key));
         header.insert("Sec-WebSocket-Version", "13");
         header.insert("Sec-WebSocket-Protocol", "chat, superchat");
+        if let Some(config) = &self.option.ws_deflate {
+            header.insert("Sec-WebSocket-Extensions", config.to_offer());
+        }
         self.wait_ws_operate_with_req(req).await?;
         Ok(())
     }
@@ -686,33 +1100,55 @@ where
         // }
     }
 
+    /// 发送请求, HTTP/2的连接同时返回该请求分配的流id,
+    /// 之后的响应均在扩展中带有对应的流id(`StreamIdentifier`)
     pub async fn send(
         mut self,
         mut req: RecvRequest,
-    ) -> ProtResult<Receiver<ProtResult<RecvResponse>>> {
+    ) -> ProtResult<(Receiver<ProtResult<RecvResponse>>, Option<StreamIdentifier>)> {
         self.rebuild_request(&mut req);
         let (r, s) = self.split()?;
-        tokio::spawn(async move {
-            let _sender = s;
-            if let Err(e) = self.inner_operate(req).await {
-                println!("http数据请求时发生错误: {:?}", e);
-            }
-        });
-        Ok(r)
+        let stream_id = self.spawn_operate(req, Some(s)).await?;
+        Ok((r, stream_id))
     }
 
     pub async fn send2(
         mut self,
         mut req: RecvRequest,
-    ) -> ProtResult<(Receiver<ProtResult<RecvResponse>>, Sender<RecvRequest>)> {
+    ) -> ProtResult<(
+        Receiver<ProtResult<RecvResponse>>,
+        Sender<RecvRequest>,
+        Option<StreamIdentifier>,
+    )> {
         self.rebuild_request(&mut req);
         let (r, s) = self.split()?;
+        let stream_id = self.spawn_operate(req, None).await?;
+        Ok((r, s, stream_id))
+    }
+
+    /// HTTP/2的连接在当前任务中发送请求以得到分配的流id, 之后由后台任务驱动连接,
+    /// HTTP/1(包括尝试升级h2c时)没有流id, 请求在后台任务中发送
+    async fn spawn_operate(
+        mut self,
+        req: RecvRequest,
+        sender: Option<Sender<RecvRequest>>,
+    ) -> ProtResult<Option<StreamIdentifier>> {
+        let mut req = Some(req);
+        let mut stream_id = None;
+        if self.http2.is_some() {
+            stream_id = self.send_req(req.take().unwrap()).await?;
+        }
         tokio::spawn(async move {
-            if let Err(e) = self.inner_operate(req).await {
+            let _sender = sender;
+            let ret = match req {
+                Some(req) => self.inner_operate(req).await,
+                None => self.wait_operate().await,
+            };
+            if let Err(e) = ret {
                 println!("http数据请求时发生错误: {:?}", e);
             }
         });
-        Ok((r, s))
+        Ok(stream_id)
     }
 
     pub async fn send_now(mut self, mut req: RecvRequest) -> ProtResult<RecvResponse> {
@@ -735,6 +1171,126 @@ where
         }
     }
 
+    /// 将服务端收到的请求转发到该连接并返回上游的响应, 用于代理.
+    /// 请求包体边接收边转发, 上游的响应头到达后即返回, 响应包体同样边接收边回复,
+    /// 两个方向互不等待, 大的上传及下载均无需完整缓存. 逐跳的头不转发,
+    /// 重复的头按HeaderHelper::fold_headers合并, Set-Cookie等逐个保留
+    pub async fn splice(self, mut req: RecvRequest) -> ProtResult<RecvResponse> {
+        HeaderHelper::remove_hop_headers(req.headers_mut());
+        HeaderHelper::fold_header_map(req.headers_mut())?;
+        // 如来自HTTP/2的请求未声明长度, 以chunked的方式转发
+        if !req.body().is_end()
+            && !req.headers().is_chunked()
+            && req.headers().get_option_value(&HeaderName::CONTENT_LENGTH).is_none()
+        {
+            req.headers_mut().insert(HeaderName::TRANSFER_ENCODING, "chunked");
+        }
+        let mut res = self.send_now(req).await?;
+        HeaderHelper::remove_hop_headers(res.headers_mut());
+        if HeaderHelper::fold_header_map(res.headers_mut()).is_err() {
+            return Err(ProtError::Status(502, "invalid upstream headers"));
+        }
+        Ok(res)
+    }
+
+    /// 下载到文件, 支持断点续传: 文件已存在时以`Range: bytes=N-`请求剩余部分并追加写入,
+    /// 以`If-Range`带上之前保存的ETag, 资源已变更或服务端不支持范围请求(返回200)时重新下载.
+    /// ETag保存在`<path>.etag`中, 下载完成后删除, 返回文件的总大小.
+    /// 文件中为未编码的数据, 请求固定`Accept-Encoding: identity`, 保证范围与文件的偏移一致
+    pub async fn download_resumable<P: AsRef<Path>>(self, url: &str, path: P) -> ProtResult<u64> {
+        let path = path.as_ref();
+        let etag_path = PathBuf::from(format!("{}.etag", path.display()));
+        let existing = match tokio::fs::metadata(path).await {
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        };
+        let etag = tokio::fs::read_to_string(&etag_path).await.ok();
+
+        let mut builder = self
+            .request("GET", url)
+            .header("Accept-Encoding", "identity");
+        if existing > 0 {
+            builder = builder.header("Range", format!("bytes={}-", existing));
+            // If-Range只可使用强校验的ETag, 弱ETag时服务端可能返回不一致的范围
+            if let Some(etag) = etag.as_ref().filter(|e| !e.starts_with("W/")) {
+                builder = builder.header("If-Range", etag.clone());
+            }
+        }
+        let req = builder.body(Body::empty())?;
+        let mut res = self.send_now(req).await?;
+        let res_etag = res.headers().get_str_value(&"ETag");
+        let status = res.status();
+        let (mut file, expect) = if status == 206 {
+            let range = ContentRange::from_headers(res.headers());
+            let Some((start, _)) = range.and_then(|r| r.range) else {
+                return Err(ProtError::Extension("invalid content range"));
+            };
+            if start != existing {
+                return Err(ProtError::Extension("content range not match file"));
+            }
+            // 编码后的范围与文件中未编码数据的偏移不一致, 不可追加
+            if res
+                .headers()
+                .get_str_value(&HeaderName::CONTENT_ENCODING)
+                .is_some_and(|v| !v.trim().eq_ignore_ascii_case("identity"))
+            {
+                return Err(ProtError::Extension("encoded partial content"));
+            }
+            // 服务端未按If-Range校验, 资源已变更, 已下载的部分不可用
+            if etag.is_some() && res_etag.is_some() && etag != res_etag {
+                let _ = tokio::fs::remove_file(path).await;
+                let _ = tokio::fs::remove_file(&etag_path).await;
+                return Err(ProtError::Extension("resource changed"));
+            }
+            log::trace!("从{}处继续下载文件:{}", existing, path.display());
+            let file = OpenOptions::new().append(true).open(path).await?;
+            (file, range.and_then(|r| r.total))
+        } else if status == 200 {
+            if existing > 0 {
+                log::trace!("服务端未返回部分内容, 重新下载文件:{}", path.display());
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(path)
+                .await?;
+            let expect = res
+                .headers()
+                .get_str_value(&HeaderName::CONTENT_LENGTH)
+                .and_then(|v| v.trim().parse::<u64>().ok());
+            (file, expect)
+        } else if status == 416
+            && ContentRange::from_headers(res.headers()).is_some_and(|r| r.total == Some(existing))
+        {
+            // 请求的范围超出文件大小, 已下载完成
+            let _ = tokio::fs::remove_file(&etag_path).await;
+            return Ok(existing);
+        } else {
+            return Err(ProtError::Extension("unexpected download status"));
+        };
+        match &res_etag {
+            Some(etag) => tokio::fs::write(&etag_path, etag).await?,
+            None => {
+                let _ = tokio::fs::remove_file(&etag_path).await;
+            }
+        }
+
+        let mut size = file.metadata().await?.len();
+        while let Some(bin) = res.body_mut().next().await {
+            let bin = bin?;
+            file.write_all(bin.chunk()).await?;
+            size += bin.remaining() as u64;
+        }
+        file.flush().await?;
+        // 连接中断时包体提前结束, 保留已下载的部分及ETag以便续传
+        if expect.is_some_and(|total| size < total) {
+            return Err(ProtError::Extension("download interrupted"));
+        }
+        let _ = tokio::fs::remove_file(&etag_path).await;
+        Ok(size)
+    }
+
     pub async fn recv(&mut self) -> ProtResult<RecvResponse> {
         if let Some(recv) = &mut self.receiver {
             if let Some(res) = recv.recv().await {
@@ -756,3 +1312,87 @@ where
 //             // drop(self.)
 //         }
 //     }
+
+type HandleRequest = (RecvRequest, oneshot::Sender<ProtResult<RecvResponse>>);
+
+/// 可克隆的客户端句柄, 多个任务并发发送的请求复用同一个HTTP/2连接,
+/// 响应按流id分发给对应的调用方
+#[derive(Clone)]
+pub struct ClientHandle {
+    sender: Sender<HandleRequest>,
+}
+
+impl ClientHandle {
+    pub async fn send(&self, req: RecvRequest) -> ProtResult<RecvResponse> {
+        let (callback, receiver) = oneshot::channel();
+        if self.sender.send((req, callback)).await.is_err() {
+            return Err(ProtError::Extension("client connection closed"));
+        }
+        match receiver.await {
+            Ok(res) => res,
+            Err(_) => Err(ProtError::Extension("client connection closed")),
+        }
+    }
+
+    /// 发送请求, 服务端以REFUSED_STREAM拒绝(未处理该流)时重新发送, 最多重试max_retries次,
+    /// 包体不可复制(如通道或文件)的请求不重试, 直接返回错误
+    pub async fn send_retry(
+        &self,
+        mut req: RecvRequest,
+        max_retries: usize,
+    ) -> ProtResult<RecvResponse> {
+        let mut retries = 0;
+        loop {
+            // 发送前保留一份完整的请求, 已发送的包体被读取后仍可重发
+            let retry = if retries < max_retries {
+                HttpHelper::try_clone_request(&req)
+            } else {
+                None
+            };
+            match self.send(req).await {
+                Err(e) if e.is_retryable() && retry.is_some() => {
+                    log::trace!("请求被拒绝, 第{}次重试: {:?}", retries + 1, e);
+                    retries += 1;
+                    req = retry.unwrap();
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// 后台连接是否已关闭
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+/// 携带客户端默认配置的请求构建器
+pub struct RequestBuilder {
+    method: String,
+    url: String,
+    headers: HeaderMap,
+}
+
+impl RequestBuilder {
+    /// 设置请求头, 覆盖同名的默认请求头
+    pub fn header<N, V>(mut self, name: N, value: V) -> Self
+    where
+        HeaderName: From<N>,
+        HeaderValue: From<V>,
+    {
+        self.headers
+            .insert(HeaderName::from(name), HeaderValue::from(value));
+        self
+    }
+
+    pub fn body<B>(self, body: B) -> ProtResult<RecvRequest>
+    where
+        Body: From<B>,
+    {
+        let mut builder = Request::builder().method(&*self.method).url(&*self.url);
+        for (name, value) in self.headers.iter() {
+            builder = builder.header(name.clone(), value.clone());
+        }
+        Ok(builder.body(Body::from(body))?)
+    }
+}
@@ -21,4 +21,5 @@ impl Consts {
     pub const COMPRESS_METHOD_GZIP: i8 = 1;
     pub const COMPRESS_METHOD_DEFLATE: i8 = 2;
     pub const COMPRESS_METHOD_BROTLI: i8 = 3;
+    pub const COMPRESS_METHOD_ZSTD: i8 = 4;
 }
\ No newline at end of file
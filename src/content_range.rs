@@ -0,0 +1,70 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 15:32:40
+
+use webparse::HeaderMap;
+
+/// 响应头中的`Content-Range`, 格式为`bytes start-end/total`,
+/// 范围无法满足时(416)为`bytes */total`, 总大小未知时total为`*`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentRange {
+    /// 包含的字节范围, 首尾均包含在内
+    pub range: Option<(u64, u64)>,
+    /// 资源的总大小, 未知时为None
+    pub total: Option<u64>,
+}
+
+impl ContentRange {
+    pub fn parse(value: &str) -> Option<ContentRange> {
+        let value = value.trim();
+        let (unit, value) = value.split_once(' ')?;
+        if !unit.eq_ignore_ascii_case("bytes") {
+            return None;
+        }
+        let (range, total) = value.trim().split_once('/')?;
+        let total = match total.trim() {
+            "*" => None,
+            v => Some(v.parse::<u64>().ok()?),
+        };
+        let range = match range.trim() {
+            "*" if total.is_some() => None,
+            // 范围和总大小不能同时未知
+            "*" => return None,
+            v => {
+                let (start, end) = v.split_once('-')?;
+                let start = start.trim().parse::<u64>().ok()?;
+                let end = end.trim().parse::<u64>().ok()?;
+                if end < start || total.is_some_and(|t| end >= t) {
+                    return None;
+                }
+                Some((start, end))
+            }
+        };
+        Some(ContentRange { range, total })
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Option<ContentRange> {
+        Self::parse(&headers.get_str_value(&"Content-Range")?)
+    }
+
+    /// 本次包含的字节数
+    pub fn size(&self) -> Option<u64> {
+        self.range.map(|(start, end)| end - start + 1)
+    }
+
+    /// 是否已包含到资源的末尾, 总大小未知时返回false
+    pub fn is_last(&self) -> bool {
+        match (self.range, self.total) {
+            (Some((_, end)), Some(total)) => end + 1 == total,
+            _ => false,
+        }
+    }
+}
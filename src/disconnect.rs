@@ -0,0 +1,71 @@
+// Copyright 2022 - 2024 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/19 09:32:18
+
+use std::sync::{Arc, Mutex};
+
+use tokio_util::sync::CancellationToken;
+use webparse::http::http2::frame::{Reason, StreamIdentifier};
+
+use crate::ProtError;
+
+/// 对端断开的通知, 服务端放入请求的extensions中,
+/// 耗时的处理(如SSE或大文件下载)可与其`select!`, 对端断开时及时停止处理.
+/// HTTP/1在对端关闭连接时完成, HTTP/2在该流被重置或连接关闭时完成
+#[derive(Debug, Clone)]
+pub struct Disconnected {
+    token: Arc<CancellationToken>,
+    /// HTTP/2中被重置的流及重置的原因
+    reason: Arc<Mutex<Option<(StreamIdentifier, Reason)>>>,
+}
+
+impl Disconnected {
+    pub(crate) fn new(token: CancellationToken) -> Self {
+        Self {
+            token: Arc::new(token),
+            reason: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// 等待对端断开
+    pub async fn disconnected(&self) {
+        self.token.cancelled().await
+    }
+
+    /// 对端是否已断开
+    pub fn is_disconnected(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// 是否仍有请求方持有该通知
+    pub(crate) fn is_observed(&self) -> bool {
+        Arc::strong_count(&self.token) > 1
+    }
+
+    /// 流被对端重置时返回对应的错误, 其它断开的情况返回None
+    pub fn error(&self) -> Option<ProtError> {
+        self.reason
+            .lock()
+            .unwrap()
+            .map(|(id, reason)| ProtError::StreamReset(id, reason))
+    }
+
+    /// 对端断开, 通知持有方
+    pub(crate) fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// 流被对端重置, 记录原因后通知
+    pub(crate) fn reset(&self, stream_id: StreamIdentifier, reason: Reason) {
+        *self.reason.lock().unwrap() = Some((stream_id, reason));
+        self.token.cancel();
+    }
+}
@@ -16,8 +16,13 @@ use std::{
 };
 
 use algorithm::buf::Binary;
-use tokio::sync::mpsc::error::SendError;
-use webparse::{http::http2::frame::Reason, http2::frame::Settings, WebError};
+use tokio::sync::mpsc::error::{SendError, TrySendError};
+use tokio::time::error::Elapsed;
+use webparse::{
+    http::http2::frame::{GoAway, Reason, StreamIdentifier},
+    http2::frame::Settings,
+    WebError,
+};
 
 use crate::RecvRequest;
 
@@ -75,6 +80,10 @@ impl TimeoutError {
 pub enum ProtError {
     /// 标准错误库的错误类型
     IoError(io::Error),
+    /// 从连接读取数据时发生的错误
+    ReadError(io::Error),
+    /// 向连接写入数据时发生的错误
+    WriteError(io::Error),
     /// 解析库发生错误
     WebError(WebError),
     /// 其它错误信息
@@ -82,6 +91,8 @@ pub enum ProtError {
     Timeout(TimeoutError),
 
     SendError,
+    /// 通道已关闭, 参数表示所在的模块
+    ChannelClosed(&'static str),
     /// 协议数据升级, 第一参数表示将要写给客户端的消息, 第二参数表示原来未处理的请求
     ServerUpgradeHttp2(Binary, Option<RecvRequest>),
     /// 协议数据升级, 第一参数表示将要写给客户端的消息, 第二参数表示原来未处理的请求
@@ -91,8 +102,53 @@ pub enum ProtError {
     ClientUpgradeWs(RecvRequest),
     /// 发生错误或者收到关闭消息将要关闭该链接
     GoAway(Binary, Reason, Initiator),
+    /// 需直接以该状态码回复对端的错误, 如413
+    Status(u16, &'static str),
+    /// 对端以RST_STREAM重置了该流, 参数为被重置的流及重置的原因
+    StreamReset(StreamIdentifier, Reason),
+    /// 压缩数据时发生的错误, 参数为压缩方式及原始错误
+    Compress(&'static str, io::Error),
+    /// 解压数据时发生的错误, 参数为压缩方式及原始错误
+    Decompress(&'static str, io::Error),
 }
 
+/// 经由io::Error传递的压缩错误, 转为ProtError时还原为Compress或Decompress
+#[derive(Debug)]
+pub(crate) struct CompressError {
+    pub method: &'static str,
+    pub is_decompress: bool,
+    pub source: io::Error,
+}
+
+impl CompressError {
+    pub fn new_io(method: &'static str, is_decompress: bool, source: io::Error) -> io::Error {
+        io::Error::new(
+            source.kind(),
+            CompressError {
+                method,
+                is_decompress,
+                source,
+            },
+        )
+    }
+}
+
+impl Display for CompressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = if self.is_decompress {
+            "decompress"
+        } else {
+            "compress"
+        };
+        f.write_fmt(format_args!(
+            "{} {} error: {}",
+            self.method, op, self.source
+        ))
+    }
+}
+
+impl std::error::Error for CompressError {}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Initiator {
     User,
@@ -104,8 +160,19 @@ impl Display for ProtError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ProtError::IoError(_) => f.write_str("io error"),
+            ProtError::ReadError(_) => f.write_str("read error"),
+            ProtError::WriteError(_) => f.write_str("write error"),
             ProtError::WebError(w) => w.fmt(f),
-            ProtError::GoAway(_, _, _) => f.write_str("go away frame"),
+            ProtError::GoAway(debug_data, reason, _) => {
+                f.write_fmt(format_args!("go away frame {:?}", reason))?;
+                if !debug_data.as_slice().is_empty() {
+                    f.write_fmt(format_args!(
+                        " {}",
+                        String::from_utf8_lossy(debug_data.as_slice())
+                    ))?;
+                }
+                Ok(())
+            }
             ProtError::Extension(s) => f.write_fmt(format_args!("extension {}", s)),
             ProtError::Timeout(t) => t.fmt(f),
             ProtError::ServerUpgradeHttp2(_, _) => f.write_str("receive server upgrade http2 info"),
@@ -113,19 +180,43 @@ impl Display for ProtError {
             ProtError::ServerUpgradeWs(_) => f.write_str("receive server upgrade ws info"),
             ProtError::ClientUpgradeWs(_) => f.write_str("receive client upgrade ws info"),
             ProtError::SendError => f.write_str("send erorr"),
+            ProtError::ChannelClosed(s) => f.write_fmt(format_args!("channel closed {}", s)),
+            ProtError::Status(code, s) => f.write_fmt(format_args!("status {} {}", code, s)),
+            ProtError::StreamReset(id, r) => {
+                f.write_fmt(format_args!("stream {:?} reset {:?}", id, r))
+            }
+            ProtError::Compress(m, e) => f.write_fmt(format_args!("{} compress error: {}", m, e)),
+            ProtError::Decompress(m, e) => {
+                f.write_fmt(format_args!("{} decompress error: {}", m, e))
+            }
         }
     }
 }
 
 impl From<io::Error> for ProtError {
     fn from(value: io::Error) -> Self {
+        if value.get_ref().map_or(false, |e| e.is::<CompressError>()) {
+            let e = value
+                .into_inner()
+                .unwrap()
+                .downcast::<CompressError>()
+                .unwrap();
+            if e.is_decompress {
+                return ProtError::Decompress(e.method, e.source);
+            }
+            return ProtError::Compress(e.method, e.source);
+        }
         ProtError::IoError(value)
     }
 }
 
 impl From<WebError> for ProtError {
     fn from(value: WebError) -> Self {
-        ProtError::WebError(value)
+        match value {
+            // 包体处理中的压缩错误经由WebError传递, 还原为对应的类型
+            WebError::Io(e) if e.get_ref().map_or(false, |e| e.is::<CompressError>()) => e.into(),
+            value => ProtError::WebError(value),
+        }
     }
 }
 
@@ -135,6 +226,21 @@ impl<T> From<SendError<T>> for ProtError {
     }
 }
 
+impl<T> From<TrySendError<T>> for ProtError {
+    fn from(value: TrySendError<T>) -> Self {
+        match value {
+            TrySendError::Full(_) => ProtError::SendError,
+            TrySendError::Closed(_) => ProtError::ChannelClosed("try send"),
+        }
+    }
+}
+
+impl From<Elapsed> for ProtError {
+    fn from(_: Elapsed) -> Self {
+        ProtError::Timeout(TimeoutError::Extension("elapsed"))
+    }
+}
+
 unsafe impl Send for ProtError {}
 
 unsafe impl Sync for ProtError {}
@@ -144,6 +250,31 @@ impl ProtError {
         Self::GoAway(Binary::new(), reason, Initiator::Library)
     }
 
+    pub(crate) fn remote_go_away(frame: &GoAway) -> Self {
+        Self::GoAway(frame.debug_data().clone(), frame.reason(), Initiator::Remote)
+    }
+
+    /// GOAWAY的原因
+    pub fn go_away_reason(&self) -> Option<Reason> {
+        match self {
+            Self::GoAway(_, reason, _) => Some(*reason),
+            _ => None,
+        }
+    }
+
+    /// GOAWAY附带的调试数据, 对端发送时常包含关闭的原因
+    pub fn go_away_debug_data(&self) -> Option<&Binary> {
+        match self {
+            Self::GoAway(debug_data, _, _) => Some(debug_data),
+            _ => None,
+        }
+    }
+
+    /// 是否为对端发送的GOAWAY
+    pub fn is_remote_go_away(&self) -> bool {
+        matches!(self, Self::GoAway(_, _, Initiator::Remote))
+    }
+
     pub fn is_timeout(&self) -> (bool, bool) {
         match self {
             Self::Timeout(timeout) => (true, timeout.is_client()),
@@ -151,13 +282,47 @@ impl ProtError {
         }
     }
 
+    pub fn channel_closed(val: &'static str) -> Self {
+        Self::ChannelClosed(val)
+    }
+
+    pub fn is_channel_closed(&self) -> bool {
+        match self {
+            Self::ChannelClosed(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn is_io(&self) -> bool {
         match self {
-            Self::IoError(_) => true,
+            Self::IoError(_) | Self::ReadError(_) | Self::WriteError(_) => true,
             _ => false,
         }
     }
 
+    /// 压缩或解压出错时的压缩方式
+    pub fn compress_method(&self) -> Option<&'static str> {
+        match self {
+            Self::Compress(method, _) | Self::Decompress(method, _) => Some(method),
+            _ => None,
+        }
+    }
+
+    /// 是否为解压数据时发生的错误
+    pub fn is_decompress(&self) -> bool {
+        matches!(self, Self::Decompress(_, _))
+    }
+
+    /// 是否为读取连接时发生的错误
+    pub fn is_read_error(&self) -> bool {
+        matches!(self, Self::ReadError(_))
+    }
+
+    /// 是否为写入连接时发生的错误
+    pub fn is_write_error(&self) -> bool {
+        matches!(self, Self::WriteError(_))
+    }
+
     pub fn is_read_timeout(&self) -> (bool, bool) {
         match self {
             Self::Timeout(timeout) => timeout.is_read(),
@@ -172,6 +337,37 @@ impl ProtError {
         }
     }
 
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            Self::Status(code, _) => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// 流被对端重置的原因
+    pub fn stream_reset_reason(&self) -> Option<Reason> {
+        match self {
+            Self::StreamReset(_, reason) => Some(*reason),
+            _ => None,
+        }
+    }
+
+    /// 被对端重置的流
+    pub fn stream_reset_id(&self) -> Option<StreamIdentifier> {
+        match self {
+            Self::StreamReset(id, _) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// 请求是否可以安全重试, 如REFUSED_STREAM表示服务端未处理该流
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::StreamReset(_, reason) => *reason == Reason::REFUSED_STREAM,
+            _ => false,
+        }
+    }
+
     pub fn is_server_upgrade_http2(&self) -> bool {
         match self {
             Self::ServerUpgradeHttp2(_, _) => true,
@@ -12,7 +12,7 @@
 
 use webparse::{Serialize, Request, Response, HeaderName, HeaderMap, Version};
 
-use crate::{Body, ProtResult, Consts, RecvResponse, RecvRequest};
+use crate::{Body, ProtError, ProtResult, Consts, RecvResponse, RecvRequest};
 
 pub struct HeaderHelper;
 
@@ -61,6 +61,8 @@ impl HeaderHelper {
                 return Consts::COMPRESS_METHOD_DEFLATE;
             } else if value.contains(b"br") {
                 return Consts::COMPRESS_METHOD_BROTLI;
+            } else if value.contains(b"zstd") {
+                return Consts::COMPRESS_METHOD_ZSTD;
             }
         };
         return Consts::COMPRESS_METHOD_NONE;
@@ -72,6 +74,13 @@ impl HeaderHelper {
             headers.remove(&HeaderName::TRANSFER_ENCODING);
             headers.remove(&HeaderName::CONNECTION);
             headers.remove(&"Keep-Alive");
+            // HTTP/2中TE仅允许为trailers, 如gRPC的请求
+            if headers
+                .get_str_value(&"TE")
+                .is_some_and(|v| !v.trim().eq_ignore_ascii_case("trailers"))
+            {
+                headers.remove(&"TE");
+            }
         }
         let is_chunked = headers.is_chunked();
         let compress = if is_client {
@@ -88,6 +97,14 @@ impl HeaderHelper {
                 let len = body.body_len();
                 headers.insert(HeaderName::CONTENT_LENGTH, len);
                 
+            } else if !is_chunked && header_body_len == 0 && body.is_reader() {
+                // 读取器的包体已知大小时设置长度, 否则以chunked的方式发送
+                if let Some(len) = body.size_hint() {
+                    headers.insert(HeaderName::CONTENT_LENGTH, len.to_string());
+                } else if version.is_http1() {
+                    headers.insert(HeaderName::TRANSFER_ENCODING, "chunked");
+                    body.set_chunked(true);
+                }
             }
         } else {
             if header_body_len == 0 {
@@ -114,6 +131,165 @@ impl HeaderHelper {
         Ok(())
     }
 
+    /// 移除逐跳的头及Connection中列出的头, 用于代理转发.
+    /// Transfer-Encoding保留, 包体仍以相同的方式分帧, Trailer声明的trailer头随包体转发
+    pub fn remove_hop_headers(headers: &mut HeaderMap) {
+        if let Some(value) = headers.get_str_value(&HeaderName::CONNECTION) {
+            for token in Self::split_list(&value) {
+                if !token.eq_ignore_ascii_case("Transfer-Encoding") {
+                    headers.remove(&HeaderName::from(token));
+                }
+            }
+        }
+        headers.remove(&HeaderName::CONNECTION);
+        for name in ["Keep-Alive", "Proxy-Connection", "TE", "Upgrade"] {
+            headers.remove(&name);
+        }
+    }
+
+    /// 该头的多个值是否可以用逗号合并, Set-Cookie等值中可能含有逗号的头需逐个保留
+    pub fn is_foldable(name: &str) -> bool {
+        !["set-cookie", "set-cookie2", "www-authenticate", "proxy-authenticate"]
+            .iter()
+            .any(|n| name.eq_ignore_ascii_case(n))
+    }
+
+    /// 只能出现一次的头, 重复时值必须相同
+    pub fn is_singleton(name: &str) -> bool {
+        [
+            "content-length",
+            "content-type",
+            "host",
+            "authorization",
+            "proxy-authorization",
+            "location",
+            "etag",
+            "last-modified",
+            "if-modified-since",
+            "if-unmodified-since",
+            "max-forwards",
+            "age",
+            "expires",
+            "retry-after",
+        ]
+        .iter()
+        .any(|n| name.eq_ignore_ascii_case(n))
+    }
+
+    /// 检查只能出现一次的头, 如Content-Length重复且值不同时返回400
+    pub fn check_singletons(headers: &[(String, String)]) -> ProtResult<()> {
+        for (i, (name, value)) in headers.iter().enumerate() {
+            if !Self::is_singleton(name) {
+                continue;
+            }
+            let is_conflict = headers[..i]
+                .iter()
+                .any(|(n, v)| n.eq_ignore_ascii_case(name) && v.trim() != value.trim());
+            if is_conflict {
+                log::trace!("重复的头且值不同:{}", name);
+                return Err(ProtError::Status(400, "duplicate singleton header"));
+            }
+        }
+        Ok(())
+    }
+
+    /// 规范化头名称, 如`content-type`转为`Content-Type`
+    pub fn canonical_name(name: &str) -> String {
+        name.split('-')
+            .map(|part| {
+                let mut chars = part.chars();
+                match chars.next() {
+                    Some(c) => {
+                        c.to_ascii_uppercase().to_string() + &chars.as_str().to_ascii_lowercase()
+                    }
+                    None => String::new(),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("-")
+    }
+
+    /// 拆分列表头的值, 如`gzip, br`拆为`gzip`及`br`, 忽略空值, 引号内的逗号不拆分
+    pub fn split_list(value: &str) -> Vec<String> {
+        let mut list = vec![];
+        let mut item = String::new();
+        let mut in_quote = false;
+        for c in value.chars() {
+            match c {
+                '"' => {
+                    in_quote = !in_quote;
+                    item.push(c);
+                }
+                ',' if !in_quote => {
+                    if !item.trim().is_empty() {
+                        list.push(item.trim().to_string());
+                    }
+                    item.clear();
+                }
+                _ => item.push(c),
+            }
+        }
+        if !item.trim().is_empty() {
+            list.push(item.trim().to_string());
+        }
+        list
+    }
+
+    /// 合并重复的头并规范化名称, 用于代理或改写头时:
+    /// 不可合并的头(如Set-Cookie)每个值单独保留, 只能出现一次的头保留首个值,
+    /// 其它头的值以`, `合并并去除重复值, 保持原有顺序
+    pub fn fold_headers(headers: Vec<(String, String)>) -> Vec<(String, String)> {
+        let mut result: Vec<(String, String)> = vec![];
+        let mut folded: Vec<(usize, Vec<String>)> = vec![];
+        for (name, value) in headers {
+            let name = Self::canonical_name(name.trim());
+            if Self::is_singleton(&name) {
+                if !result.iter().any(|(n, _)| *n == name) {
+                    result.push((name, value));
+                }
+                continue;
+            }
+            if !Self::is_foldable(&name) {
+                result.push((name, value));
+                continue;
+            }
+            match folded.iter_mut().find(|(idx, _)| result[*idx].0 == name) {
+                Some((_, values)) => {
+                    for v in Self::split_list(&value) {
+                        if !values.contains(&v) {
+                            values.push(v);
+                        }
+                    }
+                }
+                None => {
+                    folded.push((result.len(), Self::split_list(&value)));
+                    result.push((name, value));
+                }
+            }
+        }
+        for (idx, values) in folded {
+            result[idx].1 = values.join(", ");
+        }
+        result
+    }
+
+    /// 按fold_headers整理头, 只能出现一次的头重复且值不同时返回错误
+    pub fn fold_header_map(headers: &mut HeaderMap) -> ProtResult<()> {
+        let list: Vec<(String, String)> = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Self::check_singletons(&list)?;
+        let folded = Self::fold_headers(list);
+        for (name, _) in &folded {
+            headers.remove(&HeaderName::from(name.clone()));
+        }
+        for (name, value) in folded {
+            headers.push(name, value);
+        }
+        Ok(())
+    }
+
     pub fn process_request_header(version: Version, is_client: bool, req: &mut RecvRequest) -> ProtResult<()> {
         let (h, b) = req.headers_body_mut();
         Self::process_headers(version, is_client, h, b)?;
@@ -52,6 +52,16 @@ where
         self.io.into_io()
     }
 
+    /// 设置每次读取的缓冲区大小, 默认16KB
+    pub fn set_read_buf_size(&mut self, read_buf_size: usize) {
+        self.io.set_read_buf_size(read_buf_size);
+    }
+
+    /// 设置响应头的最大大小, 超出时返回431的错误并关闭连接
+    pub fn set_max_header_size(&mut self, max_header_size: usize) {
+        self.io.set_max_header_size(max_header_size);
+    }
+
     pub fn set_timeout_layer(&mut self, timeout_layer: Option<TimeoutLayer>) {
         self.timeout = timeout_layer;
     }
@@ -171,6 +181,15 @@ where
                 is_write_end,
                 is_idle,
             )?;
+            let (read_time, write_time) = (*self.io.get_read_time(), *self.io.get_write_time());
+            self.timeout.as_mut().unwrap().poll_idle_ready(
+                cx,
+                "client",
+                read_time,
+                write_time,
+                is_read_end,
+                is_write_end,
+            )?;
         }
         Pin::new(&mut self.io).poll_response(cx)
     }
@@ -11,19 +11,28 @@
 // Created Date: 2023/09/14 09:42:25
 
 use std::{
-    collections::LinkedList,
+    collections::{LinkedList, VecDeque},
+    fs::File,
+    future::Future,
     pin::Pin,
     task::{ready, Context, Poll},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use algorithm::buf::{Binary, BinaryMut, Bt, BtMut};
 use tokio::{
     io::{AsyncRead, AsyncWrite, ReadBuf},
-    sync::mpsc::Sender,
+    sync::mpsc::{error::TrySendError, Sender},
+    time::Sleep,
 };
 
-use crate::{Body, HeaderHelper, ProtError, ProtResult, RecvRequest, RecvResponse, SendStream};
+use crate::{
+    body::{body_pipe, PipeSender, TrailerSlot},
+    http1::{send_file, SendFileFn},
+    http_helper::AsteriskForm,
+    Body, HeaderHelper, ProtError, ProtResult, RecvRequest, RecvResponse, RequestId, SendStream,
+    Timings,
+};
 use webparse::{http::http2, Request, Response, Version};
 
 pub struct IoBuffer<T> {
@@ -35,17 +44,54 @@ pub struct IoBuffer<T> {
 
     inner: ConnectionInfo,
 
+    /// 请求行的最大长度, 超出返回414
+    max_request_line_bytes: usize,
+    /// 消息头的最大大小, 未结束的消息头超出时返回431
+    max_header_size: usize,
+    /// 每次从socket读取前预留的缓冲区大小
+    read_buf_size: usize,
+    /// 请求包体直接从连接的缓冲区读取, 不经过通道
+    is_stream_body: bool,
+    /// 请求头及包体各自的最长读取时间, 超出后回复408
+    read_timeout: Option<Duration>,
+    read_timeout_sleep: Option<Pin<Box<Sleep>>>,
+    /// 当前读取阶段(是否为包体)及开始的时间
+    read_start: Option<(bool, Instant)>,
+
     ready_time: Instant,
+    /// 最后一次读取到数据的时间
+    read_time: Instant,
+    /// 最后一次写出数据的时间
+    write_time: Instant,
+    /// 客户端各请求开始发送的时间, 按响应顺序取出
+    request_times: VecDeque<Instant>,
+    /// 当前响应收到第一个字节的时间
+    first_byte_time: Option<Instant>,
+
+    /// 连接支持时直接发送纯文件响应的方法, 为None时经过缓冲区发送
+    send_file_fn: Option<SendFileFn<T>>,
+    /// 正在直接发送的文件, 剩余的大小及是否已发送过数据
+    send_file: Option<(File, u64, bool)>,
 }
 
 struct ConnectionInfo {
     deal_req: usize,
     read_sender: Option<Sender<(bool, Binary)>>,
+    /// 流式读取的请求包体的写入端
+    read_pipe: Option<PipeSender>,
+    /// 当前包体的trailer头写入位置
+    read_trailers: Option<TrailerSlot>,
     res_list: LinkedList<RecvResponse>,
     req_list: LinkedList<RecvRequest>,
     is_keep_alive: bool,
     is_delay_close: bool,
     is_idle: bool,
+    /// 请求带有`Expect: 100-continue`, 等待处理函数决定是否继续
+    is_expect_continue: bool,
+    /// 待发送的100 Continue
+    is_send_continue: bool,
+    /// 响应发送完毕后关闭连接
+    is_close_after_write: bool,
 
     req_status: SendStatus,
     res_status: SendStatus,
@@ -118,17 +164,37 @@ where
             inner: ConnectionInfo {
                 deal_req: 0,
                 read_sender: None,
+                read_pipe: None,
+                read_trailers: None,
                 res_list: LinkedList::new(),
                 req_list: LinkedList::new(),
                 is_keep_alive: false,
                 is_delay_close: false,
                 is_idle: true,
+                is_expect_continue: false,
+                is_send_continue: false,
+                is_close_after_write: false,
 
                 req_status: SendStatus::default(),
                 res_status: SendStatus::default(),
             },
 
+            max_request_line_bytes: 65_536,
+            max_header_size: 131_072,
+            read_buf_size: 16_384,
+            is_stream_body: false,
+            read_timeout: None,
+            read_timeout_sleep: None,
+            read_start: None,
+
             ready_time: Instant::now(),
+            read_time: Instant::now(),
+            write_time: Instant::now(),
+            request_times: VecDeque::new(),
+            first_byte_time: None,
+
+            send_file_fn: None,
+            send_file: None,
         }
     }
 
@@ -140,10 +206,155 @@ where
         self.send_stream.read_buf.put_slice(binary.as_slice());
     }
 
+    pub fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.send_stream.set_max_chunk_size(max_chunk_size);
+    }
+
+    pub fn set_max_body_size(&mut self, max_body_size: usize) {
+        self.send_stream.set_max_body_size(max_body_size);
+    }
+
+    pub fn set_max_request_line_bytes(&mut self, max_request_line_bytes: usize) {
+        self.max_request_line_bytes = max_request_line_bytes;
+    }
+
+    pub fn set_max_header_size(&mut self, max_header_size: usize) {
+        self.max_header_size = max_header_size;
+        // trailer头与消息头使用相同的大小限制
+        self.send_stream.set_max_trailer_size(max_header_size);
+    }
+
+    pub fn set_read_buf_size(&mut self, read_buf_size: usize) {
+        self.read_buf_size = read_buf_size.max(1);
+    }
+
+    /// 设置直接发送文件的方法, 仅对无压缩无分块的纯文件响应生效
+    pub fn set_send_file(&mut self, send_file_fn: Option<SendFileFn<T>>) {
+        self.send_file_fn = send_file_fn;
+    }
+
+    pub fn set_stream_body(&mut self, is_stream_body: bool) {
+        self.is_stream_body = is_stream_body;
+    }
+
+    pub fn set_read_timeout(&mut self, read_timeout: Option<Duration>) {
+        self.read_timeout = read_timeout;
+    }
+
+    pub fn get_read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// 更新当前读取阶段的开始时间, 请求头从收到第一个字节开始计算, 包体从请求头结束开始计算
+    fn update_read_start(&mut self) {
+        let is_body = self.inner.req_status.is_read_header_end;
+        if !is_body && self.send_stream.read_buf.is_empty() {
+            self.read_start = None;
+            return;
+        }
+        match self.read_start {
+            Some((phase, _)) if phase == is_body => {}
+            _ => self.read_start = Some((is_body, Instant::now())),
+        }
+    }
+
+    /// 检查当前读取阶段是否超时, 未超时则在到期时唤醒
+    fn poll_read_timeout(&mut self, cx: &mut Context<'_>) -> ProtResult<()> {
+        self.update_read_start();
+        let (Some(timeout), Some((_, start))) = (self.read_timeout, self.read_start) else {
+            return Ok(());
+        };
+        let deadline = start + timeout;
+        if Instant::now() >= deadline {
+            self.read_start = None;
+            return Err(ProtError::Status(408, "request timeout"));
+        }
+        match &mut self.read_timeout_sleep {
+            Some(sleep) => sleep.as_mut().reset(deadline.into()),
+            None => {
+                self.read_timeout_sleep = Some(Box::pin(tokio::time::sleep_until(deadline.into())))
+            }
+        }
+        let _ = self.read_timeout_sleep.as_mut().unwrap().as_mut().poll(cx);
+        Ok(())
+    }
+
+    /// 在完整解析前检查请求行长度, 避免超长的请求行占用内存
+    fn check_request_line(&self) -> ProtResult<()> {
+        let buf = self.send_stream.read_buf.chunk();
+        let check_len = std::cmp::min(buf.len(), self.max_request_line_bytes + 1);
+        let line_len = buf[..check_len]
+            .iter()
+            .position(|c| *c == b'\n')
+            .unwrap_or(check_len);
+        if line_len > self.max_request_line_bytes {
+            return Err(ProtError::Status(414, "request line too long"));
+        }
+        Ok(())
+    }
+
+    /// 请求目标为asterisk-form时返回`*`在缓冲区中的位置, 仅OPTIONS请求允许使用
+    fn check_asterisk_form(&self) -> ProtResult<Option<usize>> {
+        let buf = self.send_stream.read_buf.chunk();
+        let line_len = buf.iter().position(|c| *c == b'\n').unwrap_or(buf.len());
+        let line = &buf[..line_len];
+        let Some(method_len) = line.iter().position(|c| *c == b' ') else {
+            return Ok(None);
+        };
+        if !line[method_len + 1..].starts_with(b"* ") {
+            return Ok(None);
+        }
+        if &line[..method_len] != b"OPTIONS" {
+            return Err(ProtError::Status(400, "invalid asterisk-form target"));
+        }
+        Ok(Some(method_len + 1))
+    }
+
+    /// 在完整解析前检查消息头的大小, 避免不结束的消息头无限占用内存
+    fn check_header_size(&self, reason: &'static str) -> ProtResult<()> {
+        let buf = self.send_stream.read_buf.chunk();
+        if buf.len() <= self.max_header_size {
+            return Ok(());
+        }
+        // 限制内已有空行则消息头已完整, 之后为包体或流水线中的请求
+        let limit = &buf[..self.max_header_size];
+        let is_end =
+            limit.windows(2).any(|w| w == b"\n\n") || limit.windows(3).any(|w| w == b"\n\r\n");
+        if !is_end {
+            return Err(ProtError::Status(431, reason));
+        }
+        Ok(())
+    }
+
+    /// 检查请求头中是否含有已废弃的折行(obs-fold), 可被用于请求走私, 直接返回400
+    fn check_obs_fold(&self) -> ProtResult<()> {
+        let buf = self.send_stream.read_buf.chunk();
+        for i in 1..buf.len() {
+            if buf[i - 1] != b'\n' {
+                continue;
+            }
+            match buf[i] {
+                b' ' | b'\t' => return Err(ProtError::Status(400, "obs-fold header")),
+                // 请求头已结束
+                b'\r' | b'\n' => break,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_ready_time(&self) -> &Instant {
         &self.ready_time
     }
 
+    pub fn get_read_time(&self) -> &Instant {
+        &self.read_time
+    }
+
+    pub fn get_write_time(&self) -> &Instant {
+        &self.write_time
+    }
+
     pub fn check_finish_status(&mut self) {
         if (self.inner.req_list.is_empty() || self.inner.req_status.is_send_finish)
             && (self.inner.res_list.is_empty() || self.inner.res_status.is_send_finish)
@@ -173,6 +384,11 @@ where
     }
 
     pub fn poll_write(&mut self, cx: &mut Context<'_>) -> Poll<ProtResult<usize>> {
+        // 之前的响应已全部写入后才发送100 Continue
+        if self.inner.is_send_continue && self.inner.res_list.is_empty() {
+            self.write_buf.put_slice(b"HTTP/1.1 100 Continue\r\n\r\n");
+            self.inner.is_send_continue = false;
+        }
         if let Some(res) = self.inner.res_list.front_mut() {
             if !self.inner.res_status.is_send_header {
                 self.inner.res_status.is_chunked = res.headers().is_chunked();
@@ -181,12 +397,22 @@ where
                 self.inner.res_status.is_send_header = true;
             }
 
-            if !res.body().is_end() || !self.inner.res_status.is_send_body {
+            if !self.inner.res_status.is_send_body
+                && self.send_file_fn.is_some()
+                && !self.inner.res_status.is_chunked
+            {
+                self.send_file = Self::take_send_file(res.body_mut());
+                self.inner.res_status.is_send_body = self.send_file.is_some();
+            }
+
+            if self.send_file.is_none()
+                && (!res.body().is_end() || !self.inner.res_status.is_send_body)
+            {
                 self.inner.res_status.is_send_body = true;
                 let _ = res.body_mut().poll_encode_write(cx, &mut self.write_buf);
             }
 
-            if res.body().is_end() {
+            if res.body().is_end() && self.send_file.is_none() {
                 self.inner.res_status.is_send_finish = true;
                 self.inner.deal_req += 1;
             }
@@ -221,13 +447,24 @@ where
         }
 
         if self.write_buf.is_empty() {
+            if self.send_file.is_some() {
+                return self.poll_send_file(cx);
+            }
             return Poll::Ready(Ok(0));
         }
 
-        match ready!(Pin::new(&mut self.io).poll_write(cx, &self.write_buf.chunk()))? {
+        match ready!(Pin::new(&mut self.io).poll_write(cx, &self.write_buf.chunk()))
+            .map_err(ProtError::WriteError)?
+        {
             n => {
+                if n > 0 {
+                    self.write_time = Instant::now();
+                }
                 self.write_buf.advance(n);
                 if self.write_buf.is_empty() {
+                    if self.send_file.is_some() {
+                        return self.poll_send_file(cx);
+                    }
                     return Poll::Ready(Ok(n));
                 }
             }
@@ -235,12 +472,64 @@ where
         Poll::Pending
     }
 
+    /// 取出纯文件响应的文件, 文件仍有未完成的异步操作时保留原包体
+    fn take_send_file(body: &mut Body) -> Option<(File, u64, bool)> {
+        let (file, size) = body.take_plain_file()?;
+        match file.try_into_std() {
+            Ok(file) => Some((file, size, false)),
+            Err(file) => {
+                *body = Body::new_file(file, size);
+                None
+            }
+        }
+    }
+
+    /// 消息头发送完毕后直接发送文件, 完成后继续处理后续的响应
+    fn poll_send_file(&mut self, cx: &mut Context<'_>) -> Poll<ProtResult<usize>> {
+        let send_file_fn = match self.send_file_fn {
+            Some(f) => f,
+            None => return Poll::Ready(Ok(0)),
+        };
+        while let Some((file, left, is_start)) = &mut self.send_file {
+            if *left == 0 {
+                break;
+            }
+            let count = (*left).min(0x7fff_f000) as usize;
+            match ready!(send_file_fn(&mut self.io, cx, file, count)) {
+                Ok(0) => {
+                    return Poll::Ready(Err(ProtError::Extension(
+                        "file shorter than content length",
+                    )));
+                }
+                Ok(n) => {
+                    self.write_time = Instant::now();
+                    *left -= n as u64;
+                    *is_start = true;
+                }
+                Err(e) if !*is_start && send_file::is_unsupported(&e) => {
+                    // 不支持时该连接后续均改用缓冲区发送
+                    log::trace!("连接不支持sendfile, 改用缓冲区发送: {:?}", e);
+                    let (file, left, _) = self.send_file.take().unwrap();
+                    self.send_file_fn = None;
+                    if let Some(res) = self.inner.res_list.front_mut() {
+                        *res.body_mut() = Body::new_file(tokio::fs::File::from_std(file), left);
+                    }
+                    return self.poll_write(cx);
+                }
+                Err(e) => return Poll::Ready(Err(ProtError::WriteError(e))),
+            }
+        }
+        self.send_file = None;
+        self.poll_write(cx)
+    }
+
     pub fn poll_read(&mut self, cx: &mut Context<'_>) -> Poll<ProtResult<usize>> {
-        self.send_stream.read_buf.reserve(1);
+        // 预留足够的空间, 减少读取的系统调用次数
+        self.send_stream.read_buf.reserve(self.read_buf_size);
         let n = {
             let mut buf = ReadBuf::uninit(self.send_stream.read_buf.chunk_mut());
             let ptr = buf.filled().as_ptr();
-            ready!(Pin::new(&mut self.io).poll_read(cx, &mut buf)?);
+            ready!(Pin::new(&mut self.io).poll_read(cx, &mut buf)).map_err(ProtError::ReadError)?;
             assert_eq!(ptr, buf.filled().as_ptr());
             buf.filled().len()
         };
@@ -248,10 +537,39 @@ where
         unsafe {
             self.send_stream.read_buf.advance_mut(n);
         }
+        if n > 0 {
+            self.read_time = Instant::now();
+        }
         self.send_stream.process_data()?;
         Poll::Ready(Ok(n))
     }
 
+    /// 处理函数运行期间检测对端是否已关闭连接, 读到的流水线数据保留在缓冲区中待之后解析
+    pub fn poll_peer_closed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            // 已缓存过多的流水线数据, 暂不读取
+            if self.send_stream.read_buf.chunk().len() > self.max_header_size {
+                return Poll::Pending;
+            }
+            self.send_stream.read_buf.reserve(self.read_buf_size);
+            let n = {
+                let mut buf = ReadBuf::uninit(self.send_stream.read_buf.chunk_mut());
+                match Pin::new(&mut self.io).poll_read(cx, &mut buf) {
+                    Poll::Ready(Ok(())) => buf.filled().len(),
+                    Poll::Ready(Err(_)) => return Poll::Ready(()),
+                    Poll::Pending => return Poll::Pending,
+                }
+            };
+            if n == 0 {
+                return Poll::Ready(());
+            }
+            unsafe {
+                self.send_stream.read_buf.advance_mut(n);
+            }
+            self.read_time = Instant::now();
+        }
+    }
+
     pub fn poll_read_all(&mut self, cx: &mut Context<'_>) -> Poll<ProtResult<usize>> {
         let mut size = 0;
         loop {
@@ -285,83 +603,164 @@ where
         if n == Poll::Ready(0) && self.inner.is_active_close() && self.write_buf.is_empty() {
             return Poll::Ready(None);
         }
-        match ready!(self.poll_read_all(cx)?) {
-            // socket被断开, 提前结束
-            0 => {
-                log::trace!("收到socket的关闭信号, 关闭当前socket");
+        if self.inner.is_close_after_write {
+            if self.inner.res_list.is_empty() && self.write_buf.is_empty() {
                 return Poll::Ready(None);
             }
-            // 收到新的消息头, 解析包体消息
-            _n @ _ => {
-                if self.inner.req_status.is_read_header_end {
-                    self.do_deal_body(true)?;
-
-                    if self.inner.req_status.is_read_finish {
-                        self.inner.req_status.clear_read();
-                        self.send_stream.set_end_headers(false);
-                    }
-                    // 如果还有数据可能是keep-alive继续读取头信息
-                    if self.send_stream.read_buf.is_empty()
-                        && !self.inner.req_status.is_read_header_end
-                    {
-                        return Poll::Pending;
-                    }
+            return Poll::Pending;
+        }
+        // 处理函数未及时读取包体时暂停读取socket
+        ready!(self.poll_deal_pipe(cx)?);
+        let read = self.poll_read_all(cx)?;
+        if let Err(e) = self.poll_read_timeout(cx) {
+            log::trace!("读取请求超时, 回复408");
+            return Poll::Ready(Some(Err(e)));
+        }
+        let mut is_eof = false;
+        match read {
+            Poll::Ready(0) => {
+                // socket被断开, 已收到的流水线请求仍需处理
+                if self.send_stream.read_buf.is_empty() {
+                    log::trace!("收到socket的关闭信号, 关闭当前socket");
+                    return Poll::Ready(None);
                 }
-                let mut request = Request::new();
-                let size = match request.parse_buffer(&mut self.send_stream.read_buf.clone()) {
-                    Err(e) => {
-                        if e.is_partial() {
-                            return Poll::Pending;
-                        } else {
-                            if self.send_stream.read_buf.remaining() >= http2::MAIGC_LEN
-                                && &self.send_stream.read_buf[..http2::MAIGC_LEN]
-                                    == http2::HTTP2_MAGIC
-                            {
-                                // self.read_buf.advance(http2::MAIGC_LEN);
-                                let err = ProtError::ServerUpgradeHttp2(Binary::new(), None);
-                                return Poll::Ready(Some(Err(err)));
-                            }
-                            return Poll::Ready(Some(Err(e.into())));
-                        }
-                    }
-                    Ok(n) => n,
-                };
-                // let size = request.parse_buffer(&mut self.read_buf.clone())?;
-                if request.is_partial() {
+                is_eof = true;
+            }
+            Poll::Ready(_) => {}
+            // 缓冲区中已有后续的流水线请求, 无需等待socket的新数据
+            Poll::Pending => {
+                if self.send_stream.read_buf.is_empty() {
                     return Poll::Pending;
                 }
-                self.send_stream.set_new_body();
-                let method = HeaderHelper::get_compress_method(request.headers());
+            }
+        }
+        // socket已断开时不会再有新数据, 无法继续解析则关闭
+        let pending = move || {
+            if is_eof {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            }
+        };
+        if self.inner.req_status.is_read_header_end {
+            if self.inner.read_pipe.is_some() {
+                ready!(self.poll_deal_pipe(cx)?);
+            } else {
+                self.do_deal_body(true)?;
 
-                self.send_stream.read_buf.advance(size);
-                self.inner.req_status.is_send_body = false;
-                self.inner.req_status.is_send_finish = false;
-                self.inner.req_status.is_read_header_end = true;
-                self.inner.is_keep_alive = request.is_keep_alive();
-                let body_len = request.get_body_len();
-                self.inner.req_status.left_read_body_len = if body_len < 0 {
-                    usize::MAX
+                if self.inner.req_status.is_read_finish {
+                    self.inner.req_status.clear_read();
+                    self.send_stream.set_end_headers(false);
+                }
+            }
+            // 包体未读取完毕, 剩余数据不能作为新的请求解析
+            if self.inner.req_status.is_read_header_end {
+                return pending();
+            }
+            // 如果还有数据可能是keep-alive继续读取头信息
+            if self.send_stream.read_buf.is_empty() {
+                return pending();
+            }
+        }
+        // 收到新的消息头, 解析包体消息
+        self.check_request_line()?;
+        self.check_header_size("request header fields too large")?;
+        self.check_obs_fold()?;
+        let asterisk = self.check_asterisk_form()?;
+        let mut buf = match asterisk {
+            // 以长度相同的`/`代替`*`解析, 解析的长度保持不变
+            Some(pos) => {
+                let mut data = self.send_stream.read_buf.chunk().to_vec();
+                data[pos] = b'/';
+                BinaryMut::from(data)
+            }
+            None => self.send_stream.read_buf.clone(),
+        };
+        let mut request = Request::new();
+        let size = match request.parse_buffer(&mut buf) {
+            Err(e) => {
+                if e.is_partial() {
+                    return pending();
                 } else {
-                    body_len as usize
-                };
-                if !request.method().is_nobody() && body_len == 0 {
-                    self.inner.req_status.left_read_body_len = usize::MAX;
-                    if request.headers().is_chunked() {
-                        self.inner.req_status.is_chunked = true;
+                    if self.send_stream.read_buf.remaining() >= http2::MAIGC_LEN
+                            && &self.send_stream.read_buf[..http2::MAIGC_LEN] == http2::HTTP2_MAGIC
+                    {
+                        // self.read_buf.advance(http2::MAIGC_LEN);
+                        let err = ProtError::ServerUpgradeHttp2(Binary::new(), None);
+                        return Poll::Ready(Some(Err(err)));
                     }
+                    return Poll::Ready(Some(Err(e.into())));
                 }
+            }
+            Ok(n) => n,
+        };
+        // let size = request.parse_buffer(&mut self.read_buf.clone())?;
+        if request.is_partial() {
+            return pending();
+        }
+        // 声明的包体大小超出限制, 不读取包体直接返回413
+        if request.get_body_len() > 0
+            && request.get_body_len() as usize > self.send_stream.get_max_body_size()
+        {
+            return Poll::Ready(Some(Err(ProtError::Status(413, "payload too large"))));
+        }
+        self.send_stream.set_new_body();
+        let method = HeaderHelper::get_compress_method(request.headers());
 
-                let (mut recv, sender) =
-                    Self::build_body(&mut self.inner.req_status, &mut self.send_stream)?;
-                recv.set_origin_compress_method(method);
-                if recv.is_end() {
-                    self.inner.req_status.clear_read();
-                    self.send_stream.set_end_headers(false);
-                }
-                self.inner.read_sender = sender;
-                return Poll::Ready(Some(Ok(request.into(recv).0)));
+        self.send_stream.read_buf.advance(size);
+        self.inner.req_status.is_send_body = false;
+        self.inner.req_status.is_send_finish = false;
+        self.inner.req_status.is_read_header_end = true;
+        self.inner.is_keep_alive = request.is_keep_alive();
+        let body_len = request.get_body_len();
+        self.inner.req_status.left_read_body_len = if body_len < 0 {
+            usize::MAX
+        } else {
+            body_len as usize
+        };
+        if !request.method().is_nobody() && body_len == 0 {
+            self.inner.req_status.left_read_body_len = usize::MAX;
+            if request.headers().is_chunked() {
+                self.inner.req_status.is_chunked = true;
             }
         }
+
+        let (mut recv, sender, pipe) = Self::build_body(
+            &mut self.inner.req_status,
+            &mut self.send_stream,
+            self.is_stream_body,
+        )?;
+        recv.set_origin_compress_method(method);
+        if recv.is_end() {
+            self.inner.req_status.clear_read();
+            self.send_stream.set_end_headers(false);
+        }
+        // 客户端等待100 Continue后才发送包体
+        self.inner.is_expect_continue = !recv.is_end()
+            && request.version() == &Version::Http11
+            && request
+                .headers()
+                .get_str_value(&"Expect")
+                .is_some_and(|v| v.trim().eq_ignore_ascii_case("100-continue"));
+        // 包体的读取时间从请求头结束开始计算
+        self.read_start = if recv.is_end() {
+            None
+        } else {
+            Some((true, Instant::now()))
+        };
+        self.inner.read_sender = sender;
+        self.inner.read_pipe = pipe;
+        self.inner.read_trailers = Some(recv.trailer_slot());
+        // 串行处理, 已完成及待发送的响应数即为之前的请求数
+        let request_id = self.inner.deal_req + self.inner.res_list.len() + 1;
+        let mut request = request.into(recv).0;
+        request
+            .extensions_mut()
+            .insert(RequestId::new(request_id as u64));
+        if asterisk.is_some() {
+            request.extensions_mut().insert(AsteriskForm);
+        }
+        return Poll::Ready(Some(Ok(request)));
     }
 
     pub fn do_deal_body(&mut self, is_req: bool) -> ProtResult<bool> {
@@ -377,14 +776,27 @@ where
                     Ok(p) => {
                         let mut read_data = BinaryMut::new();
                         match self.send_stream.read_data(&mut read_data)? {
-                            0 => return Ok(false),
+                            // 仅收到结束标识(如结束的chunk及trailer)时也需通知包体结束
+                            0 if !self.send_stream.is_end() || status.is_read_finish => {
+                                return Ok(false)
+                            }
                             _ => {
+                                if self.send_stream.is_end() {
+                                    if let (Some(trailers), Some(slot)) = (
+                                        self.send_stream.take_trailers(),
+                                        &self.inner.read_trailers,
+                                    ) {
+                                        *slot.lock().unwrap() = Some(trailers);
+                                    }
+                                }
                                 p.send((self.send_stream.is_end(), read_data.freeze()));
                                 status.is_read_finish = self.send_stream.is_end();
                             }
                         }
                     }
-                    Err(_) => return Err(ProtError::Extension("sender error")),
+                    // 读取端未及时读取, 等待下次处理
+                    Err(TrySendError::Full(_)) => return Ok(false),
+                    Err(_) => return Err(ProtError::channel_closed("http1 body")),
                 }
             }
         }
@@ -398,6 +810,79 @@ where
         }
     }
 
+    pub fn is_expect_continue(&self) -> bool {
+        self.inner.is_expect_continue
+    }
+
+    /// 接受包体, 发送100 Continue通知客户端发送包体
+    pub fn send_continue(&mut self) {
+        self.inner.is_expect_continue = false;
+        self.inner.is_send_continue = true;
+    }
+
+    /// 拒绝包体, 不再读取该请求的包体, 响应发送完毕后关闭连接
+    pub fn reject_continue(&mut self) {
+        self.inner.is_expect_continue = false;
+        self.abort_read_body();
+    }
+
+    /// 停止读取当前请求的包体, 读取端将结束, 响应发送完毕后关闭连接
+    pub fn abort_read_body(&mut self) {
+        self.inner.is_close_after_write = true;
+        self.inner.read_sender = None;
+        self.inner.read_pipe = None;
+    }
+
+    /// 是否正在读取请求的包体
+    pub fn is_read_body(&self) -> bool {
+        self.inner.req_status.is_read_header_end
+    }
+
+    /// 读取当前请求的包体, 包体读取完毕或连接关闭时返回
+    pub fn poll_read_body(&mut self, cx: &mut Context<'_>) -> Poll<ProtResult<()>> {
+        loop {
+            if self.inner.read_pipe.is_some() {
+                ready!(self.poll_deal_pipe(cx)?);
+            } else {
+                self.do_deal_body(true)?;
+                if self.inner.req_status.is_read_finish {
+                    self.inner.req_status.clear_read();
+                    self.send_stream.set_end_headers(false);
+                }
+            }
+            if !self.inner.req_status.is_read_header_end {
+                return Poll::Ready(Ok(()));
+            }
+            let read = self.poll_read_all(cx)?;
+            self.poll_read_timeout(cx)?;
+            if ready!(read) == 0 {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+
+    /// 将请求包体直接写入读取端的缓冲区, 读取端缓存超出read_buf_size时返回Pending
+    fn poll_deal_pipe(&mut self, cx: &mut Context<'_>) -> Poll<ProtResult<()>> {
+        let Some(pipe) = &self.inner.read_pipe else {
+            return Poll::Ready(Ok(()));
+        };
+        let send_stream = &mut self.send_stream;
+        ready!(pipe.poll_write(cx, self.read_buf_size, |buf| send_stream.move_data(buf)))?;
+        if !send_stream.is_end() {
+            return Poll::Ready(Ok(()));
+        }
+        if let (Some(trailers), Some(slot)) =
+            (send_stream.take_trailers(), &self.inner.read_trailers)
+        {
+            *slot.lock().unwrap() = Some(trailers);
+        }
+        pipe.set_end();
+        self.inner.read_pipe = None;
+        self.inner.req_status.clear_read();
+        self.send_stream.set_end_headers(false);
+        Poll::Ready(Ok(()))
+    }
+
     pub fn poll_response(
         &mut self,
         cx: &mut Context<'_>,
@@ -411,6 +896,8 @@ where
             n @ _ => {
                 if n == 0 {
                     self.inner.is_delay_close = true;
+                } else if !self.inner.res_status.is_read_header_end {
+                    self.first_byte_time.get_or_insert_with(Instant::now);
                 }
                 if self.inner.res_status.is_read_header_end {
                     let is_close = self.do_deal_body(false)?;
@@ -424,6 +911,10 @@ where
                         return Poll::Pending;
                     }
                 }
+                if let Err(e) = self.check_header_size("response header fields too large") {
+                    self.inner.is_delay_close = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
                 let mut response = Response::new(());
                 let size = match response.parse_buffer(&mut self.send_stream.read_buf.clone()) {
                     Err(e) => {
@@ -466,7 +957,9 @@ where
                         self.inner.res_status.is_chunked = true;
                     }
                 } else if response.status() == 101 {
-                    return Poll::Ready(Some(Ok(response.into(Body::empty()).0)));
+                    let mut response = response.into(Body::empty()).0;
+                    response.extensions_mut().insert(self.take_timings());
+                    return Poll::Ready(Some(Ok(response)));
                     // if response
                     //     .headers()
                     //     .is_contains(&"Connection", "Upgrade".as_bytes())
@@ -478,8 +971,8 @@ where
                     //     // ))));
                     // }
                 }
-                let (mut recv, sender) =
-                    Self::build_body(&mut self.inner.res_status, &mut self.send_stream)?;
+                let (mut recv, sender, _) =
+                    Self::build_body(&mut self.inner.res_status, &mut self.send_stream, false)?;
 
                 HeaderHelper::process_headers(
                     Version::Http11,
@@ -491,7 +984,10 @@ where
                     self.inner.res_status.clear_read();
                 }
                 self.inner.read_sender = sender;
-                return Poll::Ready(Some(Ok(response.into(recv).0)));
+                self.inner.read_trailers = Some(recv.trailer_slot());
+                let mut response = response.into(recv).0;
+                response.extensions_mut().insert(self.take_timings());
+                return Poll::Ready(Some(Ok(response)));
             }
         }
     }
@@ -499,26 +995,51 @@ where
     fn build_body(
         status: &mut SendStatus,
         send_stream: &mut SendStream,
-    ) -> ProtResult<(Body, Option<Sender<(bool, Binary)>>)> {
+        is_stream: bool,
+    ) -> ProtResult<(Body, Option<Sender<(bool, Binary)>>, Option<PipeSender>)> {
         send_stream.set_left_body(status.left_read_body_len);
         send_stream.set_chunked(status.is_chunked);
 
         if status.left_read_body_len == 0 {
-            return Ok((Body::empty(), None));
+            return Ok((Body::empty(), None, None));
         } else {
             send_stream.process_data()?;
             let mut read_data = BinaryMut::new();
             send_stream.read_data(&mut read_data)?;
+            if is_stream && !send_stream.is_end() {
+                let (pipe, receiver) = body_pipe();
+                return Ok((
+                    Body::new_pipe(receiver, read_data, false),
+                    None,
+                    Some(pipe),
+                ));
+            }
             let (sender, receiver) = tokio::sync::mpsc::channel::<(bool, Binary)>(30);
-            return Ok((
-                Body::new(receiver, read_data, send_stream.is_end()),
-                Some(sender),
-            ));
+            let mut body = Body::new(receiver, read_data, send_stream.is_end());
+            if let Some(trailers) = send_stream.take_trailers() {
+                body.set_trailers(trailers);
+            }
+            return Ok((body, Some(sender), None));
+        }
+    }
+
+    /// 取出当前响应的请求时间点
+    fn take_timings(&mut self) -> Timings {
+        Timings {
+            request_start: self.request_times.pop_front(),
+            first_byte: self.first_byte_time.take(),
+            response_done: Some(Instant::now()),
+            ..Default::default()
         }
     }
 
     fn set_now_end(&mut self) {
-        self.inner.req_status.clear();
+        // 服务端流水线中后续请求的包体可能仍在读取, 保留其读取状态
+        if self.is_server {
+            self.inner.req_status.clear_write();
+        } else {
+            self.inner.req_status.clear();
+        }
         self.inner.res_status.clear();
         self.ready_time = Instant::now();
         self.inner.is_idle = true;
@@ -535,9 +1056,19 @@ where
         Ok(())
     }
 
-    pub fn send_request(&mut self, req: RecvRequest) -> ProtResult<()> {
+    pub fn send_request(&mut self, mut req: RecvRequest) -> ProtResult<()> {
         self.check_finish_status();
+        // 声明为chunked的请求包体按块编码发送, 流式的包体可边接收边发送
+        if req.headers().is_chunked() {
+            req.body_mut().set_chunked(true);
+        }
+        if self.inner.is_idle {
+            // 空闲的连接重新开始计算读写时间
+            self.read_time = Instant::now();
+            self.write_time = self.read_time;
+        }
         self.inner.req_list.push_back(req);
+        self.request_times.push_back(Instant::now());
         self.inner.is_idle = false;
         Ok(())
     }
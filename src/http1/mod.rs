@@ -13,8 +13,12 @@
 mod server_connection;
 mod client_connection;
 mod io;
+mod send_file;
 
 
 pub use self::io::IoBuffer;
+pub use self::send_file::SendFileFn;
+#[cfg(target_os = "linux")]
+pub use self::send_file::poll_send_file;
 pub use self::server_connection::ServerH1Connection;
 pub use self::client_connection::ClientH1Connection;
\ No newline at end of file
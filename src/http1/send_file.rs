@@ -0,0 +1,68 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 04:20:15
+
+use std::{
+    fs::File,
+    io,
+    task::{Context, Poll},
+};
+
+/// 将文件的数据直接写入连接, 返回写入的字节数, 由连接类型决定能否使用
+pub type SendFileFn<T> = fn(&mut T, &mut Context<'_>, &File, usize) -> Poll<io::Result<usize>>;
+
+/// 通过sendfile将文件从当前位置直接发送到TCP连接, 数据不经过用户态的缓冲区
+#[cfg(target_os = "linux")]
+pub fn poll_send_file(
+    io: &mut tokio::net::TcpStream,
+    cx: &mut Context<'_>,
+    file: &File,
+    count: usize,
+) -> Poll<io::Result<usize>> {
+    use std::os::fd::AsRawFd;
+    use tokio::io::Interest;
+
+    loop {
+        std::task::ready!(io.poll_write_ready(cx))?;
+        let ret = io.try_io(Interest::WRITABLE, || {
+            // 偏移为空时从文件当前位置读取并更新位置, 兼容Range已经seek的文件
+            let n = unsafe {
+                libc::sendfile(
+                    io.as_raw_fd(),
+                    file.as_raw_fd(),
+                    std::ptr::null_mut(),
+                    count,
+                )
+            };
+            if n < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(n as usize)
+            }
+        });
+        match ret {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+            ret => return Poll::Ready(ret),
+        }
+    }
+}
+
+/// 文件系统不支持sendfile时, 退回到缓冲读取的方式
+pub fn is_unsupported(err: &io::Error) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        matches!(err.raw_os_error(), Some(libc::EINVAL) | Some(libc::ENOSYS))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        err.kind() == io::ErrorKind::Unsupported
+    }
+}
@@ -11,6 +11,7 @@
 // Created Date: 2023/10/07 09:41:02
 
 use std::{
+    future::{poll_fn, Future},
     net::SocketAddr,
     pin::Pin,
     task::{Context, Poll},
@@ -21,19 +22,21 @@ use algorithm::buf::{Binary, BinaryMut};
 // use futures_core::{Stream};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_stream::{Stream, StreamExt};
-use webparse::Version;
+use webparse::{Response, Version};
 
 use crate::{
-    ws::ServerWsConnection, HeaderHelper, HttpHelper, HttpTrait, Middleware, ProtResult,
-    RecvRequest, RecvResponse, ServerH2Connection, TimeoutLayer,
+    ws::ServerWsConnection, Disconnected, HeaderHelper, HttpHelper, HttpTrait, Middleware,
+    ProtError, ProtResult, RecvRequest, RecvResponse, ServerH2Connection, TimeoutLayer,
 };
 
-use super::IoBuffer;
+use super::{IoBuffer, SendFileFn};
 
 pub struct ServerH1Connection<T> {
     io: IoBuffer<T>,
 
     timeout: Option<TimeoutLayer>,
+    /// 是否允许keep-alive
+    keep_alive: bool,
 }
 
 impl<T> ServerH1Connection<T>
@@ -45,27 +48,47 @@ where
             io: IoBuffer::new(io, true),
 
             timeout: None,
+            keep_alive: true,
         }
     }
 
     pub fn new_by_cache(io: T, binary: BinaryMut) -> Self {
         let mut io = IoBuffer::new(io, true);
         io.set_read_cache(binary);
-        ServerH1Connection { io, timeout: None }
+        ServerH1Connection {
+            io,
+            timeout: None,
+            keep_alive: true,
+        }
     }
 
     pub fn into_io(self) -> T {
         self.io.into_io()
     }
 
+    /// 设置每次读取的缓冲区大小, 默认16KB
+    pub fn set_read_buf_size(&mut self, read_buf_size: usize) {
+        self.io.set_read_buf_size(read_buf_size);
+    }
+
+    /// 设置请求包体是否直接从连接的缓冲区读取, 不经过通道
+    pub fn set_stream_body(&mut self, stream_body: bool) {
+        self.io.set_stream_body(stream_body);
+    }
+
+    /// 设置直接发送纯文件响应的方法
+    pub fn set_send_file(&mut self, send_file_fn: Option<SendFileFn<T>>) {
+        self.io.set_send_file(send_file_fn);
+    }
+
+    /// 设置是否允许keep-alive, 关闭后每个响应均带`Connection: close`并关闭连接
+    pub fn set_keep_alive(&mut self, keep_alive: bool) {
+        self.keep_alive = keep_alive;
+    }
+
+    /// 设置请求头及包体各自的最长读取时间, 超出后回复408并关闭连接
     pub fn set_read_timeout(&mut self, read_timeout: Option<Duration>) {
-        if self.timeout.is_none() {
-            self.timeout = Some(TimeoutLayer::new());
-        }
-        self.timeout
-            .as_mut()
-            .unwrap()
-            .set_read_timeout(read_timeout);
+        self.io.set_read_timeout(read_timeout);
     }
 
     pub fn set_write_timeout(&mut self, write_timeout: Option<Duration>) {
@@ -92,10 +115,30 @@ where
         self.timeout.as_mut().unwrap().set_ka_timeout(timeout);
     }
 
-    pub fn set_timeout_layer(&mut self, timeout_layer: Option<TimeoutLayer>) {
+    pub fn set_timeout_layer(&mut self, mut timeout_layer: Option<TimeoutLayer>) {
+        // 读取超时由IoBuffer按请求头及包体分别处理, 以便回复408
+        if let Some(read_timeout) = timeout_layer.as_mut().and_then(|t| t.read_timeout.take()) {
+            self.io.set_read_timeout(Some(read_timeout));
+        }
         self.timeout = timeout_layer;
     }
 
+    pub fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.io.set_max_chunk_size(max_chunk_size);
+    }
+
+    pub fn set_max_body_size(&mut self, max_body_size: usize) {
+        self.io.set_max_body_size(max_body_size);
+    }
+
+    pub fn set_max_request_line_bytes(&mut self, max_request_line_bytes: usize) {
+        self.io.set_max_request_line_bytes(max_request_line_bytes);
+    }
+
+    pub fn set_max_header_size(&mut self, max_header_size: usize) {
+        self.io.set_max_header_size(max_header_size);
+    }
+
     pub fn poll_write(&mut self, cx: &mut Context<'_>) -> Poll<ProtResult<usize>> {
         self.io.poll_write(cx)
     }
@@ -105,20 +148,30 @@ where
     }
 
     pub fn into_h2(self, binary: Binary) -> ServerH2Connection<T> {
+        let read_timeout = self.io.get_read_timeout();
         let (io, read_buf, write_buf) = self.io.into();
         let mut connect = crate::http2::Builder::new().server_connection(io);
         connect.set_cache_buf(read_buf, write_buf);
         connect.set_handshake_status(binary);
         connect.set_timeout_layer(self.timeout);
+        // 升级后的读取超时由超时层处理
+        if read_timeout.is_some() {
+            connect.set_read_timeout(read_timeout);
+        }
         connect
     }
 
     pub fn into_ws(self, binary: Binary) -> ServerWsConnection<T> {
+        let read_timeout = self.io.get_read_timeout();
         let (io, read_buf, write_buf) = self.io.into();
         let mut connect = ServerWsConnection::new(io);
         connect.set_cache_buf(read_buf, write_buf);
         connect.set_handshake_status(binary);
         connect.set_timeout_layer(self.timeout);
+        // 升级后的读取超时由超时层处理
+        if read_timeout.is_some() {
+            connect.set_read_timeout(read_timeout);
+        }
         connect
     }
 
@@ -129,12 +182,77 @@ where
         f: &mut Box<dyn HttpTrait>,
         middles: &mut Vec<Box<dyn Middleware>>,
     ) -> ProtResult<Option<bool>> {
-        let mut res = HttpHelper::handle_request(Version::Http11, addr, r, f, middles).await?;
+        if self.io.is_expect_continue() {
+            if !f.on_expect_continue(&r).await {
+                log::trace!("处理函数拒绝了请求的包体, 回复417");
+                self.io.reject_continue();
+                self.send_close_response(417, "expectation failed").await?;
+                return Ok(None);
+            }
+            self.io.send_continue();
+        }
+        let disconnected = r.extensions().get::<Disconnected>().cloned();
+        let io = &mut self.io;
+        let mut is_body_end = !io.is_read_body();
+        let mut is_peer_closed = false;
+        let fut = HttpHelper::handle_request(Version::Http11, addr, r, f, middles);
+        tokio::pin!(fut);
+        // 处理函数运行期间继续发送100 Continue及读取请求包体, 包体读完后检测对端是否断开
+        let res = poll_fn(|cx| {
+            if !is_body_end {
+                let _ = io.poll_write(cx);
+                match io.poll_read_body(cx) {
+                    Poll::Ready(Ok(())) => is_body_end = true,
+                    // 读取包体超时, 不再等待处理函数
+                    Poll::Ready(Err(ProtError::Status(code, reason))) => {
+                        io.abort_read_body();
+                        return Poll::Ready(Err((code, reason)));
+                    }
+                    Poll::Ready(Err(e)) => {
+                        log::trace!("读取请求包体时出错:{:?}", e);
+                        io.abort_read_body();
+                        is_body_end = true;
+                    }
+                    Poll::Pending => {}
+                }
+            } else if !is_peer_closed && io.poll_peer_closed(cx).is_ready() {
+                log::trace!("处理请求期间对端关闭了连接");
+                is_peer_closed = true;
+                if let Some(d) = &disconnected {
+                    d.cancel();
+                }
+            }
+            fut.as_mut().poll(cx).map(Ok)
+        })
+        .await;
+        let mut res = match res {
+            Ok(res) => res?,
+            Err((code, reason)) => {
+                log::trace!("读取请求包体超时, 回复{}", code);
+                self.send_close_response(code, reason).await?;
+                return Ok(None);
+            }
+        };
         HeaderHelper::process_response_header(Version::Http11, false, &mut res)?;
+        if !self.keep_alive {
+            res.headers_mut().insert("Connection", "close");
+        }
         self.send_response(res).await?;
         return Ok(None);
     }
 
+    /// 以该状态码回复并在发送完毕后关闭连接
+    async fn send_close_response(&mut self, status: u16, reason: &'static str) -> ProtResult<()> {
+        let mut res = Response::builder()
+            .status(status)
+            .header("Connection", "close")
+            .body(reason)
+            .unwrap()
+            .into_type();
+        HeaderHelper::process_response_header(Version::Http11, false, &mut res)?;
+        self.send_response(res).await
+    }
+
     pub async fn incoming(&mut self) -> ProtResult<Option<RecvRequest>> {
         let req = self.next().await;
 
@@ -166,7 +284,8 @@ where
                 *self.io.get_ready_time(),
                 self.io.is_read_end(),
                 self.io.is_write_end(),
-                self.io.is_idle(),
+                // 正在读取包体的请求不算空闲
+                self.io.is_idle() && !self.io.is_read_body(),
             );
             self.timeout.as_mut().unwrap().poll_ready(
                 cx,
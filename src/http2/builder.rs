@@ -19,6 +19,9 @@ use crate::ServerH2Connection;
 
 use super::ClientH2Connection;
 
+/// 默认所有流缓存的待发送数据上限, 64M
+pub const DEFAULT_MAX_BUFFERED_SIZE: usize = 67_108_864;
+
 #[derive(Clone, Debug)]
 pub struct Builder {
     /// Time to keep locally reset streams around before reaping.
@@ -39,6 +42,33 @@ pub struct Builder {
 
     /// Maximum amount of bytes to "buffer" for writing per stream.
     pub max_send_buffer_size: usize,
+
+    /// 单个流的最长处理时间, 超时发送RST_STREAM(CANCEL)
+    pub stream_timeout: Option<Duration>,
+
+    /// 流在接收完成前超过该时长未收到任何帧, 发送RST_STREAM(CANCEL)
+    pub stream_idle_timeout: Option<Duration>,
+
+    /// Maximum amount of bytes to "buffer" for writing across all streams.
+    pub max_buffered_size: usize,
+
+    /// 不使用RFC7540的优先级, 忽略收到的PRIORITY帧, 按先后顺序发送
+    pub no_rfc7540_priorities: bool,
+
+    /// 接收的数据达到窗口的该比例后发送WINDOW_UPDATE, 默认为一半
+    pub window_update_threshold: f32,
+
+    /// 有数据因发送窗口不足而等待, 且超过该时长没有收发任何帧, 则发送GOAWAY关闭连接
+    pub flow_stall_timeout: Option<Duration>,
+
+    /// 本端是否发送服务端推送, 对端的SETTINGS_ENABLE_PUSH为0时同样不推送
+    pub enable_push: bool,
+
+    /// 超过该时长未收到任何帧则主动发送PING保活
+    pub keep_alive_interval: Option<Duration>,
+
+    /// 保活PING发出后超过该时长仍未收到任何帧, 则发送GOAWAY关闭连接
+    pub keep_alive_timeout: Duration,
 }
 
 impl Builder {
@@ -51,6 +81,15 @@ impl Builder {
             settings: Settings::default(),
             initial_target_connection_window_size: None,
             max_send_buffer_size: DEFAULT_MAX_SEND_BUFFER_SIZE,
+            stream_timeout: None,
+            stream_idle_timeout: None,
+            max_buffered_size: DEFAULT_MAX_BUFFERED_SIZE,
+            no_rfc7540_priorities: false,
+            window_update_threshold: 0.5,
+            flow_stall_timeout: None,
+            enable_push: true,
+            keep_alive_interval: None,
+            keep_alive_timeout: Duration::from_secs(20),
         }
     }
 
@@ -84,6 +123,12 @@ impl Builder {
         self
     }
 
+    /// 是否允许本端发送PUSH_PROMISE, 与通告给对端的SETTINGS_ENABLE_PUSH无关
+    pub fn enable_push(mut self, enable: bool) -> Self {
+        self.enable_push = enable;
+        self
+    }
+
     pub fn max_concurrent_reset_streams(mut self, max: usize) -> Self {
         self.reset_stream_max = max;
         self
@@ -105,6 +150,51 @@ impl Builder {
         self
     }
 
+    pub fn max_buffered_size(mut self, max: usize) -> Self {
+        self.max_buffered_size = max;
+        self
+    }
+
+    pub fn stream_timeout(mut self, dur: Duration) -> Self {
+        self.stream_timeout = Some(dur);
+        self
+    }
+
+    pub fn stream_idle_timeout(mut self, dur: Duration) -> Self {
+        self.stream_idle_timeout = Some(dur);
+        self
+    }
+
+    pub fn no_rfc7540_priorities(mut self, enable: bool) -> Self {
+        self.no_rfc7540_priorities = enable;
+        self
+    }
+
+    /// 超出[0, 1]的比例取边界值, 无效值使用默认的一半
+    pub fn window_update_threshold(mut self, ratio: f32) -> Self {
+        self.window_update_threshold = if ratio.is_nan() {
+            0.5
+        } else {
+            ratio.clamp(0.0, 1.0)
+        };
+        self
+    }
+
+    pub fn flow_stall_timeout(mut self, dur: Duration) -> Self {
+        self.flow_stall_timeout = Some(dur);
+        self
+    }
+
+    pub fn keep_alive_interval(mut self, dur: Duration) -> Self {
+        self.keep_alive_interval = Some(dur);
+        self
+    }
+
+    pub fn keep_alive_timeout(mut self, dur: Duration) -> Self {
+        self.keep_alive_timeout = dur;
+        self
+    }
+
     pub fn enable_connect_protocol(mut self) -> Self {
         self.settings.set_enable_connect_protocol(Some(1));
         self
@@ -12,6 +12,7 @@
 
 use std::{
     any::{Any, TypeId},
+    future::poll_fn,
     task::{ready, Context, Poll},
     time::Duration,
 };
@@ -25,7 +26,7 @@ use tokio::{
     sync::mpsc::channel,
 };
 use webparse::{
-    http::http2::frame::{Reason, StreamIdentifier},
+    http::http2::frame::{GoAway, Reason, StreamIdentifier},
     http2::frame::Settings,
     Request, Response, Serialize,
 };
@@ -35,7 +36,11 @@ use crate::{
     RecvResponse, TimeoutLayer,
 };
 
-use super::{codec::Codec, control::ControlConfig, Control};
+use super::{
+    codec::{Codec, FrameInterceptor},
+    control::ControlConfig,
+    Control, StreamMetrics,
+};
 
 pub struct ClientH2Connection<T> {
     codec: Codec<T>,
@@ -86,6 +91,16 @@ where
                         reset_stream_max: builder.reset_stream_max,
                         remote_reset_stream_max: builder.pending_accept_reset_stream_max,
                         settings: builder.settings.clone(),
+                        stream_timeout: builder.stream_timeout,
+                        stream_idle_timeout: builder.stream_idle_timeout,
+                        max_buffered_size: builder.max_buffered_size,
+                        no_rfc7540_priorities: builder.no_rfc7540_priorities,
+                        window_update_threshold: builder.window_update_threshold,
+                        flow_stall_timeout: builder.flow_stall_timeout,
+                        keep_alive_interval: builder.keep_alive_interval,
+                        keep_alive_timeout: builder.keep_alive_timeout,
+                        enable_push: builder.enable_push,
+                        remote_enable_push: true,
                     },
                     sender,
                     false,
@@ -108,6 +123,21 @@ where
         connect
     }
 
+    /// 该连接上流的并发统计
+    pub fn stream_metrics(&self) -> StreamMetrics {
+        self.inner.control.stream_metrics()
+    }
+
+    /// 收到的对端GOAWAY帧, 可获取关闭的原因及调试数据
+    pub fn remote_go_away(&self) -> Option<&GoAway> {
+        self.inner.control.remote_go_away()
+    }
+
+    /// 设置帧拦截器, 收发的每一帧均会经过该拦截器
+    pub fn set_frame_interceptor(&mut self, interceptor: Option<FrameInterceptor>) {
+        self.codec.set_frame_interceptor(interceptor);
+    }
+
     pub fn set_timeout_layer(&mut self, timeout_layer: Option<TimeoutLayer>) {
         self.timeout = timeout_layer;
     }
@@ -173,6 +203,18 @@ where
                 is_write_end,
                 is_idle,
             )?;
+            let (read_time, write_time) = (
+                *self.inner.control.get_read_time(),
+                *self.inner.control.get_write_time(),
+            );
+            self.timeout.as_mut().unwrap().poll_idle_ready(
+                cx,
+                "client",
+                read_time,
+                write_time,
+                is_read_end,
+                is_write_end,
+            )?;
         }
         self.inner.control.poll_response(cx, &mut self.codec)
     }
@@ -236,7 +278,10 @@ where
                 let e = ProtError::GoAway(debug_data.clone(), reason, initiator);
                 tracing::debug!(error = ?e, "Connection::poll; connection error");
 
-                if self.inner.control.last_goaway_reason() == &reason {
+                // 对端发送的GOAWAY无需回复, 直接关闭
+                if initiator == Initiator::Remote
+                    || self.inner.control.last_goaway_reason() == &reason
+                {
                     self.inner.state = State::Closing(reason, initiator);
                     return Ok(());
                 }
@@ -260,7 +305,6 @@ where
             .inner
             .control
             .error
-            .take()
             .as_ref()
             .map_or((Binary::new(), Reason::NO_ERROR), |frame| {
                 (frame.debug_data().clone(), frame.reason())
@@ -297,9 +341,30 @@ where
         self.inner.control.send_response(res, stream_id).await
     }
 
-    pub fn send_request(&mut self, req: RecvRequest) -> ProtResult<()> {
+    /// 缓存的待发送数据超过上限时, 等待数据写出后再继续
+    pub async fn wait_buffer_ready(&mut self) -> ProtResult<()> {
+        poll_fn(|cx| {
+            if !self.inner.control.is_buffer_full() {
+                return Poll::Ready(Ok(()));
+            }
+            ready!(self.inner.control.poll_write(cx, &mut self.codec, false))?;
+            if self.inner.control.is_buffer_full() {
+                Poll::Pending
+            } else {
+                Poll::Ready(Ok(()))
+            }
+        })
+        .await
+    }
+
+    /// 发送请求, 返回分配的流id
+    pub fn send_request(&mut self, req: RecvRequest) -> ProtResult<StreamIdentifier> {
         self.inner.control.send_request(req)
     }
+
+    pub fn is_finish_stream(&self, stream_id: &StreamIdentifier) -> bool {
+        self.inner.control.is_finish_stream(stream_id)
+    }
 }
 
 impl<T> Stream for ClientH2Connection<T>
@@ -322,6 +387,10 @@ where
                             // HeaderHelper::process_response_header(Version::Http2, true, &mut v)?;
                             return Poll::Ready(Some(Ok(v)));
                         }
+                        // 单个流被重置, 不影响连接上的其它流
+                        Poll::Ready(Some(Err(e @ ProtError::StreamReset(..)))) => {
+                            return Poll::Ready(Some(Err(e)));
+                        }
                         Poll::Ready(v) => {
                             let _ = self.handle_poll_result(v)?;
                             continue;
@@ -19,11 +19,23 @@ use tokio::io::AsyncRead;
 use tokio_stream::Stream;
 use tokio_util::codec::FramedRead as InnerFramedRead;
 use tokio_util::codec::LengthDelimitedCodec;
-use webparse::http::http2::frame::{Frame, Kind};
+use webparse::http::http2::frame::{Frame, Reason};
 use webparse::http::http2::{frame, Decoder};
 use webparse::http2::DEFAULT_SETTINGS_HEADER_TABLE_SIZE;
 
-use crate::ProtResult;
+use super::pseudo::{PseudoDecoder, PseudoHeaders};
+use crate::{ProtError, ProtResult};
+
+/// 帧头的长度
+pub(super) const FRAME_HEADER_LEN: usize = 9;
+pub(super) const KIND_HEADERS: u8 = 0x1;
+pub(super) const KIND_PUSH_PROMISE: u8 = 0x5;
+const KIND_CONTINUATION: u8 = 0x9;
+const FLAG_END_HEADERS: u8 = 0x4;
+pub(super) const FLAG_PADDED: u8 = 0x8;
+pub(super) const FLAG_PRIORITY: u8 = 0x20;
+/// 拼接后的头部块的最大大小, 防止无休止的CONTINUATION帧耗尽内存
+const MAX_HEADER_BLOCK_SIZE: usize = 1_048_576;
 
 #[derive(Debug)]
 pub struct FramedRead<T> {
@@ -34,24 +46,36 @@ pub struct FramedRead<T> {
     max_header_list_size: usize,
 
     partial: Option<Partial>,
+
+    /// 按原始顺序记录头部块中的伪头部
+    pseudo: PseudoDecoder,
 }
 
 /// Partially loaded headers frame
 #[derive(Debug)]
-#[allow(dead_code)]
 struct Partial {
-    /// Empty frame
-    frame: Continuable,
+    /// HEADERS或PUSH_PROMISE的帧类型
+    kind: u8,
+    /// 首帧的标志位, 已去除PADDED
+    flags: u8,
+    stream_id: [u8; 4],
 
     /// Partial header payload
     buf: BinaryMut,
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
-enum Continuable {
-    Headers(frame::Headers),
-    PushPromise(frame::PushPromise),
+impl Partial {
+    /// 拼接成带END_HEADERS的完整帧数据
+    fn into_frame(self) -> Binary {
+        let len = self.buf.remaining();
+        let mut data = Vec::with_capacity(FRAME_HEADER_LEN + len);
+        data.extend_from_slice(&(len as u32).to_be_bytes()[1..]);
+        data.push(self.kind);
+        data.push(self.flags | FLAG_END_HEADERS);
+        data.extend_from_slice(&self.stream_id);
+        data.extend_from_slice(self.buf.chunk());
+        Binary::from(data)
+    }
 }
 
 impl<T> FramedRead<T> {
@@ -74,6 +98,7 @@ where
             decoder: Decoder::new(),
             max_header_list_size: DEFAULT_SETTINGS_HEADER_TABLE_SIZE,
             partial: None,
+            pseudo: PseudoDecoder::new(),
         }
     }
 
@@ -88,6 +113,11 @@ where
     pub fn set_cache_buf(&mut self, read_buf: BinaryMut) {
         self.inner.read_buffer_mut().put_slice(read_buf.chunk());
     }
+
+    /// 取出最近收到的头部块中的伪头部, 供校验其顺序及是否完整
+    pub fn take_pseudo_headers(&mut self) -> Option<PseudoHeaders> {
+        self.pseudo.take_last()
+    }
 }
 
 impl<T> AsyncRead for FramedRead<T>
@@ -123,20 +153,26 @@ where
         loop {
             let bytes = match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
                 Some(Ok(bytes)) => bytes,
-                Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                Some(Err(e)) => return Poll::Ready(Some(Err(ProtError::ReadError(e)))),
                 None => {
                     return Poll::Ready(None);
                 }
             };
 
+            #[cfg(feature = "frame-trace")]
+            super::trace::trace_frames(super::Direction::Inbound, &bytes[..]);
+
             let Self {
                 ref mut decoder,
                 max_header_list_size,
                 ref mut partial,
+                ref mut pseudo,
                 ..
             } = *self;
 
-            if let Some(frame) = decode_frame(decoder, max_header_list_size, partial, bytes)? {
+            if let Some(frame) =
+                decode_frame(decoder, max_header_list_size, partial, pseudo, bytes)?
+            {
                 log::trace!("HTTP2:收到帧数据: {:?}", frame);
                 println!("HTTP2:收到帧数据: {:?}", frame);
                 return Poll::Ready(Some(Ok(frame)));
@@ -149,26 +185,76 @@ fn decode_frame(
     decoder: &mut Decoder,
     max_header_list_size: usize,
     partial_inout: &mut Option<Partial>,
+    pseudo: &mut PseudoDecoder,
     bytes: BytesMut,
 ) -> ProtResult<Option<Frame>> {
-    use bytes::Buf;
     let span = tracing::trace_span!("FramedRead::decode_frame", offset = bytes.len());
     let _e = span.enter();
 
-    let mut bytes = Binary::from(bytes.chunk().to_vec());
+    let mut bytes = Binary::from(bytes[..].to_vec());
 
     tracing::trace!("decoding frame from {}B", bytes.len());
 
-    // Parse the head
-    let head = frame::FrameHeader::parse(&mut bytes)?;
-
-    if partial_inout.is_some() && head.kind() != &Kind::Continuation {
-        // proto_err!(conn: "expected CONTINUATION, got {:?}", head.kind());
-        // return Err(Error::library_go_away(Reason::PROTOCOL_ERROR));
+    if bytes.remaining() >= FRAME_HEADER_LEN {
+        let chunk = bytes.chunk();
+        let (kind, flags) = (chunk[3], chunk[4]);
+        let stream_id = [chunk[5] & 0x7F, chunk[6], chunk[7], chunk[8]];
+        if let Some(partial) = partial_inout {
+            // 头部块未结束前只能收到同一个流的CONTINUATION帧
+            if kind != KIND_CONTINUATION || stream_id != partial.stream_id {
+                log::trace!("HTTP2头部块未结束, 收到非CONTINUATION帧:{}", kind);
+                return Err(ProtError::library_go_away(Reason::PROTOCOL_ERROR));
+            }
+            bytes.advance(FRAME_HEADER_LEN);
+            partial.buf.put_slice(bytes.chunk());
+            if partial.buf.remaining() > MAX_HEADER_BLOCK_SIZE {
+                return Err(ProtError::library_go_away(Reason::ENHANCE_YOUR_CALM));
+            }
+            if flags & FLAG_END_HEADERS == 0 {
+                return Ok(None);
+            }
+            bytes = partial_inout.take().unwrap().into_frame();
+        } else if kind == KIND_CONTINUATION {
+            return Err(ProtError::library_go_away(Reason::PROTOCOL_ERROR));
+        } else if (kind == KIND_HEADERS || kind == KIND_PUSH_PROMISE)
+            && flags & FLAG_END_HEADERS == 0
+        {
+            bytes.advance(FRAME_HEADER_LEN);
+            let mut payload = bytes.chunk().to_vec();
+            let mut flags = flags;
+            // 填充只在首帧中, 拼接前先去除
+            if flags & FLAG_PADDED != 0 {
+                let pad = match payload.first() {
+                    Some(pad) if (*pad as usize) < payload.len() => *pad as usize,
+                    _ => return Err(ProtError::library_go_away(Reason::PROTOCOL_ERROR)),
+                };
+                payload.truncate(payload.len() - pad);
+                payload.remove(0);
+                flags &= !FLAG_PADDED;
+            }
+            let mut buf = BinaryMut::new();
+            buf.put_slice(&payload);
+            *partial_inout = Some(Partial {
+                kind,
+                flags,
+                stream_id,
+                buf,
+            });
+            return Ok(None);
+        }
     }
 
-    let _kind = head.kind();
+    // 解码后的头部已失去原始顺序, 解码成功后再遍历原始头部块
+    let raw = match bytes.chunk().get(3) {
+        Some(&KIND_HEADERS) | Some(&KIND_PUSH_PROMISE) => Some(bytes.clone()),
+        _ => None,
+    };
+    // Parse the head
+    let head = frame::FrameHeader::parse(&mut bytes)?;
     let frame = Frame::parse(head, bytes, decoder, max_header_list_size)?;
+    if let Some(raw) = raw {
+        pseudo.decode_frame(raw.chunk());
+    }
 
     Ok(Some(frame))
 }
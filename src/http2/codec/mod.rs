@@ -13,18 +13,21 @@
 mod error;
 mod framed_read;
 mod framed_write;
+mod pseudo;
+mod trace;
 
+use std::fmt::Debug;
 use std::io;
 use std::pin::Pin;
 use std::sync::{Arc, RwLock};
-use std::task::{Context, Poll};
+use std::task::{ready, Context, Poll};
 
-use algorithm::buf::BinaryMut;
+use algorithm::buf::{BinaryMut, Bt, BtMut};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_stream::Stream;
 use tokio_util::codec::length_delimited;
 use webparse::http::http2::encoder::Encoder;
-use webparse::http::http2::frame::Frame;
+use webparse::http::http2::frame::{Frame, Settings};
 use webparse::http::http2::{
     HeaderIndex, DEFAULT_MAX_FRAME_SIZE, DEFAULT_SETTINGS_HEADER_TABLE_SIZE,
 };
@@ -33,13 +36,53 @@ use crate::ProtResult;
 
 pub use self::framed_read::FramedRead;
 pub use self::framed_write::FramedWrite;
+pub use self::pseudo::PseudoHeaders;
+pub use self::trace::{format_frame_head, FRAME_TRACE_TARGET};
+
+use self::framed_read::{
+    FLAG_PADDED, FLAG_PRIORITY, FRAME_HEADER_LEN, KIND_HEADERS, KIND_PUSH_PROMISE,
+};
+
+/// 帧的方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// 接收到的帧
+    Inbound,
+    /// 将要发送的帧
+    Outbound,
+}
+
+/// 拦截器对帧的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameAction {
+    /// 继续处理该帧(可能已被修改)
+    Continue,
+    /// 丢弃该帧
+    Drop,
+}
+
+/// 帧拦截器, 可查看或修改收发的每一帧, 用于调试及故障注入
+pub type FrameInterceptor = Box<dyn FnMut(Direction, &mut Frame) -> FrameAction + Send + Sync>;
 
-#[derive(Debug)]
 pub struct Codec<T> {
     inner: FramedRead<FramedWrite<T>>,
     header_index: Arc<RwLock<HeaderIndex>>,
     header_table_size: usize,
+    /// 待通知对端的动态表大小变化, 为(期间的最小值, 最终值)
+    pending_table_size: Option<(usize, usize)>,
     max_send_frame_size: usize,
+    interceptor: Option<FrameInterceptor>,
+}
+
+impl<T: Debug> Debug for Codec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Codec")
+            .field("inner", &self.inner)
+            .field("header_table_size", &self.header_table_size)
+            .field("max_send_frame_size", &self.max_send_frame_size)
+            .field("interceptor", &self.interceptor.is_some())
+            .finish()
+    }
 }
 
 impl<T> Codec<T>
@@ -87,10 +130,17 @@ where
             inner,
             header_index,
             header_table_size: DEFAULT_SETTINGS_HEADER_TABLE_SIZE,
+            pending_table_size: None,
             max_send_frame_size: DEFAULT_MAX_FRAME_SIZE as usize,
+            interceptor: None,
         }
     }
 
+    /// 设置帧拦截器, 未设置时不产生额外开销
+    pub fn set_frame_interceptor(&mut self, interceptor: Option<FrameInterceptor>) {
+        self.interceptor = interceptor;
+    }
+
     pub fn is_write_end(&self) -> bool {
         self.inner.get_ref().is_write_end()
     }
@@ -121,14 +171,138 @@ where
         self.inner.get_mut()
     }
 
-    pub fn send_frame(&mut self, frame: Frame) -> ProtResult<usize> {
+    pub fn send_frame(&mut self, mut frame: Frame) -> ProtResult<usize> {
+        if let Some(interceptor) = &mut self.interceptor {
+            if interceptor(Direction::Outbound, &mut frame) == FrameAction::Drop {
+                log::trace!("HTTP2:拦截器丢弃发送帧: {:?}", frame);
+                return Ok(0);
+            }
+        }
         log::trace!("HTTP2:发送帧数据: {:?}", frame);
+        #[cfg(feature = "frame-trace")]
+        let start = self.framed_write().get_mut_bytes().remaining();
+        let size = self.encode_frame(frame)?;
+        #[cfg(feature = "frame-trace")]
+        trace::trace_frames(
+            Direction::Outbound,
+            &self.framed_write().get_mut_bytes().chunk()[start..],
+        );
+        Ok(size)
+    }
+
+    /// 发送SETTINGS帧, 并在帧后附加webparse中未定义的参数,
+    /// 如RFC 9218的SETTINGS_NO_RFC7540_PRIORITIES
+    pub fn send_settings(&mut self, settings: Settings, extra: &[(u16, u32)]) -> ProtResult<usize> {
+        if extra.is_empty() {
+            return self.send_frame(Frame::Settings(settings));
+        }
+        let mut frame = Frame::Settings(settings);
+        if let Some(interceptor) = &mut self.interceptor {
+            if interceptor(Direction::Outbound, &mut frame) == FrameAction::Drop {
+                log::trace!("HTTP2:拦截器丢弃发送帧: {:?}", frame);
+                return Ok(0);
+            }
+        }
+        let mut encoder = Encoder::new_index(self.header_index.clone(), self.max_send_frame_size);
+        let mut buf = BinaryMut::new();
+        frame.encode(&mut buf, &mut encoder)?;
+        let data = buf.chunk();
+        let len = u32::from_be_bytes([0, data[0], data[1], data[2]]) as usize + extra.len() * 6;
+        log::trace!("HTTP2:发送SETTINGS帧, 附加参数: {:?}", extra);
+
+        #[cfg(feature = "frame-trace")]
+        let start = self.framed_write().get_mut_bytes().remaining();
+        let dst = self.framed_write().get_mut_bytes();
+        dst.put_slice(&(len as u32).to_be_bytes()[1..]);
+        dst.put_slice(&data[3..]);
+        for (id, value) in extra {
+            dst.put_slice(&id.to_be_bytes());
+            dst.put_slice(&value.to_be_bytes());
+        }
+        #[cfg(feature = "frame-trace")]
+        trace::trace_frames(
+            Direction::Outbound,
+            &self.framed_write().get_mut_bytes().chunk()[start..],
+        );
+        Ok(data.len() + extra.len() * 6)
+    }
+
+    fn encode_frame(&mut self, frame: Frame) -> ProtResult<usize> {
+        if matches!(frame, Frame::Headers(_) | Frame::PushPromise(_)) {
+            if let Some(update) = self.pending_table_size.take() {
+                return self.send_header_with_size_update(frame, update);
+            }
+        }
         let mut encoder = Encoder::new_index(self.header_index.clone(), self.max_send_frame_size);
         let usize = frame.encode(self.framed_write().get_mut_bytes(), &mut encoder)?;
         Ok(usize)
     }
 
+    /// 在头部块的最前面加入动态表大小更新的指令, 多次变化且期间有更小的值时需先通知最小值
+    fn send_header_with_size_update(
+        &mut self,
+        mut frame: Frame,
+        (min_size, size): (usize, usize),
+    ) -> ProtResult<usize> {
+        let mut prefix = vec![];
+        if min_size < size {
+            Self::encode_size_update(min_size, &mut prefix);
+        }
+        Self::encode_size_update(size, &mut prefix);
+
+        // 预留指令的长度, 保证首帧不超过对端的最大帧大小
+        let mut encoder = Encoder::new_index(
+            self.header_index.clone(),
+            self.max_send_frame_size - prefix.len(),
+        );
+        let mut buf = BinaryMut::new();
+        frame.encode(&mut buf, &mut encoder)?;
+        let data = buf.chunk();
+        let (kind, flags) = (data[3], data[4]);
+        let mut offset = FRAME_HEADER_LEN;
+        if flags & FLAG_PADDED != 0 {
+            offset += 1;
+        }
+        if kind == KIND_HEADERS && flags & FLAG_PRIORITY != 0 {
+            offset += 5;
+        }
+        if kind == KIND_PUSH_PROMISE {
+            offset += 4;
+        }
+        let len = u32::from_be_bytes([0, data[0], data[1], data[2]]) as usize + prefix.len();
+        log::trace!("HTTP2:通知对端动态表大小变化: {:?}", (min_size, size));
+
+        let dst = self.framed_write().get_mut_bytes();
+        dst.put_slice(&(len as u32).to_be_bytes()[1..]);
+        dst.put_slice(&data[3..offset]);
+        dst.put_slice(&prefix);
+        dst.put_slice(&data[offset..]);
+        Ok(data.len() + prefix.len())
+    }
+
+    /// HPACK的动态表大小更新指令, 以001开头的5位前缀整数
+    fn encode_size_update(size: usize, dst: &mut Vec<u8>) {
+        if size < 31 {
+            dst.push(0x20 | size as u8);
+            return;
+        }
+        dst.push(0x20 | 31);
+        let mut rest = size - 31;
+        while rest >= 128 {
+            dst.push((rest % 128) as u8 | 0x80);
+            rest /= 128;
+        }
+        dst.push(rest as u8);
+    }
+
     pub fn set_send_header_table_size(&mut self, size: usize) {
+        if size != self.header_table_size || self.pending_table_size.is_some() {
+            let min_size = match self.pending_table_size {
+                Some((min_size, _)) => min_size.min(size),
+                None => self.header_table_size.min(size),
+            };
+            self.pending_table_size = Some((min_size, size));
+        }
         self.header_table_size = size;
         if let Ok(mut header) = self.header_index.write() {
             header.set_max_table_size(size);
@@ -156,6 +330,19 @@ where
     type Item = ProtResult<Frame>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.inner).poll_next(cx)
+        if self.interceptor.is_none() {
+            return Pin::new(&mut self.inner).poll_next(cx);
+        }
+        loop {
+            let mut frame = match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
+                Some(Ok(frame)) => frame,
+                ret => return Poll::Ready(ret),
+            };
+            let interceptor = self.interceptor.as_mut().unwrap();
+            if interceptor(Direction::Inbound, &mut frame) == FrameAction::Continue {
+                return Poll::Ready(Some(Ok(frame)));
+            }
+            log::trace!("HTTP2:拦截器丢弃接收帧: {:?}", frame);
+        }
     }
 }
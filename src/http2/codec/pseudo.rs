@@ -0,0 +1,473 @@
+// Copyright 2022 - 2024 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 02:21:08
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::OnceLock;
+
+use webparse::http::http2::frame::Reason;
+
+use super::framed_read::{
+    FLAG_PADDED, FLAG_PRIORITY, FRAME_HEADER_LEN, KIND_HEADERS, KIND_PUSH_PROMISE,
+};
+use crate::{ProtError, ProtResult};
+
+const PSEUDO_METHOD: u8 = 0x1;
+const PSEUDO_SCHEME: u8 = 0x2;
+const PSEUDO_PATH: u8 = 0x4;
+const PSEUDO_AUTHORITY: u8 = 0x8;
+const PSEUDO_PROTOCOL: u8 = 0x10;
+const PSEUDO_STATUS: u8 = 0x20;
+const PSEUDO_REQUEST: u8 =
+    PSEUDO_METHOD | PSEUDO_SCHEME | PSEUDO_PATH | PSEUDO_AUTHORITY | PSEUDO_PROTOCOL;
+
+/// 动态表每个条目额外计算的大小
+const ENTRY_OVERHEAD: usize = 32;
+
+/// 头部块中的伪头部, 按收到的原始顺序记录
+#[derive(Debug, Default, Clone)]
+pub struct PseudoHeaders {
+    flags: u8,
+    is_connect: bool,
+    saw_regular: bool,
+    /// 顺序错误, 重复或未知的伪头部
+    error: Option<&'static str>,
+}
+
+impl PseudoHeaders {
+    fn record(&mut self, name: &[u8], value: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+        if name.first() != Some(&b':') {
+            self.saw_regular = true;
+            return;
+        }
+        if self.saw_regular {
+            self.error = Some("pseudo header after regular header");
+            return;
+        }
+        let flag = match name {
+            b":method" => PSEUDO_METHOD,
+            b":scheme" => PSEUDO_SCHEME,
+            b":path" => PSEUDO_PATH,
+            b":authority" => PSEUDO_AUTHORITY,
+            b":protocol" => PSEUDO_PROTOCOL,
+            b":status" => PSEUDO_STATUS,
+            _ => {
+                self.error = Some("unknown pseudo header");
+                return;
+            }
+        };
+        if self.flags & flag != 0 {
+            self.error = Some("duplicate pseudo header");
+            return;
+        }
+        self.flags |= flag;
+        if flag == PSEUDO_METHOD {
+            self.is_connect = value == b"CONNECT";
+        } else if flag == PSEUDO_PATH && value.is_empty() {
+            self.error = Some("empty :path");
+        }
+    }
+
+    /// 作为请求头校验, CONNECT只有:method及:authority, 其它请求须有:method, :scheme及:path
+    pub fn check_request(&self) -> Result<(), &'static str> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if self.flags & PSEUDO_STATUS != 0 {
+            return Err(":status in request");
+        }
+        if self.flags & PSEUDO_METHOD == 0 {
+            return Err("missing :method");
+        }
+        if self.flags & PSEUDO_PROTOCOL != 0 && !self.is_connect {
+            return Err(":protocol without CONNECT");
+        }
+        if self.is_connect && self.flags & PSEUDO_PROTOCOL == 0 {
+            if self.flags & PSEUDO_AUTHORITY == 0 {
+                return Err("missing :authority in CONNECT");
+            }
+            if self.flags & (PSEUDO_SCHEME | PSEUDO_PATH) != 0 {
+                return Err(":scheme or :path in CONNECT");
+            }
+        } else if self.flags & (PSEUDO_SCHEME | PSEUDO_PATH) != PSEUDO_SCHEME | PSEUDO_PATH {
+            return Err("missing :scheme or :path");
+        }
+        Ok(())
+    }
+
+    /// 作为trailer头校验, 不能包含伪头部
+    pub fn check_trailers(&self) -> Result<(), &'static str> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if self.flags != 0 {
+            return Err("pseudo header in trailers");
+        }
+        Ok(())
+    }
+
+    /// 作为响应头(或trailer头)校验, 不能包含请求的伪头部
+    pub fn check_response(&self) -> Result<(), &'static str> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if self.flags & PSEUDO_REQUEST != 0 {
+            return Err("request pseudo header in response");
+        }
+        Ok(())
+    }
+}
+
+/// 按原始顺序遍历HPACK头部块以获取伪头部的顺序, 只维护与解码器一致的动态表,
+/// 解码后的头部已失去原始顺序. webparse的解码结果保留顺序后应删除此解码器.
+/// 解码以webparse为准, 两者不一致时不再校验伪头部, 不影响连接
+#[derive(Debug)]
+pub struct PseudoDecoder {
+    table: VecDeque<(Vec<u8>, Vec<u8>)>,
+    size: usize,
+    max_size: usize,
+    /// 最近一个头部块的结果
+    last: Option<PseudoHeaders>,
+    /// 动态表已与解码器不一致
+    is_broken: bool,
+}
+
+impl PseudoDecoder {
+    pub fn new() -> Self {
+        Self {
+            table: VecDeque::new(),
+            size: 0,
+            max_size: 4096,
+            last: None,
+            is_broken: false,
+        }
+    }
+
+    pub fn take_last(&mut self) -> Option<PseudoHeaders> {
+        self.last.take()
+    }
+
+    /// 遍历完整帧(已拼接CONTINUATION)中的头部块, 非HEADERS或PUSH_PROMISE帧则忽略
+    pub fn decode_frame(&mut self, frame: &[u8]) {
+        if self.is_broken {
+            return;
+        }
+        if let Some(block) = header_block(frame) {
+            match self.decode(block) {
+                Ok(pseudo) => self.last = Some(pseudo),
+                Err(_) => {
+                    log::trace!("HTTP2伪头部解码与解码器不一致, 之后不再校验");
+                    self.is_broken = true;
+                    self.table.clear();
+                    self.last = None;
+                }
+            }
+        }
+    }
+
+    fn decode(&mut self, block: &[u8]) -> ProtResult<PseudoHeaders> {
+        let mut pseudo = PseudoHeaders::default();
+        let mut pos = 0;
+        while pos < block.len() {
+            let first = block[pos];
+            if first & 0x80 != 0 {
+                let index = decode_int(block, &mut pos, 7)?;
+                let (name, value) = self.get(index)?;
+                pseudo.record(name, value);
+            } else if first & 0x40 != 0 {
+                let index = decode_int(block, &mut pos, 6)?;
+                let name = match index {
+                    0 => decode_string(block, &mut pos)?,
+                    _ => self.get(index)?.0.to_vec(),
+                };
+                let value = decode_string(block, &mut pos)?;
+                pseudo.record(&name, &value);
+                self.insert(name, value);
+            } else if first & 0x20 != 0 {
+                self.max_size = decode_int(block, &mut pos, 5)?;
+                self.evict(0);
+            } else {
+                let index = decode_int(block, &mut pos, 4)?;
+                let name = match index {
+                    0 => decode_string(block, &mut pos)?,
+                    _ => self.get(index)?.0.to_vec(),
+                };
+                let value = decode_string(block, &mut pos)?;
+                pseudo.record(&name, &value);
+            }
+        }
+        Ok(pseudo)
+    }
+
+    fn get(&self, index: usize) -> ProtResult<(&[u8], &[u8])> {
+        if index == 0 {
+            return Err(compression_error());
+        }
+        if let Some((name, value)) = STATIC_TABLE.get(index - 1) {
+            return Ok((name.as_bytes(), value.as_bytes()));
+        }
+        match self.table.get(index - 1 - STATIC_TABLE.len()) {
+            Some((name, value)) => Ok((name, value)),
+            None => Err(compression_error()),
+        }
+    }
+
+    fn insert(&mut self, name: Vec<u8>, value: Vec<u8>) {
+        let size = name.len() + value.len() + ENTRY_OVERHEAD;
+        self.evict(size);
+        // 超过动态表大小的条目清空动态表且不加入
+        if self.size + size <= self.max_size {
+            self.size += size;
+            self.table.push_front((name, value));
+        }
+    }
+
+    fn evict(&mut self, extra: usize) {
+        while self.size + extra > self.max_size {
+            match self.table.pop_back() {
+                Some((name, value)) => self.size -= name.len() + value.len() + ENTRY_OVERHEAD,
+                None => break,
+            }
+        }
+    }
+}
+
+/// 取出帧中的头部块, 去除填充, 优先级及PUSH_PROMISE的流id
+fn header_block(frame: &[u8]) -> Option<&[u8]> {
+    let (kind, flags) = (*frame.get(3)?, *frame.get(4)?);
+    if kind != KIND_HEADERS && kind != KIND_PUSH_PROMISE {
+        return None;
+    }
+    let payload = frame.get(FRAME_HEADER_LEN..)?;
+    let (mut start, mut end) = (0, payload.len());
+    if flags & FLAG_PADDED != 0 {
+        start += 1;
+        end = end.checked_sub(*payload.first()? as usize)?;
+    }
+    if kind == KIND_HEADERS && flags & FLAG_PRIORITY != 0 {
+        start += 5;
+    }
+    if kind == KIND_PUSH_PROMISE {
+        start += 4;
+    }
+    payload.get(start..end)
+}
+
+fn compression_error() -> ProtError {
+    ProtError::library_go_away(Reason::COMPRESSION_ERROR)
+}
+
+fn decode_int(buf: &[u8], pos: &mut usize, prefix: u8) -> ProtResult<usize> {
+    let mask = ((1u16 << prefix) - 1) as u8;
+    let mut value = (buf[*pos] & mask) as usize;
+    *pos += 1;
+    if value < mask as usize {
+        return Ok(value);
+    }
+    let mut shift = 0;
+    loop {
+        let byte = *buf.get(*pos).ok_or_else(compression_error)?;
+        *pos += 1;
+        if shift > 28 {
+            return Err(compression_error());
+        }
+        value += ((byte & 0x7F) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+fn decode_string(buf: &[u8], pos: &mut usize) -> ProtResult<Vec<u8>> {
+    let is_huffman = *buf.get(*pos).ok_or_else(compression_error)? & 0x80 != 0;
+    let len = decode_int(buf, pos, 7)?;
+    let data = buf
+        .get(*pos..pos.saturating_add(len))
+        .ok_or_else(compression_error)?;
+    *pos += len;
+    if is_huffman {
+        huffman_decode(data)
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+fn huffman_decode(data: &[u8]) -> ProtResult<Vec<u8>> {
+    static CODES: OnceLock<HashMap<(u8, u32), u16>> = OnceLock::new();
+    let codes = CODES.get_or_init(|| {
+        HUFFMAN_TABLE
+            .iter()
+            .enumerate()
+            .map(|(sym, &(code, len))| ((len, code), sym as u16))
+            .collect()
+    });
+    let mut result = Vec::with_capacity(data.len() * 8 / 5);
+    let (mut code, mut len) = (0u32, 0u8);
+    for byte in data {
+        for i in (0..8).rev() {
+            code = (code << 1) | ((byte >> i) & 1) as u32;
+            len += 1;
+            match codes.get(&(len, code)) {
+                // 不允许出现EOS
+                Some(256) => return Err(compression_error()),
+                Some(sym) => {
+                    result.push(*sym as u8);
+                    (code, len) = (0, 0);
+                }
+                None if len >= 30 => return Err(compression_error()),
+                None => {}
+            }
+        }
+    }
+    // 结尾的填充须为EOS的前缀, 且不超过7位
+    if len > 7 || code != (1 << len) - 1 {
+        return Err(compression_error());
+    }
+    Ok(result)
+}
+
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// 各符号的哈夫曼编码及位数, 最后一项为EOS
+#[rustfmt::skip]
+const HUFFMAN_TABLE: [(u32, u8); 257] = [
+    (0x1ff8, 13), (0x7fffd8, 23), (0xfffffe2, 28), (0xfffffe3, 28),
+    (0xfffffe4, 28), (0xfffffe5, 28), (0xfffffe6, 28), (0xfffffe7, 28),
+    (0xfffffe8, 28), (0xffffea, 24), (0x3ffffffc, 30), (0xfffffe9, 28),
+    (0xfffffea, 28), (0x3ffffffd, 30), (0xfffffeb, 28), (0xfffffec, 28),
+    (0xfffffed, 28), (0xfffffee, 28), (0xfffffef, 28), (0xffffff0, 28),
+    (0xffffff1, 28), (0xffffff2, 28), (0x3ffffffe, 30), (0xffffff3, 28),
+    (0xffffff4, 28), (0xffffff5, 28), (0xffffff6, 28), (0xffffff7, 28),
+    (0xffffff8, 28), (0xffffff9, 28), (0xffffffa, 28), (0xffffffb, 28),
+    (0x14, 6), (0x3f8, 10), (0x3f9, 10), (0xffa, 12),
+    (0x1ff9, 13), (0x15, 6), (0xf8, 8), (0x7fa, 11),
+    (0x3fa, 10), (0x3fb, 10), (0xf9, 8), (0x7fb, 11),
+    (0xfa, 8), (0x16, 6), (0x17, 6), (0x18, 6),
+    (0x0, 5), (0x1, 5), (0x2, 5), (0x19, 6),
+    (0x1a, 6), (0x1b, 6), (0x1c, 6), (0x1d, 6),
+    (0x1e, 6), (0x1f, 6), (0x5c, 7), (0xfb, 8),
+    (0x7ffc, 15), (0x20, 6), (0xffb, 12), (0x3fc, 10),
+    (0x1ffa, 13), (0x21, 6), (0x5d, 7), (0x5e, 7),
+    (0x5f, 7), (0x60, 7), (0x61, 7), (0x62, 7),
+    (0x63, 7), (0x64, 7), (0x65, 7), (0x66, 7),
+    (0x67, 7), (0x68, 7), (0x69, 7), (0x6a, 7),
+    (0x6b, 7), (0x6c, 7), (0x6d, 7), (0x6e, 7),
+    (0x6f, 7), (0x70, 7), (0x71, 7), (0x72, 7),
+    (0xfc, 8), (0x73, 7), (0xfd, 8), (0x1ffb, 13),
+    (0x7fff0, 19), (0x1ffc, 13), (0x3ffc, 14), (0x22, 6),
+    (0x7ffd, 15), (0x3, 5), (0x23, 6), (0x4, 5),
+    (0x24, 6), (0x5, 5), (0x25, 6), (0x26, 6),
+    (0x27, 6), (0x6, 5), (0x74, 7), (0x75, 7),
+    (0x28, 6), (0x29, 6), (0x2a, 6), (0x7, 5),
+    (0x2b, 6), (0x76, 7), (0x2c, 6), (0x8, 5),
+    (0x9, 5), (0x2d, 6), (0x77, 7), (0x78, 7),
+    (0x79, 7), (0x7a, 7), (0x7b, 7), (0x7ffe, 15),
+    (0x7fc, 11), (0x3ffd, 14), (0x1ffd, 13), (0xffffffc, 28),
+    (0xfffe6, 20), (0x3fffd2, 22), (0xfffe7, 20), (0xfffe8, 20),
+    (0x3fffd3, 22), (0x3fffd4, 22), (0x3fffd5, 22), (0x7fffd9, 23),
+    (0x3fffd6, 22), (0x7fffda, 23), (0x7fffdb, 23), (0x7fffdc, 23),
+    (0x7fffdd, 23), (0x7fffde, 23), (0xffffeb, 24), (0x7fffdf, 23),
+    (0xffffec, 24), (0xffffed, 24), (0x3fffd7, 22), (0x7fffe0, 23),
+    (0xffffee, 24), (0x7fffe1, 23), (0x7fffe2, 23), (0x7fffe3, 23),
+    (0x7fffe4, 23), (0x1fffdc, 21), (0x3fffd8, 22), (0x7fffe5, 23),
+    (0x3fffd9, 22), (0x7fffe6, 23), (0x7fffe7, 23), (0xffffef, 24),
+    (0x3fffda, 22), (0x1fffdd, 21), (0xfffe9, 20), (0x3fffdb, 22),
+    (0x3fffdc, 22), (0x7fffe8, 23), (0x7fffe9, 23), (0x1fffde, 21),
+    (0x7fffea, 23), (0x3fffdd, 22), (0x3fffde, 22), (0xfffff0, 24),
+    (0x1fffdf, 21), (0x3fffdf, 22), (0x7fffeb, 23), (0x7fffec, 23),
+    (0x1fffe0, 21), (0x1fffe1, 21), (0x3fffe0, 22), (0x1fffe2, 21),
+    (0x7fffed, 23), (0x3fffe1, 22), (0x7fffee, 23), (0x7fffef, 23),
+    (0xfffea, 20), (0x3fffe2, 22), (0x3fffe3, 22), (0x3fffe4, 22),
+    (0x7ffff0, 23), (0x3fffe5, 22), (0x3fffe6, 22), (0x7ffff1, 23),
+    (0x3ffffe0, 26), (0x3ffffe1, 26), (0xfffeb, 20), (0x7fff1, 19),
+    (0x3fffe7, 22), (0x7ffff2, 23), (0x3fffe8, 22), (0x1ffffec, 25),
+    (0x3ffffe2, 26), (0x3ffffe3, 26), (0x3ffffe4, 26), (0x7ffffde, 27),
+    (0x7ffffdf, 27), (0x3ffffe5, 26), (0xfffff1, 24), (0x1ffffed, 25),
+    (0x7fff2, 19), (0x1fffe3, 21), (0x3ffffe6, 26), (0x7ffffe0, 27),
+    (0x7ffffe1, 27), (0x3ffffe7, 26), (0x7ffffe2, 27), (0xfffff2, 24),
+    (0x1fffe4, 21), (0x1fffe5, 21), (0x3ffffe8, 26), (0x3ffffe9, 26),
+    (0xffffffd, 28), (0x7ffffe3, 27), (0x7ffffe4, 27), (0x7ffffe5, 27),
+    (0xfffec, 20), (0xfffff3, 24), (0xfffed, 20), (0x1fffe6, 21),
+    (0x3fffe9, 22), (0x1fffe7, 21), (0x1fffe8, 21), (0x7ffff3, 23),
+    (0x3fffea, 22), (0x3fffeb, 22), (0x1ffffee, 25), (0x1ffffef, 25),
+    (0xfffff4, 24), (0xfffff5, 24), (0x3ffffea, 26), (0x7ffff4, 23),
+    (0x3ffffeb, 26), (0x7ffffe6, 27), (0x3ffffec, 26), (0x3ffffed, 26),
+    (0x7ffffe7, 27), (0x7ffffe8, 27), (0x7ffffe9, 27), (0x7ffffea, 27),
+    (0x7ffffeb, 27), (0xffffffe, 28), (0x7ffffec, 27), (0x7ffffed, 27),
+    (0x7ffffee, 27), (0x7ffffef, 27), (0x7fffff0, 27), (0x3ffffee, 26),
+    (0x3fffffff, 30),
+];
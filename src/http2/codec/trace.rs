@@ -0,0 +1,64 @@
+// Copyright 2022 - 2024 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 01:38:06
+
+#[cfg(feature = "frame-trace")]
+use super::framed_read::FRAME_HEADER_LEN;
+use super::Direction;
+
+/// 帧跟踪日志的target, 可单独开启该target的trace级别日志
+pub const FRAME_TRACE_TARGET: &str = "wmhttp::frame";
+
+fn kind_name(kind: u8) -> &'static str {
+    match kind {
+        0x0 => "DATA",
+        0x1 => "HEADERS",
+        0x2 => "PRIORITY",
+        0x3 => "RST_STREAM",
+        0x4 => "SETTINGS",
+        0x5 => "PUSH_PROMISE",
+        0x6 => "PING",
+        0x7 => "GOAWAY",
+        0x8 => "WINDOW_UPDATE",
+        0x9 => "CONTINUATION",
+        _ => "UNKNOWN",
+    }
+}
+
+/// 以固定的格式描述帧头, 如`send DATA type=0x0 stream=1 flags=0x1 len=5`,
+/// head需包含完整的9字节帧头
+pub fn format_frame_head(direction: Direction, head: &[u8]) -> String {
+    let len = u32::from_be_bytes([0, head[0], head[1], head[2]]);
+    let stream_id = u32::from_be_bytes([head[5] & 0x7F, head[6], head[7], head[8]]);
+    let direction = match direction {
+        Direction::Inbound => "recv",
+        Direction::Outbound => "send",
+    };
+    format!(
+        "{} {} type={:#x} stream={} flags={:#x} len={}",
+        direction,
+        kind_name(head[3]),
+        head[3],
+        stream_id,
+        head[4],
+        len
+    )
+}
+
+/// 记录data中连续的每一帧, 一个头部块可能被编码为多帧
+#[cfg(feature = "frame-trace")]
+pub(crate) fn trace_frames(direction: Direction, mut data: &[u8]) {
+    while data.len() >= FRAME_HEADER_LEN {
+        log::trace!(target: FRAME_TRACE_TARGET, "{}", format_frame_head(direction, data));
+        let len = u32::from_be_bytes([0, data[0], data[1], data[2]]) as usize;
+        data = &data[std::cmp::min(FRAME_HEADER_LEN + len, data.len())..];
+    }
+}
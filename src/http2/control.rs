@@ -12,29 +12,42 @@
 
 use std::{
     collections::{HashMap, HashSet, LinkedList},
+    future::Future,
     pin::Pin,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     task::{ready, Context, Poll},
     time::{Duration, Instant},
 };
 
-use algorithm::buf::Binary;
+use algorithm::buf::{Binary, Bt};
 use tokio_stream::Stream;
 
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     sync::mpsc::Sender,
+    time::Sleep,
 };
 use webparse::{
-    http::http2::frame::{Frame, GoAway, Reason, Settings, StreamIdentifier},
+    http::http2::frame::{
+        Frame, GoAway, Reason, Reset, Settings, StreamIdentifier, WindowUpdate,
+    },
     Request,
 };
 
-use crate::{ProtError, ProtResult, RecvRequest, RecvResponse};
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::{Disconnected, ProtError, ProtResult, RecvRequest, RecvResponse, RequestId, Timings};
 
 use super::{
-    codec::Codec, inner_stream::InnerStream, send_response::SendControl, state::StateHandshake,
-    PriorityQueue, SendRequest, SendResponse, StateGoAway, StatePingPong, StateSettings,
+    codec::{Codec, PseudoHeaders},
+    inner_stream::InnerStream,
+    send_response::SendControl,
+    state::StateHandshake,
+    PriorityParam, PriorityQueue, RecvFlowControl, SendRequest, SendResponse, StateGoAway,
+    StatePingPong, StateSettings, StreamMetrics,
 };
 
 use webparse::http2::WindowSize;
@@ -49,6 +62,26 @@ pub struct ControlConfig {
     pub reset_stream_max: usize,
     pub remote_reset_stream_max: usize,
     pub settings: Settings,
+    /// 单个流的最长处理时间
+    pub stream_timeout: Option<Duration>,
+    /// 流在接收完成前无任何帧的最长时间
+    pub stream_idle_timeout: Option<Duration>,
+    /// 所有流缓存的待发送数据上限
+    pub max_buffered_size: usize,
+    /// 忽略RFC7540的优先级
+    pub no_rfc7540_priorities: bool,
+    /// 发送WINDOW_UPDATE的阈值比例
+    pub window_update_threshold: f32,
+    /// 流量控制无进展的最长等待时间
+    pub flow_stall_timeout: Option<Duration>,
+    /// 本端是否发送服务端推送
+    pub enable_push: bool,
+    /// 对端SETTINGS_ENABLE_PUSH的值, 默认为允许
+    pub remote_enable_push: bool,
+    /// 主动发送保活PING的间隔
+    pub keep_alive_interval: Option<Duration>,
+    /// 等待保活PING回复的最长时间
+    pub keep_alive_timeout: Duration,
 }
 
 impl ControlConfig {
@@ -86,6 +119,49 @@ pub struct Control {
     sender_push: Sender<(StreamIdentifier, RecvResponse)>,
 
     ready_time: Instant,
+    /// 最后一次收到帧的时间
+    read_time: Instant,
+    /// 最后一次数据全部写出的时间
+    write_time: Instant,
+
+    /// 连接级别的接收流量控制
+    recv_flow: RecvFlowControl,
+    /// 流级别的接收流量控制
+    stream_recv_flow: HashMap<StreamIdentifier, RecvFlowControl>,
+    /// 本地通告的流初始窗口大小
+    local_window_size: WindowSize,
+
+    /// 流的开始时间, 用于检测单个流的处理超时
+    stream_start: HashMap<StreamIdentifier, Instant>,
+    stream_timeout_sleep: Option<Pin<Box<Sleep>>>,
+    /// 接收中的流最后收到帧的时间, 用于清理长时间无数据的流
+    stream_active: HashMap<StreamIdentifier, Instant>,
+    stream_idle_sleep: Option<Pin<Box<Sleep>>>,
+    /// 等待发送窗口时检测连接是否已无进展
+    flow_stall_sleep: Option<Pin<Box<Sleep>>>,
+    /// 保活PING的定时器
+    keep_alive_sleep: Option<Pin<Box<Sleep>>>,
+    /// 保活PING未收到回复, 连接因超时关闭
+    is_keep_alive_timeout: bool,
+
+    /// 连接关闭时通知所有流, Control释放时自动触发
+    disconnect: CancellationToken,
+    _disconnect_guard: DropGuard,
+    /// 每个流的断开通知, 收到RST_STREAM时触发
+    stream_disconnect: HashMap<StreamIdentifier, Disconnected>,
+    /// 服务端各流是否已有最终响应, 与该流的SendControl共享, 流关闭时移除
+    stream_final: HashMap<StreamIdentifier, Arc<AtomicBool>>,
+    /// 客户端每个流的请求时间点
+    stream_timings: HashMap<StreamIdentifier, Timings>,
+
+    /// 被对端重置的流, 之后处理完成的响应直接丢弃
+    remote_reset_streams: HashSet<StreamIdentifier>,
+    /// 收到的RST_STREAM数量, 用于限制remote_reset_stream_max
+    remote_reset_count: usize,
+
+    /// 当前活跃的流, 用于统计并发数
+    active_streams: HashSet<StreamIdentifier>,
+    metrics: StreamMetrics,
 
     is_server: bool,
 }
@@ -96,9 +172,16 @@ impl Control {
         sender_push: Sender<(StreamIdentifier, RecvResponse)>,
         is_server: bool,
     ) -> Self {
+        let local_window_size = config.get_initial_window_size();
+        let recv_flow =
+            RecvFlowControl::new(DEFAULT_INITIAL_WINDOW_SIZE, config.window_update_threshold);
+        // 发送窗口由对端的SETTINGS决定, 收到前使用默认值
+        let mut send_frames = PriorityQueue::new(DEFAULT_INITIAL_WINDOW_SIZE);
+        send_frames.set_no_priorities(config.no_rfc7540_priorities);
+        let disconnect = CancellationToken::new();
         Control {
             recv_frames: HashMap::new(),
-            send_frames: PriorityQueue::new(config.get_initial_window_size()),
+            send_frames,
             ready_queue: LinkedList::new(),
             response_queue: Arc::new(Mutex::new(Vec::new())),
             request_queue: Vec::new(),
@@ -114,13 +197,86 @@ impl Control {
 
             is_server,
             ready_time: Instant::now(),
+            read_time: Instant::now(),
+            write_time: Instant::now(),
+            recv_flow,
+            stream_recv_flow: HashMap::new(),
+            local_window_size,
+            stream_start: HashMap::new(),
+            stream_timeout_sleep: None,
+            stream_active: HashMap::new(),
+            stream_idle_sleep: None,
+            flow_stall_sleep: None,
+            keep_alive_sleep: None,
+            is_keep_alive_timeout: false,
+            _disconnect_guard: disconnect.clone().drop_guard(),
+            disconnect,
+            stream_disconnect: HashMap::new(),
+            stream_final: HashMap::new(),
+            stream_timings: HashMap::new(),
+            remote_reset_streams: HashSet::new(),
+            remote_reset_count: 0,
+            active_streams: HashSet::new(),
+            metrics: StreamMetrics::new(),
+        }
+    }
+
+    /// 流并发数的统计
+    pub fn stream_metrics(&self) -> StreamMetrics {
+        self.metrics.clone()
+    }
+
+    fn open_stream(&mut self, stream_id: StreamIdentifier) {
+        if self.active_streams.insert(stream_id) {
+            self.metrics.open();
         }
     }
 
+    fn close_stream(&mut self, stream_id: StreamIdentifier) {
+        if self.active_streams.remove(&stream_id) {
+            self.metrics.close();
+        }
+        // 流已关闭, 不再允许发送临时响应
+        if let Some(is_final) = self.stream_final.remove(&stream_id) {
+            is_final.store(true, Ordering::Release);
+        }
+    }
+
+    /// 设置连接级别的断开通知, 如由Server在连接结束时触发
+    pub fn set_disconnect_token(&mut self, token: CancellationToken) {
+        self._disconnect_guard = token.clone().drop_guard();
+        self.disconnect = token;
+    }
+
+    /// 连接已结束, 通知所有正在处理的流
+    pub fn cancel_disconnect(&self) {
+        self.disconnect.cancel();
+    }
+
+    /// 生成该流的断开通知, 并清理已无人持有的旧通知
+    fn stream_disconnected(&mut self, stream_id: StreamIdentifier) -> Disconnected {
+        self.stream_disconnect.retain(|_, d| d.is_observed());
+        let disconnected = Disconnected::new(self.disconnect.child_token());
+        self.stream_disconnect.insert(stream_id, disconnected.clone());
+        disconnected
+    }
+
+    pub fn set_stream_timeout(&mut self, stream_timeout: Option<Duration>) {
+        self.config.stream_timeout = stream_timeout;
+    }
+
     pub fn get_ready_time(&self) -> &Instant {
         &self.ready_time
     }
 
+    pub fn get_read_time(&self) -> &Instant {
+        &self.read_time
+    }
+
+    pub fn get_write_time(&self) -> &Instant {
+        &self.write_time
+    }
+
     pub fn is_read_end(&self) -> bool {
         self.finish_streams.contains(&self.last_stream_id)
     }
@@ -145,26 +301,43 @@ impl Control {
         self.is_read_end() && self.is_write_end(codec)
     }
 
+    /// 缓存的待发送数据是否已超过上限
+    pub fn is_buffer_full(&self) -> bool {
+        self.send_frames.buffered_size() >= self.config.max_buffered_size
+    }
+
     pub fn encode_response(&mut self, cx: &mut Context) -> ProtResult<()> {
+        // 缓存数据超过上限, 暂不读取包体数据, 等待数据写出
+        if self.is_buffer_full() {
+            return Ok(());
+        }
         let mut list = self.response_queue.lock().unwrap();
         if list.len() == 0 {
             return Ok(());
         }
         let mut new_list = vec![];
+        let mut closed = vec![];
         // let vals = (*list).drain(..).collect::<Vec<SendResponse>>();
         for mut l in (*list).drain(..) {
             let (is_send, vec) = l.encode_frames(cx);
             self.send_frames.send_frames(l.stream_id, vec)?;
             if !is_send {
                 new_list.push(l);
+            } else if !l.is_informational {
+                self.stream_start.remove(&l.stream_id);
+                closed.push(l.stream_id);
             }
         }
         list.extend(new_list);
+        drop(list);
+        for stream_id in closed {
+            self.close_stream(stream_id);
+        }
         Ok(())
     }
 
     pub fn encode_request(&mut self, cx: &mut Context) -> ProtResult<()> {
-        if self.request_queue.is_empty() {
+        if self.request_queue.is_empty() || self.is_buffer_full() {
             return Ok(());
         }
         let vals = self.request_queue.drain(..).collect::<Vec<SendRequest>>();
@@ -192,17 +365,30 @@ impl Control {
         T: AsyncRead + AsyncWrite + Unpin,
     {
         // 等待接收中，不能写入新消息
+        self.poll_stream_timeout(cx)?;
+        self.poll_stream_idle(cx)?;
+        self.poll_flow_stall(cx);
+        self.poll_keep_alive(cx);
         self.encode_response(cx)?;
         self.encode_request(cx)?;
         if let Some(reason) = ready!(self.goaway.poll_handle(cx, codec)?) {
-            return Poll::Ready(Err(ProtError::library_go_away(reason)));
+            return Poll::Ready(Err(self.go_away_error(reason)));
         };
         ready!(self.ping_pong.poll_handle(cx, codec))?;
         match ready!(self.send_frames.poll_handle(cx, codec)) {
             Some(Err(e)) => return Poll::Ready(Err(e)),
             _ => (),
         }
-        ready!(codec.poll_flush(cx))?;
+        if self.poll_drain() {
+            if let Some(reason) = ready!(self.goaway.poll_handle(cx, codec)?) {
+                return Poll::Ready(Err(self.go_away_error(reason)));
+            };
+        }
+        let has_write = !codec.is_write_end();
+        ready!(codec.poll_flush(cx)).map_err(ProtError::WriteError)?;
+        if has_write {
+            self.write_time = Instant::now();
+        }
         Poll::Ready(Ok(()))
     }
 
@@ -229,8 +415,11 @@ impl Control {
 
             match Pin::new(&mut *codec).poll_next(cx) {
                 Poll::Ready(Some(Ok(frame))) => {
+                    self.read_time = Instant::now();
+                    self.ping_pong.recv_frame();
                     match &frame {
                         Frame::Settings(settings) => {
+                            self.recv_remote_settings(settings)?;
                             self.setting
                                 .recv_setting(codec, settings.clone(), &mut self.config)?;
                         }
@@ -238,7 +427,10 @@ impl Control {
                             let _ = self.recv_frame(frame, cx)?;
                         }
                         Frame::Headers(_) => {
-                            let _ = self.recv_frame(frame, cx)?;
+                            let pseudo = codec.get_reader().take_pseudo_headers();
+                            if self.check_request_pseudo(frame.stream_id(), pseudo)? {
+                                let _ = self.recv_frame(frame, cx)?;
+                            }
                         }
                         Frame::Priority(v) => {
                             self.send_frames.priority_recv(v.clone());
@@ -250,10 +442,12 @@ impl Control {
                         Frame::GoAway(e) => {
                             self.error = Some(e.clone());
                         }
-                        Frame::WindowUpdate(_v) => {
-                            // self.config.settings.set_initial_window_size(Some(v.size_increment()))
+                        Frame::WindowUpdate(v) => {
+                            self.recv_window_update(v)?;
+                        }
+                        Frame::Reset(v) => {
+                            self.recv_reset(v);
                         }
-                        Frame::Reset(_v) => {}
                     }
                 }
                 Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
@@ -264,7 +458,7 @@ impl Control {
                     }
                     None => {
                         if let Some(e) = &self.error {
-                            return Poll::Ready(Some(Err(ProtError::library_go_away(e.reason()))));
+                            return Poll::Ready(Some(Err(ProtError::remote_go_away(e))));
                         } else {
                             // 有收到消息, 再处理一次数据, 如ack settings或者goway消息
                             if has_change {
@@ -299,8 +493,11 @@ impl Control {
 
             match Pin::new(&mut *codec).poll_next(cx) {
                 Poll::Ready(Some(Ok(frame))) => {
+                    self.read_time = Instant::now();
+                    self.ping_pong.recv_frame();
                     match &frame {
                         Frame::Settings(settings) => {
+                            self.recv_remote_settings(settings)?;
                             let _finish = self.setting.recv_setting(
                                 codec,
                                 settings.clone(),
@@ -323,10 +520,25 @@ impl Control {
                         Frame::GoAway(e) => {
                             self.error = Some(e.clone());
                         }
-                        Frame::WindowUpdate(_v) => {
-                            // self.config.settings.set_initial_window_size(Some(v.size_increment()))
+                        Frame::WindowUpdate(v) => {
+                            self.recv_window_update(v)?;
+                        }
+                        Frame::Reset(v) => {
+                            let stream_id = v.stream_id();
+                            if self.finish_streams.contains(&stream_id) {
+                                continue;
+                            }
+                            log::trace!("HTTP2流被服务端重置:{:?}, 原因:{:?}", stream_id, v.reason());
+                            self.close_stream(stream_id);
+                            self.stream_recv_flow.remove(&stream_id);
+                            self.stream_timings.remove(&stream_id);
+                            self.finish_stream(stream_id);
+                            self.request_queue.retain(|r| r.stream_id != stream_id);
+                            return Poll::Ready(Some(Err(ProtError::StreamReset(
+                                stream_id,
+                                v.reason(),
+                            ))));
                         }
-                        Frame::Reset(_v) => {}
                     }
                 }
                 Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
@@ -337,7 +549,7 @@ impl Control {
                     }
                     None => {
                         if let Some(e) = &self.error {
-                            return Poll::Ready(Some(Err(ProtError::library_go_away(e.reason()))));
+                            return Poll::Ready(Some(Err(ProtError::remote_go_away(e))));
                         } else {
                             return Poll::Pending;
                         }
@@ -357,6 +569,10 @@ impl Control {
     pub fn finish_stream(&mut self, stream_id: StreamIdentifier) {
         self.recv_frames.remove(&stream_id);
         self.finish_streams.insert(stream_id);
+        // 客户端收到完整的响应即结束该流
+        if !self.is_server {
+            self.close_stream(stream_id);
+        }
     }
 
     pub fn build_request_frame(&mut self) -> Poll<Option<ProtResult<RecvRequest>>> {
@@ -375,12 +591,24 @@ impl Control {
                 if is_end {
                     self.finish_stream(stream_id);
                 }
+                if let Some(value) = r.headers().get_str_value(&"Priority") {
+                    self.send_frames
+                        .priority_header(stream_id, PriorityParam::parse(&value));
+                }
                 let method = r.method().clone();
+                let disconnected = self.stream_disconnected(stream_id);
+                r.extensions_mut().insert(disconnected);
+                r.extensions_mut()
+                    .insert(RequestId::new(u32::from(stream_id) as u64));
                 r.extensions_mut().insert(stream_id);
+                let is_final = Arc::new(AtomicBool::new(false));
+                self.stream_final.insert(stream_id, is_final.clone());
                 r.extensions_mut().insert(SendControl::new(
                     stream_id,
                     self.sender_push.clone(),
                     method,
+                    self.response_queue.clone(),
+                    is_final,
                 ));
                 Poll::Ready(Some(Ok(r)))
             }
@@ -404,11 +632,18 @@ impl Control {
                     self.finish_stream(stream_id);
                 }
                 // let method = r.method().clone();
+                if let Some(mut timings) = self.stream_timings.remove(&stream_id) {
+                    timings.response_done = Some(Instant::now());
+                    r.extensions_mut().insert(timings);
+                }
                 r.extensions_mut().insert(stream_id);
+                // 客户端收到的响应不能再发送临时响应
                 r.extensions_mut().insert(SendControl::new(
                     stream_id,
                     self.sender_push.clone(),
                     webparse::Method::Get,
+                    self.response_queue.clone(),
+                    Arc::new(AtomicBool::new(true)),
                 ));
                 Poll::Ready(Some(Ok(r)))
             }
@@ -428,6 +663,78 @@ impl Control {
         Ok(())
     }
 
+    /// 处理对端的SETTINGS, 调整发送窗口并记录是否允许推送,
+    /// 窗口超出上限为连接级别的FLOW_CONTROL_ERROR
+    fn recv_remote_settings(&mut self, settings: &Settings) -> ProtResult<()> {
+        if settings.is_ack() {
+            return Ok(());
+        }
+        if let Some(size) = settings.initial_window_size() {
+            if let Err(reason) = self.send_frames.set_initial_window_size(size) {
+                return Err(ProtError::library_go_away(reason));
+            }
+        }
+        if let Some(enable) = settings.is_push_enabled() {
+            self.config.remote_enable_push = enable;
+        }
+        Ok(())
+    }
+
+    /// 本端及对端是否均允许服务端推送
+    pub fn is_push_enabled(&self) -> bool {
+        self.config.enable_push && self.config.remote_enable_push
+    }
+
+    /// 收到WINDOW_UPDATE, 释放因窗口不足而等待发送的数据,
+    /// 窗口溢出时连接级别返回GOAWAY, 流级别重置该流
+    fn recv_window_update(&mut self, v: &WindowUpdate) -> ProtResult<()> {
+        let stream_id = v.stream_id();
+        if let Err(reason) = self.send_frames.window_update(stream_id, v.size_increment()) {
+            if stream_id.is_zero() {
+                return Err(ProtError::library_go_away(reason));
+            }
+            self.reset_stream(stream_id, reason)?;
+        }
+        Ok(())
+    }
+
+    /// 记录收到的数据, 累计达到阈值后发送WINDOW_UPDATE
+    fn recv_data_flow(
+        &mut self,
+        stream_id: StreamIdentifier,
+        size: u32,
+        is_end_stream: bool,
+    ) -> ProtResult<()> {
+        if size == 0 {
+            return Ok(());
+        }
+        let mut frames = vec![];
+        if let Some(increment) = self.recv_flow.recv_data(size) {
+            frames.push(Frame::WindowUpdate(WindowUpdate::new(
+                StreamIdentifier::zero(),
+                increment,
+            )));
+        }
+        // 已结束的流只计入连接的窗口
+        if is_end_stream || self.finish_streams.contains(&stream_id) {
+            self.stream_recv_flow.remove(&stream_id);
+        } else {
+            let window_size = self.local_window_size;
+            let ratio = self.config.window_update_threshold;
+            let flow = self
+                .stream_recv_flow
+                .entry(stream_id)
+                .or_insert_with(|| RecvFlowControl::new(window_size, ratio));
+            if let Some(increment) = flow.recv_data(size) {
+                frames.push(Frame::WindowUpdate(WindowUpdate::new(stream_id, increment)));
+            }
+        }
+        if !frames.is_empty() {
+            self.send_frames.send_frames(StreamIdentifier::zero(), frames)?;
+        }
+        Ok(())
+    }
+
     pub fn recv_frame(
         &mut self,
         frame: Frame<Binary>,
@@ -439,9 +746,46 @@ impl Control {
         }
 
         let is_end_headers = frame.is_end_headers();
-        let _is_end_stream = frame.is_end_stream();
+        let is_end_stream = frame.is_end_stream();
+        if let Frame::Data(d) = &frame {
+            self.recv_data_flow(stream_id, d.payload().remaining() as u32, is_end_stream)?;
+        }
+        // 已结束或已重置的流收到的迟到帧直接丢弃, 不可重新创建该流
+        if self.finish_streams.contains(&stream_id) {
+            log::trace!("HTTP2丢弃已结束的流的帧:{:?}", stream_id);
+            return Poll::Ready(None);
+        }
+        if let Some(timings) = self.stream_timings.get_mut(&stream_id) {
+            timings.first_byte.get_or_insert_with(Instant::now);
+        }
+
+        // 已构建的流再收到HEADERS帧为trailer头, 不再重新构建
+        let is_builder = self
+            .recv_frames
+            .get(&stream_id)
+            .is_some_and(|s| s.is_builder());
+        // 优雅关闭中拒绝新的流, 对端可安全地在新连接上重试
+        if self.is_server
+            && self.goaway.is_draining()
+            && !self.recv_frames.contains_key(&stream_id)
+        {
+            if self.finish_streams.insert(stream_id) {
+                log::trace!("HTTP2连接关闭中, 拒绝新的流:{:?}", stream_id);
+                self.send_frames.send_frames(
+                    stream_id,
+                    vec![Frame::Reset(Reset::new(stream_id, Reason::REFUSED_STREAM))],
+                )?;
+            }
+            return Poll::Ready(None);
+        }
 
         let is_end = if !self.recv_frames.contains_key(&stream_id) {
+            if self.is_server && self.config.stream_timeout.is_some() {
+                self.stream_start.insert(stream_id, Instant::now());
+            }
+            if self.is_server {
+                self.open_stream(stream_id);
+            }
             self.recv_frames.insert(stream_id, InnerStream::new(frame));
             false
         } else {
@@ -454,10 +798,17 @@ impl Control {
         if is_end {
             self.finish_stream(stream_id);
         }
+        if self.is_server && self.config.stream_idle_timeout.is_some() {
+            if is_end || is_end_stream {
+                self.stream_active.remove(&stream_id);
+            } else {
+                self.stream_active.insert(stream_id, Instant::now());
+            }
+        }
 
         self.last_stream_id = self.last_stream_id.max(stream_id);
 
-        if is_end_headers {
+        if is_end_headers && !is_builder {
             self.ready_queue.push_back(stream_id);
             Poll::Ready(Some(Ok(true)))
         } else {
@@ -465,6 +816,288 @@ impl Control {
         }
     }
 
+    /// 校验请求中的伪头部, 首个HEADERS帧为请求头, 之后的为trailer头,
+    /// 不合法时以PROTOCOL_ERROR重置该流并返回false
+    fn check_request_pseudo(
+        &mut self,
+        stream_id: StreamIdentifier,
+        pseudo: Option<PseudoHeaders>,
+    ) -> ProtResult<bool> {
+        let Some(pseudo) = pseudo else {
+            return Ok(true);
+        };
+        // 已结束的流不再校验, 由recv_frame丢弃
+        if self.finish_streams.contains(&stream_id) {
+            return Ok(true);
+        }
+        let result = if self.recv_frames.contains_key(&stream_id) {
+            pseudo.check_trailers()
+        } else {
+            pseudo.check_request()
+        };
+        match result {
+            Ok(()) => Ok(true),
+            Err(reason) => {
+                log::trace!("HTTP2请求头不合法:{}, 重置流:{:?}", reason, stream_id);
+                self.last_stream_id = self.last_stream_id.max(stream_id);
+                self.reset_stream(stream_id, Reason::PROTOCOL_ERROR)?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// 获取该流剩余的可处理时长, 未配置超时则返回None
+    pub fn stream_remaining(&self, stream_id: &StreamIdentifier) -> Option<Duration> {
+        let timeout = self.config.stream_timeout?;
+        let start = self.stream_start.get(stream_id)?;
+        Some((*start + timeout).saturating_duration_since(Instant::now()))
+    }
+
+    /// 重置该流, 丢弃该流未发送完的数据并发送RST_STREAM, 已被对端重置的流不再发送
+    pub fn reset_stream(&mut self, stream_id: StreamIdentifier, reason: Reason) -> ProtResult<()> {
+        log::trace!("HTTP2重置流:{:?}, 原因:{:?}", stream_id, reason);
+        let is_remote_reset = self.remote_reset_streams.remove(&stream_id);
+        self.close_stream(stream_id);
+        self.stream_start.remove(&stream_id);
+        self.stream_active.remove(&stream_id);
+        self.stream_recv_flow.remove(&stream_id);
+        self.recv_frames.remove(&stream_id);
+        self.finish_streams.insert(stream_id);
+        self.ready_queue = std::mem::take(&mut self.ready_queue)
+            .into_iter()
+            .filter(|id| id != &stream_id)
+            .collect();
+        self.response_queue
+            .lock()
+            .unwrap()
+            .retain(|r| r.stream_id != stream_id);
+        self.send_frames.remove_stream(&stream_id);
+        if is_remote_reset {
+            return Ok(());
+        }
+        self.send_frames
+            .send_frames(stream_id, vec![Frame::Reset(Reset::new(stream_id, reason))])
+    }
+
+    /// 服务端收到RST_STREAM, 清理该流所有的状态, 丢弃待发送的响应并通知正在处理的请求
+    fn recv_reset(&mut self, v: &Reset) {
+        let stream_id = v.stream_id();
+        log::trace!("HTTP2流被客户端重置:{:?}, 原因:{:?}", stream_id, v.reason());
+        self.remote_reset_count += 1;
+        if self.remote_reset_count > self.config.remote_reset_stream_max {
+            log::trace!("HTTP2收到的重置次数超过上限:{}", self.remote_reset_count);
+        }
+        self.close_stream(stream_id);
+        self.stream_start.remove(&stream_id);
+        self.stream_active.remove(&stream_id);
+        self.stream_recv_flow.remove(&stream_id);
+        self.recv_frames.remove(&stream_id);
+        self.finish_streams.insert(stream_id);
+        // 服务端等待处理函数的响应, 响应到达或该流被本端重置时移除
+        if self.is_server {
+            self.remote_reset_streams.insert(stream_id);
+        }
+        self.send_frames.remove_stream(&stream_id);
+        self.ready_queue = std::mem::take(&mut self.ready_queue)
+            .into_iter()
+            .filter(|id| id != &stream_id)
+            .collect();
+        self.response_queue
+            .lock()
+            .unwrap()
+            .retain(|r| r.stream_id != stream_id);
+        if let Some(d) = self.stream_disconnect.remove(&stream_id) {
+            d.reset(stream_id, v.reason());
+        }
+    }
+
+    /// 收到的RST_STREAM数量
+    pub fn remote_reset_count(&self) -> usize {
+        self.remote_reset_count
+    }
+
+    /// 检测单个流是否处理超时, 超时则取消该流, 不影响其它的流
+    fn poll_stream_timeout(&mut self, cx: &mut Context<'_>) -> ProtResult<()> {
+        let timeout = match self.config.stream_timeout {
+            Some(timeout) => timeout,
+            None => return Ok(()),
+        };
+        let now = Instant::now();
+        let mut expired = vec![];
+        let mut next: Option<Instant> = None;
+        for (id, start) in &self.stream_start {
+            let deadline = *start + timeout;
+            if deadline <= now {
+                expired.push(*id);
+            } else {
+                next = Some(next.map_or(deadline, |n| n.min(deadline)));
+            }
+        }
+        for id in expired {
+            self.reset_stream(id, Reason::CANCEL)?;
+        }
+        if let Some(next) = next {
+            if self.stream_timeout_sleep.is_some() {
+                self.stream_timeout_sleep
+                    .as_mut()
+                    .unwrap()
+                    .as_mut()
+                    .set(tokio::time::sleep_until(next.into()));
+            } else {
+                self.stream_timeout_sleep = Some(Box::pin(tokio::time::sleep_until(next.into())));
+            }
+            let _ = Pin::new(self.stream_timeout_sleep.as_mut().unwrap()).poll(cx);
+        }
+        Ok(())
+    }
+
+    /// 接收中的流超过stream_idle_timeout未收到任何帧, 以CANCEL重置该流, 释放并发数
+    fn poll_stream_idle(&mut self, cx: &mut Context<'_>) -> ProtResult<()> {
+        let timeout = match self.config.stream_idle_timeout {
+            Some(timeout) => timeout,
+            None => return Ok(()),
+        };
+        let now = Instant::now();
+        let mut expired = vec![];
+        let mut next: Option<Instant> = None;
+        for (id, active) in &self.stream_active {
+            let deadline = *active + timeout;
+            if deadline <= now {
+                expired.push(*id);
+            } else {
+                next = Some(next.map_or(deadline, |n| n.min(deadline)));
+            }
+        }
+        for id in expired {
+            log::trace!("HTTP2流超过{:?}未收到数据, 重置该流:{:?}", timeout, id);
+            if let Some(d) = self.stream_disconnect.remove(&id) {
+                d.reset(id, Reason::CANCEL);
+            }
+            self.reset_stream(id, Reason::CANCEL)?;
+        }
+        if let Some(next) = next {
+            if self.stream_idle_sleep.is_some() {
+                self.stream_idle_sleep
+                    .as_mut()
+                    .unwrap()
+                    .as_mut()
+                    .set(tokio::time::sleep_until(next.into()));
+            } else {
+                self.stream_idle_sleep = Some(Box::pin(tokio::time::sleep_until(next.into())));
+            }
+            let _ = Pin::new(self.stream_idle_sleep.as_mut().unwrap()).poll(cx);
+        }
+        Ok(())
+    }
+
+    /// 数据因发送窗口不足而阻塞, 且长时间没有收发任何帧, 认为双方已死锁, 发送GOAWAY关闭连接
+    fn poll_flow_stall(&mut self, cx: &mut Context<'_>) {
+        let timeout = match self.config.flow_stall_timeout {
+            Some(timeout) => timeout,
+            None => return,
+        };
+        if !self.send_frames.is_blocked() {
+            self.flow_stall_sleep = None;
+            return;
+        }
+        let deadline = self.read_time.max(self.write_time) + timeout;
+        if deadline <= Instant::now() {
+            log::trace!("HTTP2发送窗口超过{:?}未更新, 关闭连接", timeout);
+            self.flow_stall_sleep = None;
+            self.go_away_now_data(
+                Reason::FLOW_CONTROL_ERROR,
+                Binary::from(b"flow control stalled".to_vec()),
+            );
+            return;
+        }
+        if self.flow_stall_sleep.is_some() {
+            self.flow_stall_sleep
+                .as_mut()
+                .unwrap()
+                .as_mut()
+                .set(tokio::time::sleep_until(deadline.into()));
+        } else {
+            self.flow_stall_sleep = Some(Box::pin(tokio::time::sleep_until(deadline.into())));
+        }
+        let _ = Pin::new(self.flow_stall_sleep.as_mut().unwrap()).poll(cx);
+    }
+
+    /// 本端发送GOAWAY后关闭连接的错误, 保活超时的关闭返回超时错误
+    fn go_away_error(&self, reason: Reason) -> ProtError {
+        if self.is_keep_alive_timeout {
+            ProtError::ka_timeout(if self.is_server { "server" } else { "client" })
+        } else {
+            ProtError::library_go_away(reason)
+        }
+    }
+
+    /// 超过间隔未收到帧时发送PING, 发送后超时仍未收到任何帧则关闭连接
+    fn poll_keep_alive(&mut self, cx: &mut Context<'_>) {
+        let interval = match self.config.keep_alive_interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        let now = Instant::now();
+        let mut deadline = match self.ping_pong.ping_sent() {
+            Some(sent) => sent + self.config.keep_alive_timeout,
+            None => self.read_time + interval,
+        };
+        if deadline <= now {
+            if self.ping_pong.ping_sent().is_some() {
+                log::trace!(
+                    "HTTP2保活PING超过{:?}未收到回复, 关闭连接",
+                    self.config.keep_alive_timeout
+                );
+                self.keep_alive_sleep = None;
+                self.is_keep_alive_timeout = true;
+                self.go_away_now_data(
+                    Reason::PROTOCOL_ERROR,
+                    Binary::from(b"keep alive timeout".to_vec()),
+                );
+                return;
+            }
+            self.ping_pong.send_ping();
+            deadline = now + self.config.keep_alive_timeout;
+        }
+        if self.keep_alive_sleep.is_some() {
+            self.keep_alive_sleep
+                .as_mut()
+                .unwrap()
+                .as_mut()
+                .set(tokio::time::sleep_until(deadline.into()));
+        } else {
+            self.keep_alive_sleep = Some(Box::pin(tokio::time::sleep_until(deadline.into())));
+        }
+        let _ = Pin::new(self.keep_alive_sleep.as_mut().unwrap()).poll(cx);
+    }
+
+    /// 优雅关闭, 先发送last_stream_id为最大值的GOAWAY, 之后不再接受新的流,
+    /// 已有的流处理完毕后再发送带实际last_stream_id的GOAWAY并关闭连接
+    pub fn graceful_shutdown(&mut self) {
+        if self.goaway.is_draining() || self.goaway.is_close_now() {
+            return;
+        }
+        let frame = GoAway::new(StreamIdentifier::from(0x7FFF_FFFF), Reason::NO_ERROR);
+        self.goaway.go_away_graceful(frame);
+    }
+
+    /// 优雅关闭中所有的流均已处理完毕则发送最终的GOAWAY, 返回是否已发起关闭
+    fn poll_drain(&mut self) -> bool {
+        if !self.goaway.is_draining() || self.goaway.is_close_now() {
+            return false;
+        }
+        if !self.active_streams.is_empty()
+            || !self.ready_queue.is_empty()
+            || !self.send_frames.is_empty()
+            || !self.response_queue.lock().unwrap().is_empty()
+        {
+            return false;
+        }
+        log::trace!("HTTP2优雅关闭, 已有的流处理完毕, 关闭连接");
+        self.go_away_now(Reason::NO_ERROR);
+        true
+    }
+
     pub fn go_away_now(&mut self, e: Reason) {
         let frame = GoAway::new(self.last_stream_id, e);
         self.goaway.go_away_now(frame);
@@ -475,6 +1108,11 @@ impl Control {
         self.goaway.go_away_now(frame);
     }
 
+    /// 收到的对端GOAWAY帧
+    pub fn remote_go_away(&self) -> Option<&GoAway> {
+        self.error.as_ref()
+    }
+
     pub fn last_goaway_reason(&mut self) -> &Reason {
         self.goaway.reason()
     }
@@ -505,18 +1143,52 @@ impl Control {
         stream_id: StreamIdentifier,
         push: Option<StreamIdentifier>,
     ) -> ProtResult<()> {
+        // 不允许推送时丢弃推送的响应, 客户端需要时会正常请求该资源
+        if push.is_some() && !self.is_push_enabled() {
+            log::warn!("对端或本端已禁用服务端推送, 丢弃推送的响应:{:?}", stream_id);
+            return Ok(());
+        }
+        // 流已被对端重置, 处理完成的响应不再发送
+        if self.remote_reset_streams.remove(&stream_id) {
+            log::trace!("HTTP2流已被重置, 丢弃响应:{:?}", stream_id);
+            return Ok(());
+        }
         let mut data = self.response_queue.lock().unwrap();
+        if push.is_none() {
+            if let Some(is_final) = self.stream_final.get(&stream_id) {
+                is_final.store(true, Ordering::Release);
+            }
+        }
         let is_end = res.body().is_end();
         let response = SendResponse::new(stream_id, push, res, webparse::Method::Get, is_end);
         data.push(response);
         Ok(())
     }
 
-    pub fn send_request(&mut self, req: RecvRequest) -> ProtResult<()> {
+    /// 流是否已结束
+    pub fn is_finish_stream(&self, stream_id: &StreamIdentifier) -> bool {
+        self.finish_streams.contains(stream_id)
+    }
+
+    /// 发送请求, 返回分配的流id
+    pub fn send_request(&mut self, req: RecvRequest) -> ProtResult<StreamIdentifier> {
         let is_end = req.body().is_end();
+        if self.active_streams.is_empty() {
+            // 空闲的连接重新开始计算读写时间
+            self.read_time = Instant::now();
+            self.write_time = self.read_time;
+        }
         let next_id = self.next_stream_id();
+        self.open_stream(next_id);
+        self.stream_timings.insert(
+            next_id,
+            Timings {
+                request_start: Some(Instant::now()),
+                ..Default::default()
+            },
+        );
         self.request_queue
             .push(SendRequest::new(next_id, req, is_end));
-        Ok(())
+        Ok(next_id)
     }
 }
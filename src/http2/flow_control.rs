@@ -10,24 +10,143 @@
 // -----
 // Created Date: 2023/09/14 09:42:25
 
-use webparse::http2::WindowSize;
+use std::collections::HashMap;
 
+use webparse::{
+    http::http2::frame::{Reason, StreamIdentifier},
+    http2::{WindowSize, DEFAULT_INITIAL_WINDOW_SIZE},
+};
+
+/// 发送窗口的最大值, 2^31-1
+const MAX_WINDOW_SIZE: i64 = (1 << 31) - 1;
+
+/// 发送方向的流量控制, 记录连接及每个流剩余可发送的数据大小
 #[derive(Debug)]
-#[allow(dead_code)]
 pub struct FlowControl {
+    /// 对端设置的流初始窗口大小
     window_size: i32,
+    /// 连接级别的剩余窗口
     available: i32,
+    /// 各流的剩余窗口, 未记录的流为初始窗口大小
+    streams: HashMap<StreamIdentifier, i32>,
 }
 
 impl FlowControl {
     pub fn new(default: WindowSize) -> Self {
         Self {
             window_size: default as i32,
-            available: default as i32,
+            available: DEFAULT_INITIAL_WINDOW_SIZE as i32,
+            streams: HashMap::new(),
         }
     }
 
     pub fn is_available(&self) -> bool {
         self.available > 0
     }
+
+    /// 连接级别的剩余窗口
+    pub fn available(&self) -> i32 {
+        self.available
+    }
+
+    /// 该流当前可发送的数据大小, 同时受连接窗口的限制
+    pub fn stream_available(&self, stream_id: &StreamIdentifier) -> i32 {
+        let stream = self
+            .streams
+            .get(stream_id)
+            .copied()
+            .unwrap_or(self.window_size);
+        std::cmp::min(self.available, stream)
+    }
+
+    /// 对端修改了SETTINGS_INITIAL_WINDOW_SIZE, 按差值调整所有流的窗口,
+    /// 任一窗口超出2^31-1时返回FLOW_CONTROL_ERROR
+    pub fn set_initial_window_size(&mut self, size: WindowSize) -> Result<(), Reason> {
+        let delta = size as i64 - self.window_size as i64;
+        if size as i64 > MAX_WINDOW_SIZE
+            || self.streams.values().any(|v| *v as i64 + delta > MAX_WINDOW_SIZE)
+        {
+            return Err(Reason::FLOW_CONTROL_ERROR);
+        }
+        for v in self.streams.values_mut() {
+            *v = (*v as i64 + delta) as i32;
+        }
+        self.window_size = size as i32;
+        Ok(())
+    }
+
+    /// 收到WINDOW_UPDATE, 流id为0时增加连接级别的窗口, 超出上限返回FLOW_CONTROL_ERROR
+    pub fn window_update(
+        &mut self,
+        stream_id: StreamIdentifier,
+        increment: WindowSize,
+    ) -> Result<(), Reason> {
+        if increment == 0 {
+            return Err(Reason::PROTOCOL_ERROR);
+        }
+        let window_size = self.window_size;
+        let value = if stream_id.is_zero() {
+            &mut self.available
+        } else {
+            self.streams.entry(stream_id).or_insert(window_size)
+        };
+        let next = *value as i64 + increment as i64;
+        if next > MAX_WINDOW_SIZE {
+            return Err(Reason::FLOW_CONTROL_ERROR);
+        }
+        *value = next as i32;
+        Ok(())
+    }
+
+    /// 发送数据后扣减连接及该流的窗口
+    pub fn send_data(&mut self, stream_id: StreamIdentifier, size: u32) {
+        self.available -= size as i32;
+        let window_size = self.window_size;
+        *self.streams.entry(stream_id).or_insert(window_size) -= size as i32;
+    }
+
+    /// 流结束后不再记录其窗口
+    pub fn remove_stream(&mut self, stream_id: &StreamIdentifier) {
+        self.streams.remove(stream_id);
+    }
+}
+
+/// 接收方向的流量控制, 累计已接收的数据达到阈值后才发送WINDOW_UPDATE, 避免过于频繁
+#[derive(Debug, Clone)]
+pub struct RecvFlowControl {
+    window_size: u32,
+    threshold: u32,
+    unacked: u32,
+}
+
+impl RecvFlowControl {
+    /// ratio为窗口的比例, 如0.5表示接收了半个窗口的数据后更新
+    pub fn new(window_size: WindowSize, ratio: f32) -> Self {
+        let threshold = ((window_size as f32 * ratio) as u32).max(1);
+        Self {
+            window_size,
+            threshold,
+            unacked: 0,
+        }
+    }
+
+    pub fn window_size(&self) -> WindowSize {
+        self.window_size
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// 记录收到的数据, 达到阈值时返回需要增加的窗口大小
+    pub fn recv_data(&mut self, size: u32) -> Option<u32> {
+        self.unacked = self.unacked.saturating_add(size);
+        if self.unacked >= self.threshold {
+            let increment = self.unacked;
+            self.unacked = 0;
+            Some(increment)
+        } else {
+            None
+        }
+    }
 }
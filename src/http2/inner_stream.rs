@@ -26,7 +26,7 @@ use webparse::{
     Version,
 };
 
-use crate::{HeaderHelper, ProtError, ProtResult, RecvRequest, RecvResponse};
+use crate::{body::TrailerSlot, HeaderHelper, ProtError, ProtResult, RecvRequest, RecvResponse};
 
 use crate::Body;
 
@@ -39,6 +39,8 @@ pub struct InnerStream {
     end_headers: bool,
     end_stream: bool,
     is_builder: bool,
+    /// 包体结束后收到的trailer头写入位置
+    trailers: Option<TrailerSlot>,
 }
 
 impl InnerStream {
@@ -53,9 +55,15 @@ impl InnerStream {
             end_headers: false,
             end_stream: false,
             is_builder: false,
+            trailers: None,
         }
     }
 
+    /// 是否已构建出请求或响应, 之后收到的HEADERS帧为trailer头
+    pub fn is_builder(&self) -> bool {
+        self.is_builder
+    }
+
     pub fn is_end(&self) -> bool {
         self.is_builder && self.end_stream && self.frames.is_empty()
     }
@@ -78,24 +86,45 @@ impl InnerStream {
         }
 
         while !self.frames.is_empty() {
-            if let Some(sender) = &mut self.sender {
-                if let Poll::Ready(Ok(_)) = sender.poll_reserve(cx) {
+            let sender = match &mut self.sender {
+                Some(sender) => sender,
+                None => {
+                    // 无包体或接收方已关闭, 丢弃后续的数据, 流结束时仍需正常完成
+                    self.frames.clear();
+                    break;
+                }
+            };
+            match sender.poll_reserve(cx) {
+                Poll::Ready(Ok(_)) => {
                     let frame = self.frames.pop_front().unwrap();
                     match frame {
                         Frame::Data(d) => {
                             self.recv_len += d.payload().remaining();
+                            // 长度为0且带END_STREAM的数据帧同样需要通知包体结束
                             let _ = sender.send_item((d.is_end_stream(), d.into_payload()));
                             if self.recv_len > self.content_len {
                                 return Err(ProtError::Extension("content len must not more"));
                             }
                         }
+                        Frame::Headers(header) if header.is_end_stream() => {
+                            let mut builder = header.into_response(response::Response::builder())?;
+                            if let (Some(slot), Some(fields)) =
+                                (&self.trailers, builder.headers_mut())
+                            {
+                                *slot.lock().unwrap() = Some(fields.clone());
+                            }
+                            let _ = sender.send_item((true, Binary::from(vec![])));
+                        }
                         _ => {
                             return Err(ProtError::Extension("must be data frame"));
                         }
                     }
-                } else {
-                    return Ok(false);
                 }
+                Poll::Ready(Err(_)) => {
+                    log::trace!("HTTP2包体接收方已关闭, 丢弃后续数据");
+                    self.sender = None;
+                }
+                Poll::Pending => return Ok(false),
             }
         }
 
@@ -136,6 +165,7 @@ impl InnerStream {
 
             Body::new(receiver, binary, is_end_stream)
         };
+        self.trailers = Some(recv.trailer_slot());
         self.content_len = builder.get_body_len() as usize;
         if self.content_len == 0 {
             self.content_len = usize::MAX;
@@ -185,6 +215,7 @@ impl InnerStream {
             builder.headers_mut().unwrap(),
             &mut recv,
         )?;
+        self.trailers = Some(recv.trailer_slot());
         self.content_len = builder.get_body_len() as usize;
         if self.content_len == 0 {
             self.content_len = usize::MAX;
@@ -21,12 +21,16 @@ mod inner_stream;
 mod builder;
 mod priority_queue;
 mod flow_control;
+mod stream_metrics;
 
-pub use flow_control::FlowControl;
-pub use priority_queue::PriorityQueue;
+pub use codec::{Codec, Direction, FrameAction, FrameInterceptor};
+pub use codec::{format_frame_head, FRAME_TRACE_TARGET};
+pub use flow_control::{FlowControl, RecvFlowControl};
+pub use priority_queue::{PriorityParam, PriorityQueue};
 pub use inner_stream::InnerStream;
 pub use send_response::{SendResponse, SendControl};
 pub use send_request::SendRequest;
+pub use stream_metrics::StreamMetrics;
 pub use control::{Control, ControlConfig};
 pub use client_connection::ClientH2Connection;
 pub use server_connection::ServerH2Connection;
@@ -10,25 +10,88 @@
 // -----
 // Created Date: 2023/09/14 09:42:25
 
-use std::{task::{Context, Poll}, collections::HashMap};
+use std::{task::{Context, Poll}, collections::{HashMap, VecDeque}};
 
-use algorithm::buf::Binary;
+use algorithm::buf::{Binary, Bt};
 use rbtree::RBTree;
 use tokio::io::{AsyncRead, AsyncWrite};
 use webparse::{
-    http::http2::{frame::{Frame, Priority, PriorityFrame, StreamIdentifier}, WindowSize},
+    http::http2::{frame::{Data, Flag, Frame, FrameHeader, Kind, Priority, PriorityFrame, Reason, StreamIdentifier}, WindowSize},
 };
 
 use crate::ProtResult;
 
 use super::{codec::Codec, FlowControl};
 
+/// RFC 9218中通过`Priority`头指定的优先级参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityParam {
+    /// 紧急程度, 0~7, 越小越优先, 默认为3
+    pub urgency: u8,
+    /// 是否可与同级的响应交替发送
+    pub incremental: bool,
+}
+
+impl Default for PriorityParam {
+    fn default() -> Self {
+        Self {
+            urgency: 3,
+            incremental: false,
+        }
+    }
+}
+
+impl PriorityParam {
+    /// 解析如`u=1, i`格式的头, 无法识别的参数忽略
+    pub fn parse(value: &str) -> Self {
+        let mut param = Self::default();
+        for item in value.split(',') {
+            let (key, val) = match item.split_once('=') {
+                Some((k, v)) => (k.trim(), Some(v.trim())),
+                None => (item.trim(), None),
+            };
+            match key {
+                "u" => {
+                    if let Some(u) = val.and_then(|v| v.parse::<u8>().ok()) {
+                        if u <= 7 {
+                            param.urgency = u;
+                        }
+                    }
+                }
+                "i" => param.incremental = val.map(|v| v == "?1").unwrap_or(true),
+                _ => {}
+            }
+        }
+        param
+    }
+
+    /// 转换成发送队列中的权重, 紧急程度越高权重越大,
+    /// 同一紧急程度下非incremental的优先发送
+    pub fn weight(&self) -> u8 {
+        let weight = (7 - self.urgency) * 32 + 16;
+        if self.incremental {
+            weight
+        } else {
+            weight + 1
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct PriorityQueue {
     pub send_queue: RBTree<PriorityFrame<Binary>, ()>,
     pub hash_weight: HashMap<StreamIdentifier, u8>,
     pub hash_depend: HashMap<StreamIdentifier, StreamIdentifier>,
+    /// 请求中`Priority`头指定的权重, 优先于PRIORITY帧
+    header_weight: HashMap<StreamIdentifier, u8>,
     pub flow_control: FlowControl,
+    /// 队列中待发送的数据大小
+    buffered_size: usize,
+    /// 不使用RFC7540的优先级, 忽略PRIORITY帧
+    no_priorities: bool,
+    /// 因流的发送窗口不足而等待的帧, 按流分别保存, 窗口更新后按顺序发送,
+    /// 不影响其它仍有窗口的流
+    blocked: HashMap<StreamIdentifier, VecDeque<Frame<Binary>>>,
 }
 
 impl PriorityQueue {
@@ -39,15 +102,52 @@ impl PriorityQueue {
                 (StreamIdentifier::zero(), 255),
             ]),
             hash_depend: HashMap::new(),
+            header_weight: HashMap::new(),
             flow_control: FlowControl::new(init_windows_size),
+            buffered_size: 0,
+            no_priorities: false,
+            blocked: HashMap::new(),
         }
     }
 
+    pub fn set_no_priorities(&mut self, no_priorities: bool) {
+        self.no_priorities = no_priorities;
+        if no_priorities {
+            // 连接级别的控制帧仍然优先发送
+            self.hash_weight.retain(|id, _| id.is_zero());
+            self.hash_depend.clear();
+        }
+    }
+
+    pub fn is_no_priorities(&self) -> bool {
+        self.no_priorities
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.send_queue.is_empty()
+        self.send_queue.is_empty() && self.blocked.is_empty()
+    }
+
+    /// 是否有数据因发送窗口不足而等待
+    pub fn is_blocked(&self) -> bool {
+        !self.blocked.is_empty()
+    }
+
+    pub fn buffered_size(&self) -> usize {
+        self.buffered_size
+    }
+
+    fn data_size(frame: &Frame<Binary>) -> usize {
+        match frame {
+            Frame::Data(d) => d.payload().remaining(),
+            _ => 0,
+        }
     }
 
     pub fn priority_recv(&mut self, p: Priority) {
+        if self.no_priorities {
+            log::trace!("未启用优先级, 忽略PRIORITY帧");
+            return;
+        }
         let (id, depend_id, weight) = p.into();
         self.hash_weight.insert(id, weight);
         if !depend_id.is_zero() {
@@ -59,7 +159,22 @@ impl PriorityQueue {
         }
     }
 
+    /// 根据请求中的`Priority`头设置流的优先级
+    pub fn priority_header(&mut self, stream_id: StreamIdentifier, param: PriorityParam) {
+        self.header_weight.insert(stream_id, param.weight());
+    }
+
     pub fn weight(&self, stream_id: &StreamIdentifier) -> u8 {
+        if let Some(weight) = self.header_weight.get(stream_id) {
+            return *weight;
+        }
+        if self.no_priorities {
+            // RFC7540的优先级不参与调度, 未指定的流按RFC 9218默认的紧急程度
+            if stream_id.is_zero() {
+                return 255;
+            }
+            return PriorityParam::default().weight();
+        }
         if self.hash_weight.contains_key(stream_id) {
             self.hash_weight[stream_id]
         } else {
@@ -67,13 +182,118 @@ impl PriorityQueue {
         }
     }
 
+    /// 对端修改了流的初始窗口大小, 窗口超出上限返回FLOW_CONTROL_ERROR
+    pub fn set_initial_window_size(&mut self, size: WindowSize) -> Result<(), Reason> {
+        self.flow_control.set_initial_window_size(size)
+    }
+
+    /// 收到WINDOW_UPDATE, 增加对应的发送窗口, 被阻塞的DATA帧可继续发送
+    pub fn window_update(&mut self, stream_id: StreamIdentifier, increment: WindowSize) -> Result<(), Reason> {
+        log::trace!("HTTP2收到窗口更新:{:?}, 增加:{}", stream_id, increment);
+        self.flow_control.window_update(stream_id, increment)
+    }
+
+    /// 按发送窗口取出可发送的部分, 窗口不足时拆分DATA帧, 剩余部分等待窗口更新
+    fn take_sendable(&mut self, frame: Frame<Binary>) -> (Option<Frame<Binary>>, Option<Frame<Binary>>) {
+        let stream_id = frame.stream_id();
+        let data = match frame {
+            Frame::Data(data) => data,
+            Frame::Reset(_) => {
+                self.flow_control.remove_stream(&stream_id);
+                return (Some(frame), None);
+            }
+            _ => return (Some(frame), None),
+        };
+        let size = data.payload().remaining();
+        let available = self.flow_control.stream_available(&stream_id);
+        if size == 0 || size as i64 <= available as i64 {
+            self.flow_control.send_data(stream_id, size as u32);
+            if data.is_end_stream() {
+                self.flow_control.remove_stream(&stream_id);
+            }
+            return (Some(Frame::Data(data)), None);
+        }
+        if available <= 0 {
+            log::trace!("HTTP2发送窗口不足, 等待窗口更新:{:?}", stream_id);
+            return (None, Some(Frame::Data(data)));
+        }
+        let is_end_stream = data.is_end_stream();
+        let mut payload = data.into_payload();
+        let head = Binary::from(payload.chunk()[..available as usize].to_vec());
+        payload.advance(available as usize);
+        self.flow_control.send_data(stream_id, available as u32);
+
+        let header = FrameHeader::new(Kind::Data, Flag::zero(), stream_id);
+        let send = Data::new(header, head);
+        let flag = if is_end_stream {
+            Flag::end_stream()
+        } else {
+            Flag::zero()
+        };
+        let header = FrameHeader::new(Kind::Data, flag, stream_id);
+        let remain = Data::new(header, payload);
+        (Some(Frame::Data(send)), Some(Frame::Data(remain)))
+    }
+
+    /// 取出一个已有窗口的等待中的流的首帧, 多个流时按优先级
+    fn take_unblocked(&mut self) -> Option<Frame<Binary>> {
+        let stream_id = self
+            .blocked
+            .iter()
+            .filter(|(id, queue)| match queue.front() {
+                Some(frame) if frame.is_data() => {
+                    Self::data_size(frame) == 0 || self.flow_control.stream_available(id) > 0
+                }
+                _ => true,
+            })
+            .max_by_key(|(id, _)| self.weight(id))
+            .map(|(id, _)| id.clone())?;
+        let frame = self.blocked.get_mut(&stream_id).unwrap().pop_front()?;
+        let (send, remain) = self.take_sendable(frame);
+        let queue = self.blocked.get_mut(&stream_id).unwrap();
+        if let Some(remain) = remain {
+            queue.push_front(remain);
+        }
+        if queue.is_empty() {
+            self.blocked.remove(&stream_id);
+        }
+        send
+    }
+
     pub fn send_frames(&mut self, stream_id: StreamIdentifier, vec: Vec<Frame<Binary>>) -> ProtResult<()> {
         for v in vec {
+            self.buffered_size += Self::data_size(&v);
             self.send_queue.insert(PriorityFrame::new(v, self.weight(&stream_id)), ());
         }
         Ok(())
     }
 
+    fn purge_blocked(&mut self, stream_id: &StreamIdentifier) {
+        if let Some(queue) = self.blocked.remove(stream_id) {
+            for frame in queue {
+                self.buffered_size -= Self::data_size(&frame);
+            }
+        }
+    }
+
+    /// 流已被重置, 丢弃该流所有待发送及等待窗口的帧, 并清理优先级及窗口
+    pub fn remove_stream(&mut self, stream_id: &StreamIdentifier) {
+        self.purge_blocked(stream_id);
+        let mut send_queue = RBTree::new();
+        while let Some((frame, _)) = self.send_queue.pop_first() {
+            if &frame.frame.stream_id() == stream_id {
+                self.buffered_size -= Self::data_size(&frame.frame);
+            } else {
+                send_queue.insert(frame, ());
+            }
+        }
+        self.send_queue = send_queue;
+        self.hash_weight.remove(stream_id);
+        self.hash_depend.remove(stream_id);
+        self.header_weight.remove(stream_id);
+        self.flow_control.remove_stream(stream_id);
+    }
+
     pub fn poll_handle<T>(
         &mut self,
         cx: &mut Context<'_>,
@@ -83,22 +303,37 @@ impl PriorityQueue {
         T: AsyncRead + AsyncWrite + Unpin,
     {
         loop {
-            if !codec.poll_ready(cx)?.is_ready() || self.send_queue.is_empty() {
+            if !codec.poll_ready(cx)?.is_ready() {
                 return Poll::Ready(None);
             }
-            if self.flow_control.is_available() {
-                let first = self.send_queue.pop_first().unwrap();
-                let _is_data = first.0.frame.is_data();
-                let _size = codec.send_frame(first.0.frame)?;
-            } else {
-                let first = self.send_queue.get_first().unwrap();
-                if first.0.frame.is_data() {
-                    return Poll::Ready(None)
+            // 窗口更新后等待中的数据优先发送
+            if let Some(send) = self.take_unblocked() {
+                self.buffered_size -= Self::data_size(&send);
+                codec.send_frame(send)?;
+                continue;
+            }
+            let frame = match self.send_queue.pop_first() {
+                Some(first) => first.0.frame,
+                None => return Poll::Ready(None),
+            };
+            let stream_id = frame.stream_id();
+            if let Some(queue) = self.blocked.get_mut(&stream_id) {
+                if !matches!(frame, Frame::Reset(_)) {
+                    // 排在该流等待中的数据之后, 保证同一个流的帧顺序
+                    queue.push_back(frame);
+                    continue;
                 }
-                let first = self.send_queue.pop_first().unwrap();
-                codec.send_frame(first.0.frame)?;
+                // 重置的流不再发送等待中的数据
+                self.purge_blocked(&stream_id);
+            }
+            let (send, remain) = self.take_sendable(frame);
+            if let Some(remain) = remain {
+                self.blocked.entry(stream_id).or_default().push_back(remain);
+            }
+            if let Some(send) = send {
+                self.buffered_size -= Self::data_size(&send);
+                codec.send_frame(send)?;
             }
-
         }
     }
 
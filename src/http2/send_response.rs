@@ -13,7 +13,13 @@
 use algorithm::buf::{Binary, BinaryMut, Bt};
 use webparse::http::http2::frame::PushPromise;
 
-use std::task::Context;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::Context,
+};
 use tokio::sync::mpsc::Sender;
 use webparse::{
     http::http2::frame::{Data, Flag, Frame, FrameHeader, Headers, Kind, StreamIdentifier},
@@ -21,7 +27,7 @@ use webparse::{
 };
 use webparse::{HeaderMap, HeaderName, HeaderValue};
 
-use crate::{ProtResult, RecvResponse};
+use crate::{Body, ProtError, ProtResult, RecvResponse};
 
 #[derive(Debug)]
 pub struct SendResponse {
@@ -31,6 +37,8 @@ pub struct SendResponse {
     pub encode_header: bool,
     pub encode_body: bool,
     pub is_end_stream: bool,
+    /// 1xx的临时响应, 仅发送HEADERS帧且不结束流
+    pub is_informational: bool,
 
     pub method: Method,
 }
@@ -50,10 +58,17 @@ impl SendResponse {
             encode_header: false,
             encode_body: false,
             is_end_stream,
+            is_informational: false,
             method,
         }
     }
 
+    pub fn new_informational(stream_id: StreamIdentifier, response: RecvResponse) -> Self {
+        let mut value = Self::new(stream_id, None, response, Method::Get, false);
+        value.is_informational = true;
+        value
+    }
+
     pub fn encode_headers(response: &RecvResponse) -> (HeaderMap, bool) {
         let mut headers = HeaderMap::new();
         let mut is_end = false;
@@ -77,7 +92,17 @@ impl SendResponse {
 
     pub fn encode_frames(&mut self, cx: &mut Context) -> (bool, Vec<Frame<Binary>>) {
         let mut result = vec![];
+        if self.is_informational {
+            // 临时响应不能携带END_STREAM, 后续还有最终响应
+            let header = FrameHeader::new(Kind::Headers, Flag::end_headers(), self.stream_id);
+            let (fields, _) = Self::encode_headers(&self.response);
+            let mut header = Headers::new(header, fields);
+            header.set_status(self.response.status());
+            result.push(Frame::Headers(header));
+            return (true, result);
+        }
         if !self.encode_header {
+            let mut end_on_headers = false;
             if let Some(push_id) = &self.push_id {
                 let header =
                     FrameHeader::new(Kind::PushPromise, Flag::end_headers(), self.stream_id);
@@ -86,6 +111,7 @@ impl SendResponse {
                 let mut push = PushPromise::new(header, push_id.clone(), fields);
                 if is_end {
                     push.flags_mut().set_end_stream();
+                    end_on_headers = true;
                 }
                 push.set_status(self.response.status());
                 result.push(Frame::PushPromise(push));
@@ -95,21 +121,36 @@ impl SendResponse {
                 let header = FrameHeader::new(Kind::Headers, Flag::end_headers(), self.stream_id);
                 let (fields, is_end) = Self::encode_headers(&self.response);
                 let mut header = Headers::new(header, fields);
-                if is_end {
+                // 带trailer时由最后的HEADERS帧结束流
+                if is_end && self.response.body().trailers().is_none() {
                     header.flags_mut().set_end_stream();
+                    end_on_headers = true;
                 }
                 header.set_status(self.response.status());
                 result.push(Frame::Headers(header));
                 self.encode_header = true;
             }
+            // 声明Content-Length为0的已由头部结束流, 不再发送包体
+            if end_on_headers {
+                self.encode_body = true;
+                self.is_end_stream = true;
+                return (true, result);
+            }
         }
 
         if !self.response.body().is_end() || !self.encode_body {
             self.encode_body = true;
             let mut binary = BinaryMut::new();
             let _ = self.response.body_mut().poll_encode_write(cx, &mut binary);
+            let is_end = self.response.body().is_end();
+            // 包体结束后发送trailer头, 如gRPC的grpc-status
+            let trailers = if is_end {
+                self.response.body().trailers()
+            } else {
+                None
+            };
             if binary.remaining() > 0 {
-                self.is_end_stream = self.response.body().is_end();
+                self.is_end_stream = is_end && trailers.is_none();
                 let flag = if self.is_end_stream {
                     Flag::end_stream()
                 } else {
@@ -119,6 +160,13 @@ impl SendResponse {
                 let data = Data::new(header, binary.freeze());
                 result.push(Frame::Data(data));
             }
+            if let Some(trailers) = trailers {
+                let header = FrameHeader::new(Kind::Headers, Flag::end_headers(), self.stream_id);
+                let mut header = Headers::new(header, trailers);
+                header.flags_mut().set_end_stream();
+                result.push(Frame::Headers(header));
+                self.is_end_stream = true;
+            }
         }
 
         (self.is_end_stream, result)
@@ -130,6 +178,9 @@ pub struct SendControl {
     pub stream_id: StreamIdentifier,
     pub sender: Sender<(StreamIdentifier, RecvResponse)>,
     pub method: Method,
+    response_queue: Arc<Mutex<Vec<SendResponse>>>,
+    /// 该流的最终响应已交给连接或流已关闭, 由连接在加入最终响应时设置
+    is_final: Arc<AtomicBool>,
 }
 
 impl SendControl {
@@ -137,11 +188,15 @@ impl SendControl {
         stream_id: StreamIdentifier,
         sender: Sender<(StreamIdentifier, RecvResponse)>,
         method: Method,
+        response_queue: Arc<Mutex<Vec<SendResponse>>>,
+        is_final: Arc<AtomicBool>,
     ) -> Self {
         SendControl {
             stream_id,
             sender,
             method,
+            response_queue,
+            is_final,
         }
     }
 
@@ -149,6 +204,26 @@ impl SendControl {
         let _ = self.sender.send((self.stream_id, res)).await;
         Ok(())
     }
+
+    /// 在最终响应前发送1xx的临时响应, 如103 Early Hints, 可多次调用
+    pub fn send_informational(&mut self, status: u16, headers: HeaderMap) -> ProtResult<()> {
+        // HTTP/2中不允许101协议切换
+        if !(100..200).contains(&status) || status == 101 {
+            return Err(ProtError::Extension("informational status must be 1xx"));
+        }
+        let mut response = webparse::Response::builder()
+            .status(status)
+            .body(Body::empty())?;
+        *response.headers_mut() = headers;
+        // 连接在持有队列锁时标记最终响应, 加锁后检查避免与其交错
+        let mut queue = self.response_queue.lock().unwrap();
+        // 最终响应已加入队列或已发出后不能再发送临时响应
+        if self.is_final.load(Ordering::Acquire) {
+            return Err(ProtError::Extension("final response already sent"));
+        }
+        queue.push(SendResponse::new_informational(self.stream_id, response));
+        Ok(())
+    }
 }
 
 unsafe impl Sync for SendControl {}
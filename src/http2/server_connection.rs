@@ -11,20 +11,25 @@
 // Created Date: 2023/10/07 09:41:03
 
 use std::{
+    collections::VecDeque,
+    future::{poll_fn, Future},
     net::SocketAddr,
+    pin::Pin,
     task::{ready, Context, Poll},
     time::Duration,
 };
 
 use algorithm::buf::{Binary, BinaryMut};
+use futures::{future::BoxFuture, stream::FuturesUnordered};
 use tokio_stream::Stream;
+use tokio_util::sync::CancellationToken;
 
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     sync::mpsc::{channel, Receiver},
 };
 use webparse::{
-    http::http2::frame::{Reason, StreamIdentifier},
+    http::http2::frame::{GoAway, Reason, StreamIdentifier},
     Version,
 };
 
@@ -33,14 +38,25 @@ use crate::{
     ProtError, ProtResult, RecvRequest, RecvResponse, TimeoutLayer,
 };
 
-use super::{codec::Codec, control::ControlConfig, Control};
+use super::{
+    codec::{Codec, FrameInterceptor},
+    control::ControlConfig,
+    Control, StreamMetrics,
+};
 
 pub struct ServerH2Connection<T> {
     codec: Codec<T>,
     inner: InnerConnection,
     timeout: Option<TimeoutLayer>,
+    /// 处理函数运行期间读取到的新请求或连接的结束, 之后由incoming依次返回
+    pending: VecDeque<Option<ProtResult<RecvRequest>>>,
+    /// 各流独立处理中的请求
+    handling: FuturesUnordered<StreamHandle>,
 }
 
+/// 单个流的独立处理, 完成时返回该流及响应, 处理超时返回None
+type StreamHandle = BoxFuture<'static, (StreamIdentifier, Option<ProtResult<RecvResponse>>)>;
+
 struct InnerConnection {
     state: State,
 
@@ -85,6 +101,16 @@ where
                         reset_stream_max: builder.reset_stream_max,
                         remote_reset_stream_max: builder.pending_accept_reset_stream_max,
                         settings: builder.settings.clone(),
+                        stream_timeout: builder.stream_timeout,
+                        stream_idle_timeout: builder.stream_idle_timeout,
+                        max_buffered_size: builder.max_buffered_size,
+                        no_rfc7540_priorities: builder.no_rfc7540_priorities,
+                        window_update_threshold: builder.window_update_threshold,
+                        flow_stall_timeout: builder.flow_stall_timeout,
+                        keep_alive_interval: builder.keep_alive_interval,
+                        keep_alive_timeout: builder.keep_alive_timeout,
+                        enable_push: builder.enable_push,
+                        remote_enable_push: true,
                     },
                     sender,
                     true,
@@ -92,6 +118,8 @@ where
                 receiver_push: Some(receiver),
             },
             timeout: None,
+            pending: VecDeque::new(),
+            handling: FuturesUnordered::new(),
         }
     }
 
@@ -108,6 +136,11 @@ where
         connect
     }
 
+    /// 设置帧拦截器, 收发的每一帧均会经过该拦截器
+    pub fn set_frame_interceptor(&mut self, interceptor: Option<FrameInterceptor>) {
+        self.codec.set_frame_interceptor(interceptor);
+    }
+
     pub fn set_read_timeout(&mut self, read_timeout: Option<Duration>) {
         if self.timeout.is_none() {
             self.timeout = Some(TimeoutLayer::new());
@@ -146,6 +179,40 @@ where
         self.timeout = timeout_layer;
     }
 
+    pub fn set_stream_timeout(&mut self, stream_timeout: Option<Duration>) {
+        self.inner.control.set_stream_timeout(stream_timeout);
+    }
+
+    /// 该连接上流的并发统计
+    pub fn stream_metrics(&self) -> StreamMetrics {
+        self.inner.control.stream_metrics()
+    }
+
+    /// 发送GOAWAY, 发送完成后连接结束
+    pub fn go_away_now(&mut self, reason: Reason) {
+        self.inner.control.go_away_now(reason);
+    }
+
+    /// 优雅关闭, 不再接受新的流, 已有的流处理完毕后关闭连接
+    pub fn graceful_shutdown(&mut self) {
+        self.inner.control.graceful_shutdown();
+    }
+
+    /// 收到的对端GOAWAY帧, 可获取关闭的原因及调试数据
+    pub fn remote_go_away(&self) -> Option<&GoAway> {
+        self.inner.control.remote_go_away()
+    }
+
+    /// 收到对端RST_STREAM的数量
+    pub fn remote_reset_count(&self) -> usize {
+        self.inner.control.remote_reset_count()
+    }
+
+    /// 设置连接级别的断开通知, 连接结束时通知所有流
+    pub fn set_disconnect_token(&mut self, token: CancellationToken) {
+        self.inner.control.set_disconnect_token(token);
+    }
+
     pub fn pull_accept(&mut self, _cx: &mut Context<'_>) -> Poll<Option<ProtResult<()>>> {
         Poll::Pending
     }
@@ -185,20 +252,123 @@ where
         middles: &mut Vec<Box<dyn Middleware>>,
     ) -> ProtResult<Option<bool>> {
         let stream_id: Option<StreamIdentifier> = r.extensions_mut().remove::<StreamIdentifier>();
+        let stream_id = stream_id.unwrap_or(StreamIdentifier::client_first());
+
+        let remaining = self.inner.control.stream_remaining(&stream_id);
+        if let Some((mut f, mut middles)) = Self::fork_handler(f, middles) {
+            // 该流独立处理, 由incoming在完成时发送响应
+            let addr = *addr;
+            self.handling.push(Box::pin(async move {
+                let fut =
+                    HttpHelper::handle_request(Version::Http2, &addr, r, &mut f, &mut middles);
+                let res = match remaining {
+                    Some(remaining) => tokio::time::timeout(remaining, fut).await.ok(),
+                    None => Some(fut.await),
+                };
+                (stream_id, res)
+            }));
+            return Ok(None);
+        }
 
-        let res = HttpHelper::handle_request(Version::Http2, addr, r, f, middles).await?;
-        self.send_response(res, stream_id.unwrap_or(StreamIdentifier::client_first()))
-            .await?;
+        let mut sleep = remaining.map(|remaining| Box::pin(tokio::time::sleep(remaining)));
+        let fut = HttpHelper::handle_request(Version::Http2, addr, r, f, middles);
+        tokio::pin!(fut);
+        // 处理函数运行期间继续读取连接, 以便及时通知对端的重置及连接的断开
+        let res = poll_fn(|cx| {
+            if let Poll::Ready(res) = fut.as_mut().poll(cx) {
+                return Poll::Ready(Some(res));
+            }
+            if let Some(sleep) = &mut sleep {
+                if sleep.as_mut().poll(cx).is_ready() {
+                    return Poll::Ready(None);
+                }
+            }
+            self.poll_while_handle(cx);
+            Poll::Pending
+        })
+        .await;
+        let res = match res {
+            Some(res) => res?,
+            None => {
+                // 处理超时, 丢弃处理函数并取消该流, 不影响其它的流
+                self.inner.control.reset_stream(stream_id, Reason::CANCEL)?;
+                return Ok(None);
+            }
+        };
+        self.send_response(res, stream_id).await?;
         return Ok(None);
     }
 
+    /// 处理函数及所有中间件均可复制时, 返回供单个流使用的副本
+    fn fork_handler(
+        f: &Box<dyn HttpTrait>,
+        middles: &Vec<Box<dyn Middleware>>,
+    ) -> Option<(Box<dyn HttpTrait>, Vec<Box<dyn Middleware>>)> {
+        let f = f.fork()?;
+        let middles = middles
+            .iter()
+            .map(|m| m.fork())
+            .collect::<Option<Vec<_>>>()?;
+        Some((f, middles))
+    }
+
+    /// 独立处理的流已完成, 发送响应, 处理超时则取消该流
+    async fn finish_handle(
+        &mut self,
+        stream_id: StreamIdentifier,
+        res: Option<ProtResult<RecvResponse>>,
+    ) -> ProtResult<()> {
+        match res {
+            Some(res) => self.send_response(res?, stream_id).await,
+            // 已由连接的超时检测或对端重置
+            None if self.inner.control.stream_remaining(&stream_id).is_none() => Ok(()),
+            None => {
+                // 处理超时, 丢弃处理函数并取消该流, 不影响其它的流
+                self.inner.control.reset_stream(stream_id, Reason::CANCEL)
+            }
+        }
+    }
+
+    /// 处理期间读取到连接的结束
+    fn is_read_end(&self) -> bool {
+        matches!(self.pending.back(), Some(None) | Some(Some(Err(_))))
+    }
+
+    /// 读取处理期间收到的帧, 新的请求暂存, 连接结束时通知所有的流
+    fn poll_while_handle(&mut self, cx: &mut Context<'_>) {
+        loop {
+            if self.is_read_end() {
+                return;
+            }
+            match Pin::new(&mut *self).poll_next(cx) {
+                Poll::Pending => return,
+                Poll::Ready(Some(Ok(r))) => self.pending.push_back(Some(Ok(r))),
+                Poll::Ready(v) => {
+                    log::trace!("处理请求期间连接已结束");
+                    self.inner.control.cancel_disconnect();
+                    self.pending.push_back(v);
+                }
+            }
+        }
+    }
+
     pub async fn incoming(&mut self) -> ProtResult<Option<RecvRequest>> {
         use tokio_stream::StreamExt;
+        if let Some(req) = self.pending.pop_front() {
+            return req.transpose();
+        }
         loop {
             let mut receiver = self.inner.receiver_push.take().unwrap();
+            let mut handling = std::mem::take(&mut self.handling);
             tokio::select! {
+                Some((stream_id, res)) = handling.next(), if !handling.is_empty() => {
+                    self.inner.receiver_push = Some(receiver);
+                    self.handling = handling;
+                    self.finish_handle(stream_id, res).await?;
+                },
                 res = receiver.recv() => {
                     self.inner.receiver_push = Some(receiver);
+                    self.handling = handling;
                     if res.is_some() {
                         let res = res.unwrap();
                         let id = self.inner.control.next_stream_id();
@@ -209,6 +379,7 @@ where
                 },
                 req = self.next() => {
                     self.inner.receiver_push = Some(receiver);
+                    self.handling = handling;
                     match req {
                         None => return Ok(None),
                         Some(Err(e)) => return Err(e),
@@ -232,7 +403,10 @@ where
                 let e = ProtError::GoAway(debug_data.clone(), reason, initiator);
                 tracing::debug!(error = ?e, "Connection::poll; connection error");
 
-                if self.inner.control.last_goaway_reason() == &reason {
+                // 对端发送的GOAWAY无需回复, 直接关闭
+                if initiator == Initiator::Remote
+                    || self.inner.control.last_goaway_reason() == &reason
+                {
                     self.inner.state = State::Closing(reason, initiator);
                     return Ok(());
                 }
@@ -256,7 +430,6 @@ where
             .inner
             .control
             .error
-            .take()
             .as_ref()
             .map_or((Binary::new(), Reason::NO_ERROR), |frame| {
                 (frame.debug_data().clone(), frame.reason())
@@ -277,11 +450,33 @@ where
         self.inner.control.set_handshake_status(binary, false)
     }
 
+    /// 缓存的待发送数据超过上限时, 等待数据写出后再继续,
+    /// 期间读取对端的WINDOW_UPDATE, 连接已结束时不再等待
+    pub async fn wait_buffer_ready(&mut self) -> ProtResult<()> {
+        poll_fn(|cx| {
+            if !self.inner.control.is_buffer_full() {
+                return Poll::Ready(Ok(()));
+            }
+            self.poll_while_handle(cx);
+            if self.is_read_end() {
+                return Poll::Ready(Ok(()));
+            }
+            ready!(self.inner.control.poll_write(cx, &mut self.codec, false))?;
+            if self.inner.control.is_buffer_full() {
+                Poll::Pending
+            } else {
+                Poll::Ready(Ok(()))
+            }
+        })
+        .await
+    }
+
     pub async fn send_response(
         &mut self,
         mut res: RecvResponse,
         stream_id: StreamIdentifier,
     ) -> ProtResult<()> {
+        self.wait_buffer_ready().await?;
         HeaderHelper::process_response_header(Version::Http2, false, &mut res)?;
         self.inner.control.send_response(res, stream_id).await
     }
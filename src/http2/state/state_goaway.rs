@@ -19,6 +19,8 @@ use crate::{http2::codec::Codec, ProtResult};
 
 pub struct StateGoAway {
     close_now: bool,
+    /// 已发送首个GOAWAY, 等待已有的流处理完毕
+    is_draining: bool,
     goaway: Option<GoAway>,
     reason: Reason,
 }
@@ -27,6 +29,7 @@ impl StateGoAway {
     pub fn new() -> Self {
         StateGoAway {
             close_now: false,
+            is_draining: false,
             goaway: None,
             reason: Reason::NO_ERROR,
         }
@@ -48,6 +51,10 @@ impl StateGoAway {
 
             let reason = frame.reason();
             codec.send_frame(Frame::GoAway(frame))?;
+            // 优雅关闭的首个GOAWAY, 连接继续处理已有的流
+            if !self.close_now {
+                return Poll::Ready(None);
+            }
             return Poll::Ready(Some(Ok(reason)));
         } else if self.is_close_now() {
             return match self.goaway.as_ref().map(|going_away| going_away.reason()) {
@@ -64,6 +71,16 @@ impl StateGoAway {
         self.goaway = Some(frame);
     }
 
+    /// 发送GOAWAY通知对端不再创建新的流, 但不关闭连接
+    pub fn go_away_graceful(&mut self, frame: GoAway) {
+        self.is_draining = true;
+        self.goaway = Some(frame);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.is_draining
+    }
+
     pub fn is_close_now(&self) -> bool {
         self.close_now
     }
@@ -10,26 +10,60 @@
 // -----
 // Created Date: 2023/09/14 09:42:25
 
-use std::task::{Context, Poll};
+use std::{
+    task::{Context, Poll},
+    time::Instant,
+};
 
 use tokio::io::{AsyncRead, AsyncWrite};
 use webparse::http::http2::frame::{Ping, Frame};
 
 use crate::{http2::codec::Codec, ProtResult};
 
+/// 保活PING的数据, 用于区分对端的PING
+const KEEP_ALIVE_PAYLOAD: [u8; 8] = *b"wmhttpka";
+
 pub struct StatePingPong {
     ping: Option<Ping>,
+    /// 待发送的保活PING
+    keep_alive: Option<Ping>,
+    /// 保活PING的发送时间, 收到任意帧后清除
+    ping_sent: Option<Instant>,
 }
 
 impl StatePingPong {
     pub fn new() -> Self {
-        StatePingPong { ping: None }
+        StatePingPong {
+            ping: None,
+            keep_alive: None,
+            ping_sent: None,
+        }
     }
 
     pub fn receive(&mut self, ping: Ping) {
+        // 对端回复的ACK无需再回复
+        if ping.is_ack() {
+            return;
+        }
         self.ping = Some(ping);
     }
 
+    /// 发送保活PING, 等待对端回复
+    pub fn send_ping(&mut self) {
+        self.keep_alive = Some(Ping::new(KEEP_ALIVE_PAYLOAD));
+        self.ping_sent = Some(Instant::now());
+    }
+
+    /// 等待回复中的保活PING的发送时间
+    pub fn ping_sent(&self) -> Option<Instant> {
+        self.ping_sent
+    }
+
+    /// 收到任意帧均表示连接存活
+    pub fn recv_frame(&mut self) {
+        self.ping_sent = None;
+    }
+
     
     pub fn poll_handle<T>(
         &mut self,
@@ -47,7 +81,13 @@ impl StatePingPong {
 
             let pong = frame.ret_pong();
             codec.send_frame(Frame::Ping(pong))?;
-            return Poll::Ready(Ok(()));
+        }
+        if let Some(frame) = self.keep_alive.take() {
+            if !codec.poll_ready(cx)?.is_ready() {
+                self.keep_alive = Some(frame);
+                return Poll::Pending;
+            }
+            codec.send_frame(Frame::Ping(frame))?;
         }
         return Poll::Ready(Ok(()));
     }
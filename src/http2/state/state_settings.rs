@@ -20,6 +20,9 @@ use crate::{
     ProtError, ProtResult,
 };
 
+/// RFC 9218中的SETTINGS_NO_RFC7540_PRIORITIES
+const SETTINGS_NO_RFC7540_PRIORITIES: u16 = 0x9;
+
 pub struct StateSettings {
     state: LocalState,
     remote: Option<Settings>,
@@ -68,7 +71,13 @@ impl StateSettings {
         let mut is_wait = true;
         match &self.state {
             LocalState::Send(settings) => {
-                codec.send_frame(Frame::Settings(settings.clone()))?;
+                // 告知对端本端不使用RFC7540的优先级
+                let extra: &[(u16, u32)] = if config.no_rfc7540_priorities {
+                    &[(SETTINGS_NO_RFC7540_PRIORITIES, 1)]
+                } else {
+                    &[]
+                };
+                codec.send_settings(settings.clone(), extra)?;
                 self.state = LocalState::WaitAck(settings.clone());
             }
             LocalState::WaitAck(_) => {}
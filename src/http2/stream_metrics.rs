@@ -0,0 +1,63 @@
+// Copyright 2022 - 2024 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/20 09:12:40
+
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc,
+};
+
+/// HTTP/2连接上流的并发统计, 可复制后在其它线程中读取
+#[derive(Debug, Clone, Default)]
+pub struct StreamMetrics {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// 当前活跃的流数
+    current: AtomicUsize,
+    /// 活跃流数的峰值
+    peak: AtomicUsize,
+    /// 累计打开的流数
+    total: AtomicU64,
+}
+
+impl StreamMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 当前活跃的流数
+    pub fn current(&self) -> usize {
+        self.inner.current.load(Ordering::Relaxed)
+    }
+
+    /// 活跃流数的峰值
+    pub fn peak(&self) -> usize {
+        self.inner.peak.load(Ordering::Relaxed)
+    }
+
+    /// 累计打开的流数
+    pub fn total(&self) -> u64 {
+        self.inner.total.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn open(&self) {
+        let current = self.inner.current.fetch_add(1, Ordering::Relaxed) + 1;
+        self.inner.peak.fetch_max(current, Ordering::Relaxed);
+        self.inner.total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn close(&self) {
+        self.inner.current.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -12,13 +12,72 @@
 
 use std::net::SocketAddr;
 
-use webparse::{HeaderName, Response, Version};
+use webparse::{HeaderName, Request, Response, Version};
 
-use crate::{HttpTrait, Middleware, ProtResult, RecvRequest, RecvResponse};
+use crate::{
+    http2::PriorityParam, ContentRange, HttpTrait, Middleware, OverloadLayer, ProtResult,
+    RecvRequest, RecvResponse, TlsInfo,
+};
 
 pub struct HttpHelper;
 
+/// HTTP/1请求行中的请求目标为`*`, 解析时以`/`代替, 通过该标记还原
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct AsteriskForm;
+
 impl HttpHelper {
+    /// 复制请求用于重试, 包体无法复制的请求不可重试, 返回None
+    pub fn try_clone_request(req: &RecvRequest) -> Option<RecvRequest> {
+        let body = req.body().try_clone()?;
+        let mut clone = Request::builder()
+            .method(req.method().clone())
+            .url(req.url().clone())
+            .version(req.version().clone())
+            .body(body)
+            .ok()?;
+        *clone.headers_mut() = req.headers().clone();
+        Some(clone)
+    }
+
+    /// 请求头中声明的包体大小, 无需读取包体, 可用于配额检查或日志.
+    /// chunked或未声明Content-Length时返回None
+    pub fn declared_body_len(req: &RecvRequest) -> Option<u64> {
+        if req.headers().is_chunked() {
+            return None;
+        }
+        req.headers()
+            .get_str_value(&HeaderName::CONTENT_LENGTH)?
+            .trim()
+            .parse::<u64>()
+            .ok()
+    }
+
+    /// 解析响应中的Content-Range, 如范围请求返回的206, 用于断点续传时确认收到的范围及总大小
+    pub fn content_range(res: &RecvResponse) -> Option<ContentRange> {
+        ContentRange::from_headers(res.headers())
+    }
+
+    /// 是否为asterisk-form的`OPTIONS *`请求
+    pub fn is_asterisk_options(req: &RecvRequest) -> bool {
+        req.method().as_str() == "OPTIONS"
+            && (req.path() == "*" || req.extensions().get::<AsteriskForm>().is_some())
+    }
+
+    /// 是否为TLS的0-RTT早期数据中收到的非幂等请求, 该类请求可能被重放, 不可直接处理
+    pub fn is_unsafe_early_data(req: &RecvRequest) -> bool {
+        let is_early = req
+            .extensions()
+            .get::<TlsInfo>()
+            .is_some_and(|info| info.is_early_data);
+        if !is_early {
+            return false;
+        }
+        !matches!(
+            req.method().as_str(),
+            "GET" | "HEAD" | "OPTIONS" | "TRACE" | "PUT" | "DELETE"
+        )
+    }
+
     pub async fn handle_request(
         version: Version,
         addr: &Option<SocketAddr>,
@@ -45,19 +104,65 @@ impl HttpHelper {
             r.headers_mut()
                 .system_insert("{client_addr}".to_string(), format!("{}", addr));
         }
+        // RFC 9218的优先级参数, HTTP/2中已用于调度响应的发送顺序
+        if let Some(value) = r.headers().get_str_value(&"Priority") {
+            let param = PriorityParam::parse(&value);
+            r.extensions_mut().insert(param);
+        }
         let mut response = None;
+        // 已进入的中间件数, 只有这些中间件处理响应
+        let mut entered = 0;
 
-        f.middle_operate(&mut r, middles).await?;
+        // 过载时不调用处理函数, 处理期间持有正在处理的计数
+        let overload = r.extensions().get::<OverloadLayer>().cloned();
+        let in_flight = overload.as_ref().and_then(|layer| layer.try_acquire());
+        if let (Some(layer), None) = (&overload, &in_flight) {
+            log::trace!("服务过载, 正在处理的请求数:{}, 回复503", layer.in_flight());
+            let mut res = Response::builder()
+                .status(503)
+                .header("Retry-After", layer.retry_after().as_secs().max(1).to_string())
+                .body("service unavailable")
+                .unwrap()
+                .into_type();
+            *res.version_mut() = version;
+            response = Some(res);
+        } else if Self::is_unsafe_early_data(&r) {
+            // RFC 8470, 由客户端在握手完成后重新发送
+            log::trace!("0-RTT早期数据中收到非幂等请求:{}, 回复425", r.method().as_str());
+            let mut res = Response::builder()
+                .status(425)
+                .body("too early")
+                .unwrap()
+                .into_type();
+            *res.version_mut() = version;
+            response = Some(res);
+        } else {
+            // 告知处理方该请求来自早期数据
+            if r.extensions().get::<TlsInfo>().is_some_and(|info| info.is_early_data) {
+                r.headers_mut().insert("Early-Data", "1");
+            }
+            f.middle_operate(&mut r, middles).await?;
 
-        for middle in middles.iter_mut() {
-            if let Some(res) = middle.as_mut().process_request(&mut r).await? {
-                response = Some(res);
-                break;
+            entered = middles.len();
+            for (i, middle) in middles.iter_mut().enumerate() {
+                if let Some(mut res) = middle.as_mut().process_request(&mut r).await? {
+                    // 中间件直接返回响应, 跳过之后的中间件及处理函数
+                    log::trace!("中间件直接返回响应:{}", res.status());
+                    *res.version_mut() = version.clone();
+                    response = Some(res);
+                    entered = i + 1;
+                    break;
+                }
             }
         }
 
         if response.is_none() {
-            let res = match f.operate(r).await {
+            let res = if Self::is_asterisk_options(&r) {
+                f.operate_options(r).await
+            } else {
+                f.operate(r).await
+            };
+            let res = match res {
                 Ok(mut res) => {
                     *res.version_mut() = version;
                     // 如果外部有设置编码，内部不做改变，如果有body大小值，不做任何改变，因为改变会变更大小值
@@ -67,6 +172,11 @@ impl HttpHelper {
                             .get_option_value(&HeaderName::CONTENT_ENCODING)
                             .is_none()
                         && (!res.body().is_end() || res.body_mut().origin_len() > 1024)
+                        // 事件流需逐个事件发送, 压缩会缓存数据
+                        && !res
+                            .headers()
+                            .get_str_value(&"Content-Type")
+                            .is_some_and(|v| v.starts_with("text/event-stream"))
                     {
                         if gzip {
                             res.headers_mut()
@@ -86,6 +196,10 @@ impl HttpHelper {
                     for i in 0usize..middles.len() {
                         middles[i].process_error(None, &e).await;
                     }
+                    // 该类错误不回复, 直接关闭连接
+                    if f.is_close_on_error(&e) {
+                        return Err(e);
+                    }
                     Response::builder()
                         .status(500)
                         .body("server inner error")
@@ -96,7 +210,7 @@ impl HttpHelper {
             response = Some(res);
         }
         let mut response = response.unwrap();
-        for i in (0usize..middles.len()).rev() {
+        for i in (0usize..entered).rev() {
             middles[i].process_response(&mut response).await?;
         }
         Ok(response)
@@ -10,8 +10,10 @@
 // -----
 // Created Date: 2023/11/10 02:23:05
 
+mod overload;
 mod rate_limit;
 mod timeout;
 
+pub use overload::{InFlight, OverloadLayer};
 pub use rate_limit::{RateLimitLayer, Rate};
 pub use timeout::TimeoutLayer;
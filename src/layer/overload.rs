@@ -0,0 +1,103 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/20 16:18:27
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// 服务端的过载保护, 正在处理的请求数达到高水位后直接回复503,
+/// 降到低水位以下后恢复处理. 需在所有连接间共享同一个实例
+#[derive(Debug, Clone)]
+pub struct OverloadLayer {
+    in_flight: Arc<AtomicUsize>,
+    is_overload: Arc<AtomicBool>,
+    high_water: usize,
+    low_water: usize,
+    /// 503中Retry-After告知客户端的重试间隔
+    retry_after: Duration,
+}
+
+impl OverloadLayer {
+    pub fn new(high_water: usize, low_water: usize) -> Self {
+        Self {
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            is_overload: Arc::new(AtomicBool::new(false)),
+            high_water,
+            low_water: low_water.min(high_water),
+            retry_after: Duration::from_secs(1),
+        }
+    }
+
+    pub fn set_retry_after(&mut self, retry_after: Duration) {
+        self.retry_after = retry_after;
+    }
+
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+
+    pub fn high_water(&self) -> usize {
+        self.high_water
+    }
+
+    pub fn low_water(&self) -> usize {
+        self.low_water
+    }
+
+    /// 当前正在处理的请求数
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    pub fn is_overload(&self) -> bool {
+        self.is_overload.load(Ordering::Relaxed)
+    }
+
+    /// 尝试开始处理一个请求, 过载时返回None, 返回的计数在释放时结束
+    pub fn try_acquire(&self) -> Option<InFlight> {
+        let now = self.in_flight();
+        if self.is_overload() {
+            if now > self.low_water {
+                return None;
+            }
+            log::trace!("正在处理的请求数{}已降到低水位, 恢复处理", now);
+            self.is_overload.store(false, Ordering::Relaxed);
+        }
+        if now >= self.high_water {
+            log::trace!("正在处理的请求数{}达到高水位, 开始拒绝请求", now);
+            self.is_overload.store(true, Ordering::Relaxed);
+            return None;
+        }
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        Some(InFlight {
+            layer: self.clone(),
+        })
+    }
+}
+
+/// 正在处理的请求计数, 释放时减少
+pub struct InFlight {
+    layer: OverloadLayer,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let now = self.layer.in_flight.fetch_sub(1, Ordering::Relaxed) - 1;
+        if now <= self.layer.low_water {
+            self.layer.is_overload.store(false, Ordering::Relaxed);
+        }
+    }
+}
@@ -28,11 +28,17 @@ pub struct TimeoutLayer {
     pub timeout: Option<Duration>,
     /// keep alive 超时时长
     pub ka_timeout: Option<Duration>,
+    /// 连接读取的空闲超时, 超过该时长未收到数据则断开
+    pub read_idle_timeout: Option<Duration>,
+    /// 连接写入的空闲超时, 超过该时长数据未能写出则断开
+    pub write_idle_timeout: Option<Duration>,
 
     read_timeout_sleep: Option<Pin<Box<Sleep>>>,
     write_timeout_sleep: Option<Pin<Box<Sleep>>>,
     timeout_sleep: Option<Pin<Box<Sleep>>>,
     ka_timeout_sleep: Option<Pin<Box<Sleep>>>,
+    read_idle_sleep: Option<Pin<Box<Sleep>>>,
+    write_idle_sleep: Option<Pin<Box<Sleep>>>,
 }
 
 impl Clone for TimeoutLayer {
@@ -43,10 +49,14 @@ impl Clone for TimeoutLayer {
             write_timeout: self.write_timeout.clone(),
             timeout: self.timeout.clone(),
             ka_timeout: self.ka_timeout.clone(),
+            read_idle_timeout: self.read_idle_timeout.clone(),
+            write_idle_timeout: self.write_idle_timeout.clone(),
             read_timeout_sleep: None,
             write_timeout_sleep: None,
             timeout_sleep: None,
             ka_timeout_sleep: None,
+            read_idle_sleep: None,
+            write_idle_sleep: None,
 
         }
     }
@@ -60,11 +70,15 @@ impl TimeoutLayer {
             write_timeout: None,
             timeout: None,
             ka_timeout: None,
+            read_idle_timeout: None,
+            write_idle_timeout: None,
 
             read_timeout_sleep: None,
             write_timeout_sleep: None,
             timeout_sleep: None,
             ka_timeout_sleep: None,
+            read_idle_sleep: None,
+            write_idle_sleep: None,
         }
     }
     
@@ -88,6 +102,58 @@ impl TimeoutLayer {
         self.ka_timeout = ka_timeout;
     }
 
+    pub fn set_read_idle_timeout(&mut self, read_idle_timeout: Option<Duration>) {
+        self.read_idle_timeout = read_idle_timeout;
+    }
+
+    pub fn set_write_idle_timeout(&mut self, write_idle_timeout: Option<Duration>) {
+        self.write_idle_timeout = write_idle_timeout;
+    }
+
+    fn poll_sleep(sleep: &mut Option<Pin<Box<Sleep>>>, cx: &mut Context<'_>, next: Instant) {
+        if sleep.is_some() {
+            sleep.as_mut().unwrap().as_mut().set(tokio::time::sleep_until(next.into()));
+        } else {
+            *sleep = Some(Box::pin(tokio::time::sleep_until(next.into())));
+        }
+        let _ = Pin::new(sleep.as_mut().unwrap()).poll(cx);
+    }
+
+    /// 检查连接的读写空闲超时, read_time及write_time为最后一次读取及写出数据的时间.
+    /// 数据未写完前对端无需响应, 读取的空闲从两者中较晚的时间开始计算
+    pub fn poll_idle_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+        info: &'static str,
+        read_time: Instant,
+        write_time: Instant,
+        is_read_end: bool,
+        is_write_end: bool,
+    ) -> ProtResult<()> {
+        let now = Instant::now();
+        if !is_read_end {
+            if let Some(read) = &self.read_idle_timeout {
+                let next = read_time.max(write_time) + *read;
+                if now >= next {
+                    log::trace!("连接读取空闲超时, 断开连接");
+                    return Err(crate::ProtError::read_timeout(info));
+                }
+                Self::poll_sleep(&mut self.read_idle_sleep, cx, next);
+            }
+        }
+        if !is_write_end {
+            if let Some(write) = &self.write_idle_timeout {
+                let next = write_time + *write;
+                if now >= next {
+                    log::trace!("连接写入空闲超时, 断开连接");
+                    return Err(crate::ProtError::write_timeout(info));
+                }
+                Self::poll_sleep(&mut self.write_idle_sleep, cx, next);
+            }
+        }
+        Ok(())
+    }
+
     pub fn poll_ready(
         &mut self,
         cx: &mut Context<'_>,
@@ -0,0 +1,69 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/14 16:05:27
+
+use algorithm::buf::{Binary, Bt};
+use futures::SinkExt;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_stream::StreamExt;
+use tokio_util::codec::{length_delimited, Framed, LengthDelimitedCodec};
+
+use crate::ProtResult;
+
+/// 长度前缀的消息读写, 每条消息为4字节大端的长度加上消息内容,
+/// 可用于在连接上构建自定义的二进制协议
+pub struct LengthDelimited<T> {
+    inner: Framed<T, LengthDelimitedCodec>,
+}
+
+impl<T> LengthDelimited<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn new(io: T) -> Self {
+        Self::with_max_frame_length(io, 8 * 1024 * 1024)
+    }
+
+    /// 指定单条消息的最大长度, 超出时读取返回错误
+    pub fn with_max_frame_length(io: T, max_frame_length: usize) -> Self {
+        let inner = length_delimited::Builder::new()
+            .big_endian()
+            .length_field_length(4)
+            .max_frame_length(max_frame_length)
+            .new_framed(io);
+        Self { inner }
+    }
+
+    /// 读取一条完整的消息, 连接关闭时返回None
+    pub async fn read_message(&mut self) -> ProtResult<Option<Binary>> {
+        match self.inner.next().await {
+            Some(Ok(bytes)) => Ok(Some(Binary::from(bytes.to_vec()))),
+            Some(Err(e)) => Err(e.into()),
+            None => Ok(None),
+        }
+    }
+
+    /// 写入一条消息并刷新
+    pub async fn write_message(&mut self, msg: Binary) -> ProtResult<()> {
+        self.inner
+            .send(bytes::Bytes::copy_from_slice(msg.chunk()))
+            .await?;
+        Ok(())
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+
+    pub fn into_io(self) -> T {
+        self.inner.into_inner()
+    }
+}
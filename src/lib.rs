@@ -18,31 +18,54 @@ mod error;
 mod header_helper;
 mod http_helper;
 mod stream;
+mod tls_info;
 pub mod ws;
 
 mod body;
+mod disconnect;
+mod request_id;
 mod send_stream;
 mod consts;
 mod layer;
 mod middle;
+mod length_delimited;
+mod listener;
+mod multipart;
 mod proxy;
+mod resolver;
+mod timings;
+mod sse;
+mod content_range;
 pub mod plugins;
 
 use std::any::Any;
 
-pub use self::body::Body;
+pub use self::body::{Body, BodySender, DigestAlgorithm, proxy_body, send_line};
+pub use self::multipart::MultipartBuilder;
+pub use self::length_delimited::LengthDelimited;
+pub use self::listener::{listen, ListenBuilder, Listener};
 pub use self::send_stream::SendStream;
 pub use self::stream::MaybeHttpsStream;
+pub use self::tls_info::{EarlyData, TlsInfo};
+pub use self::disconnect::Disconnected;
+pub use self::request_id::RequestId;
 
-pub use self::client::{Client, ClientOption};
-pub use self::server::Server;
+pub use self::client::{Client, ClientHandle, ClientOption, RequestBuilder};
+pub use self::resolver::{GaiResolver, Resolve};
+pub use self::timings::Timings;
+pub use self::sse::{SseEvent, SseResponse, SseSender};
+pub use self::content_range::ContentRange;
+pub use self::server::{Server, ConnInfo, CloseReason};
 pub use self::error::{ProtResult, ProtError, Initiator};
 pub use self::http2::{Builder, ServerH2Connection, StateHandshake, SendControl};
 pub use self::header_helper::HeaderHelper;
 pub use self::consts::Consts;
 pub use self::http_helper::HttpHelper;
-pub use self::layer::{RateLimitLayer, TimeoutLayer, Rate};
-pub use self::middle::Middleware;
+pub use self::layer::{InFlight, OverloadLayer, RateLimitLayer, TimeoutLayer, Rate};
+pub use self::middle::{
+    Middleware, CacheMiddleware, ContentTypeMiddleware, HttpsRedirectMiddleware,
+    IdempotencyMiddleware, SecurityHeadersMiddleware,
+};
 
 
 use webparse::{Request, Response};
@@ -64,6 +87,28 @@ pub trait HttpTrait: Send + Sync + Any {
         Ok(())
     }
 
+    /// 处理`OPTIONS *`请求, 即针对整个服务器的能力查询, 默认返回204及支持的方法
+    async fn operate_options(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+        let response = Response::builder()
+            .version(req.version().clone())
+            .status(204)
+            .header("Allow", "GET, HEAD, POST, PUT, DELETE, PATCH, OPTIONS")
+            .body(Body::empty())?;
+        Ok(response)
+    }
+
+    /// 请求带有`Expect: 100-continue`时在读取包体前调用, 返回false则回复417且不读取包体,
+    /// 可根据请求头提前拒绝过大的上传
+    async fn on_expect_continue(&mut self, _req: &RecvRequest) -> bool {
+        true
+    }
+
+    /// 处理函数返回错误时是否直接关闭连接, 默认返回false即回复500
+    /// 在回复500前会先调用中间件的process_error, 中间件可在process_response中替换错误页面
+    fn is_close_on_error(&self, _err: &ProtError) -> bool {
+        false
+    }
+
     async fn close_connect(&mut self) {
         
     }
@@ -73,6 +118,12 @@ pub trait HttpTrait: Send + Sync + Any {
         true
     }
 
+    /// 复制出独立的处理函数, 与所有中间件均返回Some时HTTP/2的每个流并发处理,
+    /// 慢的流不阻塞其它的流, 默认返回None即依次处理
+    fn fork(&self) -> Option<Box<dyn HttpTrait>> {
+        None
+    }
+
     fn as_any(&self) -> Option<&dyn Any> {
         None
     }
@@ -0,0 +1,124 @@
+// Copyright 2022 - 2024 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/19 14:22:07
+
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_stream::Stream;
+
+use crate::ProtResult;
+
+/// 创建监听地址的构建器, 默认开启SO_REUSEADDR, backlog为1024
+pub fn listen(addr: SocketAddr) -> ListenBuilder {
+    ListenBuilder::new(addr)
+}
+
+/// 监听socket的配置, 避免自行创建时遗漏常用的选项
+#[derive(Debug, Clone)]
+pub struct ListenBuilder {
+    addr: SocketAddr,
+    reuse_addr: bool,
+    reuse_port: bool,
+    backlog: u32,
+    /// IPv6地址是否同时接收IPv4的连接, 不设置则使用系统默认值
+    dual_stack: Option<bool>,
+}
+
+impl ListenBuilder {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            reuse_addr: true,
+            reuse_port: false,
+            backlog: 1024,
+            dual_stack: None,
+        }
+    }
+
+    /// 设置SO_REUSEADDR, 重启时可立即重新绑定处于TIME_WAIT的地址
+    pub fn reuse_addr(mut self, reuse_addr: bool) -> Self {
+        self.reuse_addr = reuse_addr;
+        self
+    }
+
+    /// 设置SO_REUSEPORT, 多个进程或线程可绑定同一地址, 由内核分配连接, 仅unix有效
+    pub fn reuse_port(mut self, reuse_port: bool) -> Self {
+        self.reuse_port = reuse_port;
+        self
+    }
+
+    /// 设置等待accept的连接队列长度
+    pub fn backlog(mut self, backlog: u32) -> Self {
+        self.backlog = backlog;
+        self
+    }
+
+    /// 设置IPv6地址是否同时接收IPv4的连接, 仅IPv6地址有效
+    pub fn dual_stack(mut self, dual_stack: bool) -> Self {
+        self.dual_stack = Some(dual_stack);
+        self
+    }
+
+    /// 按配置创建并监听socket, 需在tokio的运行时中调用
+    pub fn bind(self) -> ProtResult<Listener> {
+        let domain = Domain::for_address(self.addr);
+        let socket = Socket::new(domain, Type::STREAM, Some(Protocol::TCP))?;
+        socket.set_reuse_address(self.reuse_addr)?;
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        socket.set_reuse_port(self.reuse_port)?;
+        if let (Some(dual_stack), true) = (self.dual_stack, self.addr.is_ipv6()) {
+            socket.set_only_v6(!dual_stack)?;
+        }
+        socket.set_nonblocking(true)?;
+        socket.bind(&self.addr.into())?;
+        socket.listen(self.backlog.min(i32::MAX as u32) as i32)?;
+        let inner = TcpListener::from_std(socket.into())?;
+        Ok(Listener { inner })
+    }
+}
+
+/// 已开始监听的socket, 作为数据流返回接收到的连接, 可直接交给`Server`处理
+#[derive(Debug)]
+pub struct Listener {
+    inner: TcpListener,
+}
+
+impl Listener {
+    pub fn local_addr(&self) -> ProtResult<SocketAddr> {
+        Ok(self.inner.local_addr()?)
+    }
+
+    pub async fn accept(&self) -> ProtResult<(TcpStream, SocketAddr)> {
+        Ok(self.inner.accept().await?)
+    }
+
+    pub fn into_inner(self) -> TcpListener {
+        self.inner
+    }
+}
+
+impl Stream for Listener {
+    type Item = ProtResult<(TcpStream, SocketAddr)>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.inner.poll_accept(cx) {
+            Poll::Ready(Ok(v)) => Poll::Ready(Some(Ok(v))),
+            Poll::Ready(Err(e)) => Poll::Ready(Some(Err(e.into()))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
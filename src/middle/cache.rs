@@ -0,0 +1,257 @@
+// Copyright 2022 - 2024 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/08 09:36:52
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use algorithm::buf::{Binary, BinaryMut, Bt};
+use async_trait::async_trait;
+use webparse::{HeaderMap, Response};
+
+use crate::{Body, Middleware, ProtResult, RecvRequest, RecvResponse};
+
+/// 缓存的单个响应
+#[derive(Clone)]
+struct CacheEntry {
+    /// Vary指定的请求头及请求时的值
+    vary: Vec<(String, Option<String>)>,
+    status: u16,
+    headers: HeaderMap,
+    body: Binary,
+    stored_at: Instant,
+    max_age: Duration,
+}
+
+impl CacheEntry {
+    fn is_match(&self, request: &RecvRequest) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| &request.headers().get_str_value(&&**name) == value)
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.stored_at.elapsed() < self.max_age
+    }
+
+    fn build_response(&self) -> ProtResult<RecvResponse> {
+        let mut response = Response::builder()
+            .status(self.status)
+            .body(Body::new_binary(BinaryMut::from(self.body.chunk().to_vec())))?;
+        *response.headers_mut() = self.headers.clone();
+        Ok(response)
+    }
+}
+
+/// 等待响应的请求信息
+struct Pending {
+    key: String,
+    request_headers: HeaderMap,
+    /// 正在进行条件请求验证的缓存
+    revalidate: Option<CacheEntry>,
+}
+
+/// 基于内存的响应缓存, 以方法+地址+Vary指定的请求头区分缓存,
+/// 克隆后的中间件共享同一份缓存
+pub struct CacheMiddleware {
+    cache: Arc<Mutex<HashMap<String, Vec<CacheEntry>>>>,
+    /// 单个缓存的最大包体大小
+    max_entry_size: usize,
+    pending: Option<Pending>,
+}
+
+impl Clone for CacheMiddleware {
+    fn clone(&self) -> Self {
+        Self {
+            cache: self.cache.clone(),
+            max_entry_size: self.max_entry_size,
+            pending: None,
+        }
+    }
+}
+
+impl CacheMiddleware {
+    pub fn new(max_entry_size: usize) -> Self {
+        Self {
+            cache: Arc::new(Mutex::new(HashMap::new())),
+            max_entry_size,
+            pending: None,
+        }
+    }
+
+    /// 缓存中的条目数
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap().values().map(|v| v.len()).sum()
+    }
+
+    fn cache_key(request: &RecvRequest) -> Option<String> {
+        match request.method().as_str() {
+            "GET" | "HEAD" => Some(format!("{} {}", request.method().as_str(), request.url())),
+            _ => None,
+        }
+    }
+
+    /// 解析Cache-Control, 返回可缓存的时长, 不可缓存返回None
+    fn parse_max_age(headers: &HeaderMap) -> Option<Duration> {
+        let value = headers.get_str_value(&"Cache-Control")?;
+        let mut max_age = None;
+        for item in value.split(',').map(|s| s.trim().to_ascii_lowercase()) {
+            if item == "no-store" || item == "private" {
+                return None;
+            }
+            if item == "no-cache" {
+                max_age = Some(0);
+            } else if let Some(age) = item.strip_prefix("max-age=") {
+                if max_age.is_none() {
+                    max_age = age.trim_matches('"').parse::<u64>().ok();
+                }
+            }
+        }
+        max_age.map(Duration::from_secs)
+    }
+
+    fn find_entry(&self, key: &str, request: &RecvRequest) -> Option<CacheEntry> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(key)?
+            .iter()
+            .find(|e| e.is_match(request))
+            .cloned()
+    }
+
+    fn store_entry(&self, key: String, entry: CacheEntry) {
+        let mut cache = self.cache.lock().unwrap();
+        let list = cache.entry(key).or_insert_with(Vec::new);
+        list.retain(|e| e.vary != entry.vary);
+        list.push(entry);
+    }
+}
+
+#[async_trait]
+impl Middleware for CacheMiddleware {
+    async fn process_request(
+        &mut self,
+        request: &mut RecvRequest,
+    ) -> ProtResult<Option<RecvResponse>> {
+        self.pending = None;
+        let key = match Self::cache_key(request) {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        if let Some(value) = request.headers().get_str_value(&"Cache-Control") {
+            if value.contains("no-store") {
+                return Ok(None);
+            }
+        }
+        let mut revalidate = None;
+        if let Some(entry) = self.find_entry(&key, request) {
+            if entry.is_fresh() {
+                log::trace!("命中缓存:{}", key);
+                return Ok(Some(entry.build_response()?));
+            }
+            // 缓存过期, 有验证信息则发起条件请求
+            let etag = entry.headers.get_str_value(&"ETag");
+            let modified = entry.headers.get_str_value(&"Last-Modified");
+            if etag.is_some() || modified.is_some() {
+                if let Some(etag) = etag {
+                    request.headers_mut().insert("If-None-Match", etag);
+                }
+                if let Some(modified) = modified {
+                    request.headers_mut().insert("If-Modified-Since", modified);
+                }
+                revalidate = Some(entry);
+            }
+        }
+        self.pending = Some(Pending {
+            key,
+            request_headers: request.headers().clone(),
+            revalidate,
+        });
+        Ok(None)
+    }
+
+    async fn process_response(&mut self, response: &mut RecvResponse) -> ProtResult<()> {
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+
+        if response.status() == 304 {
+            if let Some(mut entry) = pending.revalidate {
+                entry.stored_at = Instant::now();
+                if let Some(max_age) = Self::parse_max_age(response.headers()) {
+                    entry.max_age = max_age;
+                }
+                *response = entry.build_response()?;
+                self.store_entry(pending.key, entry);
+            }
+            return Ok(());
+        }
+
+        if response.status() != 200 {
+            return Ok(());
+        }
+        let max_age = match Self::parse_max_age(response.headers()) {
+            Some(max_age) => max_age,
+            None => return Ok(()),
+        };
+        let body_len = response.get_body_len();
+        if body_len < 0 || body_len as usize > self.max_entry_size {
+            return Ok(());
+        }
+        if !response.body().is_end() && body_len == 0 {
+            return Ok(());
+        }
+
+        let mut vary = vec![];
+        let mut names = vec![];
+        if let Some(value) = response.headers().get_str_value(&"Vary") {
+            if value.trim() == "*" {
+                return Ok(());
+            }
+            names.extend(value.split(',').map(|s| s.trim().to_ascii_lowercase()));
+        }
+        // 有压缩时需按客户端支持的压缩方式区分
+        if response.headers().get_str_value(&"Content-Encoding").is_some()
+            && !names.iter().any(|n| n == "accept-encoding")
+        {
+            names.push("accept-encoding".to_string());
+        }
+        for name in names.into_iter().filter(|n| !n.is_empty()) {
+            let value = pending.request_headers.get_str_value(&&*name);
+            vary.push((name, value));
+        }
+
+        let mut buffer = BinaryMut::new();
+        response.body_mut().read_all(&mut buffer).await;
+        let body = buffer.freeze();
+        *response.body_mut() = Body::new_binary(BinaryMut::from(body.chunk().to_vec()));
+        if body.remaining() > self.max_entry_size {
+            return Ok(());
+        }
+
+        self.store_entry(
+            pending.key,
+            CacheEntry {
+                vary,
+                status: 200,
+                headers: response.headers().clone(),
+                body,
+                stored_at: Instant::now(),
+                max_age,
+            },
+        );
+        Ok(())
+    }
+}
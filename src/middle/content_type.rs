@@ -0,0 +1,105 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 16:58:31
+
+use async_trait::async_trait;
+use webparse::Response;
+
+use crate::{Body, Middleware, ProtResult, RecvRequest, RecvResponse};
+
+/// 校验请求包体的Content-Type, 不在允许列表内则直接返回415
+#[derive(Debug, Clone)]
+pub struct ContentTypeMiddleware {
+    /// 允许的类型, 如`application/json`, 支持`text/*`的通配
+    allows: Vec<String>,
+    /// 无包体的方法(如GET/HEAD)不做校验
+    skip_nobody: bool,
+}
+
+impl ContentTypeMiddleware {
+    pub fn new() -> Self {
+        Self {
+            allows: vec![],
+            skip_nobody: true,
+        }
+    }
+
+    pub fn allow(mut self, content_type: &str) -> Self {
+        self.allows.push(Self::normalize(content_type));
+        self
+    }
+
+    /// 设置为false时无包体的方法也需要携带允许的Content-Type
+    pub fn skip_nobody(mut self, skip_nobody: bool) -> Self {
+        self.skip_nobody = skip_nobody;
+        self
+    }
+
+    /// 去除参数部分并转为小写, 如`Application/JSON; charset=utf-8`转为`application/json`
+    pub fn normalize(content_type: &str) -> String {
+        let mime = content_type.split(';').next().unwrap_or("");
+        mime.trim().to_ascii_lowercase()
+    }
+
+    pub fn is_allow(&self, content_type: &str) -> bool {
+        let mime = Self::normalize(content_type);
+        self.allows.iter().any(|allow| {
+            if allow == "*/*" || allow == &mime {
+                return true;
+            }
+            match allow.strip_suffix("/*") {
+                Some(prefix) => mime.split_once('/').is_some_and(|(t, _)| t == prefix),
+                None => false,
+            }
+        })
+    }
+}
+
+impl Default for ContentTypeMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Middleware for ContentTypeMiddleware {
+    async fn process_request(
+        &mut self,
+        request: &mut RecvRequest,
+    ) -> ProtResult<Option<RecvResponse>> {
+        if self.skip_nobody && request.method().is_nobody() {
+            return Ok(None);
+        }
+        let content_type = request.headers().get_str_value(&"Content-Type");
+        if let Some(content_type) = &content_type {
+            if self.is_allow(content_type) {
+                // 统一类型部分的大小写, 保留charset等参数
+                let value = match content_type.split_once(';') {
+                    Some((_, params)) => format!("{};{}", Self::normalize(content_type), params),
+                    None => Self::normalize(content_type),
+                };
+                request.headers_mut().insert("Content-Type", value);
+                return Ok(None);
+            }
+        }
+        log::trace!("不支持的请求类型:{:?}, 返回415", content_type);
+        let response = Response::builder()
+            .version(request.version().clone())
+            .status(415)
+            .header("Accept", self.allows.join(", "))
+            .body(Body::empty())?;
+        Ok(Some(response))
+    }
+
+    async fn process_response(&mut self, _response: &mut RecvResponse) -> ProtResult<()> {
+        Ok(())
+    }
+}
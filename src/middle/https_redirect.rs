@@ -0,0 +1,111 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/15 17:10:26
+
+use async_trait::async_trait;
+use webparse::Response;
+
+use crate::{Body, Middleware, ProtResult, RecvRequest, RecvResponse, TlsInfo};
+
+/// 将非TLS连接上的安全方法(GET/HEAD)请求重定向到https地址
+#[derive(Debug, Clone)]
+pub struct HttpsRedirectMiddleware {
+    /// 重定向的主机名, 为空则使用请求的Host
+    host: Option<String>,
+    /// 重定向的端口, 为空则使用默认的443
+    port: Option<u16>,
+    /// 重定向的状态码, 301或308
+    status: u16,
+}
+
+impl HttpsRedirectMiddleware {
+    pub fn new() -> Self {
+        Self {
+            host: None,
+            port: None,
+            status: 301,
+        }
+    }
+
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = Some(host.to_string());
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// 使用308永久重定向
+    pub fn permanent_308(mut self) -> Self {
+        self.status = 308;
+        self
+    }
+
+    fn location(&self, request: &RecvRequest) -> Option<String> {
+        let host = match &self.host {
+            Some(host) => host.clone(),
+            None => {
+                let host = request.headers().get_str_value(&"Host")?;
+                // 去除原有的端口, 保留IPv6地址中的冒号
+                match host.rsplit_once(':') {
+                    Some((h, p))
+                        if p.parse::<u16>().is_ok() && (!h.contains(':') || h.ends_with(']')) =>
+                    {
+                        h.to_string()
+                    }
+                    _ => host,
+                }
+            }
+        };
+        let port = match self.port {
+            Some(port) if port != 443 => format!(":{}", port),
+            _ => String::new(),
+        };
+        Some(format!("https://{}{}{}", host, port, request.path()))
+    }
+}
+
+impl Default for HttpsRedirectMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Middleware for HttpsRedirectMiddleware {
+    async fn process_request(
+        &mut self,
+        request: &mut RecvRequest,
+    ) -> ProtResult<Option<RecvResponse>> {
+        if request.extensions().get::<TlsInfo>().is_some() {
+            return Ok(None);
+        }
+        if !matches!(request.method().as_str(), "GET" | "HEAD") {
+            return Ok(None);
+        }
+        let location = match self.location(request) {
+            Some(location) => location,
+            None => return Ok(None),
+        };
+        log::trace!("非TLS请求重定向到:{}", location);
+        let response = Response::builder()
+            .status(self.status)
+            .header("Location", location)
+            .body(Body::empty())?;
+        Ok(Some(response))
+    }
+
+    async fn process_response(&mut self, _response: &mut RecvResponse) -> ProtResult<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright 2022 - 2024 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 23:08:45
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::Hasher,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use algorithm::buf::{Binary, BinaryMut, Bt};
+use async_trait::async_trait;
+use tokio::sync::Notify;
+use webparse::{HeaderMap, Response};
+
+use crate::{Body, Middleware, ProtError, ProtResult, RecvRequest, RecvResponse};
+
+/// 默认保存的响应包体的最大大小
+const DEFAULT_MAX_BODY_SIZE: usize = 1_048_576;
+
+/// 已完成的请求所保存的响应
+#[derive(Clone)]
+struct StoredResponse {
+    /// 首次请求的包体指纹
+    fingerprint: u64,
+    status: u16,
+    headers: HeaderMap,
+    body: Binary,
+    stored_at: Instant,
+}
+
+impl StoredResponse {
+    fn build_response(&self) -> ProtResult<RecvResponse> {
+        let mut response = Response::builder()
+            .status(self.status)
+            .body(Body::new_binary(BinaryMut::from(self.body.chunk().to_vec())))?;
+        *response.headers_mut() = self.headers.clone();
+        response.headers_mut().insert("Idempotent-Replayed", "true");
+        Ok(response)
+    }
+}
+
+enum KeyState {
+    /// 首个请求正在处理, 重复的请求等待其完成, 附带首个请求的包体指纹
+    InFlight(Arc<Notify>, u64),
+    Done(StoredResponse),
+}
+
+impl KeyState {
+    fn fingerprint(&self) -> u64 {
+        match self {
+            KeyState::InFlight(_, fingerprint) => *fingerprint,
+            KeyState::Done(stored) => stored.fingerprint,
+        }
+    }
+}
+
+/// 根据`Idempotency-Key`请求头去重, 重复的请求直接返回首次处理的响应,
+/// 并发的重复请求等待首个请求处理完成. 同一个键的请求包体不同时返回422.
+/// 克隆后的中间件共享同一份记录
+pub struct IdempotencyMiddleware {
+    store: Arc<Mutex<HashMap<String, KeyState>>>,
+    /// 响应保存的时长
+    ttl: Duration,
+    /// 保存的响应包体的最大大小, 超出则不保存
+    max_body_size: usize,
+    /// 当前正在处理的键及请求包体指纹
+    pending: Option<(String, u64)>,
+}
+
+impl Clone for IdempotencyMiddleware {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            ttl: self.ttl,
+            max_body_size: self.max_body_size,
+            pending: None,
+        }
+    }
+}
+
+impl IdempotencyMiddleware {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            store: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            pending: None,
+        }
+    }
+
+    /// 设置保存的响应包体的最大大小, 默认1M
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// 记录中的键数
+    pub fn len(&self) -> usize {
+        self.store.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.lock().unwrap().is_empty()
+    }
+
+    fn store_key(request: &RecvRequest) -> Option<String> {
+        let key = request.headers().get_str_value(&"Idempotency-Key")?;
+        let key = key.trim();
+        if key.is_empty() {
+            return None;
+        }
+        Some(format!("{} {} {}", request.method().as_str(), request.path(), key))
+    }
+
+    /// 读取请求包体计算指纹, 读取后将包体放回请求中
+    async fn fingerprint(request: &mut RecvRequest) -> ProtResult<u64> {
+        let mut buffer = BinaryMut::new();
+        if request.body_mut().read_all(&mut buffer).await.is_none() {
+            return Err(ProtError::Extension("read idempotent request body failed"));
+        }
+        let mut hasher = DefaultHasher::new();
+        hasher.write(buffer.chunk());
+        *request.body_mut() = Body::new_binary(buffer);
+        Ok(hasher.finish())
+    }
+
+    /// 同一个键的请求包体与首次请求不同
+    fn mismatch_response() -> ProtResult<RecvResponse> {
+        Ok(Response::builder().status(422).body(Body::new_text(
+            "idempotency key reused with different payload".to_string(),
+        ))?)
+    }
+
+    /// 是否保存该响应, 事件流及超出大小或长度未知的包体不保存
+    fn is_storable(&self, response: &RecvResponse) -> bool {
+        let is_event_stream = response
+            .headers()
+            .get_str_value(&"Content-Type")
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        if is_event_stream {
+            return false;
+        }
+        match response.body().size_hint() {
+            Some(size) => size <= self.max_body_size as u64,
+            None => false,
+        }
+    }
+
+    /// 结束正在处理的键, 有响应则保存, 否则移除以便重试, 并唤醒等待的请求
+    fn finish(&mut self, stored: Option<StoredResponse>) {
+        let Some((key, _)) = self.pending.take() else {
+            return;
+        };
+        let mut store = self.store.lock().unwrap();
+        let state = match stored {
+            Some(stored) => store.insert(key, KeyState::Done(stored)),
+            None => store.remove(&key),
+        };
+        if let Some(KeyState::InFlight(notify, _)) = state {
+            notify.notify_waiters();
+        }
+    }
+}
+
+impl Drop for IdempotencyMiddleware {
+    fn drop(&mut self) {
+        // 处理中途连接断开, 释放该键
+        self.finish(None);
+    }
+}
+
+#[async_trait]
+impl Middleware for IdempotencyMiddleware {
+    async fn process_request(
+        &mut self,
+        request: &mut RecvRequest,
+    ) -> ProtResult<Option<RecvResponse>> {
+        self.finish(None);
+        let key = match Self::store_key(request) {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let fingerprint = Self::fingerprint(request).await?;
+        loop {
+            let notify = {
+                let mut store = self.store.lock().unwrap();
+                let ttl = self.ttl;
+                store.retain(|_, state| match state {
+                    KeyState::Done(stored) => stored.stored_at.elapsed() < ttl,
+                    KeyState::InFlight(..) => true,
+                });
+                match store.get(&key) {
+                    Some(state) if state.fingerprint() != fingerprint => {
+                        log::trace!("幂等请求的包体与首次请求不同:{}", key);
+                        return Ok(Some(Self::mismatch_response()?));
+                    }
+                    Some(KeyState::Done(stored)) => {
+                        log::trace!("重复的幂等请求, 返回已保存的响应:{}", key);
+                        return Ok(Some(stored.build_response()?));
+                    }
+                    Some(KeyState::InFlight(notify, _)) => notify.clone(),
+                    None => {
+                        let state = KeyState::InFlight(Arc::new(Notify::new()), fingerprint);
+                        store.insert(key.clone(), state);
+                        self.pending = Some((key, fingerprint));
+                        return Ok(None);
+                    }
+                }
+            };
+            // 创建后即可收到通知, 再次确认仍在处理中, 避免错过唤醒
+            let notified = notify.notified();
+            let is_waiting = matches!(
+                self.store.lock().unwrap().get(&key),
+                Some(KeyState::InFlight(n, _)) if Arc::ptr_eq(n, &notify)
+            );
+            if is_waiting {
+                notified.await;
+            }
+        }
+    }
+
+    async fn process_response(&mut self, response: &mut RecvResponse) -> ProtResult<()> {
+        let fingerprint = match &self.pending {
+            Some((_, fingerprint)) => *fingerprint,
+            None => return Ok(()),
+        };
+        // 服务端错误不保存, 允许客户端重试
+        if response.status().is_server_error() || !self.is_storable(response) {
+            self.finish(None);
+            return Ok(());
+        }
+        let mut buffer = BinaryMut::new();
+        if response.body_mut().read_all(&mut buffer).await.is_none() {
+            self.finish(None);
+            return Err(ProtError::Extension("read idempotent response body failed"));
+        }
+        let body = buffer.freeze();
+        *response.body_mut() = Body::new_binary(BinaryMut::from(body.chunk().to_vec()));
+        self.finish(Some(StoredResponse {
+            fingerprint,
+            status: response.status().as_u16(),
+            headers: response.headers().clone(),
+            body,
+            stored_at: Instant::now(),
+        }));
+        Ok(())
+    }
+}
@@ -17,11 +17,28 @@ use crate::{RecvRequest, ProtResult, RecvResponse, ProtError};
 
 #[async_trait]
 pub trait Middleware: Send + Sync {
+    /// 按添加顺序调用, 返回Some(response)时不再调用之后的中间件及处理函数,
+    /// 直接回复该响应, 并按逆序调用已进入的中间件(包括自身)的process_response
     async fn process_request(&mut self, request: &mut RecvRequest) -> ProtResult<Option<RecvResponse>>;
     async fn process_response(&mut self, response: &mut RecvResponse) -> ProtResult<()>;
     async fn process_error(&mut self, _request: Option<&mut RecvRequest>, _error: &ProtError) {}
+
+    /// 复制出独立的中间件, 供HTTP/2中各流并发处理时使用, 返回None则依次处理
+    fn fork(&self) -> Option<Box<dyn Middleware>> {
+        None
+    }
 }
 
 mod base;
+mod cache;
+mod content_type;
+mod https_redirect;
+mod idempotency;
+mod security_headers;
 
-pub use base::BaseMiddleware;
\ No newline at end of file
+pub use base::BaseMiddleware;
+pub use cache::CacheMiddleware;
+pub use content_type::ContentTypeMiddleware;
+pub use https_redirect::HttpsRedirectMiddleware;
+pub use idempotency::IdempotencyMiddleware;
+pub use security_headers::SecurityHeadersMiddleware;
\ No newline at end of file
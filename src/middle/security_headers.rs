@@ -0,0 +1,120 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 19:41:05
+
+use async_trait::async_trait;
+
+use crate::{Middleware, ProtResult, RecvRequest, RecvResponse, TlsInfo};
+
+/// 向响应添加常用的安全头, 处理函数已设置的头保持不变, 值为None则不添加
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersMiddleware {
+    /// Strict-Transport-Security, 仅在TLS连接上添加
+    hsts: Option<String>,
+    content_type_options: Option<String>,
+    frame_options: Option<String>,
+    content_security_policy: Option<String>,
+    referrer_policy: Option<String>,
+    expect_ct: Option<String>,
+    /// 当前连接是否为TLS, 由请求的TlsInfo判断
+    is_tls: bool,
+}
+
+impl SecurityHeadersMiddleware {
+    pub fn new() -> Self {
+        Self {
+            hsts: Some("max-age=31536000; includeSubDomains".to_string()),
+            content_type_options: Some("nosniff".to_string()),
+            frame_options: Some("SAMEORIGIN".to_string()),
+            content_security_policy: None,
+            referrer_policy: Some("strict-origin-when-cross-origin".to_string()),
+            expect_ct: None,
+            is_tls: false,
+        }
+    }
+
+    pub fn hsts(mut self, value: Option<&str>) -> Self {
+        self.hsts = value.map(|v| v.to_string());
+        self
+    }
+
+    pub fn content_type_options(mut self, value: Option<&str>) -> Self {
+        self.content_type_options = value.map(|v| v.to_string());
+        self
+    }
+
+    pub fn frame_options(mut self, value: Option<&str>) -> Self {
+        self.frame_options = value.map(|v| v.to_string());
+        self
+    }
+
+    /// 默认不添加, 需按站点的资源来源配置
+    pub fn content_security_policy(mut self, value: Option<&str>) -> Self {
+        self.content_security_policy = value.map(|v| v.to_string());
+        self
+    }
+
+    pub fn referrer_policy(mut self, value: Option<&str>) -> Self {
+        self.referrer_policy = value.map(|v| v.to_string());
+        self
+    }
+
+    /// 已被主流浏览器废弃, 默认不添加
+    pub fn expect_ct(mut self, value: Option<&str>) -> Self {
+        self.expect_ct = value.map(|v| v.to_string());
+        self
+    }
+
+    fn headers(&self) -> Vec<(&'static str, &Option<String>)> {
+        let mut headers = vec![
+            ("X-Content-Type-Options", &self.content_type_options),
+            ("X-Frame-Options", &self.frame_options),
+            ("Content-Security-Policy", &self.content_security_policy),
+            ("Referrer-Policy", &self.referrer_policy),
+        ];
+        // 明文连接上的HSTS及Expect-CT会被浏览器忽略
+        if self.is_tls {
+            headers.push(("Strict-Transport-Security", &self.hsts));
+            headers.push(("Expect-CT", &self.expect_ct));
+        }
+        headers
+    }
+}
+
+impl Default for SecurityHeadersMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Middleware for SecurityHeadersMiddleware {
+    async fn process_request(
+        &mut self,
+        request: &mut RecvRequest,
+    ) -> ProtResult<Option<RecvResponse>> {
+        // 同一连接的请求均为相同的传输方式
+        self.is_tls = request.extensions().get::<TlsInfo>().is_some();
+        Ok(None)
+    }
+
+    async fn process_response(&mut self, response: &mut RecvResponse) -> ProtResult<()> {
+        for (name, value) in self.headers() {
+            let Some(value) = value else {
+                continue;
+            };
+            if response.headers().get_str_value(&name).is_none() {
+                response.headers_mut().insert(name, value.clone());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/13 16:48:05
+
+use std::{collections::VecDeque, io};
+
+use algorithm::buf::Binary;
+use tokio_stream::StreamExt;
+use webparse::HeaderMap;
+
+use crate::{Body, ProtResult};
+
+/// 构建流式的multipart响应, 每个部分依次写入, 不会缓存全部数据
+pub struct MultipartBuilder {
+    boundary: String,
+    sub_type: String,
+    parts: Vec<(HeaderMap, Body)>,
+}
+
+/// 按顺序生成各部分的数据, 读取Body时才读取各部分的包体
+struct PartsWriter {
+    boundary: String,
+    parts: VecDeque<(HeaderMap, Body)>,
+    /// 正在写入包体的部分
+    current: Option<Body>,
+    is_end: bool,
+}
+
+impl PartsWriter {
+    async fn next_data(&mut self) -> Option<ProtResult<Binary>> {
+        if self.is_end {
+            return None;
+        }
+        if let Some(body) = &mut self.current {
+            match body.next().await {
+                Some(Ok(bin)) => return Some(Ok(bin)),
+                Some(Err(e)) => {
+                    log::trace!("读取multipart的部分数据时出错:{:?}", e);
+                    self.is_end = true;
+                    return Some(Err(e));
+                }
+                None => {
+                    self.current = None;
+                    return Some(Ok(Binary::from_static(b"\r\n")));
+                }
+            }
+        }
+        match self.parts.pop_front() {
+            Some((headers, body)) => {
+                let mut head = format!("--{}\r\n", self.boundary);
+                for (name, value) in headers.iter() {
+                    head += &format!("{}: {}\r\n", name, value);
+                }
+                head += "\r\n";
+                self.current = Some(body);
+                Some(Ok(Binary::from(head.into_bytes())))
+            }
+            None => {
+                self.is_end = true;
+                let end = format!("--{}--\r\n", self.boundary);
+                Some(Ok(Binary::from(end.into_bytes())))
+            }
+        }
+    }
+}
+
+impl MultipartBuilder {
+    pub fn new() -> Self {
+        let rand: [u8; 12] = rand::random();
+        let boundary = rand.iter().map(|v| format!("{:02x}", v)).collect::<String>();
+        Self::with_boundary(boundary)
+    }
+
+    pub fn with_boundary(boundary: String) -> Self {
+        MultipartBuilder {
+            boundary,
+            sub_type: "mixed".to_string(),
+            parts: vec![],
+        }
+    }
+
+    /// 设置multipart的子类型, 默认为mixed
+    pub fn sub_type(mut self, sub_type: &str) -> Self {
+        self.sub_type = sub_type.to_string();
+        self
+    }
+
+    /// 添加一个部分, 包体可以是流式的Body
+    pub fn part(mut self, headers: HeaderMap, body: Body) -> Self {
+        self.parts.push((headers, body));
+        self
+    }
+
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// 响应头中的Content-Type值
+    pub fn content_type(&self) -> String {
+        format!("multipart/{}; boundary={}", self.sub_type, self.boundary)
+    }
+
+    /// 生成流式的Body, 读取时依次写入各部分, 结束时写入最终的分隔符.
+    /// 部分的包体出错时读取该Body返回错误
+    pub fn build(self) -> Body {
+        let writer = PartsWriter {
+            boundary: self.boundary,
+            parts: self.parts.into(),
+            current: None,
+            is_end: false,
+        };
+        let stream = futures::stream::unfold(writer, |mut writer| async move {
+            let data = writer
+                .next_data()
+                .await?
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()));
+            Some((data, writer))
+        });
+        Body::from_stream(stream)
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/21 14:05:43
+
+use std::{io, net::SocketAddr, time::Duration};
+
+use async_trait::async_trait;
+use futures::{stream::FuturesUnordered, StreamExt};
+use tokio::net::TcpStream;
+
+use crate::ProtResult;
+
+/// 域名解析, 返回的地址按顺序尝试连接
+#[async_trait]
+pub trait Resolve: Send + Sync {
+    async fn resolve(&self, host: &str, port: u16) -> ProtResult<Vec<SocketAddr>>;
+}
+
+/// 默认使用系统的域名解析
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GaiResolver;
+
+#[async_trait]
+impl Resolve for GaiResolver {
+    async fn resolve(&self, host: &str, port: u16) -> ProtResult<Vec<SocketAddr>> {
+        Ok(tokio::net::lookup_host((host, port)).await?.collect())
+    }
+}
+
+/// Happy Eyeballs中启动下一个连接尝试前的等待时间, RFC 8305推荐250ms
+const HAPPY_EYEBALLS_DELAY: Duration = Duration::from_millis(250);
+
+/// 按顺序连接, 返回第一个成功的连接
+pub(crate) async fn connect_in_order(addrs: Vec<SocketAddr>) -> io::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in addrs {
+        match TcpStream::connect(addr).await {
+            Ok(tcp) => return Ok(tcp),
+            Err(e) => {
+                log::trace!("连接{}失败:{:?}, 尝试下一个地址", addr, e);
+                last_err = Some(e);
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| no_address()))
+}
+
+/// Happy Eyeballs, IPv6及IPv4的地址交替排列, 上一个尝试未完成时间隔启动下一个, 取最先成功的连接
+pub(crate) async fn connect_happy_eyeballs(addrs: Vec<SocketAddr>) -> io::Result<TcpStream> {
+    let mut pending = interleave(addrs).into_iter();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_err = None;
+    if let Some(addr) = pending.next() {
+        attempts.push(TcpStream::connect(addr));
+    }
+    while !attempts.is_empty() {
+        tokio::select! {
+            Some(result) = attempts.next() => match result {
+                Ok(tcp) => return Ok(tcp),
+                Err(e) => {
+                    last_err = Some(e);
+                    // 失败时立即开始下一个尝试
+                    if let Some(addr) = pending.next() {
+                        attempts.push(TcpStream::connect(addr));
+                    }
+                }
+            },
+            _ = tokio::time::sleep(HAPPY_EYEBALLS_DELAY), if pending.len() > 0 => {
+                attempts.push(TcpStream::connect(pending.next().unwrap()));
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| no_address()))
+}
+
+/// 以第一个地址的协议族开始, 交替排列两种协议族的地址
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = addrs.first().is_some_and(|a| a.is_ipv6());
+    let (first, second): (Vec<_>, Vec<_>) =
+        addrs.into_iter().partition(|a| a.is_ipv6() == first_v6);
+    let mut result = Vec::with_capacity(first.len() + second.len());
+    let (mut first, mut second) = (first.into_iter(), second.into_iter());
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => break,
+            (a, b) => result.extend(a.into_iter().chain(b)),
+        }
+    }
+    result
+}
+
+fn no_address() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no address resolved")
+}
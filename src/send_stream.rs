@@ -14,9 +14,9 @@ use std::fmt::Debug;
 use std::io::Read;
 use algorithm::buf::{Binary, BinaryMut, Bt, BtMut};
 use tokio_stream::Stream;
-use webparse::{Helper, HttpError, Serialize, WebError};
+use webparse::{HeaderMap, Helper, HttpError, Serialize, WebError};
 
-use crate::ProtResult;
+use crate::{ProtError, ProtResult};
 
 #[derive(Debug)]
 pub struct SendStream {
@@ -26,6 +26,18 @@ pub struct SendStream {
     is_end: bool,
     is_end_headers: bool,
     left_read_body_len: usize,
+    /// 已读取的包体大小
+    read_body_len: usize,
+    /// chunked格式下单个chunk的最大大小
+    max_chunk_size: usize,
+    /// 包体的最大大小
+    max_body_size: usize,
+    /// trailer头的最大大小
+    max_trailer_size: usize,
+    /// 已读取到结束的chunk, 等待读取trailer头
+    is_trailer: bool,
+    /// chunked结束后的trailer头
+    trailers: Option<HeaderMap>,
 }
 
 impl SendStream {
@@ -37,6 +49,13 @@ impl SendStream {
             is_end_headers: false,
             is_chunked: false,
             left_read_body_len: 0,
+            read_body_len: 0,
+            // 防止声明超大的chunk, 限定默认大小为16M
+            max_chunk_size: 16_777_216,
+            max_body_size: usize::MAX,
+            max_trailer_size: 131_072,
+            is_trailer: false,
+            trailers: None,
         }
     }
 
@@ -52,6 +71,86 @@ impl SendStream {
         self.is_end = false;
         self.is_chunked = false;
         self.left_read_body_len = 0;
+        self.read_body_len = 0;
+        self.is_trailer = false;
+        self.trailers = None;
+    }
+
+    pub fn take_trailers(&mut self) -> Option<HeaderMap> {
+        self.trailers.take()
+    }
+
+    /// 解析结束chunk后的trailer头, 以空行结束, 数据不足返回false.
+    /// 超出max_trailer_size返回431, 重复的头逐个保留
+    fn parse_trailers(&mut self) -> ProtResult<bool> {
+        let buf = self.read_buf.chunk();
+        if buf.starts_with(b"\r\n") {
+            self.read_buf.advance(2);
+            return Ok(true);
+        }
+        let end = match buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(end) => end,
+            None if buf.len() > self.max_trailer_size => {
+                return Err(ProtError::Status(431, "trailer too large"));
+            }
+            None => return Ok(false),
+        };
+        if end > self.max_trailer_size {
+            return Err(ProtError::Status(431, "trailer too large"));
+        }
+        let mut trailers = HeaderMap::new();
+        for line in buf[..end].split(|c| *c == b'\n') {
+            let line = String::from_utf8_lossy(line);
+            match line.trim_end().split_once(':') {
+                Some((name, value)) if !name.trim().is_empty() => {
+                    trailers.push(name.trim().to_string(), value.trim().to_string());
+                }
+                _ => return Err(ProtError::Extension("invalid trailer header")),
+            }
+        }
+        self.read_buf.advance(end + 4);
+        self.trailers = Some(trailers);
+        Ok(true)
+    }
+
+    pub fn set_max_trailer_size(&mut self, max_trailer_size: usize) {
+        self.max_trailer_size = max_trailer_size;
+    }
+
+    pub fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        self.max_chunk_size = max_chunk_size;
+    }
+
+    pub fn set_max_body_size(&mut self, max_body_size: usize) {
+        self.max_body_size = max_body_size;
+    }
+
+    pub fn get_max_body_size(&self) -> usize {
+        self.max_body_size
+    }
+
+    /// 预读chunk头中声明的大小, 数据不足则返回None
+    fn peek_chunk_size(buf: &[u8]) -> Option<usize> {
+        let mut size: usize = 0;
+        let mut has_digit = false;
+        for b in buf {
+            let v = match b {
+                b'0'..=b'9' => b - b'0',
+                b'a'..=b'f' => b - b'a' + 10,
+                b'A'..=b'F' => b - b'A' + 10,
+                _ => break,
+            };
+            has_digit = true;
+            size = match size.checked_mul(16).and_then(|s| s.checked_add(v as usize)) {
+                Some(size) => size,
+                None => return Some(usize::MAX),
+            };
+        }
+        if has_digit {
+            Some(size)
+        } else {
+            None
+        }
     }
 
     pub fn set_left_body(&mut self, left_read_body_len: usize) {
@@ -77,11 +176,31 @@ impl SendStream {
                 if self.is_end {
                     return Ok(());
                 }
+                if self.is_trailer {
+                    if self.parse_trailers()? {
+                        self.is_end = true;
+                    }
+                    break;
+                }
+                // 在数据到达前先校验声明的chunk大小
+                if let Some(size) = Self::peek_chunk_size(self.read_buf.chunk()) {
+                    if size > self.max_chunk_size {
+                        return Err(ProtError::Status(413, "chunk too large"));
+                    }
+                    if size > self.max_body_size.saturating_sub(self.read_body_len) {
+                        return Err(ProtError::Status(413, "body too large"));
+                    }
+                }
                 // TODO 接收小部分的chunk
                 match Helper::parse_chunk_data(&mut self.read_buf.clone()) {
                     Ok((use_size, chunk_size)) => {
-                        self.is_end = chunk_size == 0;
+                        self.read_body_len += chunk_size;
                         self.read_buf.advance(use_size);
+                        // 结束的chunk后可能跟随trailer头
+                        if chunk_size == 0 {
+                            self.is_trailer = true;
+                            continue;
+                        }
                         self.real_read_buf
                             .put_slice(&self.read_buf.chunk()[..chunk_size]);
                         self.read_buf.advance(chunk_size);
@@ -121,6 +240,23 @@ impl SendStream {
         Ok(size)
     }
 
+    /// 同read_data, 目标为空时直接交换缓冲区, 不复制已解析的包体数据
+    pub fn move_data(&mut self, read_data: &mut BinaryMut) -> ProtResult<usize> {
+        self.process_data()?;
+
+        let size = self.real_read_buf.remaining();
+        if size == 0 {
+            return Ok(0);
+        }
+        if read_data.has_remaining() {
+            read_data.put_slice(&self.real_read_buf.chunk());
+            self.real_read_buf.advance_all();
+        } else {
+            *read_data = std::mem::replace(&mut self.real_read_buf, BinaryMut::new());
+        }
+        Ok(size)
+    }
+
     // /// 返回Some则表示数据发送不成功，需要重新进行投递
     // pub fn send_data(&mut self, binary: Binary, is_end_stream: bool) -> Option<(bool, Binary)> {
     //     if let Some(Err(e)) = self
@@ -13,28 +13,89 @@
 use std::{
     any::{Any, TypeId},
     future::poll_fn,
+    io,
     net::SocketAddr,
-    time::Duration,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 use algorithm::buf::{Binary, BinaryMut};
+use rustls::{
+    pki_types::{CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig,
+};
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::TcpStream,
     sync::mpsc::{channel, Receiver},
 };
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
 use tokio_stream::StreamExt;
+use tokio_util::sync::{CancellationToken, DropGuard};
 use webparse::{
-    http::http2::frame::StreamIdentifier, ws::OwnedMessage, Request, Response, Serialize,
+    http::http2::frame::StreamIdentifier,
+    ws::OwnedMessage,
+    Request, Response, Serialize,
 };
 
 use super::{http1::ServerH1Connection, middle::BaseMiddleware};
+#[cfg(target_os = "linux")]
+use crate::http1::{poll_send_file, SendFileFn};
 use crate::{
-    ws::{ServerWsConnection, WsHandshake, WsOption, WsTrait},
-    Body, HttpTrait, Middleware, ProtError, ProtResult, RecvRequest, ServerH2Connection,
-    TimeoutLayer,
+    http2::StreamMetrics,
+    ws::{DeflateConfig, ServerWsConnection, WsHandshake, WsOption, WsTrait},
+    Body, Disconnected, EarlyData, HttpTrait, Middleware, OverloadLayer, ProtError, ProtResult,
+    RecvRequest, ServerH2Connection, TimeoutLayer, TlsInfo,
 };
 
+/// 连接的基本信息, 在连接建立及关闭时回调
+#[derive(Debug, Clone)]
+pub struct ConnInfo {
+    /// 客户端地址
+    pub addr: Option<SocketAddr>,
+    /// 是否为TLS连接
+    pub is_tls: bool,
+    /// 已处理的请求数
+    pub req_num: usize,
+    /// 连接已持续的时间
+    pub elapsed: Duration,
+}
+
+/// 连接关闭的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    /// 正常结束, 如对端关闭或keep-alive结束
+    Normal,
+    /// 读取数据出错
+    ReadError,
+    /// 写入数据出错
+    WriteError,
+    /// 读写或空闲超时
+    Timeout,
+    /// 服务主动停止
+    Shutdown,
+    /// 连接达到最长存活时间
+    MaxAge,
+    /// 其它协议错误
+    Error,
+}
+
+impl CloseReason {
+    fn from_result(result: &ProtResult<()>) -> Self {
+        match result {
+            Ok(()) => CloseReason::Normal,
+            Err(ProtError::Timeout(_)) => CloseReason::Timeout,
+            Err(ProtError::ReadError(_)) => CloseReason::ReadError,
+            Err(ProtError::WriteError(_)) => CloseReason::WriteError,
+            Err(_) => CloseReason::Error,
+        }
+    }
+}
+
+pub type ConnectCallback = Box<dyn FnMut(ConnInfo) + Send + Sync>;
+pub type DisconnectCallback = Box<dyn FnMut(ConnInfo, CloseReason) + Send + Sync>;
+
 pub struct Builder {
     inner: ServerOption,
 }
@@ -96,30 +157,161 @@ impl Builder {
         self
     }
 
+    pub fn stream_timeout(mut self, stream_timeout: Duration) -> Self {
+        self.inner.stream_timeout = Some(stream_timeout);
+        self
+    }
+
+    /// 是否允许HTTP/1的keep-alive, 关闭后无论客户端请求如何, 每个响应后均关闭连接
+    pub fn keep_alive(mut self, keep_alive: bool) -> Self {
+        self.inner.keep_alive = keep_alive;
+        self
+    }
+
+    /// 过载保护, 正在处理的请求数超出高水位时直接回复503.
+    /// 各连接需传入同一个OverloadLayer的克隆, 计数才在连接间共享
+    pub fn overload_layer(mut self, overload: Option<OverloadLayer>) -> Self {
+        self.inner.overload = overload;
+        self
+    }
+
+    /// 连接的最长存活时间, 到达后HTTP/1回复`Connection: close`, HTTP/2发送GOAWAY,
+    /// 使客户端重新建立连接, 便于负载均衡
+    pub fn max_connection_age(mut self, max_connection_age: Duration) -> Self {
+        self.inner.max_connection_age = Some(max_connection_age);
+        self
+    }
+
+    /// 到达最长存活时间后, 等待进行中的请求处理完成的时长, 超出则强制关闭
+    pub fn max_connection_age_grace(mut self, grace: Duration) -> Self {
+        self.inner.max_connection_age_grace = Some(grace);
+        self
+    }
+
+    /// WebSocket是否支持permessage-deflate压缩, 客户端请求时协商, 默认关闭
+    pub fn enable_per_message_deflate(mut self, enable: bool) -> Self {
+        self.inner.ws_deflate = enable.then(DeflateConfig::new);
+        self
+    }
+
+    /// 以自定义的配置支持WebSocket的permessage-deflate压缩
+    pub fn per_message_deflate(mut self, config: DeflateConfig) -> Self {
+        self.inner.ws_deflate = Some(config);
+        self
+    }
+
     pub fn value(self) -> ServerOption {
         self.inner
     }
 
+    /// 连接开始服务时回调
+    pub fn on_connect<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(ConnInfo) + Send + Sync + 'static,
+    {
+        self.inner.on_connect = Some(Box::new(f));
+        self
+    }
+
+    /// 连接结束服务时回调, 附带关闭的原因
+    pub fn on_disconnect<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(ConnInfo, CloseReason) + Send + Sync + 'static,
+    {
+        self.inner.on_disconnect = Some(Box::new(f));
+        self
+    }
+
     pub fn middle<M: Middleware + 'static>(mut self, middle: M) -> Self {
         self.inner.middles.push(Box::new(middle));
         self
     }
 
+    /// 设置TLS的服务端配置, 之后通过stream_tls完成握手
+    pub fn tls_config(mut self, config: Arc<ServerConfig>) -> Self {
+        self.inner.tls = Some(TlsAcceptor::from(config));
+        self
+    }
+
+    /// 以证书链及私钥启用TLS, 设置client_ca时要求客户端提供由其签发的证书(双向认证)
+    pub fn tls_cert(
+        self,
+        certs: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+        client_ca: Option<RootCertStore>,
+    ) -> ProtResult<Self> {
+        let builder = ServerConfig::builder();
+        let builder = match client_ca {
+            Some(roots) => {
+                let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                    .build()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder
+            .with_single_cert(certs, key)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(self.tls_config(Arc::new(config)))
+    }
+
     pub fn stream<T>(self, stream: T) -> Server<T>
     where
         T: AsyncRead + AsyncWrite + Unpin,
     {
         let mut server = Server::new(stream, self.inner.addr);
         server.set_timeout_layer(self.inner.timeout.clone());
+        server.set_stream_timeout(self.inner.stream_timeout.clone());
+        server.set_keep_alive(self.inner.keep_alive);
+        server.set_overload_layer(self.inner.overload);
+        server.set_max_connection_age(self.inner.max_connection_age);
+        server.set_max_connection_age_grace(self.inner.max_connection_age_grace);
+        server.set_ws_deflate(self.inner.ws_deflate);
+        server.on_connect = self.inner.on_connect;
+        server.on_disconnect = self.inner.on_disconnect;
         server
     }
+
+    /// 完成TLS握手后创建服务, 握手的会话信息放入每个请求的extensions中,
+    /// 双向认证时客户端未提供有效证书则握手失败
+    pub async fn stream_tls<T>(self, stream: T) -> ProtResult<Server<TlsStream<T>>>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let acceptor = match &self.inner.tls {
+            Some(acceptor) => acceptor.clone(),
+            None => return Err(ProtError::Extension("tls not configured")),
+        };
+        let stream = acceptor.accept(stream).await?;
+        let tls_info = TlsInfo::from_server_stream(&stream);
+        let mut server = self.stream(stream);
+        server.set_tls_info(Some(tls_info));
+        Ok(server)
+    }
 }
 
 // #[derive(Default)]
 pub struct ServerOption {
     addr: Option<SocketAddr>,
     timeout: Option<TimeoutLayer>,
+    /// HTTP2中单个流的最长处理时间
+    stream_timeout: Option<Duration>,
+    /// 是否允许HTTP/1的keep-alive
+    keep_alive: bool,
+    /// 过载保护, 所有连接共享
+    overload: Option<OverloadLayer>,
+    /// 连接的最长存活时间
+    max_connection_age: Option<Duration>,
+    max_connection_age_grace: Option<Duration>,
+    /// WebSocket的permessage-deflate配置, 为None时不压缩
+    ws_deflate: Option<DeflateConfig>,
     middles: Vec<Box<dyn Middleware>>,
+    on_connect: Option<ConnectCallback>,
+    on_disconnect: Option<DisconnectCallback>,
+    /// TLS握手的配置, 为None时不支持stream_tls
+    tls: Option<TlsAcceptor>,
 }
 
 impl Default for ServerOption {
@@ -127,7 +319,16 @@ impl Default for ServerOption {
         Self {
             addr: Default::default(),
             timeout: Default::default(),
+            stream_timeout: None,
+            keep_alive: true,
+            overload: None,
+            max_connection_age: None,
+            max_connection_age_grace: None,
+            ws_deflate: None,
             middles: vec![Box::new(BaseMiddleware::new(false))],
+            on_connect: None,
+            on_disconnect: None,
+            tls: None,
         }
     }
 }
@@ -146,14 +347,50 @@ where
     callback_ws: Option<Box<dyn WsTrait>>,
     addr: Option<SocketAddr>,
     timeout: Option<TimeoutLayer>,
+    /// HTTP2中单个流的最长处理时间
+    stream_timeout: Option<Duration>,
+    /// TLS连接的会话信息
+    tls_info: Option<TlsInfo>,
+    /// 0-RTT早期数据阶段, 该阶段收到的请求标记为早期数据
+    early_data: Option<EarlyData>,
     req_num: usize,
     max_req_num: usize,
+    /// 是否允许HTTP/1的keep-alive
+    keep_alive: bool,
+    overload: Option<OverloadLayer>,
+    /// 连接的最长存活时间
+    max_connection_age: Option<Duration>,
+    /// 到达最长存活时间后等待处理完成的时长
+    max_connection_age_grace: Option<Duration>,
+    /// WebSocket的permessage-deflate配置
+    ws_deflate: Option<DeflateConfig>,
+    /// 是否已到达最长存活时间, 正在关闭中
+    is_aged: bool,
+    /// 连接开始服务的时间
+    start_time: Instant,
+    /// 主动结束时记录的关闭原因
+    close_reason: Option<CloseReason>,
+    on_connect: Option<ConnectCallback>,
+    on_disconnect: Option<DisconnectCallback>,
+    /// 连接结束时通知正在处理的请求, Server释放时自动触发
+    disconnect: CancellationToken,
+    _disconnect_guard: DropGuard,
 }
 
 impl Server<TcpStream> {
     pub fn builder() -> Builder {
         Builder::new()
     }
+
+    /// 设置HTTP/1的纯文件响应是否通过sendfile直接写入socket, 不经过缓冲区,
+    /// 压缩、分块或限速的响应仍按原方式发送, 默认false
+    #[cfg(target_os = "linux")]
+    pub fn set_send_file(&mut self, send_file: bool) {
+        if let Some(http) = &mut self.http1 {
+            let send_file_fn: SendFileFn<TcpStream> = poll_send_file;
+            http.set_send_file(send_file.then_some(send_file_fn));
+        }
+    }
 }
 
 impl<T> Server<T>
@@ -161,6 +398,7 @@ where
     T: AsyncRead + AsyncWrite + Unpin,
 {
     pub fn new(io: T, addr: Option<SocketAddr>) -> Self {
+        let disconnect = CancellationToken::new();
         Self {
             http1: Some(ServerH1Connection::new(io)),
             http2: None,
@@ -171,8 +409,23 @@ where
             callback_ws: None,
 
             timeout: None,
+            stream_timeout: None,
+            tls_info: None,
+            early_data: None,
             req_num: 0,
             max_req_num: usize::MAX,
+            keep_alive: true,
+            overload: None,
+            max_connection_age: None,
+            max_connection_age_grace: None,
+            ws_deflate: None,
+            is_aged: false,
+            start_time: Instant::now(),
+            close_reason: None,
+            on_connect: None,
+            on_disconnect: None,
+            _disconnect_guard: disconnect.clone().drop_guard(),
+            disconnect,
         }
     }
 }
@@ -182,6 +435,7 @@ where
     T: AsyncRead + AsyncWrite + Unpin,
 {
     pub fn new_by_cache(io: T, addr: Option<SocketAddr>, binary: BinaryMut) -> Self {
+        let disconnect = CancellationToken::new();
         Self {
             http1: Some(ServerH1Connection::new_by_cache(io, binary)),
             http2: None,
@@ -191,8 +445,23 @@ where
             callback_http: None,
             callback_ws: None,
             timeout: None,
+            stream_timeout: None,
+            tls_info: None,
+            early_data: None,
             req_num: 0,
             max_req_num: usize::MAX,
+            keep_alive: true,
+            overload: None,
+            max_connection_age: None,
+            max_connection_age_grace: None,
+            ws_deflate: None,
+            is_aged: false,
+            start_time: Instant::now(),
+            close_reason: None,
+            on_connect: None,
+            on_disconnect: None,
+            _disconnect_guard: disconnect.clone().drop_guard(),
+            disconnect,
         }
     }
 
@@ -267,6 +536,146 @@ where
         }
     }
 
+    pub fn set_stream_timeout(&mut self, stream_timeout: Option<Duration>) {
+        self.stream_timeout = stream_timeout;
+        if let Some(http) = &mut self.http2 {
+            http.set_stream_timeout(stream_timeout);
+        }
+    }
+
+    /// 设置TLS的会话信息, 每个请求的extensions中均可获取,
+    /// 其中的is_early_data按请求收到时的早期数据阶段设置
+    pub fn set_tls_info(&mut self, tls_info: Option<TlsInfo>) {
+        self.tls_info = tls_info;
+    }
+
+    /// 设置接收0-RTT早期数据时的阶段, 由TLS层在握手确认后调用`EarlyData::confirm`
+    pub fn set_early_data(&mut self, early_data: Option<EarlyData>) {
+        self.early_data = early_data;
+    }
+
+    /// 请求收到时的TLS会话信息, 只有早期数据阶段收到的请求标记为早期数据
+    fn request_tls_info(&self) -> Option<TlsInfo> {
+        let mut info = self.tls_info.clone()?;
+        info.is_early_data = self.early_data.as_ref().is_some_and(|e| e.is_early());
+        Some(info)
+    }
+
+    /// 设置chunked请求中单个chunk的最大大小, 超出返回413
+    pub fn set_max_chunk_size(&mut self, max_chunk_size: usize) {
+        if let Some(http) = &mut self.http1 {
+            http.set_max_chunk_size(max_chunk_size);
+        }
+    }
+
+    /// 设置请求包体的最大大小, 声明的Content-Length或chunked的数据超出时返回413
+    pub fn set_max_body_size(&mut self, max_body_size: usize) {
+        if let Some(http) = &mut self.http1 {
+            http.set_max_body_size(max_body_size);
+        }
+    }
+
+    /// 设置请求行的最大长度, 超出返回414, 默认65536
+    pub fn set_max_request_line_bytes(&mut self, max_request_line_bytes: usize) {
+        if let Some(http) = &mut self.http1 {
+            http.set_max_request_line_bytes(max_request_line_bytes);
+        }
+    }
+
+    /// 设置请求头的最大大小, 请求头未结束即超出时返回431并关闭连接, 默认128KB
+    pub fn set_max_header_size(&mut self, max_header_size: usize) {
+        if let Some(http) = &mut self.http1 {
+            http.set_max_header_size(max_header_size);
+        }
+    }
+
+    /// 设置HTTP/1每次从socket读取的缓冲区大小, 默认16KB
+    pub fn set_read_buf_size(&mut self, read_buf_size: usize) {
+        if let Some(http) = &mut self.http1 {
+            http.set_read_buf_size(read_buf_size);
+        }
+    }
+
+    /// 设置HTTP/1的请求包体是否直接从连接的缓冲区读取, 不经过通道缓存,
+    /// 处理函数读取较慢时暂停读取socket, 适用于大文件上传, 默认false
+    pub fn set_stream_body(&mut self, stream_body: bool) {
+        if let Some(http) = &mut self.http1 {
+            http.set_stream_body(stream_body);
+        }
+    }
+
+    /// 设置是否允许HTTP/1的keep-alive, 关闭后每个响应后均关闭连接
+    pub fn set_keep_alive(&mut self, keep_alive: bool) {
+        self.keep_alive = keep_alive;
+        if let Some(http) = &mut self.http1 {
+            http.set_keep_alive(keep_alive);
+        }
+    }
+
+    /// 设置过载保护, 过载时请求不经处理函数直接回复503
+    pub fn set_overload_layer(&mut self, overload: Option<OverloadLayer>) {
+        self.overload = overload;
+    }
+
+    /// 设置连接的最长存活时间, 到达后优雅地关闭连接
+    pub fn set_max_connection_age(&mut self, max_connection_age: Option<Duration>) {
+        self.max_connection_age = max_connection_age;
+    }
+
+    /// 设置到达最长存活时间后等待处理完成的时长, 默认不等待
+    pub fn set_max_connection_age_grace(&mut self, grace: Option<Duration>) {
+        self.max_connection_age_grace = grace;
+    }
+
+    /// 设置WebSocket的permessage-deflate配置, 为None时不压缩
+    pub fn set_ws_deflate(&mut self, ws_deflate: Option<DeflateConfig>) {
+        self.ws_deflate = ws_deflate;
+    }
+
+    /// 下一次需要处理存活时间的时间点, 到达存活时间前为存活时间, 之后为强制关闭的时间
+    fn age_deadline(&self) -> Option<Instant> {
+        let mut deadline = self.start_time + self.max_connection_age?;
+        if self.is_aged {
+            deadline += self.max_connection_age_grace.unwrap_or_default();
+        }
+        Some(deadline)
+    }
+
+    /// 到达最长存活时间, 不再接收新的请求, HTTP/2中已有的流在等待时长内继续处理
+    fn reach_max_age(&mut self) {
+        log::trace!("连接到达最长存活时间:{:?}, 开始关闭", self.max_connection_age);
+        self.is_aged = true;
+        self.close_reason = Some(CloseReason::MaxAge);
+        if let Some(h2) = &mut self.http2 {
+            h2.graceful_shutdown();
+        } else {
+            self.set_keep_alive(false);
+        }
+    }
+
+    pub fn set_on_connect(&mut self, on_connect: Option<ConnectCallback>) {
+        self.on_connect = on_connect;
+    }
+
+    pub fn set_on_disconnect(&mut self, on_disconnect: Option<DisconnectCallback>) {
+        self.on_disconnect = on_disconnect;
+    }
+
+    /// HTTP/2连接上流的并发统计, 非HTTP/2连接返回None
+    pub fn h2_stream_metrics(&self) -> Option<StreamMetrics> {
+        self.http2.as_ref().map(|h2| h2.stream_metrics())
+    }
+
+    /// 当前连接的基本信息
+    pub fn conn_info(&self) -> ConnInfo {
+        ConnInfo {
+            addr: self.addr,
+            is_tls: self.tls_info.is_some(),
+            req_num: self.req_num,
+            elapsed: self.start_time.elapsed(),
+        }
+    }
+
     pub fn middle<M: Middleware + 'static>(&mut self, middle: M) {
         self.middles.push(Box::new(middle));
     }
@@ -339,10 +748,22 @@ where
         Ok(())
     }
 
-    pub async fn handle_request(&mut self, r: RecvRequest) -> ProtResult<Option<bool>> {
+    pub async fn handle_request(&mut self, mut r: RecvRequest) -> ProtResult<Option<bool>> {
         if self.callback_http.is_none() {
             return Err(ProtError::Extension("http callback is none"));
         }
+        if r.extensions().get::<TlsInfo>().is_none() {
+            if let Some(info) = self.request_tls_info() {
+                r.extensions_mut().insert(info);
+            }
+        }
+        // HTTP/2中已设置流级别的断开通知
+        if r.extensions().get::<Disconnected>().is_none() {
+            r.extensions_mut().insert(Disconnected::new(self.disconnect.child_token()));
+        }
+        if let Some(overload) = &self.overload {
+            r.extensions_mut().insert(overload.clone());
+        }
         let result = if let Some(h1) = &mut self.http1 {
             h1.handle_request(
                 &self.addr,
@@ -381,7 +802,22 @@ where
             ProtError::ServerUpgradeHttp2(b, r) => {
                 if self.http1.is_some() {
                     self.http2 = Some(self.http1.take().unwrap().into_h2(b));
-                    if let Some(r) = r {
+                    self.http2
+                        .as_mut()
+                        .unwrap()
+                        .set_stream_timeout(self.stream_timeout);
+                    self.http2
+                        .as_mut()
+                        .unwrap()
+                        .set_disconnect_token(self.disconnect.child_token());
+                    if let Some(mut r) = r {
+                        if let Some(info) = self.request_tls_info() {
+                            r.extensions_mut().insert(info);
+                        }
+                        r.extensions_mut().insert(Disconnected::new(self.disconnect.child_token()));
+                        if let Some(overload) = &self.overload {
+                            r.extensions_mut().insert(overload.clone());
+                        }
                         self.http2
                             .as_mut()
                             .unwrap()
@@ -400,6 +836,21 @@ where
                     return Err(ProtError::ServerUpgradeHttp2(b, r));
                 }
             }
+            ProtError::Status(code, _) => {
+                for i in 0usize..self.middles.len() {
+                    self.middles[i].process_error(None, &err).await;
+                }
+                // 直接以该状态码回复后关闭连接
+                let response = Response::builder()
+                    .status(code)
+                    .header("Connection", "close")
+                    .header("Content-Length", "0")
+                    .body(())
+                    .unwrap();
+                self.send_response(response, None).await?;
+                self.flush().await?;
+                return Err(err);
+            }
             _ => {
                 for i in 0usize..self.middles.len() {
                     self.middles[i].process_error(None, &err).await;
@@ -454,6 +905,25 @@ where
     }
 
     pub async fn incoming(&mut self) -> ProtResult<()> {
+        self.start_time = Instant::now();
+        if let Some(f) = &mut self.on_connect {
+            f(self.conn_info());
+        }
+        let result = self.inner_serve().await;
+        // 通知仍在处理中的请求连接已断开
+        self.disconnect.cancel();
+        if self.on_disconnect.is_some() {
+            let reason = self
+                .close_reason
+                .take()
+                .unwrap_or_else(|| CloseReason::from_result(&result));
+            let info = self.conn_info();
+            (self.on_disconnect.as_mut().unwrap())(info, reason);
+        }
+        result
+    }
+
+    async fn inner_serve(&mut self) -> ProtResult<()> {
         if let Some(addr) = &self.addr {
             log::trace!("HTTP服务开始进行服务, 客户端地址:{addr}");
         } else {
@@ -461,7 +931,24 @@ where
         };
         let (mut ws_receiver, mut ws_option);
         loop {
-            match self.inner_incoming().await {
+            let result = match self.age_deadline() {
+                Some(deadline) => tokio::select! {
+                    r = self.inner_incoming() => Some(r),
+                    _ = tokio::time::sleep_until(deadline.into()) => None,
+                },
+                None => Some(self.inner_incoming().await),
+            };
+            let Some(result) = result else {
+                if self.is_aged {
+                    log::trace!("连接超过最长存活时间的等待时长, 强制关闭");
+                    self.flush().await?;
+                    self.handle_close().await?;
+                    return Ok(());
+                }
+                self.reach_max_age();
+                continue;
+            };
+            match result {
                 Err(ProtError::ServerUpgradeWs(r)) => {
                     if self.callback_ws.is_none() {
                         return Err(ProtError::Extension("websocket callback is none"));
@@ -472,19 +959,31 @@ where
                         self.flush().await?;
                         return Ok(());
                     }
+                    let deflate = self
+                        .ws_deflate
+                        .as_ref()
+                        .and_then(|c| WsHandshake::negotiate_deflate(c, &r, &mut response));
                     let mut binary = BinaryMut::new();
                     let _ = response.serialize(&mut binary);
                     let (sender, receiver) = channel::<OwnedMessage>(10);
                     let shake = WsHandshake::new(sender, Some(r), response, self.addr.clone());
                     ws_option = self.callback_ws.as_mut().unwrap().on_open(shake).await?;
 
-                    let value = if let Some(h1) = self.http1.take() {
+                    let mut value = if let Some(h1) = self.http1.take() {
                         h1.into_ws(binary.freeze())
                     } else if let Some(h2) = self.http2.take() {
                         h2.into_ws(binary.freeze())
                     } else {
                         return Err(ProtError::Extension("unknow version"));
                     };
+                    value.set_deflate(deflate);
+                    if let Some(option) = &ws_option {
+                        value.set_msg_rate(option.msg_rate);
+                        value.set_auto_pong(option.auto_pong);
+                        if let Some(size) = option.max_message_size {
+                            value.set_max_message_size(size);
+                        }
+                    }
                     self.ws = Some(value);
                     ws_receiver = receiver;
                     if ws_option.is_some() && ws_option.as_mut().unwrap().receiver.is_some() {
@@ -511,10 +1010,16 @@ where
                 }
             }
 
+            // 未开启keep-alive时HTTP/1每个请求后均关闭连接
+            let is_h1_close = self.http1.is_some() && !self.keep_alive;
             if self.req_num >= self.max_req_num
+                || is_h1_close
                 || (self.callback_http.is_some()
                     && !self.callback_http.as_mut().unwrap().is_continue_next())
             {
+                if self.req_num < self.max_req_num && !is_h1_close {
+                    self.close_reason = Some(CloseReason::Shutdown);
+                }
                 self.flush().await?;
                 self.handle_close().await?;
                 return Ok(());
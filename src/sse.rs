@@ -0,0 +1,167 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 09:36:18
+
+use std::time::Duration;
+
+use algorithm::buf::Binary;
+use webparse::Response;
+
+use crate::{Body, BodySender, ProtError, ProtResult, RecvResponse};
+
+/// Server-Sent Events中的一个事件
+#[derive(Debug, Clone, Default)]
+pub struct SseEvent {
+    event: Option<String>,
+    data: String,
+    id: Option<String>,
+    retry: Option<u64>,
+}
+
+impl SseEvent {
+    pub fn new(data: &str) -> Self {
+        SseEvent {
+            data: data.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// 事件类型, 对应`event:`字段
+    pub fn event(mut self, event: &str) -> Self {
+        self.event = Some(event.to_string());
+        self
+    }
+
+    /// 事件id, 对应`id:`字段, 断线重连时由客户端以Last-Event-ID带回
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    /// 客户端重连的等待时间, 单位毫秒
+    pub fn retry(mut self, retry: u64) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// 编码成文本格式, 多行数据拆分为多个`data:`, 以空行结束
+    pub fn encode(&self) -> Binary {
+        let mut value = String::new();
+        if let Some(event) = &self.event {
+            value += &format!("event: {}\n", event);
+        }
+        if let Some(id) = &self.id {
+            value += &format!("id: {}\n", id);
+        }
+        if let Some(retry) = &self.retry {
+            value += &format!("retry: {}\n", retry);
+        }
+        for line in self.data.split('\n') {
+            value += &format!("data: {}\n", line.strip_suffix('\r').unwrap_or(line));
+        }
+        value += "\n";
+        Binary::from(value.into_bytes())
+    }
+}
+
+/// 向SSE的包体写入事件, 每个事件作为独立的数据块立即发送
+#[derive(Clone)]
+pub struct SseSender {
+    sender: BodySender,
+}
+
+impl SseSender {
+    pub fn new(sender: BodySender) -> Self {
+        SseSender { sender }
+    }
+
+    async fn send_data(sender: &BodySender, is_end: bool, data: Binary) -> ProtResult<()> {
+        sender
+            .send((is_end, data))
+            .await
+            .map_err(|_| ProtError::channel_closed("sse body"))
+    }
+
+    /// 发送事件, 客户端断开时返回错误
+    pub async fn send(&self, event: &SseEvent) -> ProtResult<()> {
+        Self::send_data(&self.sender, false, event.encode()).await
+    }
+
+    /// 发送以`:`开头的注释行, 客户端会忽略, 可用于保持连接
+    pub async fn comment(&self, text: &str) -> ProtResult<()> {
+        let data = Binary::from(format!(": {}\n\n", text).into_bytes());
+        Self::send_data(&self.sender, false, data).await
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    /// 结束事件流
+    pub async fn close(self) -> ProtResult<()> {
+        Self::send_data(&self.sender, true, Binary::new()).await
+    }
+}
+
+/// 构建Server-Sent Events的响应, 保持连接不设置Content-Length, 并以分块的方式逐个事件发送
+pub struct SseResponse {
+    keep_alive: Option<Duration>,
+    buffer: usize,
+}
+
+impl SseResponse {
+    pub fn new() -> Self {
+        SseResponse {
+            keep_alive: None,
+            buffer: 10,
+        }
+    }
+
+    /// 定时发送注释行, 防止中间代理因连接空闲超时而断开
+    pub fn keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// 设置通道的缓存数量
+    pub fn buffer(mut self, buffer: usize) -> Self {
+        self.buffer = buffer;
+        self
+    }
+
+    /// 生成响应及写入事件的发送端, 所有发送端释放后事件流结束
+    pub fn build(self) -> (SseSender, RecvResponse) {
+        let (sender, body) = Body::sse(self.buffer);
+        if let Some(keep_alive) = self.keep_alive {
+            // 只持有弱引用, 不影响发送端释放后结束事件流
+            let weak = sender.sender.downgrade();
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(keep_alive).await;
+                    let Some(sender) = weak.upgrade() else {
+                        break;
+                    };
+                    if SseSender::new(sender).comment("keep-alive").await.is_err() {
+                        break;
+                    }
+                }
+                log::trace!("SSE事件流已结束, 停止发送保活注释");
+            });
+        }
+        let response = Response::builder()
+            .header("Content-Type", "text/event-stream")
+            .header("Cache-Control", "no-cache")
+            .header("Transfer-Encoding", "chunked")
+            .body(body)
+            .unwrap();
+        (sender, response)
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/21 16:12:50
+
+use std::time::{Duration, Instant};
+
+/// 客户端请求各阶段的时间点, 放在响应的extensions中.
+/// 连接阶段的时间只记录在该连接的第一个响应上
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timings {
+    /// 开始建立连接
+    pub start: Option<Instant>,
+    /// 域名解析完成
+    pub dns_done: Option<Instant>,
+    /// TCP连接完成
+    pub connect_done: Option<Instant>,
+    /// TLS握手完成
+    pub tls_done: Option<Instant>,
+    /// 请求开始发送
+    pub request_start: Option<Instant>,
+    /// 收到响应的第一个字节或帧
+    pub first_byte: Option<Instant>,
+    /// 响应头接收完毕
+    pub response_done: Option<Instant>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self {
+            start: Some(Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    /// 合并连接阶段的时间
+    pub(crate) fn merge_connect(&mut self, connect: &Timings) {
+        self.start = connect.start;
+        self.dns_done = connect.dns_done;
+        self.connect_done = connect.connect_done;
+        self.tls_done = connect.tls_done;
+    }
+
+    pub fn dns(&self) -> Option<Duration> {
+        Some(self.dns_done?.duration_since(self.start?))
+    }
+
+    pub fn connect(&self) -> Option<Duration> {
+        Some(self.connect_done?.duration_since(self.dns_done.or(self.start)?))
+    }
+
+    pub fn tls(&self) -> Option<Duration> {
+        Some(self.tls_done?.duration_since(self.connect_done?))
+    }
+
+    /// 从请求开始发送到收到第一个字节
+    pub fn ttfb(&self) -> Option<Duration> {
+        Some(self.first_byte?.duration_since(self.request_start?))
+    }
+
+    /// 从开始连接(复用的连接从请求开始发送)到响应头接收完毕
+    pub fn total(&self) -> Option<Duration> {
+        Some(self.response_done?.duration_since(self.start.or(self.request_start)?))
+    }
+}
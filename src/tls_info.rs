@@ -0,0 +1,94 @@
+// Copyright 2022 - 2024 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/07 10:05:31
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use tokio_rustls::server::TlsStream;
+
+/// TLS握手后的会话信息, 服务端会放入请求的extensions中
+#[derive(Debug, Clone, Default)]
+pub struct TlsInfo {
+    /// 对端的证书链, DER格式
+    pub peer_certificates: Vec<Vec<u8>>,
+    /// 客户端请求的SNI主机名
+    pub server_name: Option<String>,
+    /// 协商的ALPN协议
+    pub alpn_protocol: Option<Vec<u8>>,
+    /// 协商的加密套件
+    pub cipher_suite: Option<String>,
+    /// 协商的TLS版本
+    pub protocol_version: Option<String>,
+    /// 请求是否在TLS1.3的0-RTT早期数据中收到, 该类数据可被重放,
+    /// 由服务端按[`EarlyData`]的状态为每个请求设置
+    pub is_early_data: bool,
+}
+
+impl TlsInfo {
+    /// 从已完成握手的服务端TLS连接中提取会话信息
+    pub fn from_server_stream<T>(stream: &TlsStream<T>) -> Self {
+        let (_, conn) = stream.get_ref();
+        TlsInfo {
+            peer_certificates: conn
+                .peer_certificates()
+                .map(|certs| certs.iter().map(|c| c.as_ref().to_vec()).collect())
+                .unwrap_or_default(),
+            server_name: conn.server_name().map(|s| s.to_string()),
+            alpn_protocol: conn.alpn_protocol().map(|p| p.to_vec()),
+            cipher_suite: conn
+                .negotiated_cipher_suite()
+                .map(|s| format!("{:?}", s.suite())),
+            protocol_version: conn.protocol_version().map(|v| format!("{:?}", v)),
+            // TlsAcceptor完成完整握手后才返回, 不会读取早期数据,
+            // 自定义接收早期数据时通过EarlyData标记
+            is_early_data: false,
+        }
+    }
+
+    /// 是否有对端证书, 即是否为双向认证
+    pub fn has_peer_certificate(&self) -> bool {
+        !self.peer_certificates.is_empty()
+    }
+}
+
+/// TLS1.3的0-RTT早期数据阶段, 由接收早期数据的TLS层持有,
+/// 握手确认前收到的请求标记为早期数据, 确认后调用`confirm`, 之后的请求正常处理
+#[derive(Debug, Clone)]
+pub struct EarlyData {
+    is_early: Arc<AtomicBool>,
+}
+
+impl EarlyData {
+    pub fn new() -> Self {
+        Self {
+            is_early: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// 握手已确认, 早期数据阶段结束
+    pub fn confirm(&self) {
+        self.is_early.store(false, Ordering::Release);
+    }
+
+    /// 是否仍处于早期数据阶段
+    pub fn is_early(&self) -> bool {
+        self.is_early.load(Ordering::Acquire)
+    }
+}
+
+impl Default for EarlyData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -23,7 +23,7 @@ use webparse::ws::{CloseCode, CloseData, OwnedMessage};
 
 use crate::{ProtResult, TimeoutLayer};
 
-use super::{state::WsState, Control, WsCodec};
+use super::{state::WsState, Control, DeflateContext, WsCodec};
 
 pub struct ClientWsConnection<T> {
     codec: WsCodec<T>,
@@ -34,6 +34,8 @@ pub struct ClientWsConnection<T> {
 struct InnerConnection {
     state: WsState,
     control: Control,
+    /// 连接关闭时的关闭码及原因
+    close_frame: Option<(CloseCode, String)>,
 }
 
 unsafe impl<T> Sync for ClientWsConnection<T> {}
@@ -50,6 +52,7 @@ where
             inner: InnerConnection {
                 state: WsState::Open,
                 control: Control::new(),
+                close_frame: None,
             },
             timeout: None,
         }
@@ -151,10 +154,31 @@ where
         self.inner.control.set_handshake_status(binary, true)
     }
 
+    /// 设置收到ping时是否自动回复相同内容的pong, 默认开启
+    pub fn set_auto_pong(&mut self, auto_pong: bool) {
+        self.inner.control.set_auto_pong(auto_pong)
+    }
+
+    /// 设置握手时协商的permessage-deflate上下文
+    pub fn set_deflate(&mut self, deflate: Option<DeflateContext>) {
+        self.codec.set_deflate(deflate)
+    }
+
+    /// 设置单条消息(合并分片后)的最大大小, 超出后发送1009(Size)并关闭连接
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.codec.set_max_message_size(max_message_size)
+    }
+
     pub fn send_owned_message(&mut self, msg: OwnedMessage) -> ProtResult<()> {
         self.inner.control.send_owned_message(msg)
     }
 
+    /// 连接结束后获取关闭码及原因, 收到Close帧则为对端的关闭码,
+    /// 异常断开则为1006(Abnormal)且原因为空
+    pub fn close_frame(&self) -> Option<(CloseCode, String)> {
+        self.inner.close_frame.clone()
+    }
+
     pub fn receiver_close(&mut self, data: Option<CloseData>) -> ProtResult<()> {
         self.inner
             .state
@@ -180,9 +204,33 @@ where
                             return Poll::Pending;
                         }
                         Poll::Ready(Some(Ok(v))) => {
+                            if let OwnedMessage::Close(data) = &v {
+                                if self.inner.close_frame.is_none() {
+                                    self.inner.close_frame = Some(match data {
+                                        Some(data) => {
+                                            (data.status_code.clone(), data.reason.clone())
+                                        }
+                                        None => (CloseCode::Status, String::new()),
+                                    });
+                                }
+                            }
                             return Poll::Ready(Some(Ok(v)));
                         }
+                        Poll::Ready(Some(Err(e))) if self.codec.is_message_too_big() => {
+                            log::warn!("websocket消息超出最大大小, 关闭连接");
+                            let data =
+                                CloseData::new(CloseCode::Size, "message too big".to_string());
+                            self.inner.close_frame =
+                                Some((data.status_code.clone(), data.reason.clone()));
+                            self.send_owned_message(OwnedMessage::Close(Some(data.clone())))?;
+                            let _ = self.poll_write(cx);
+                            self.inner.state.set_closing(data);
+                            return Poll::Ready(Some(Err(e)));
+                        }
                         Poll::Ready(_e) => {
+                            if self.inner.close_frame.is_none() {
+                                self.inner.close_frame = Some((CloseCode::Abnormal, String::new()));
+                            }
                             let close = OwnedMessage::Close(Some(CloseData::new(
                                 CloseCode::Invalid,
                                 "network".to_string(),
@@ -21,14 +21,41 @@ use tokio_stream::Stream;
 use tokio_util::codec::FramedRead as InnerFramedRead;
 
 use webparse::{
-    ws::{DataFrame, OwnedMessage},
+    ws::{DataFrame, OwnedMessage, WsError},
     WebError,
 };
 
-use crate::ProtResult;
+use crate::{ws::DeflateContext, ProtError, ProtResult};
+
+/// 默认单条消息的最大大小, 防止无限的分片占满内存
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
 
 #[derive(Debug)]
-struct MyCodec(bool);
+struct MyCodec {
+    is_client: bool,
+    /// 单条消息(合并分片后)的最大大小
+    max_message_size: usize,
+    /// 当前分片消息已接收的大小
+    message_size: usize,
+    /// 消息超出了最大大小
+    is_too_big: bool,
+}
+
+impl MyCodec {
+    /// 帧头完整时返回(首字节, 负载长度), 负载未到达前即可判断大小
+    fn peek_header(src: &[u8]) -> Option<(u8, u64)> {
+        if src.len() < 2 {
+            return None;
+        }
+        let len = match src[1] & 0x7F {
+            126 if src.len() >= 4 => u16::from_be_bytes([src[2], src[3]]) as u64,
+            127 if src.len() >= 10 => u64::from_be_bytes(src[2..10].try_into().unwrap()),
+            126 | 127 => return None,
+            len => len as u64,
+        };
+        Some((src[0], len))
+    }
+}
 
 impl tokio_util::codec::Decoder for MyCodec {
     // ...
@@ -36,22 +63,42 @@ impl tokio_util::codec::Decoder for MyCodec {
     type Error = WebError;
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         use bytes::Buf;
+        // 控制帧不计入消息大小, 数据帧到达帧头即检查, 不等待整个消息合并
+        let mut message_size = self.message_size;
+        if let Some((first, len)) = Self::peek_header(src.chunk()) {
+            if first & 0x8 == 0 {
+                if first & 0x0F != 0 {
+                    message_size = 0;
+                }
+                let total = (message_size as u64).saturating_add(len);
+                if total > self.max_message_size as u64 {
+                    log::trace!("websocket消息超出最大大小:{}", self.max_message_size);
+                    self.is_too_big = true;
+                    return Err(WebError::Ws(WsError::ProtocolError("message too big")));
+                }
+                message_size = total as usize;
+            }
+        }
         let (frame, size) = {
             let mut copy = BinaryRef::from(src.chunk());
             let now_len = copy.remaining();
-            let frame = match DataFrame::read_dataframe_with_limit(&mut copy, !self.0, 100000) {
-                Ok(frame) => frame,
-                Err(WebError::Io(io)) if io.kind() == io::ErrorKind::UnexpectedEof => {
-                    return Ok(None);
-                }
-                Err(e) => {
-                    log::trace!("io error = {:?}", e);
-                    return Err(e);
-                }
-            };
+            let frame =
+                match DataFrame::read_dataframe_with_limit(&mut copy, !self.is_client, 100000) {
+                    Ok(frame) => frame,
+                    Err(WebError::Io(io)) if io.kind() == io::ErrorKind::UnexpectedEof => {
+                        return Ok(None);
+                    }
+                    Err(e) => {
+                        log::trace!("io error = {:?}", e);
+                        return Err(e);
+                    }
+                };
             (frame, now_len - copy.remaining())
         };
         src.advance(size);
+        if frame.opcode as u8 & 0x8 == 0 {
+            self.message_size = if frame.finished { 0 } else { message_size };
+        }
         return Ok(Some(frame));
     }
 }
@@ -60,6 +107,8 @@ impl tokio_util::codec::Decoder for MyCodec {
 pub struct FramedRead<T> {
     inner: InnerFramedRead<T, MyCodec>,
     caches: Vec<DataFrame>,
+    /// 协商了permessage-deflate时的压缩上下文, 发送时也使用该上下文
+    deflate: Option<DeflateContext>,
 }
 
 impl<T> FramedRead<T> {
@@ -70,6 +119,20 @@ impl<T> FramedRead<T> {
     pub fn get_ref(&self) -> &T {
         self.inner.get_ref()
     }
+
+    pub fn is_client(&self) -> bool {
+        self.inner.decoder().is_client
+    }
+
+    /// 设置单条消息(合并分片后)的最大大小, 默认64M
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.inner.decoder_mut().max_message_size = max_message_size;
+    }
+
+    /// 是否因消息超出最大大小而结束
+    pub fn is_message_too_big(&self) -> bool {
+        self.inner.decoder().is_too_big
+    }
 }
 
 impl<T> FramedRead<T>
@@ -78,8 +141,17 @@ where
 {
     pub fn new(io: T, is_client: bool) -> FramedRead<T> {
         FramedRead {
-            inner: InnerFramedRead::new(io, MyCodec(is_client)),
+            inner: InnerFramedRead::new(
+                io,
+                MyCodec {
+                    is_client,
+                    max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                    message_size: 0,
+                    is_too_big: false,
+                },
+            ),
             caches: vec![],
+            deflate: None,
         }
     }
 
@@ -90,6 +162,41 @@ where
     pub fn set_cache_buf(&mut self, read_buf: BinaryMut) {
         self.inner.read_buffer_mut().put_slice(read_buf.chunk());
     }
+
+    pub fn set_deflate(&mut self, deflate: Option<DeflateContext>) {
+        self.deflate = deflate;
+    }
+
+    pub fn get_deflate(&mut self) -> Option<&mut DeflateContext> {
+        self.deflate.as_mut()
+    }
+
+    fn too_big_error() -> ProtError {
+        ProtError::Extension("websocket message too big")
+    }
+
+    /// 合并分片为完整的消息, 首帧带RSV1标记时整条消息为压缩数据
+    fn decode_message(&mut self) -> ProtResult<OwnedMessage> {
+        let frames: Vec<DataFrame> = self.caches.drain(..).collect();
+        match &mut self.deflate {
+            Some(deflate) if frames[0].reserved[0] => {
+                let opcode = frames[0].opcode;
+                let mut data = vec![];
+                for mut frame in frames {
+                    data.append(&mut frame.data);
+                }
+                // 解压后的大小同样受限, 超出时立即停止解压
+                let max_size = self.inner.decoder().max_message_size;
+                let Some(data) = deflate.decompress_message(&data, max_size)? else {
+                    self.inner.decoder_mut().is_too_big = true;
+                    return Err(Self::too_big_error());
+                };
+                let frame = DataFrame::new(true, opcode, data);
+                Ok(OwnedMessage::from_dataframes(vec![frame])?)
+            }
+            _ => Ok(OwnedMessage::from_dataframes(frames)?),
+        }
+    }
 }
 
 impl<T> AsyncRead for FramedRead<T>
@@ -123,25 +230,38 @@ where
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
         loop {
+            // 超出大小后不再读取
+            if self.is_message_too_big() {
+                return Poll::Ready(None);
+            }
             let bytes = match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
                 Some(Ok(bytes)) => bytes,
+                Some(Err(_)) if self.is_message_too_big() => {
+                    return Poll::Ready(Some(Err(Self::too_big_error())));
+                }
                 Some(Err(WebError::Io(io))) if io.kind() == io::ErrorKind::UnexpectedEof => {
                     println!("is UnexpectedEof");
                     return Poll::Pending;
                 }
+                Some(Err(WebError::Io(io))) => {
+                    return Poll::Ready(Some(Err(ProtError::ReadError(io))))
+                }
                 Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
                 None => {
                     return Poll::Ready(None);
                 }
             };
 
+            // 控制帧不分片, 可能夹在分片的消息之间, 直接返回
+            if bytes.opcode as u8 & 0x8 != 0 {
+                let msg = OwnedMessage::from_dataframes(vec![bytes])?;
+                return Poll::Ready(Some(Ok(msg)));
+            }
             let is_finish = bytes.finished;
             self.caches.push(bytes);
             if is_finish {
-                let msg = OwnedMessage::from_dataframes(self.caches.drain(..).collect())?;
+                let msg = self.decode_message()?;
                 return Poll::Ready(Some(Ok(msg)));
-            } else {
-                return Poll::Pending;
             }
         }
     }
@@ -12,10 +12,12 @@ pub use framed_read::FramedRead;
 pub use framed_write::FramedWrite;
 use futures::Stream;
 use tokio::io::{AsyncRead, AsyncWrite};
-use webparse::ws::{DataFrameable, OwnedMessage};
+use webparse::ws::{DataFrame, DataFrameable, Opcode, OwnedMessage};
 
 use crate::ProtResult;
 
+use super::DeflateContext;
+
 #[derive(Debug)]
 pub struct WsCodec<T> {
     inner: FramedRead<FramedWrite<T>>,
@@ -72,13 +74,43 @@ where
         self.framed_write().set_cache_buf(write_buf);
     }
 
+    /// 设置协商后的permessage-deflate上下文, 之后收发的数据消息均经过压缩
+    pub fn set_deflate(&mut self, deflate: Option<DeflateContext>) {
+        self.inner.set_deflate(deflate);
+    }
+
+    pub fn is_deflate(&mut self) -> bool {
+        self.inner.get_deflate().is_some()
+    }
+
+    /// 设置单条消息(合并分片后)的最大大小
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.inner.set_max_message_size(max_message_size);
+    }
+
+    pub fn is_message_too_big(&self) -> bool {
+        self.inner.is_message_too_big()
+    }
+
     pub fn send_msg(&mut self, msg: OwnedMessage, mask: bool) -> ProtResult<usize> {
         log::trace!("Websocket:发送帧数据: {:?}", msg);
-        if mask {
-            msg.write_to(self.framed_write().get_mut_bytes(), Some(rand::random()))?;
-        } else {
-            msg.write_to(self.framed_write().get_mut_bytes(), None)?;
-        }
+        let mask = if mask { Some(rand::random()) } else { None };
+        let (opcode, data) = match (&msg, self.inner.get_deflate()) {
+            (OwnedMessage::Text(v), Some(deflate)) => {
+                (Opcode::Text, deflate.compress_message(v.as_bytes())?)
+            }
+            (OwnedMessage::Binary(v), Some(deflate)) => {
+                (Opcode::Binary, deflate.compress_message(v)?)
+            }
+            _ => {
+                msg.write_to(self.framed_write().get_mut_bytes(), mask)?;
+                return Ok(0);
+            }
+        };
+        // 压缩的消息以RSV1标记
+        let mut frame = DataFrame::new(true, opcode, data);
+        frame.reserved[0] = true;
+        frame.write_to(self.framed_write().get_mut_bytes(), mask)?;
         Ok(0)
     }
 }
@@ -0,0 +1,170 @@
+// Copyright 2022 - 2024 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 02:48:12
+
+use std::{
+    future::poll_fn,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use algorithm::buf::BinaryMut;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_stream::Stream;
+use webparse::ws::{CloseCode, CloseData, OwnedMessage};
+
+use crate::{ProtError, ProtResult};
+
+use super::{state::WsStatePingPong, DeflateContext, WsCodec};
+
+/// WebSocket的消息, 分片的消息已合并为一条
+#[derive(Debug, Clone)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<CloseData>),
+}
+
+impl From<OwnedMessage> for Message {
+    fn from(msg: OwnedMessage) -> Self {
+        match msg {
+            OwnedMessage::Text(v) => Message::Text(v),
+            OwnedMessage::Binary(v) => Message::Binary(v),
+            OwnedMessage::Ping(v) => Message::Ping(v),
+            OwnedMessage::Pong(v) => Message::Pong(v),
+            OwnedMessage::Close(v) => Message::Close(v),
+        }
+    }
+}
+
+impl From<Message> for OwnedMessage {
+    fn from(msg: Message) -> Self {
+        match msg {
+            Message::Text(v) => OwnedMessage::Text(v),
+            Message::Binary(v) => OwnedMessage::Binary(v),
+            Message::Ping(v) => OwnedMessage::Ping(v),
+            Message::Pong(v) => OwnedMessage::Pong(v),
+            Message::Close(v) => OwnedMessage::Close(v),
+        }
+    }
+}
+
+/// 握手完成后的WebSocket连接, 直接收发消息,
+/// 客户端发送的帧加掩码, 服务端发送的帧不加掩码
+pub struct WsConnection<T> {
+    codec: WsCodec<T>,
+    ping_pong: WsStatePingPong,
+    is_client: bool,
+}
+
+impl<T> WsConnection<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn new(io: T, is_client: bool) -> Self {
+        Self {
+            codec: WsCodec::new(io, is_client),
+            ping_pong: WsStatePingPong::new(),
+            is_client,
+        }
+    }
+
+    pub fn is_client(&self) -> bool {
+        self.is_client
+    }
+
+    /// 设置收到ping时是否自动回复相同内容的pong, 默认开启, ping仍会返回给调用方
+    pub fn set_auto_pong(&mut self, auto_pong: bool) {
+        self.ping_pong.set_auto_pong(auto_pong);
+    }
+
+    /// 设置握手时协商的permessage-deflate上下文, 之后的数据消息均压缩收发
+    pub fn set_deflate(&mut self, deflate: Option<DeflateContext>) {
+        self.codec.set_deflate(deflate);
+    }
+
+    /// 设置单条消息(合并分片后)的最大大小, 超出后发送1009(Size)并结束读取
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.codec.set_max_message_size(max_message_size);
+    }
+
+    pub fn into_io(self) -> T {
+        self.codec.into_io()
+    }
+
+    /// 握手时已读取或待写入的数据
+    pub fn set_cache_buf(&mut self, read_buf: BinaryMut, write_buf: BinaryMut) {
+        self.codec.set_cache_buf(read_buf, write_buf)
+    }
+
+    /// 发送消息并等待写入完成
+    pub async fn send(&mut self, msg: Message) -> ProtResult<()> {
+        self.codec.send_msg(msg.into(), self.is_client)?;
+        poll_fn(|cx| self.poll_write(cx)).await
+    }
+
+    pub async fn send_text(&mut self, s: String) -> ProtResult<()> {
+        self.send(Message::Text(s)).await
+    }
+
+    pub async fn send_binary(&mut self, data: Vec<u8>) -> ProtResult<()> {
+        self.send(Message::Binary(data)).await
+    }
+
+    /// 发送关闭消息, 之后仍可读取对端的关闭消息
+    pub async fn close(&mut self, data: Option<CloseData>) -> ProtResult<()> {
+        self.send(Message::Close(data)).await
+    }
+
+    /// 写入缓冲区中所有待发送的数据
+    pub fn poll_write(&mut self, cx: &mut Context<'_>) -> Poll<ProtResult<()>> {
+        loop {
+            self.ping_pong.poll_handle(&mut self.codec, self.is_client)?;
+            if self.codec.is_write_end() {
+                return Poll::Ready(Ok(()));
+            }
+            ready!(self.codec.poll_flush(cx)).map_err(ProtError::WriteError)?;
+        }
+    }
+}
+
+impl<T> Stream for WsConnection<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    type Item = ProtResult<Message>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // 先尽量发出未写完的数据, 写入的错误在下次发送时返回
+        let _ = self.poll_write(cx);
+        match ready!(Pin::new(&mut self.codec).poll_next(cx)) {
+            Some(Ok(msg)) => {
+                if let OwnedMessage::Ping(v) = &msg {
+                    self.ping_pong.receive(v);
+                    let _ = self.poll_write(cx);
+                }
+                Poll::Ready(Some(Ok(msg.into())))
+            }
+            Some(Err(e)) => {
+                if self.codec.is_message_too_big() {
+                    let data = CloseData::new(CloseCode::Size, "message too big".to_string());
+                    self.codec
+                        .send_msg(OwnedMessage::Close(Some(data)), self.is_client)?;
+                    let _ = self.poll_write(cx);
+                }
+                Poll::Ready(Some(Err(e)))
+            }
+            None => Poll::Ready(None),
+        }
+    }
+}
@@ -21,12 +21,16 @@ use futures::Stream;
 use tokio::io::{AsyncRead, AsyncWrite};
 use webparse::{ws::OwnedMessage};
 
-use crate::ProtResult;
+use crate::{ProtError, ProtResult};
 
-use super::{state::WsStateHandshake, WsCodec};
+use super::{
+    state::{WsStateHandshake, WsStatePingPong},
+    WsCodec,
+};
 
 pub(crate) struct Control {
     handshake: WsStateHandshake,
+    ping_pong: WsStatePingPong,
     msgs: LinkedList<OwnedMessage>,
 
     is_client: bool,
@@ -36,6 +40,7 @@ impl Control {
     pub fn new() -> Self {
         Self {
             handshake: WsStateHandshake::new_server(),
+            ping_pong: WsStatePingPong::new(),
             msgs: LinkedList::new(),
             is_client: false,
         }
@@ -46,6 +51,11 @@ impl Control {
         self.handshake.set_handshake_status(binary, is_client);
     }
 
+    /// 设置收到ping时是否自动回复pong
+    pub fn set_auto_pong(&mut self, auto_pong: bool) {
+        self.ping_pong.set_auto_pong(auto_pong);
+    }
+
     pub fn send_owned_message(&mut self, msg: OwnedMessage) -> ProtResult<()> {
         self.msgs.push_back(msg);
         Ok(())
@@ -59,11 +69,17 @@ impl Control {
     where
         T: AsyncRead + AsyncWrite + Unpin,
     {
-        while let Some(msg) = self.msgs.pop_front() {
-            codec.send_msg(msg, self.is_client)?;
+        loop {
+            self.ping_pong.poll_handle(codec, self.is_client)?;
+            while let Some(msg) = self.msgs.pop_front() {
+                codec.send_msg(msg, self.is_client)?;
+            }
+            ready!(codec.poll_flush(cx)).map_err(ProtError::WriteError)?;
+            // 写完后还有待回复的pong则继续写入
+            if !self.ping_pong.has_pending() || !codec.is_write_end() {
+                return Poll::Ready(Ok(()));
+            }
         }
-        ready!(codec.poll_flush(cx))?;
-        Poll::Ready(Ok(()))
     }
 
     pub fn poll_request<T>(
@@ -81,6 +97,11 @@ impl Control {
         match Pin::new(&mut *codec).poll_next(cx) {
             Poll::Ready(None) => return Poll::Ready(None),
             Poll::Ready(Some(Ok(msg))) => {
+                // 自动回复pong, ping仍返回给应用
+                if let OwnedMessage::Ping(v) = &msg {
+                    self.ping_pong.receive(v);
+                    let _ = self.poll_write(cx, codec);
+                }
                 return Poll::Ready(Some(Ok(msg)));
             }
             Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
@@ -0,0 +1,333 @@
+// Copyright 2022 - 2024 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 22:16:24
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use crate::{ProtError, ProtResult};
+
+/// 每条压缩消息结尾被去掉的同步标记
+static DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+static EXTENSION_NAME: &str = "permessage-deflate";
+/// 当前的deflate实现固定使用15位的窗口
+const MAX_WINDOW_BITS: u8 = 15;
+
+/// permessage-deflate的配置.
+/// 保留上下文(context takeover)时每个连接一直持有完整的压缩上下文(约256KB),
+/// 压缩率更高; 不保留时每条消息结束即释放, 内存占用有上限但压缩率较低.
+/// 默认服务端不保留上下文
+#[derive(Debug, Clone)]
+pub struct DeflateConfig {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    client_max_window_bits: u8,
+    level: u32,
+}
+
+impl Default for DeflateConfig {
+    fn default() -> Self {
+        Self {
+            server_no_context_takeover: true,
+            client_no_context_takeover: false,
+            client_max_window_bits: MAX_WINDOW_BITS,
+            level: 6,
+        }
+    }
+}
+
+impl DeflateConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 服务端压缩时是否不保留上下文, 默认true
+    pub fn server_no_context_takeover(mut self, value: bool) -> Self {
+        self.server_no_context_takeover = value;
+        self
+    }
+
+    /// 要求客户端压缩时不保留上下文, 服务端解压时也随之每条消息释放上下文, 默认false
+    pub fn client_no_context_takeover(mut self, value: bool) -> Self {
+        self.client_no_context_takeover = value;
+        self
+    }
+
+    /// 客户端声明支持时, 限制客户端压缩的窗口大小(8-15), 默认15
+    pub fn client_max_window_bits(mut self, bits: u8) -> Self {
+        assert!((8..=15).contains(&bits), "窗口大小必须在8-15之间");
+        self.client_max_window_bits = bits;
+        self
+    }
+
+    /// 压缩等级(0-9), 默认6
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// 根据客户端的Sec-WebSocket-Extensions选择第一个可接受的方案, 均不可接受返回None
+    pub fn negotiate(&self, offers: &str) -> Option<DeflateParams> {
+        offers
+            .split(',')
+            .find_map(|offer| self.negotiate_offer(offer))
+    }
+
+    fn negotiate_offer(&self, offer: &str) -> Option<DeflateParams> {
+        let mut params = DeflateParams {
+            server_no_context_takeover: self.server_no_context_takeover,
+            client_no_context_takeover: self.client_no_context_takeover,
+            server_max_window_bits: None,
+            client_max_window_bits: None,
+            level: self.level,
+        };
+        for (key, value) in Self::parse_params(offer)? {
+            match (key, value) {
+                ("server_no_context_takeover", None) => params.server_no_context_takeover = true,
+                ("client_no_context_takeover", None) => params.client_no_context_takeover = true,
+                ("server_max_window_bits", Some(v)) => {
+                    let bits = Self::parse_window_bits(v)?;
+                    // 无法以更小的窗口压缩, 拒绝该方案
+                    if bits < MAX_WINDOW_BITS {
+                        return None;
+                    }
+                    params.server_max_window_bits = Some(bits);
+                }
+                ("client_max_window_bits", None) => {
+                    params.client_max_window_bits = Some(self.client_max_window_bits);
+                }
+                ("client_max_window_bits", Some(v)) => {
+                    let bits = Self::parse_window_bits(v)?;
+                    params.client_max_window_bits = Some(bits.min(self.client_max_window_bits));
+                }
+                _ => return None,
+            }
+        }
+        Some(params)
+    }
+
+    /// 客户端握手时发送的Sec-WebSocket-Extensions
+    pub fn to_offer(&self) -> String {
+        let mut value = EXTENSION_NAME.to_string();
+        if self.client_no_context_takeover {
+            value += "; client_no_context_takeover";
+        }
+        value + "; client_max_window_bits"
+    }
+
+    /// 客户端解析服务端回复的Sec-WebSocket-Extensions, 无法遵守时返回None, 需断开连接
+    pub fn accept(&self, response: &str) -> Option<DeflateParams> {
+        let mut params = DeflateParams {
+            server_no_context_takeover: false,
+            client_no_context_takeover: self.client_no_context_takeover,
+            server_max_window_bits: None,
+            client_max_window_bits: None,
+            level: self.level,
+        };
+        for (key, value) in Self::parse_params(response)? {
+            match (key, value) {
+                ("server_no_context_takeover", None) => params.server_no_context_takeover = true,
+                ("client_no_context_takeover", None) => params.client_no_context_takeover = true,
+                // 解压固定使用最大的窗口, 可兼容服务端更小的窗口
+                ("server_max_window_bits", Some(v)) => {
+                    params.server_max_window_bits = Some(Self::parse_window_bits(v)?);
+                }
+                ("client_max_window_bits", Some(v)) => {
+                    let bits = Self::parse_window_bits(v)?;
+                    if bits < MAX_WINDOW_BITS {
+                        return None;
+                    }
+                    params.client_max_window_bits = Some(bits);
+                }
+                _ => return None,
+            }
+        }
+        Some(params)
+    }
+
+    /// 拆分单个扩展的参数, 非permessage-deflate或有重复的参数时返回None
+    fn parse_params(value: &str) -> Option<Vec<(&str, Option<&str>)>> {
+        let mut parts = value.split(';').map(|s| s.trim());
+        if !parts.next()?.eq_ignore_ascii_case(EXTENSION_NAME) {
+            return None;
+        }
+        let mut params: Vec<(&str, Option<&str>)> = vec![];
+        for part in parts.filter(|s| !s.is_empty()) {
+            let (key, value) = match part.split_once('=') {
+                Some((k, v)) => (k.trim(), Some(v.trim().trim_matches('"'))),
+                None => (part, None),
+            };
+            if params.iter().any(|(k, _)| *k == key) {
+                return None;
+            }
+            params.push((key, value));
+        }
+        Some(params)
+    }
+
+    fn parse_window_bits(value: &str) -> Option<u8> {
+        match value.parse::<u8>() {
+            Ok(bits) if (8..=15).contains(&bits) => Some(bits),
+            _ => None,
+        }
+    }
+}
+
+/// 协商后双方使用的permessage-deflate参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeflateParams {
+    pub server_no_context_takeover: bool,
+    pub client_no_context_takeover: bool,
+    pub server_max_window_bits: Option<u8>,
+    pub client_max_window_bits: Option<u8>,
+    level: u32,
+}
+
+impl DeflateParams {
+    /// 生成回复给客户端的Sec-WebSocket-Extensions
+    pub fn to_header(&self) -> String {
+        let mut value = EXTENSION_NAME.to_string();
+        if self.server_no_context_takeover {
+            value += "; server_no_context_takeover";
+        }
+        if self.client_no_context_takeover {
+            value += "; client_no_context_takeover";
+        }
+        if let Some(bits) = self.server_max_window_bits {
+            value += &format!("; server_max_window_bits={}", bits);
+        }
+        if let Some(bits) = self.client_max_window_bits {
+            value += &format!("; client_max_window_bits={}", bits);
+        }
+        value
+    }
+}
+
+/// 单个连接的压缩及解压上下文, 不保留上下文时每条消息结束后释放
+#[derive(Debug)]
+pub struct DeflateContext {
+    params: DeflateParams,
+    is_server: bool,
+    compress: Option<Compress>,
+    decompress: Option<Decompress>,
+}
+
+impl DeflateContext {
+    pub fn new(params: DeflateParams, is_server: bool) -> Self {
+        Self {
+            params,
+            is_server,
+            compress: None,
+            decompress: None,
+        }
+    }
+
+    pub fn params(&self) -> &DeflateParams {
+        &self.params
+    }
+
+    /// 当前是否持有压缩上下文
+    pub fn has_compress_context(&self) -> bool {
+        self.compress.is_some()
+    }
+
+    /// 当前是否持有解压上下文
+    pub fn has_decompress_context(&self) -> bool {
+        self.decompress.is_some()
+    }
+
+    fn is_local_no_takeover(&self) -> bool {
+        if self.is_server {
+            self.params.server_no_context_takeover
+        } else {
+            self.params.client_no_context_takeover
+        }
+    }
+
+    fn is_remote_no_takeover(&self) -> bool {
+        if self.is_server {
+            self.params.client_no_context_takeover
+        } else {
+            self.params.server_no_context_takeover
+        }
+    }
+
+    /// 压缩一条完整的消息, 去掉结尾的同步标记
+    pub fn compress_message(&mut self, data: &[u8]) -> ProtResult<Vec<u8>> {
+        let level = Compression::new(self.params.level);
+        let compress = self
+            .compress
+            .get_or_insert_with(|| Compress::new(level, false));
+        let start = compress.total_in();
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        loop {
+            if out.len() == out.capacity() {
+                out.reserve(out.capacity());
+            }
+            let consumed = (compress.total_in() - start) as usize;
+            compress
+                .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(|_| ProtError::Extension("deflate compress error"))?;
+            let consumed = (compress.total_in() - start) as usize;
+            // 输出缓冲区未写满表示同步刷新已完成
+            if consumed == data.len() && out.len() < out.capacity() {
+                break;
+            }
+        }
+        if out.ends_with(&DEFLATE_TAIL) {
+            out.truncate(out.len() - DEFLATE_TAIL.len());
+        }
+        if self.is_local_no_takeover() {
+            self.compress = None;
+        }
+        Ok(out)
+    }
+
+    /// 解压一条完整的消息, 解压后超出max_size时立即停止并返回None, 防止解压炸弹
+    pub fn decompress_message(
+        &mut self,
+        data: &[u8],
+        max_size: usize,
+    ) -> ProtResult<Option<Vec<u8>>> {
+        let decompress = self.decompress.get_or_insert_with(|| Decompress::new(false));
+        let mut input = Vec::with_capacity(data.len() + DEFLATE_TAIL.len());
+        input.extend_from_slice(data);
+        input.extend_from_slice(&DEFLATE_TAIL);
+        let start = decompress.total_in();
+        let limit = max_size.saturating_add(1);
+        let mut out = Vec::with_capacity(std::cmp::min(data.len() * 2 + 64, limit));
+        loop {
+            if out.len() == out.capacity() {
+                out.reserve(std::cmp::min(out.capacity(), limit - out.len()));
+            }
+            let consumed = (decompress.total_in() - start) as usize;
+            let status = decompress
+                .decompress_vec(&input[consumed..], &mut out, FlushDecompress::Sync)
+                .map_err(|_| ProtError::Extension("deflate decompress error"))?;
+            if out.len() > max_size {
+                log::trace!("websocket解压后的消息超出最大大小:{}", max_size);
+                return Ok(None);
+            }
+            let consumed = (decompress.total_in() - start) as usize;
+            if status == Status::StreamEnd
+                || (consumed == input.len() && out.len() < out.capacity())
+            {
+                break;
+            }
+            if status == Status::BufError && out.len() < out.capacity() {
+                return Err(ProtError::Extension("deflate decompress error"));
+            }
+        }
+        if self.is_remote_no_takeover() {
+            self.decompress = None;
+        }
+        Ok(Some(out))
+    }
+}
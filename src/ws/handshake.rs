@@ -22,7 +22,11 @@ use webparse::{
 
 use crate::{Body, ProtError, ProtResult, RecvRequest, RecvResponse};
 
+use super::{DeflateConfig, DeflateContext};
+
 static MAGIC_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// 支持的websocket协议版本
+static WS_VERSION: &str = "13";
 
 pub struct WsHandshake {
     pub sender: Sender<OwnedMessage>,
@@ -83,26 +87,58 @@ impl WsHandshake {
         let key = req.headers().get_str_value(&"Sec-WebSocket-Key");
         let protocol = req.headers().get_str_value(&"Sec-WebSocket-Protocol");
         let version = req.headers().get_str_value(&"Sec-WebSocket-Version");
-        if key.is_none() || version.as_ref().map(|s| &**s) != Some("13") {
+        // 不支持的版本返回426, 并告知支持的版本
+        if version.as_ref().map(|s| s.trim()) != Some(WS_VERSION) {
+            return Ok(Response::builder()
+                .status(426)
+                .header("Sec-WebSocket-Version", WS_VERSION)
+                .body("unsupported websocket version")
+                .unwrap()
+                .into_type());
+        }
+        if key.is_none() {
             return Ok(Response::builder()
                 .status(400)
-                .body("invalid websocket version")
+                .body("invalid websocket key")
                 .unwrap()
                 .into_type());
         }
-        let (key, protocol) = (key.unwrap(), protocol.unwrap_or("chat".to_string()));
-        let accept = Self::build_accept(&key)?;
-        let protocols: Vec<&str> = protocol
-            .split(|c| c == ',' || c == ' ')
-            .filter(|s| !s.is_empty())
-            .collect();
-        return Ok(Response::builder()
+        let accept = Self::build_accept(&key.unwrap())?;
+        let mut builder = Response::builder()
             .status(101)
             .header("Upgrade", "websocket")
             .header("Connection", "Upgrade")
-            .header("Sec-WebSocket-Accept", accept)
-            .header("Sec-WebSocket-Protocol", protocols[0].to_string())
-            .body(Body::empty())
-            .unwrap());
+            .header("Sec-WebSocket-Accept", accept);
+        // 只能选择客户端提供的子协议, 未提供时不返回该头, 否则客户端将断开连接
+        let protocol = protocol.as_ref().and_then(|p| {
+            p.split(|c| c == ',' || c == ' ')
+                .find(|s| !s.is_empty())
+                .map(|s| s.to_string())
+        });
+        if let Some(protocol) = protocol {
+            builder = builder.header("Sec-WebSocket-Protocol", protocol);
+        }
+        return Ok(builder.body(Body::empty()).unwrap());
+    }
+
+    /// 服务端根据请求中的Sec-WebSocket-Extensions协商permessage-deflate,
+    /// 成功时在101的响应中写入协商结果. 响应中已自行设置扩展时不做处理
+    pub fn negotiate_deflate(
+        config: &DeflateConfig,
+        req: &RecvRequest,
+        res: &mut RecvResponse,
+    ) -> Option<DeflateContext> {
+        if res
+            .headers()
+            .get_option_value(&"Sec-WebSocket-Extensions")
+            .is_some()
+        {
+            return None;
+        }
+        let offers = req.headers().get_str_value(&"Sec-WebSocket-Extensions")?;
+        let params = config.negotiate(&offers)?;
+        res.headers_mut()
+            .insert("Sec-WebSocket-Extensions", params.to_header());
+        Some(DeflateContext::new(params, true))
     }
 }
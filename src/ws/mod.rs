@@ -1,6 +1,8 @@
 mod client_connection;
 mod codec;
+mod connection;
 mod control;
+mod deflate;
 mod handshake;
 mod option;
 mod server_connection;
@@ -9,7 +11,9 @@ mod ws_trait;
 
 pub use client_connection::ClientWsConnection;
 pub use codec::{FramedRead, FramedWrite, WsCodec};
+pub use connection::{Message, WsConnection};
 use control::Control;
+pub use deflate::{DeflateConfig, DeflateContext, DeflateParams};
 pub use handshake::WsHandshake;
 pub use option::WsOption;
 pub use server_connection::ServerWsConnection;
@@ -13,6 +13,8 @@
 use tokio::{time::{Duration, Instant, sleep_until}, sync::mpsc::Receiver};
 use webparse::ws::OwnedMessage;
 
+use crate::Rate;
+
 
 
 // 存储由on_open返回的配置文件, 如定时器之类等
@@ -20,6 +22,12 @@ use webparse::ws::OwnedMessage;
 pub struct WsOption {
     pub interval: Option<Duration>,
     pub receiver: Option<Receiver<OwnedMessage>>,
+    /// 每个周期内允许接收的消息数, 超出则以1008(Policy)关闭连接
+    pub msg_rate: Option<Rate>,
+    /// 收到ping时是否自动回复pong, 默认开启, 关闭后可在on_ping中自行回复
+    pub auto_pong: bool,
+    /// 单条消息(合并分片后)的最大大小, 超出则以1009(Size)关闭连接
+    pub max_message_size: Option<usize>,
     next_interval: Option<Instant>,
 }
 
@@ -28,6 +36,9 @@ impl WsOption {
         Self {
             interval: None,
             receiver: None,
+            msg_rate: None,
+            auto_pong: true,
+            max_message_size: None,
             next_interval: None,
         }
     }
@@ -42,6 +53,19 @@ impl WsOption {
         self.receiver = Some(receiver);
     }
 
+    pub fn set_msg_rate(&mut self, rate: Rate) {
+        assert!(rate.per > Duration::ZERO);
+        self.msg_rate = Some(rate);
+    }
+
+    pub fn set_auto_pong(&mut self, auto_pong: bool) {
+        self.auto_pong = auto_pong;
+    }
+
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.max_message_size = Some(max_message_size);
+    }
+
     async fn inner_interval_wait(&mut self) -> Option<()> {
         sleep_until(self.next_interval.unwrap()).await;
         self.next_interval = Some(Instant::now() + self.interval.unwrap());
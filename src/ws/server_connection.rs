@@ -21,9 +21,9 @@ use tokio_stream::Stream;
 use tokio::io::{AsyncRead, AsyncWrite};
 use webparse::ws::{CloseCode, CloseData, OwnedMessage};
 
-use crate::{ProtResult, TimeoutLayer};
+use crate::{ProtResult, Rate, TimeoutLayer};
 
-use super::{state::WsState, Control, WsCodec};
+use super::{state::WsState, Control, DeflateContext, WsCodec};
 
 pub struct ServerWsConnection<T> {
     codec: WsCodec<T>,
@@ -34,6 +34,28 @@ pub struct ServerWsConnection<T> {
 struct InnerConnection {
     state: WsState,
     control: Control,
+    /// 连接关闭时的关闭码及原因
+    close_frame: Option<(CloseCode, String)>,
+    /// 接收消息的频率限制
+    msg_rate: Option<Rate>,
+    /// 当前周期的开始时间及已接收的消息数
+    msg_window: (Instant, u64),
+}
+
+impl InnerConnection {
+    /// 记录收到一条消息, 超出频率限制返回true
+    fn is_msg_over_limit(&mut self) -> bool {
+        let rate = match &self.msg_rate {
+            Some(rate) => rate,
+            None => return false,
+        };
+        let now = Instant::now();
+        if now >= self.msg_window.0 + rate.per {
+            self.msg_window = (now, 0);
+        }
+        self.msg_window.1 += 1;
+        self.msg_window.1 > rate.nums
+    }
 }
 
 unsafe impl<T> Sync for ServerWsConnection<T> {}
@@ -50,6 +72,9 @@ where
             inner: InnerConnection {
                 state: WsState::Open,
                 control: Control::new(),
+                close_frame: None,
+                msg_rate: None,
+                msg_window: (Instant::now(), 0),
             },
             timeout: None,
         }
@@ -97,6 +122,12 @@ where
         self.timeout = timeout_layer;
     }
 
+    /// 设置接收消息的频率限制, 超出后发送1008(Policy)并关闭连接
+    pub fn set_msg_rate(&mut self, msg_rate: Option<Rate>) {
+        self.inner.msg_rate = msg_rate;
+        self.inner.msg_window = (Instant::now(), 0);
+    }
+
     pub fn pull_accept(&mut self, _cx: &mut Context<'_>) -> Poll<Option<ProtResult<()>>> {
         Poll::Pending
     }
@@ -152,10 +183,31 @@ where
         self.inner.control.set_handshake_status(binary, false)
     }
 
+    /// 设置收到ping时是否自动回复相同内容的pong, 默认开启
+    pub fn set_auto_pong(&mut self, auto_pong: bool) {
+        self.inner.control.set_auto_pong(auto_pong)
+    }
+
+    /// 设置握手时协商的permessage-deflate上下文
+    pub fn set_deflate(&mut self, deflate: Option<DeflateContext>) {
+        self.codec.set_deflate(deflate)
+    }
+
+    /// 设置单条消息(合并分片后)的最大大小, 超出后发送1009(Size)并关闭连接
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.codec.set_max_message_size(max_message_size)
+    }
+
     pub fn send_owned_message(&mut self, msg: OwnedMessage) -> ProtResult<()> {
         self.inner.control.send_owned_message(msg)
     }
 
+    /// 连接结束后获取关闭码及原因, 收到Close帧则为对端的关闭码,
+    /// 异常断开则为1006(Abnormal)且原因为空, 超出消息频率限制则为1008(Policy)
+    pub fn close_frame(&self) -> Option<(CloseCode, String)> {
+        self.inner.close_frame.clone()
+    }
+
     pub fn receiver_close(&mut self, data: Option<CloseData>) -> ProtResult<()> {
         self.inner
             .state
@@ -182,9 +234,48 @@ where
                             return Poll::Pending;
                         }
                         Poll::Ready(Some(Ok(v))) => {
+                            if matches!(v, OwnedMessage::Text(_) | OwnedMessage::Binary(_))
+                                && self.inner.is_msg_over_limit()
+                            {
+                                log::warn!("websocket消息超出频率限制, 关闭连接");
+                                let data = CloseData::new(
+                                    CloseCode::Policy,
+                                    "message rate limit".to_string(),
+                                );
+                                self.inner.close_frame =
+                                    Some((data.status_code.clone(), data.reason.clone()));
+                                self.send_owned_message(OwnedMessage::Close(Some(data.clone())))?;
+                                // 先写入缓冲区, 保证关闭前关闭帧能发出
+                                let _ = self.poll_write(cx);
+                                return Poll::Ready(Some(Ok(OwnedMessage::Close(Some(data)))));
+                            }
+                            if let OwnedMessage::Close(data) = &v {
+                                if self.inner.close_frame.is_none() {
+                                    self.inner.close_frame = Some(match data {
+                                        Some(data) => {
+                                            (data.status_code.clone(), data.reason.clone())
+                                        }
+                                        None => (CloseCode::Status, String::new()),
+                                    });
+                                }
+                            }
                             return Poll::Ready(Some(Ok(v)));
                         }
+                        Poll::Ready(Some(Err(e))) if self.codec.is_message_too_big() => {
+                            log::warn!("websocket消息超出最大大小, 关闭连接");
+                            let data =
+                                CloseData::new(CloseCode::Size, "message too big".to_string());
+                            self.inner.close_frame =
+                                Some((data.status_code.clone(), data.reason.clone()));
+                            self.send_owned_message(OwnedMessage::Close(Some(data.clone())))?;
+                            let _ = self.poll_write(cx);
+                            self.inner.state.set_closing(data);
+                            return Poll::Ready(Some(Err(e)));
+                        }
                         Poll::Ready(_e) => {
+                            if self.inner.close_frame.is_none() {
+                                self.inner.close_frame = Some((CloseCode::Abnormal, String::new()));
+                            }
                             let close = OwnedMessage::Close(Some(CloseData::new(
                                 CloseCode::Abnormal,
                                 "network".to_string(),
@@ -11,8 +11,10 @@
 // Created Date: 2023/09/14 09:42:25
 
 mod state_handshake;
+mod state_ping_pong;
   
 pub use state_handshake::{WsStateHandshake};
+pub use state_ping_pong::WsStatePingPong;
 use webparse::ws::CloseData;
 
 
@@ -0,0 +1,73 @@
+// Copyright 2022 - 2024 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 03:21:54
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use webparse::ws::OwnedMessage;
+
+use crate::{ws::WsCodec, ProtResult};
+
+/// 收到ping时自动回复相同内容的pong
+#[derive(Debug)]
+pub struct WsStatePingPong {
+    /// 是否自动回复, 关闭后由应用自行处理
+    auto_pong: bool,
+    /// 待回复的ping内容, 只保留最新的一个, 防止大量的ping占用内存
+    pending: Option<Vec<u8>>,
+}
+
+impl WsStatePingPong {
+    pub fn new() -> Self {
+        Self {
+            auto_pong: true,
+            pending: None,
+        }
+    }
+
+    pub fn set_auto_pong(&mut self, auto_pong: bool) {
+        self.auto_pong = auto_pong;
+        if !auto_pong {
+            self.pending = None;
+        }
+    }
+
+    pub fn is_auto_pong(&self) -> bool {
+        self.auto_pong
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// 收到ping, 覆盖之前还未回复的ping
+    pub fn receive(&mut self, payload: &[u8]) {
+        if self.auto_pong {
+            if self.pending.is_some() {
+                log::trace!("websocket未回复的ping被新的ping覆盖");
+            }
+            self.pending = Some(payload.to_vec());
+        }
+    }
+
+    /// 发送缓冲区已写完时将待回复的pong写入, 写入受阻期间收到的ping只回复最新的一个
+    pub fn poll_handle<T>(&mut self, codec: &mut WsCodec<T>, is_client: bool) -> ProtResult<()>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        if !codec.is_write_end() {
+            return Ok(());
+        }
+        if let Some(payload) = self.pending.take() {
+            codec.send_msg(OwnedMessage::Pong(payload), is_client)?;
+        }
+        Ok(())
+    }
+}
@@ -39,9 +39,10 @@ pub trait WsTrait: Send {
     /// 服务内部出现了错误代码
     async fn on_error(&mut self, _err: ProtError) {}
 
-    /// 收到来在远端的ping消息, 默认返回pong消息
-    async fn on_ping(&mut self, val: Vec<u8>) -> ProtResult<Option<OwnedMessage>> {
-        return Ok(Some(OwnedMessage::Pong(val)));
+    /// 收到来在远端的ping消息, 连接默认已自动回复pong,
+    /// 关闭自动回复(WsOption::set_auto_pong)后可在此返回pong消息
+    async fn on_ping(&mut self, _val: Vec<u8>) -> ProtResult<Option<OwnedMessage>> {
+        Ok(None)
     }
 
     /// 收到来在远端的pong消息, 默认不做任何处理, 可自定义处理如ttl等
@@ -0,0 +1,93 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 14:08:26
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let body = if req.path() == "/sized" {
+                Body::from_async_read(&b"hello world"[..], Some(11))
+            } else {
+                // 数据分多次到达, 大小未知
+                let (mut writer, reader) = tokio::io::duplex(64);
+                tokio::spawn(async move {
+                    let _ = writer.write_all(b"hello ").await;
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    let _ = writer.write_all(b"world").await;
+                });
+                Body::from_async_read(reader, None)
+            };
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(body)
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    async fn fetch(addr: SocketAddr, path: &str) -> ProtResult<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        let req = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+        stream.write_all(req.as_bytes()).await?;
+        let mut text = String::new();
+        let mut buf = vec![0u8; 1024];
+        // 读取到包体结束标识为止
+        while !text.ends_with("hello world") && !text.ends_with("0\r\n\r\n") {
+            let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+                .await
+                .expect("response not finished")?;
+            if n == 0 {
+                break;
+            }
+            text += &String::from_utf8_lossy(&buf[..n]);
+        }
+        Ok(text)
+    }
+
+    #[tokio::test]
+    async fn test_async_read_body() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let text = fetch(addr, "/sized").await?;
+        assert!(text.to_ascii_lowercase().contains("content-length: 11\r\n"));
+        assert!(text.ends_with("\r\n\r\nhello world"));
+
+        let text = fetch(addr, "/stream").await?;
+        assert!(text.to_ascii_lowercase().contains("transfer-encoding: chunked\r\n"));
+        assert!(text.contains("hello "));
+        assert!(text.ends_with("0\r\n\r\n"));
+        Ok(())
+    }
+}
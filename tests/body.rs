@@ -0,0 +1,622 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/07 16:42:10
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use algorithm::buf::{Binary, BinaryMut, Bt};
+    use flate2::{
+        read::GzDecoder,
+        write::{DeflateEncoder, GzEncoder},
+        Compression,
+    };
+    use webparse::{HeaderMap, Request};
+    use wmhttp::{send_line, Body, Consts, DigestAlgorithm, HttpHelper, ProtError};
+
+    #[tokio::test]
+    async fn test_retry_clone() {
+        let req = Request::builder()
+            .method("POST")
+            .url("http://127.0.0.1/post")
+            .header("content-type", "text/plain")
+            .body(Body::from("retry body"))
+            .unwrap();
+
+        // 第一次发送消耗了原请求的包体, 重试的请求包体保持完整
+        let mut retry = HttpHelper::try_clone_request(&req).unwrap();
+        let mut first = req;
+        let mut buf = BinaryMut::new();
+        first.body_mut().read_all(&mut buf).await;
+        assert_eq!(buf.chunk(), b"retry body");
+        assert!(first.body().try_clone().is_none());
+
+        let mut buf = BinaryMut::new();
+        retry.body_mut().read_all(&mut buf).await;
+        assert_eq!(buf.chunk(), b"retry body");
+        assert_eq!(retry.headers().get_str_value(&"content-type").unwrap(), "text/plain");
+
+        let (_sender, receiver) = tokio::sync::mpsc::channel(1);
+        let body = Body::new(receiver, BinaryMut::new(), false);
+        assert!(body.try_clone().is_none());
+    }
+
+    async fn gzip_chunks(chunks: Vec<(bool, Vec<u8>)>) -> Vec<u8> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(chunks.len());
+        for (is_end, data) in chunks {
+            sender.send((is_end, Binary::from(data))).await.unwrap();
+        }
+        let mut body = Body::new(receiver, BinaryMut::new(), false);
+        body.add_compress_method(Consts::COMPRESS_METHOD_GZIP);
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+
+        let mut result = vec![];
+        GzDecoder::new(buf.chunk())
+            .read_to_end(&mut result)
+            .expect("gzip footer missing");
+        result
+    }
+
+    #[tokio::test]
+    async fn test_gzip_end_on_chunk_boundary() {
+        let data: Vec<u8> = (0..8192u32).map(|i| (i % 251) as u8).collect();
+
+        // 最后一块数据与结束标志同时到达
+        let result = gzip_chunks(vec![
+            (false, data[..4096].to_vec()),
+            (true, data[4096..].to_vec()),
+        ])
+        .await;
+        assert_eq!(result, data);
+
+        // 结束标志单独以空数据到达
+        let result = gzip_chunks(vec![
+            (false, data[..4096].to_vec()),
+            (false, data[4096..].to_vec()),
+            (true, vec![]),
+        ])
+        .await;
+        assert_eq!(result, data);
+    }
+
+    #[tokio::test]
+    async fn test_gzip_end_poll_next() {
+        use std::{future::poll_fn, pin::Pin, task::Poll};
+        use tokio_stream::Stream;
+
+        let data: Vec<u8> = (0..8192u32).map(|i| (i % 251) as u8).collect();
+        let (sender, receiver) = tokio::sync::mpsc::channel(4);
+        let mut body = Body::new(receiver, BinaryMut::new(), false);
+        body.add_compress_method(Consts::COMPRESS_METHOD_GZIP);
+
+        // 直接轮询poll_next, 首块数据读取后无新数据时返回Pending
+        sender
+            .send((false, Binary::from(data[..4096].to_vec())))
+            .await
+            .unwrap();
+        let mut output = vec![];
+        poll_fn(|cx| {
+            while let Poll::Ready(Some(bin)) = Pin::new(&mut body).poll_next(cx) {
+                output.extend_from_slice(bin.unwrap().chunk());
+            }
+            Poll::Ready(())
+        })
+        .await;
+
+        // 最后一块与结束标志在同一次轮询中到达, 之后的轮询需返回gzip的结尾后再结束
+        sender
+            .send((true, Binary::from(data[4096..].to_vec())))
+            .await
+            .unwrap();
+        let mut is_finish = false;
+        poll_fn(|cx| loop {
+            match Pin::new(&mut body).poll_next(cx) {
+                Poll::Ready(Some(bin)) => output.extend_from_slice(bin.unwrap().chunk()),
+                Poll::Ready(None) => {
+                    is_finish = true;
+                    return Poll::Ready(());
+                }
+                Poll::Pending => return Poll::Ready(()),
+            }
+        })
+        .await;
+        assert!(is_finish);
+
+        let mut result = vec![];
+        GzDecoder::new(&output[..])
+            .read_to_end(&mut result)
+            .expect("gzip footer missing");
+        assert_eq!(result, data);
+    }
+
+    #[tokio::test]
+    async fn test_lenient_truncated_gzip() {
+        let text = "truncated gzip body ".repeat(100);
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let mut data = encoder.finish().unwrap();
+        // 去掉gzip结尾的CRC及长度
+        data.truncate(data.len() - 8);
+
+        let mut body = Body::new_binary(BinaryMut::from(data));
+        body.set_compress_origin_gzip();
+        body.set_lenient_decompress(true);
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        assert_eq!(buf.chunk(), text.as_bytes());
+    }
+
+    /// 截断的数据在宽松模式下返回已解压的部分, 为原始数据的前缀
+    async fn lenient_truncated(mut data: Vec<u8>, method: i8) -> Vec<u8> {
+        data.truncate(data.len() - 4);
+        let mut body = Body::new_binary(BinaryMut::from(data));
+        body.set_origin_compress_method(method);
+        body.set_lenient_decompress(true);
+        let mut buf = BinaryMut::new();
+        assert!(body.read_all(&mut buf).await.is_some());
+        buf.chunk().to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_lenient_truncated_deflate() {
+        let text = "truncated deflate body ".repeat(100);
+        let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let data = encoder.finish().unwrap();
+        let result = lenient_truncated(data, Consts::COMPRESS_METHOD_DEFLATE).await;
+        assert!(!result.is_empty());
+        assert!(text.as_bytes().starts_with(&result));
+    }
+
+    #[tokio::test]
+    async fn test_lenient_truncated_brotli() {
+        let text = "truncated brotli body ".repeat(100);
+        let mut encoder = brotli::CompressorWriter::new(vec![], 4096, 5, 22);
+        encoder.write_all(text.as_bytes()).unwrap();
+        let data = encoder.into_inner();
+        let result = lenient_truncated(data, Consts::COMPRESS_METHOD_BROTLI).await;
+        assert!(text.as_bytes().starts_with(&result));
+    }
+
+    #[tokio::test]
+    async fn test_read_into_buffer() {
+        let mut body = Body::new_text("read into buffer".to_string());
+        let mut buf = vec![0u8; body.size_hint().unwrap() as usize];
+        let size = body.read_into(&mut buf).await.unwrap();
+        assert_eq!(size, buf.len());
+        assert_eq!(&buf[..], b"read into buffer");
+
+        let (sender, mut body) = Body::channel(2);
+        assert!(body.size_hint().is_none());
+        tokio::spawn(async move {
+            let _ = sender.send((false, Binary::from(b"part1".to_vec()))).await;
+            let _ = sender.send((true, Binary::from(b"part2".to_vec()))).await;
+        });
+        let mut buf = vec![0u8; 32];
+        let size = body.read_into(&mut buf).await.unwrap();
+        assert_eq!(&buf[..size], b"part1part2");
+    }
+
+    async fn replay_body(limit: usize) -> Body {
+        let (sender, mut body) = Body::channel(4);
+        assert!(body.set_replay_limit(limit));
+        sender.send((false, Binary::from(b"replay ".to_vec()))).await.unwrap();
+        sender.send((true, Binary::from(b"body".to_vec()))).await.unwrap();
+        body
+    }
+
+    #[tokio::test]
+    async fn test_replay_under_limit() {
+        let mut body = replay_body(1024).await;
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        assert_eq!(buf.chunk(), b"replay body");
+
+        let mut clone = body.try_clone().unwrap();
+        let mut buf = BinaryMut::new();
+        clone.read_all(&mut buf).await;
+        assert_eq!(buf.chunk(), b"replay body");
+
+        assert!(body.rewind());
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        assert_eq!(buf.chunk(), b"replay body");
+        assert!(body.rewind());
+    }
+
+    #[tokio::test]
+    async fn test_replay_over_limit() {
+        let mut body = replay_body(8).await;
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        assert_eq!(buf.chunk(), b"replay body");
+        assert!(!body.is_replayable());
+        assert!(body.try_clone().is_none());
+        assert!(!body.rewind());
+    }
+
+    #[tokio::test]
+    async fn test_compress_to_buffer() {
+        let text = "compress to buffer ".repeat(512);
+        let mut body = Body::new_text(text.clone());
+        let compressed = body
+            .compress_to_buffer(Consts::COMPRESS_METHOD_GZIP)
+            .unwrap();
+        assert!(compressed.remaining() < text.len());
+
+        // 发送的数据与返回的压缩数据一致, 长度可直接作为Content-Length
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        assert_eq!(buf.remaining(), compressed.remaining());
+        assert_eq!(buf.chunk(), compressed.chunk());
+
+        let mut result = String::new();
+        GzDecoder::new(buf.chunk()).read_to_string(&mut result).unwrap();
+        assert_eq!(result, text);
+    }
+
+    #[tokio::test]
+    async fn test_compress_to_buffer_decoded() {
+        let text = "decoded body ".repeat(64);
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let data = encoder.finish().unwrap();
+
+        // 压缩的包体已解压到读取缓存中, 不能与原始数据混合
+        let mut body = Body::empty();
+        body.set_compress_origin_gzip();
+        body.set_end(true);
+        body.cache_buffer(&data);
+        assert!(body
+            .compress_to_buffer(Consts::COMPRESS_METHOD_GZIP)
+            .is_err());
+
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        assert_eq!(buf.chunk(), text.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_capture_raw_bytes() {
+        let text = "raw webhook payload ".repeat(64);
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let data = encoder.finish().unwrap();
+
+        let (sender, mut body) = Body::channel(4);
+        body.set_compress_origin_gzip();
+        assert!(body.set_capture_raw(true));
+        let (first, second) = data.split_at(data.len() / 2);
+        sender.send((false, Binary::from(first.to_vec()))).await.unwrap();
+        sender.send((true, Binary::from(second.to_vec()))).await.unwrap();
+
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        assert_eq!(buf.chunk(), text.as_bytes());
+        // 原始数据为解压前收到的字节, 可用于签名校验
+        assert_eq!(body.raw_bytes().unwrap().chunk(), &data[..]);
+        assert!(!body.set_capture_raw(false));
+
+        let mut body = Body::new_text("not captured".to_string());
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        assert!(body.raw_bytes().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verify_digest() {
+        let data = br#"{"order":42,"status":"paid"}"#;
+        let mut header = HeaderMap::new();
+        header.insert("Content-MD5", "pZl00RrktPMYMmtR7wKO2w==");
+        let (sender, mut body) = Body::channel(4);
+        assert!(body.verify_digest_header(&header));
+        let (first, second) = data.split_at(10);
+        sender.send((false, Binary::from(first.to_vec()))).await.unwrap();
+        sender.send((true, Binary::from(second.to_vec()))).await.unwrap();
+        let mut buf = BinaryMut::new();
+        assert_eq!(body.read_all(&mut buf).await, Some(data.len()));
+        assert_eq!(buf.chunk(), &data[..]);
+
+        // 传输中被篡改, 结束时返回错误
+        let sha256 = "0277f383431939850de4b4c0afb0fec0138222940f6a1211fba099b14954214d";
+        let (sender, mut body) = Body::channel(4);
+        assert!(body.verify_digest(DigestAlgorithm::Sha256, sha256));
+        let mut corrupted = data.to_vec();
+        corrupted[10] ^= 1;
+        sender.send((true, Binary::from(corrupted))).await.unwrap();
+        let mut buf = BinaryMut::new();
+        assert!(body.read_all(&mut buf).await.is_none());
+
+        // 以流的方式读取时, 数据之后返回一次错误并结束
+        let (sender, mut body) = Body::channel(4);
+        assert!(body.verify_digest(DigestAlgorithm::Sha256, sha256));
+        sender.send((true, Binary::from(corrupted.clone()))).await.unwrap();
+        {
+            use tokio_stream::StreamExt;
+            let bin = body.next().await.unwrap().unwrap();
+            assert_eq!(bin.chunk(), &corrupted[..]);
+            let error = body.next().await.unwrap().unwrap_err();
+            assert!(format!("{:?}", error).contains("digest"));
+            assert!(body.next().await.is_none());
+            assert!(body.next().await.is_none());
+        }
+
+        let (_sender, mut body) = Body::channel(1);
+        assert!(!body.verify_digest(DigestAlgorithm::Md5, "not a digest"));
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_lines() {
+        use tokio_stream::StreamExt;
+        let values: Vec<serde_json::Value> = (0..5)
+            .map(|i| serde_json::json!({"id": i, "text": format!("event {}", i)}))
+            .collect();
+
+        let (sender, body) = Body::channel(2);
+        let send_values = values.clone();
+        tokio::spawn(async move {
+            for value in &send_values[..4] {
+                let line = serde_json::to_vec(value).unwrap();
+                send_line(&sender, &line).await.unwrap();
+            }
+            // 最后一行没有换行符
+            let last = serde_json::to_vec(&send_values[4]).unwrap();
+            let _ = sender.send((true, Binary::from(last))).await;
+        });
+
+        let mut lines = Box::pin(body.lines());
+        let mut result = vec![];
+        while let Some(line) = lines.next().await {
+            let line = line.unwrap();
+            result.push(serde_json::from_slice::<serde_json::Value>(line.chunk()).unwrap());
+        }
+        assert_eq!(result, values);
+
+        // 单个数据块中含多行, 且行被拆分到多个数据块中
+        let (sender, body) = Body::channel(4);
+        sender.send((false, Binary::from(b"a\r\nbb\nc".to_vec()))).await.unwrap();
+        sender.send((false, Binary::from(b"cc\n".to_vec()))).await.unwrap();
+        sender.send((true, Binary::new())).await.unwrap();
+        let mut lines = Box::pin(body.lines());
+        let mut result = vec![];
+        while let Some(line) = lines.next().await {
+            result.push(line.unwrap().chunk().to_vec());
+        }
+        assert_eq!(result, vec![b"a".to_vec(), b"bb".to_vec(), b"ccc".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn test_line_too_long() {
+        use tokio_stream::StreamExt;
+        // 限制内的行正常返回, 之后没有换行符的数据超过限制时返回错误并结束
+        let (sender, body) = Body::channel(4);
+        sender.send((false, Binary::from(b"short\n".to_vec()))).await.unwrap();
+        sender.send((false, Binary::from(vec![b'x'; 10]))).await.unwrap();
+        sender.send((false, Binary::from(vec![b'x'; 10]))).await.unwrap();
+        let mut lines = Box::pin(body.lines_with_limit(16));
+        assert_eq!(lines.next().await.unwrap().unwrap().chunk(), b"short");
+        let err = lines.next().await.unwrap().unwrap_err();
+        assert!(format!("{}", err).contains("line too long"));
+        assert!(lines.next().await.is_none());
+        drop(sender);
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_encoding() {
+        let text = "reconfigure encoding ".repeat(200);
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let gzip = encoder.finish().unwrap();
+
+        async fn read(gzip: &[u8], method: i8) -> Vec<u8> {
+            let mut body = Body::new_binary(BinaryMut::from(gzip.to_vec()));
+            body.set_compress_origin_gzip();
+            body.reconfigure_encoding(method).unwrap();
+            // 只可指定一次
+            assert!(body.reconfigure_encoding(method).is_err());
+            let mut buf = BinaryMut::new();
+            body.read_all(&mut buf).await;
+            buf.chunk().to_vec()
+        }
+
+        // 编码相同, 原样透传
+        let data = read(&gzip, Consts::COMPRESS_METHOD_GZIP).await;
+        assert_eq!(data, gzip);
+
+        // 解压后以brotli重新压缩
+        let data = read(&gzip, Consts::COMPRESS_METHOD_BROTLI).await;
+        let mut result = vec![];
+        brotli::Decompressor::new(&data[..], 4096)
+            .read_to_end(&mut result)
+            .unwrap();
+        assert_eq!(result, text.as_bytes());
+
+        // 解压为原始数据
+        let data = read(&gzip, Consts::COMPRESS_METHOD_NONE).await;
+        assert_eq!(data, text.as_bytes());
+
+        // 开始读取后不可再修改
+        let mut body = Body::new_text(text.clone());
+        let _ = body.read_now();
+        assert!(body
+            .reconfigure_encoding(Consts::COMPRESS_METHOD_GZIP)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_zstd_stream() {
+        let data: Vec<u8> = (0..8192u32).map(|i| (i % 251) as u8).collect();
+        let (sender, receiver) = tokio::sync::mpsc::channel(2);
+        sender.send((false, Binary::from(data[..4096].to_vec()))).await.unwrap();
+        sender.send((true, Binary::from(data[4096..].to_vec()))).await.unwrap();
+        let mut body = Body::new(receiver, BinaryMut::new(), false);
+        body.add_compress_method(Consts::COMPRESS_METHOD_ZSTD);
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        let compressed = buf.chunk().to_vec();
+        assert_eq!(zstd::decode_all(&compressed[..]).unwrap(), data);
+
+        // 分块传输时以结束块结尾
+        let (sender, receiver) = tokio::sync::mpsc::channel(1);
+        sender.send((true, Binary::from(data.clone()))).await.unwrap();
+        let mut body = Body::new(receiver, BinaryMut::new(), false);
+        body.set_chunked(true);
+        body.add_compress_method(Consts::COMPRESS_METHOD_ZSTD);
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        assert!(buf.chunk().ends_with(b"0\r\n\r\n"));
+
+        // 接收到的zstd数据解压
+        let mut body = Body::new_binary(BinaryMut::from(compressed));
+        body.set_compress_origin_zstd();
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        assert_eq!(buf.chunk(), &data[..]);
+    }
+
+    #[tokio::test]
+    async fn test_compress_level() {
+        let text = (0..4000)
+            .map(|i| format!("{{\"id\":{},\"name\":\"item{}\"}}", i, i % 97))
+            .collect::<String>();
+
+        async fn compress(text: &str, method: i8, f: impl FnOnce(&mut Body)) -> Vec<u8> {
+            let mut body = Body::new_text(text.to_string());
+            f(&mut body);
+            body.add_compress_method(method);
+            let mut buf = BinaryMut::new();
+            body.read_all(&mut buf).await;
+            buf.chunk().to_vec()
+        }
+
+        let gzip = Consts::COMPRESS_METHOD_GZIP;
+        let fast = compress(&text, gzip, |b| b.set_compress_level(1)).await;
+        let best = compress(&text, gzip, |b| b.set_compress_level(9)).await;
+        assert!(best.len() < fast.len());
+        let mut result = vec![];
+        GzDecoder::new(&best[..]).read_to_end(&mut result).unwrap();
+        assert_eq!(result, text.as_bytes());
+        // 超出范围按最大等级处理
+        let clamp = compress(&text, gzip, |b| b.set_compress_level(100)).await;
+        assert_eq!(clamp, best);
+
+        // brotli的质量同样受压缩等级影响, 也可单独设置
+        let brotli = Consts::COMPRESS_METHOD_BROTLI;
+        let fast = compress(&text, brotli, |b| b.set_compress_level(0)).await;
+        let best = compress(&text, brotli, |b| b.set_brotli_params(11, 30)).await;
+        assert!(best.len() < fast.len());
+        let mut result = vec![];
+        brotli::Decompressor::new(&best[..], 4096)
+            .read_to_end(&mut result)
+            .unwrap();
+        assert_eq!(result, text.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_decompress_bomb() {
+        use tokio_stream::StreamExt;
+        // 约1K的压缩数据解压后为16M
+        let mut encoder = GzEncoder::new(vec![], Compression::best());
+        encoder.write_all(&vec![0u8; 16 * 1024 * 1024]).unwrap();
+        let data = encoder.finish().unwrap();
+
+        let (sender, receiver) = tokio::sync::mpsc::channel(1);
+        sender.send((true, Binary::from(data))).await.unwrap();
+        let mut body = Body::new(receiver, BinaryMut::new(), false);
+        body.set_compress_origin_gzip();
+        body.set_max_decompressed_size(64 * 1024);
+
+        let mut size = 0;
+        let mut error = false;
+        while let Some(bin) = body.next().await {
+            match bin {
+                Ok(bin) => size += bin.remaining(),
+                Err(_) => {
+                    error = true;
+                    break;
+                }
+            }
+        }
+        assert!(error);
+        assert!(size <= 64 * 1024);
+        // 错误只返回一次, 之后数据结束
+        assert!(body.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_stream() {
+        use tokio_stream::StreamExt;
+        // 同步刷新后的数据可立即解压, 之后为无效的压缩块
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(b"hello ").unwrap();
+        encoder.flush().unwrap();
+        let head = encoder.get_ref().clone();
+
+        let (sender, receiver) = tokio::sync::mpsc::channel(4);
+        sender.send((false, Binary::from(head))).await.unwrap();
+        sender.send((false, Binary::from(vec![0xffu8; 16]))).await.unwrap();
+        sender.send((true, Binary::from(b"world".to_vec()))).await.unwrap();
+        let mut body = Body::new(receiver, BinaryMut::new(), false);
+        body.set_compress_origin_gzip();
+
+        // 出错前已解压的数据正常返回, 之后返回一次错误并结束
+        let mut data = vec![];
+        let error = loop {
+            match body.next().await {
+                Some(Ok(bin)) => data.extend_from_slice(bin.chunk()),
+                Some(Err(e)) => break e,
+                None => panic!("decode error not returned"),
+            }
+        };
+        assert_eq!(data, b"hello ");
+        assert!(matches!(error, ProtError::Decompress("gzip", _)));
+        assert_eq!(error.compress_method(), Some("gzip"));
+        assert!(body.next().await.is_none());
+        assert!(body.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_from_stream() {
+        use tokio_stream::StreamExt;
+        let chunks: Vec<std::io::Result<Vec<u8>>> =
+            vec![Ok(b"hello ".to_vec()), Ok(b"world".to_vec())];
+        let mut body = Body::from_stream(tokio_stream::iter(chunks));
+        let mut buf = BinaryMut::new();
+        body.read_all(&mut buf).await;
+        assert_eq!(buf.chunk(), b"hello world");
+
+        // 数据流的错误传递到包体的读取中, 而不是当作正常结束
+        let chunks: Vec<std::io::Result<Vec<u8>>> = vec![
+            Ok(b"hello".to_vec()),
+            Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "upstream reset")),
+        ];
+        let mut body = Body::from_stream(tokio_stream::iter(chunks));
+        let mut data = vec![];
+        let mut error = None;
+        while let Some(bin) = body.next().await {
+            match bin {
+                Ok(bin) => data.extend_from_slice(bin.chunk()),
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        assert_eq!(data, b"hello");
+        assert!(error.is_some());
+        assert!(body.next().await.is_none());
+        assert!(body.next().await.is_none());
+    }
+}
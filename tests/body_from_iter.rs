@@ -0,0 +1,106 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 21:37:05
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use algorithm::buf::{Binary, BinaryMut, Bt};
+    use tokio::io::AsyncWriteExt;
+    use tokio_stream::StreamExt;
+    use webparse::{
+        http::http2::frame::{Flag, Frame, FrameHeader, Headers, Kind, StreamIdentifier},
+        HeaderMap, Method, Response,
+    };
+    use wmhttp::{
+        http2::{Builder, Codec},
+        Body, ProtResult, ServerH2Connection,
+    };
+
+    fn headers(id: u32) -> Frame<Binary> {
+        let id = StreamIdentifier::from(id);
+        let mut flag = Flag::end_headers();
+        flag.set(Flag::end_stream(), true);
+        let header = FrameHeader::new(Kind::Headers, flag, id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "GET");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Get);
+        Frame::Headers(headers)
+    }
+
+    fn chunks() -> Vec<Vec<u8>> {
+        vec![b"one".to_vec(), b"two".to_vec(), b"three".to_vec()]
+    }
+
+    #[tokio::test]
+    async fn test_from_iter_read_all() {
+        let mut body = Body::from_iter(chunks());
+        let mut buffer = BinaryMut::new();
+        body.read_all(&mut buffer).await;
+        assert_eq!(buffer.chunk(), b"onetwothree");
+
+        let mut body: Body = Vec::<Vec<u8>>::new().into_iter().collect();
+        let mut buffer = BinaryMut::new();
+        body.read_all(&mut buffer).await;
+        assert_eq!(buffer.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_from_iter_h2_frames() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let mut server = ServerH2Connection::new(server_io, Builder::new());
+
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client.get_mut().write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        client.send_frame(headers(1))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+
+        let mut req = server.incoming().await?.unwrap();
+        let id = req.extensions_mut().remove::<StreamIdentifier>().unwrap();
+        let res = Response::builder().body(Body::from_iter(chunks())).unwrap();
+        server.send_response(res, id).await?;
+        tokio::spawn(async move {
+            while let Ok(Some(_)) = server.incoming().await {}
+        });
+
+        // 每个数据块单独作为一个DATA帧, 仅最后一帧结束流
+        let mut datas = vec![];
+        loop {
+            if let Frame::Data(d) = client.next().await.unwrap()? {
+                let is_end = d.is_end_stream();
+                datas.push((d.payload().chunk().to_vec(), is_end));
+                if is_end {
+                    break;
+                }
+            }
+        }
+        assert_eq!(
+            datas,
+            vec![
+                (b"one".to_vec(), false),
+                (b"two".to_vec(), false),
+                (b"three".to_vec(), true),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/08 10:12:26
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use webparse::{Request, Response};
+
+    use wmhttp::{Body, CacheMiddleware, Middleware, ProtResult, RecvRequest, RecvResponse};
+
+    fn build_request(encoding: &str) -> RecvRequest {
+        Request::builder()
+            .method("GET")
+            .url("http://127.0.0.1/index")
+            .header("Accept-Encoding", encoding)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn build_response(cache_control: &str, text: &str) -> RecvResponse {
+        Response::builder()
+            .status(200)
+            .header("Cache-Control", cache_control)
+            .header("Vary", "Accept-Encoding")
+            .header("Content-Length", text.len())
+            .body(Body::new_text(text.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit_and_vary() -> ProtResult<()> {
+        let mut cache = CacheMiddleware::new(1024);
+
+        let mut req = build_request("gzip");
+        assert!(cache.process_request(&mut req).await?.is_none());
+        let mut res = build_response("max-age=60", "gzip body");
+        cache.process_response(&mut res).await?;
+        assert_eq!(cache.len(), 1);
+
+        // 相同的Vary值命中缓存
+        let mut other = cache.clone();
+        let mut req = build_request("gzip");
+        let mut hit = other.process_request(&mut req).await?.expect("cache hit");
+        let mut result = BinaryMut::new();
+        hit.body_mut().read_all(&mut result).await;
+        assert_eq!(result.chunk(), b"gzip body");
+
+        // 不同的Vary值不命中
+        let mut req = build_request("br");
+        assert!(other.process_request(&mut req).await?.is_none());
+        let mut res = build_response("max-age=60", "br body");
+        other.process_response(&mut res).await?;
+        assert_eq!(cache.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cache_expire_and_no_store() -> ProtResult<()> {
+        let mut cache = CacheMiddleware::new(1024);
+
+        let mut req = build_request("gzip");
+        assert!(cache.process_request(&mut req).await?.is_none());
+        let mut res = build_response("no-store", "body");
+        cache.process_response(&mut res).await?;
+        assert_eq!(cache.len(), 0);
+
+        let mut req = build_request("gzip");
+        assert!(cache.process_request(&mut req).await?.is_none());
+        let mut res = build_response("max-age=1", "body");
+        cache.process_response(&mut res).await?;
+        assert_eq!(cache.len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let mut req = build_request("gzip");
+        assert!(cache.process_request(&mut req).await?.is_none());
+        Ok(())
+    }
+}
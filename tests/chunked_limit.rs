@@ -0,0 +1,86 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/05 09:12:44
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::empty())
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(
+            || Box::new(Operate),
+            |server| {
+                server.set_max_chunk_size(1024);
+                server.set_max_body_size(10 * 1024 * 1024);
+            },
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_huge_chunk_size() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(
+                b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\nffffffff\r\n",
+            )
+            .await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        assert!(buf[..n].starts_with(b"HTTP/1.1 413"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_declared_body_too_large() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 仅发送请求头, 声明1GB的包体
+        stream
+            .write_all(
+                b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 1073741824\r\n\r\n",
+            )
+            .await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        assert!(buf[..n].starts_with(b"HTTP/1.1 413"));
+        Ok(())
+    }
+}
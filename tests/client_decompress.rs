@@ -0,0 +1,77 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/14 10:12:36
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, net::SocketAddr};
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use flate2::read::GzDecoder;
+    use webparse::{Request, Response};
+
+    use wmhttp::{self, Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text("a".repeat(4096)))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    #[tokio::test]
+    async fn test_client_no_auto_decompress() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let url = format!("http://{}/", addr);
+        let client = Client::builder()
+            .http2(false)
+            .auto_decompress(false)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .header("Accept-Encoding", "gzip")
+            .body(Body::empty())
+            .unwrap();
+        let mut res = client.send_now(req).await?;
+        assert_eq!(
+            res.headers().get_str_value(&"Content-Encoding").as_deref(),
+            Some("gzip")
+        );
+        let mut buffer = BinaryMut::new();
+        res.body_mut().read_all(&mut buffer).await;
+        // 包体仍为gzip压缩数据
+        assert!(buffer.chunk().starts_with(&[0x1f, 0x8b]));
+        let mut text = String::new();
+        GzDecoder::new(buffer.chunk()).read_to_string(&mut text)?;
+        assert_eq!(text, "a".repeat(4096));
+        Ok(())
+    }
+}
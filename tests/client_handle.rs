@@ -0,0 +1,96 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/21 09:52:14
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use webparse::{Request, Response};
+
+    use wmhttp::{self, Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse, Server};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(req.path().clone()))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server(conns: Arc<AtomicUsize>) -> ProtResult<SocketAddr> {
+        common::serve(
+            move |stream, addr| {
+                conns.fetch_add(1, Ordering::Relaxed);
+                Server::new(stream, Some(addr))
+            },
+            |server| server.set_callback_http(Box::new(Operate)),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_one_connection() -> ProtResult<()> {
+        let conns = Arc::new(AtomicUsize::new(0));
+        let addr = run_server(conns.clone()).await?;
+        let url = format!("http://{}", addr);
+        let handle = Client::builder()
+            .http2_only(true)
+            .url(&*url)?
+            .connect()
+            .await?
+            .into_handle()?;
+
+        let mut tasks = vec![];
+        for i in 0..50 {
+            let handle = handle.clone();
+            let url = format!("{}/req/{}", url, i);
+            tasks.push(tokio::spawn(async move {
+                let req = Request::builder()
+                    .method("GET")
+                    .url(&*url)
+                    .body(Body::empty())
+                    .unwrap();
+                let mut res = handle.send(req).await.unwrap();
+                let mut buffer = BinaryMut::new();
+                res.body_mut().read_all(&mut buffer).await;
+                (i, String::from_utf8_lossy(buffer.chunk()).to_string())
+            }));
+        }
+
+        // 每个调用方收到的都是自己请求的响应
+        for task in tasks {
+            let (i, text) = task.await.unwrap();
+            assert_eq!(text, format!("/req/{}", i));
+        }
+        assert_eq!(conns.load(Ordering::Relaxed), 1);
+        Ok(())
+    }
+}
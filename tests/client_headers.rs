@@ -0,0 +1,80 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/15 11:08:43
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use webparse::{Request, Response};
+
+    use wmhttp::{
+        self, Body, Client, ClientOption, HttpTrait, ProtResult, RecvRequest, RecvResponse,
+    };
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            // 返回请求的User-Agent及X-Default
+            let agent = req.headers().get_str_value(&"User-Agent").unwrap_or_default();
+            let extra = req.headers().get_str_value(&"X-Default").unwrap_or_default();
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(format!("{}|{}", agent, extra)))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    async fn fetch(http2_only: bool, agent: Option<&str>) -> ProtResult<String> {
+        let addr = run_server().await?;
+        let url = format!("http://{}/", addr);
+        let client = Client::builder()
+            .http2_only(http2_only)
+            .default_header("X-Default", "1")
+            .url(&*url)?
+            .connect()
+            .await?;
+        let mut builder = Request::builder().method("GET").url(&*url);
+        if let Some(agent) = agent {
+            builder = builder.header("User-Agent", agent);
+        }
+        let mut res = client.send_now(builder.body(Body::empty()).unwrap()).await?;
+        let mut buffer = BinaryMut::new();
+        res.body_mut().read_all(&mut buffer).await;
+        Ok(String::from_utf8_lossy(buffer.chunk()).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_default_user_agent() -> ProtResult<()> {
+        for http2_only in [false, true] {
+            let expect = format!("{}|1", ClientOption::DEFAULT_USER_AGENT);
+            assert_eq!(fetch(http2_only, None).await?, expect);
+            // 单个请求设置的优先
+            assert_eq!(fetch(http2_only, Some("custom/1.0")).await?, "custom/1.0|1");
+        }
+        Ok(())
+    }
+}
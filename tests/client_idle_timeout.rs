@@ -0,0 +1,93 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/20 14:32:08
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::mpsc::Receiver,
+    };
+    use webparse::Request;
+
+    use wmhttp::{Body, Client, ProtResult, RecvResponse};
+
+    /// 发送完指定数据后停止响应, 但不关闭连接
+    async fn run_stall_server(data: &'static [u8]) -> ProtResult<SocketAddr> {
+        let server = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        tokio::spawn(async move {
+            if let Ok((mut stream, _)) = server.accept().await {
+                let mut buf = vec![0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(data).await;
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+        });
+        Ok(addr)
+    }
+
+    async fn send(addr: SocketAddr) -> ProtResult<RecvResponse> {
+        let url = format!("http://{}/", addr);
+        let client = Client::builder()
+            .read_idle_timeout(Duration::from_millis(200))
+            .url(&*url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .body(Body::empty())
+            .unwrap();
+        client.send_now(req).await
+    }
+
+    #[tokio::test]
+    async fn test_read_idle_mid_body() -> ProtResult<()> {
+        let addr =
+            run_stall_server(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nhello").await?;
+        let mut res = send(addr).await?;
+        assert_eq!(res.status(), 200);
+
+        // 包体传输中途停止, 连接被及时断开而不是一直等待
+        let mut buffer = BinaryMut::new();
+        tokio::time::timeout(Duration::from_secs(5), res.body_mut().read_all(&mut buffer))
+            .await
+            .expect("stalled body not abandoned");
+        assert_eq!(buffer.chunk(), b"hello");
+
+        let mut recv = res
+            .extensions_mut()
+            .remove::<Receiver<ProtResult<RecvResponse>>>()
+            .unwrap();
+        let err = recv.recv().await.unwrap().err().unwrap();
+        assert!(err.is_read_timeout().0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_idle_in_headers() -> ProtResult<()> {
+        let addr = run_stall_server(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n").await?;
+        let err = tokio::time::timeout(Duration::from_secs(5), send(addr))
+            .await
+            .expect("stalled headers not abandoned")
+            .err()
+            .unwrap();
+        assert!(err.is_read_timeout().0);
+        Ok(())
+    }
+}
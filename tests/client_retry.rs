@@ -0,0 +1,183 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 04:40:27
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use algorithm::buf::{Binary, BinaryMut, Bt};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+    use webparse::Request;
+
+    use wmhttp::{self, Body, Client, ClientHandle, ProtResult};
+
+    const KIND_DATA: u8 = 0x0;
+    const KIND_HEADERS: u8 = 0x1;
+    const KIND_SETTINGS: u8 = 0x4;
+    const FLAG_END_STREAM: u8 = 0x1;
+
+    /// 服务端收到的每个流的包体, 按收到HEADERS的顺序
+    type Received = Arc<Mutex<Vec<(u32, Vec<u8>)>>>;
+
+    fn frame(kind: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut data = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        data.extend_from_slice(&[kind, flags]);
+        data.extend_from_slice(&stream_id.to_be_bytes());
+        data.extend_from_slice(payload);
+        data
+    }
+
+    async fn read_frame(stream: &mut TcpStream) -> ProtResult<(u8, u8, u32, Vec<u8>)> {
+        let mut head = [0u8; 9];
+        stream.read_exact(&mut head).await?;
+        let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+        let mut payload = vec![0u8; len];
+        stream.read_exact(&mut payload).await?;
+        let stream_id = u32::from_be_bytes([head[5] & 0x7F, head[6], head[7], head[8]]);
+        Ok((head[3], head[4], stream_id, payload))
+    }
+
+    /// 以REFUSED_STREAM拒绝每个连接的首个流, 之后的流回复收到的包体
+    async fn serve(mut stream: TcpStream, received: Received) -> ProtResult<()> {
+        let mut preface = [0u8; 24];
+        stream.read_exact(&mut preface).await?;
+        stream.write_all(&frame(KIND_SETTINGS, 0, 0, &[])).await?;
+        let mut bodies: HashMap<u32, Vec<u8>> = HashMap::new();
+        let mut refused = None;
+        loop {
+            let (kind, flags, id, payload) = read_frame(&mut stream).await?;
+            let is_end = flags & FLAG_END_STREAM != 0;
+            match kind {
+                KIND_SETTINGS if flags & 0x1 == 0 => {
+                    stream.write_all(&frame(KIND_SETTINGS, 0x1, 0, &[])).await?;
+                    continue;
+                }
+                KIND_HEADERS => {
+                    received.lock().unwrap().push((id, vec![]));
+                    if refused.is_none() {
+                        refused = Some(id);
+                        // RST_STREAM, 原因为REFUSED_STREAM(0x7)
+                        stream.write_all(&frame(0x3, 0, id, &[0, 0, 0, 7])).await?;
+                        continue;
+                    }
+                    bodies.insert(id, vec![]);
+                }
+                KIND_DATA if bodies.contains_key(&id) => {
+                    bodies.get_mut(&id).unwrap().extend_from_slice(&payload);
+                }
+                _ => continue,
+            }
+            if is_end && bodies.contains_key(&id) {
+                let body = bodies.remove(&id).unwrap();
+                for r in received.lock().unwrap().iter_mut() {
+                    if r.0 == id {
+                        r.1 = body.clone();
+                    }
+                }
+                // :status 200, 包体原样返回
+                stream
+                    .write_all(&frame(KIND_HEADERS, 0x4, id, &[0x88]))
+                    .await?;
+                stream
+                    .write_all(&frame(KIND_DATA, FLAG_END_STREAM, id, &body))
+                    .await?;
+            }
+        }
+    }
+
+    async fn run_server(received: Received) -> ProtResult<SocketAddr> {
+        let server = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        tokio::spawn(async move {
+            loop {
+                if let Ok((stream, _)) = server.accept().await {
+                    let received = received.clone();
+                    tokio::spawn(async move {
+                        let _ = serve(stream, received).await;
+                    });
+                }
+            }
+        });
+        Ok(addr)
+    }
+
+    async fn connect(addr: SocketAddr) -> ProtResult<ClientHandle> {
+        let url = format!("http://{}", addr);
+        Client::builder()
+            .http2_only(true)
+            .url(&*url)?
+            .connect()
+            .await?
+            .into_handle()
+    }
+
+    #[tokio::test]
+    async fn test_retry_resends_body() -> ProtResult<()> {
+        let received = Received::default();
+        let addr = run_server(received.clone()).await?;
+        let handle = connect(addr).await?;
+
+        let req = Request::builder()
+            .method("POST")
+            .url(&*format!("http://{}/upload", addr))
+            .body(Body::new_text("retry body".to_string()))
+            .unwrap();
+        let mut res = tokio::time::timeout(Duration::from_secs(5), handle.send_retry(req, 2))
+            .await
+            .expect("response not received")?;
+        assert_eq!(res.status(), 200);
+        let mut buffer = BinaryMut::new();
+        res.body_mut().read_all(&mut buffer).await;
+        assert_eq!(buffer.chunk(), b"retry body");
+
+        // 首个流被拒绝后, 以新的流重新发送完整的包体
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1], (3, b"retry body".to_vec()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_streaming_body_not_retried() -> ProtResult<()> {
+        let received = Received::default();
+        let addr = run_server(received.clone()).await?;
+        let handle = connect(addr).await?;
+
+        // 通道的包体无法复制, 被拒绝后直接返回错误
+        let (sender, body) = Body::channel(2);
+        sender
+            .send((true, Binary::from(b"stream body".to_vec())))
+            .await?;
+        let req = Request::builder()
+            .method("POST")
+            .url(&*format!("http://{}/upload", addr))
+            .body(body)
+            .unwrap();
+        let err = tokio::time::timeout(Duration::from_secs(5), handle.send_retry(req, 2))
+            .await
+            .expect("response not received")
+            .unwrap_err();
+        assert!(err.is_retryable());
+        assert_eq!(received.lock().unwrap().len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/21 16:45:20
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use async_trait::async_trait;
+    use webparse::{Request, Response};
+
+    use wmhttp::{self, Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse, Timings};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text("hello".to_string()))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    async fn fetch(http2_only: bool) -> ProtResult<Timings> {
+        let addr = run_server().await?;
+        let url = format!("http://{}/", addr);
+        let client = Client::builder()
+            .http2_only(http2_only)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .body(Body::empty())
+            .unwrap();
+        let res = client.send_now(req).await?;
+        assert_eq!(res.status(), 200);
+        Ok(*res.extensions().get::<Timings>().unwrap())
+    }
+
+    fn check(timings: &Timings) {
+        // 各阶段均已记录, 明文连接没有TLS阶段
+        let start = timings.start.unwrap();
+        let dns = timings.dns_done.unwrap();
+        let connect = timings.connect_done.unwrap();
+        let request = timings.request_start.unwrap();
+        let first_byte = timings.first_byte.unwrap();
+        let done = timings.response_done.unwrap();
+        assert!(timings.tls_done.is_none());
+        assert!(timings.tls().is_none());
+
+        assert!(start <= dns);
+        assert!(dns <= connect);
+        assert!(connect <= request);
+        assert!(request <= first_byte);
+        assert!(first_byte <= done);
+        assert!(timings.ttfb().unwrap() <= timings.total().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_http1_timings() -> ProtResult<()> {
+        check(&fetch(false).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_http2_timings() -> ProtResult<()> {
+        check(&fetch(true).await?);
+        Ok(())
+    }
+}
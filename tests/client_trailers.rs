@@ -0,0 +1,121 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/15 14:52:07
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use webparse::Request;
+
+    use wmhttp::{Body, Client, ProtResult, RecvResponse};
+
+    /// 返回带trailer头的chunked响应
+    async fn run_server(trailers: Vec<u8>) -> ProtResult<SocketAddr> {
+        let server = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        tokio::spawn(async move {
+            if let Ok((mut stream, _)) = server.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let mut data = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: grpc-status\r\n\r\n\
+                    5\r\nhello\r\n0\r\n"
+                    .to_vec();
+                data.extend_from_slice(&trailers);
+                let _ = stream.write_all(&data).await;
+                let _ = stream.read(&mut buf).await;
+            }
+        });
+        Ok(addr)
+    }
+
+    async fn send(addr: SocketAddr) -> ProtResult<(Option<usize>, RecvResponse)> {
+        let url = format!("http://{}/", addr);
+        let client = Client::builder()
+            .http2(false)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .body(Body::empty())
+            .unwrap();
+        let mut res = client.send_now(req).await?;
+        let mut buffer = BinaryMut::new();
+        let read = res.body_mut().read_all(&mut buffer);
+        let size = tokio::time::timeout(Duration::from_secs(5), read)
+            .await
+            .expect("body not finished");
+        Ok((size, res))
+    }
+
+    #[tokio::test]
+    async fn test_client_chunked_trailers() -> ProtResult<()> {
+        let addr = run_server(b"grpc-status: 0\r\ngrpc-message: ok\r\n\r\n".to_vec()).await?;
+        let url = format!("http://{}/", addr);
+        let client = Client::builder()
+            .http2(false)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .body(Body::empty())
+            .unwrap();
+        let mut res = client.send_now(req).await?;
+        let mut buffer = BinaryMut::new();
+        res.body_mut().read_all(&mut buffer).await;
+        assert_eq!(buffer.chunk(), b"hello");
+
+        // 包体读取完毕后可获取trailer头
+        let trailers = res.body().trailers().unwrap();
+        assert_eq!(trailers.get_str_value(&"grpc-status").as_deref(), Some("0"));
+        assert_eq!(trailers.get_str_value(&"grpc-message").as_deref(), Some("ok"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_repeated_trailers() -> ProtResult<()> {
+        let addr = run_server(b"x-tag: a\r\nx-tag: b\r\n\r\n".to_vec()).await?;
+        let (size, res) = send(addr).await?;
+        assert!(size.is_some());
+
+        // 重复的trailer头逐个保留
+        let trailers = res.body().trailers().unwrap();
+        let tags = trailers
+            .iter()
+            .filter(|(name, _)| name.to_string().eq_ignore_ascii_case("x-tag"))
+            .map(|(_, value)| value.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(tags, vec!["a", "b"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trailers_too_large() -> ProtResult<()> {
+        // 不结束的trailer头超出消息头的大小限制, 不再等待而是结束连接
+        let mut trailers = b"x-pad: ".to_vec();
+        trailers.extend(vec![b'a'; 200 * 1024]);
+        let addr = run_server(trailers).await?;
+        let (_, res) = send(addr).await?;
+        assert!(res.body().trailers().is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 04:42:18
+
+//! 集成测试共用的服务端
+
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+
+use tokio::net::{TcpListener, TcpStream};
+use wmhttp::{ws::WsTrait, HttpTrait, ProtResult, Server};
+
+/// 监听本地的随机端口, 每个连接由build创建服务, 再由configure设置回调等, 返回监听的地址
+pub async fn serve<B, C>(build: B, configure: C) -> ProtResult<SocketAddr>
+where
+    B: Fn(TcpStream, SocketAddr) -> Server<TcpStream> + Send + 'static,
+    C: Fn(&mut Server<TcpStream>) + Send + 'static,
+{
+    let server = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = server.local_addr()?;
+    tokio::spawn(async move {
+        loop {
+            if let Ok((stream, addr)) = server.accept().await {
+                let mut server = build(stream, addr);
+                configure(&mut server);
+                tokio::spawn(async move {
+                    let _ = server.incoming().await;
+                });
+            }
+        }
+    });
+    Ok(addr)
+}
+
+/// 以`Server::new`创建服务, handler为每个连接创建HTTP的处理回调
+pub async fn run_server<H, C>(handler: H, configure: C) -> ProtResult<SocketAddr>
+where
+    H: Fn() -> Box<dyn HttpTrait> + Send + 'static,
+    C: Fn(&mut Server<TcpStream>) + Send + 'static,
+{
+    serve(
+        |stream, addr| Server::new(stream, Some(addr)),
+        move |server| {
+            configure(server);
+            server.set_callback_http(handler());
+        },
+    )
+    .await
+}
+
+/// 以`Server::new`创建服务, handler为每个连接创建WebSocket的处理回调
+pub async fn run_ws_server<H, C>(handler: H, configure: C) -> ProtResult<SocketAddr>
+where
+    H: Fn() -> Box<dyn WsTrait> + Send + 'static,
+    C: Fn(&mut Server<TcpStream>) + Send + 'static,
+{
+    serve(
+        |stream, addr| Server::new(stream, Some(addr)),
+        move |server| {
+            configure(server);
+            server.set_callback_ws(handler());
+        },
+    )
+    .await
+}
@@ -0,0 +1,110 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 15:50:12
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use webparse::{Request, Response};
+
+    use wmhttp::{
+        self, Body, Client, ContentRange, HttpHelper, HttpTrait, ProtResult, RecvRequest,
+        RecvResponse,
+    };
+
+    use crate::common;
+
+    const DATA: &str = "hello world";
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let range = req.headers().get_str_value(&"Range").unwrap_or_default();
+            let start = range
+                .trim_start_matches("bytes=")
+                .trim_end_matches('-')
+                .parse::<usize>()
+                .unwrap_or(0);
+            let response = Response::builder()
+                .version(req.version().clone())
+                .status(206)
+                .header(
+                    "Content-Range",
+                    format!("bytes {}-{}/{}", start, DATA.len() - 1, DATA.len()),
+                )
+                .body(Body::new_text(DATA[start..].to_string()))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    #[tokio::test]
+    async fn test_partial_content() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let url = format!("http://{}/file", addr);
+        let client = Client::builder().url(&*url)?.connect().await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .header("Range", "bytes=6-")
+            .body(Body::empty())
+            .unwrap();
+        let mut res = client.send_now(req).await?;
+        assert_eq!(res.status(), 206);
+        let range = HttpHelper::content_range(&res).unwrap();
+        assert_eq!(range.range, Some((6, 10)));
+        assert_eq!(range.total, Some(11));
+        assert_eq!(range.size(), Some(5));
+        assert!(range.is_last());
+
+        let mut buffer = BinaryMut::new();
+        res.body_mut().read_all(&mut buffer).await;
+        assert_eq!(buffer.chunk(), b"world");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_content_range() {
+        let range = ContentRange::parse("bytes 0-499/1234").unwrap();
+        assert_eq!(range.range, Some((0, 499)));
+        assert_eq!(range.total, Some(1234));
+        assert!(!range.is_last());
+
+        // 总大小未知
+        let range = ContentRange::parse("bytes 500-999/*").unwrap();
+        assert_eq!(range.range, Some((500, 999)));
+        assert_eq!(range.total, None);
+
+        // 范围无法满足
+        let range = ContentRange::parse("bytes */1234").unwrap();
+        assert_eq!(range.range, None);
+        assert_eq!(range.total, Some(1234));
+        assert_eq!(range.size(), None);
+
+        assert!(ContentRange::parse("bytes */*").is_none());
+        assert!(ContentRange::parse("bytes 10-5/100").is_none());
+        assert!(ContentRange::parse("bytes 0-100/100").is_none());
+        assert!(ContentRange::parse("items 0-1/2").is_none());
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 17:06:12
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{
+        self, Body, ContentTypeMiddleware, HttpTrait, ProtResult, RecvRequest, RecvResponse,
+    };
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            // 返回规范化后的Content-Type
+            let content_type = req
+                .headers()
+                .get_str_value(&"Content-Type")
+                .unwrap_or_default();
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(content_type))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(
+            || Box::new(Operate),
+            |server| {
+                server.middle(
+                    ContentTypeMiddleware::new()
+                        .allow("application/json")
+                        .allow("text/*"),
+                );
+            },
+        )
+        .await
+    }
+
+    async fn request(addr: SocketAddr, data: &[u8]) -> ProtResult<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(data).await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        Ok(String::from_utf8_lossy(&buf[..n]).to_string())
+    }
+
+    #[test]
+    fn test_content_type_allow() {
+        let middle = ContentTypeMiddleware::new()
+            .allow("application/json")
+            .allow("text/*");
+        assert!(middle.is_allow("Application/JSON; charset=utf-8"));
+        assert!(middle.is_allow("text/plain"));
+        assert!(!middle.is_allow("application/xml"));
+        assert!(!middle.is_allow("textual/plain"));
+    }
+
+    #[tokio::test]
+    async fn test_content_type_middleware() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let text = request(
+            addr,
+            b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: Application/JSON; charset=utf-8\r\nContent-Length: 2\r\n\r\n{}",
+        )
+        .await?;
+        assert!(text.starts_with("HTTP/1.1 200"));
+        assert!(text.ends_with("application/json; charset=utf-8"));
+
+        // 不在允许列表内或缺少类型均返回415
+        let text = request(
+            addr,
+            b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/xml\r\nContent-Length: 2\r\n\r\n<a",
+        )
+        .await?;
+        assert!(text.starts_with("HTTP/1.1 415"));
+        let text = request(
+            addr,
+            b"PUT / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\n{}",
+        )
+        .await?;
+        assert!(text.starts_with("HTTP/1.1 415"));
+
+        // 无包体的方法不做校验
+        let text = request(addr, b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await?;
+        assert!(text.starts_with("HTTP/1.1 200"));
+        Ok(())
+    }
+}
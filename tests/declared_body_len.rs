@@ -0,0 +1,77 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 11:20:43
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpHelper, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            // 处理前即可获取, 不读取包体
+            let len = HttpHelper::declared_body_len(&req);
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(format!("len:{:?}", len)))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    async fn send(addr: SocketAddr, data: &[u8]) -> ProtResult<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(data).await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        Ok(String::from_utf8_lossy(&buf[..n]).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_declared_body_len() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let text = send(
+            addr,
+            b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello",
+        )
+        .await?;
+        assert!(text.ends_with("len:Some(5)"));
+
+        let text = send(
+            addr,
+            b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+        )
+        .await?;
+        assert!(text.ends_with("len:None"));
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/19 10:15:46
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, sync::Arc, time::Duration};
+
+    use algorithm::buf::Binary;
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::Notify,
+    };
+    use webparse::{
+        http::http2::frame::{
+            Flag, Frame, FrameHeader, Headers, Kind, Reason, Reset, StreamIdentifier,
+        },
+        HeaderMap, Method, Response,
+    };
+
+    use wmhttp::{
+        self,
+        http2::{Builder, Codec},
+        Body, Disconnected, HttpTrait, ProtError, ProtResult, RecvRequest, RecvResponse,
+        ServerH2Connection,
+    };
+
+    use crate::common;
+
+    struct Operate {
+        notify: Arc<Notify>,
+    }
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let disconnected = req.extensions().get::<Disconnected>().unwrap().clone();
+            let notify = self.notify.clone();
+            let (sender, body) = Body::channel(1);
+            // 持续产生数据, 直到客户端断开
+            tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        _ = disconnected.disconnected() => {
+                            notify.notify_one();
+                            return;
+                        }
+                        _ = tokio::time::sleep(Duration::from_millis(10)) => {
+                            let data = Binary::from(b"data: tick\n\n".to_vec());
+                            let _ = sender.send((false, data)).await;
+                        }
+                    }
+                }
+            });
+            let response = Response::builder()
+                .header("Content-Type", "text/event-stream")
+                .body(body)
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    /// 处理函数本身耗时较长, 直到对端断开才结束
+    struct Slow {
+        notify: Arc<Notify>,
+    }
+
+    #[async_trait]
+    impl HttpTrait for Slow {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let disconnected = req.extensions().get::<Disconnected>().unwrap().clone();
+            tokio::select! {
+                _ = disconnected.disconnected() => {
+                    self.notify.notify_one();
+                    Err(ProtError::Extension("client disconnected"))
+                }
+                _ = tokio::time::sleep(Duration::from_secs(30)) => {
+                    let response = Response::builder().body(Body::empty()).unwrap();
+                    Ok(response)
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect_notified() -> ProtResult<()> {
+        let notify = Arc::new(Notify::new());
+        let handler = notify.clone();
+        let addr = common::run_server(
+            move || {
+                Box::new(Operate {
+                    notify: handler.clone(),
+                })
+            },
+            |_| {},
+        )
+        .await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET /events HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        assert!(String::from_utf8_lossy(&buf[..n]).starts_with("HTTP/1.1 200"));
+
+        // 客户端在数据流中途断开
+        drop(stream);
+        tokio::time::timeout(Duration::from_secs(5), notify.notified())
+            .await
+            .expect("handler not notified");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_slow_handler_notified() -> ProtResult<()> {
+        let notify = Arc::new(Notify::new());
+        let handler = notify.clone();
+        let addr = common::run_server(
+            move || {
+                Box::new(Slow {
+                    notify: handler.clone(),
+                })
+            },
+            |_| {},
+        )
+        .await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // 处理函数返回前客户端断开, 处理函数无需等到结束
+        drop(stream);
+        tokio::time::timeout(Duration::from_secs(5), notify.notified())
+            .await
+            .expect("handler not notified");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_slow_handler_h2_reset() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let mut server = ServerH2Connection::new(server_io, Builder::new());
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client
+            .get_mut()
+            .write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0])
+            .await?;
+        let id = StreamIdentifier::from(1);
+        let header = FrameHeader::new(Kind::Headers, Flag::end_headers(), id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "POST");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Post);
+        client.send_frame(Frame::Headers(headers))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+
+        let req = server.incoming().await?.unwrap();
+        let notify = Arc::new(Notify::new());
+        let mut f: Box<dyn HttpTrait> = Box::new(Slow {
+            notify: notify.clone(),
+        });
+        let mut middles = vec![];
+        // 处理函数运行期间客户端重置该流
+        let reset = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            client.send_frame(Frame::Reset(Reset::new(id, Reason::CANCEL)))?;
+            poll_fn(|cx| client.poll_flush(cx)).await
+        };
+        let (res, sent) = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(
+                server.handle_request(&None, req, &mut f, &mut middles),
+                reset
+            )
+        })
+        .await
+        .expect("handler not notified");
+        res?;
+        sent?;
+        tokio::time::timeout(Duration::from_secs(5), notify.notified())
+            .await
+            .expect("handler not notified");
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 16:44:05
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use wmhttp::{Client, ProtResult};
+
+    /// 依次以指定的数据回复每个连接, 并记录收到的请求
+    async fn run_server(
+        responses: Vec<&'static str>,
+    ) -> ProtResult<(SocketAddr, Arc<Mutex<Vec<String>>>)> {
+        let server = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        let requests = Arc::new(Mutex::new(vec![]));
+        let record = requests.clone();
+        tokio::spawn(async move {
+            for res in responses {
+                if let Ok((mut stream, _)) = server.accept().await {
+                    let mut buf = vec![0u8; 4096];
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    record
+                        .lock()
+                        .unwrap()
+                        .push(String::from_utf8_lossy(&buf[..n]).to_lowercase());
+                    let _ = stream.write_all(res.as_bytes()).await;
+                    // 关闭连接, 未发送完的包体即为中断
+                }
+            }
+        });
+        Ok((addr, requests))
+    }
+
+    async fn download(addr: SocketAddr, path: &Path) -> ProtResult<u64> {
+        let url = format!("http://{}/file", addr);
+        let client = Client::builder().url(&*url)?.connect().await?;
+        client.download_resumable(&url, path).await
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("wmhttp_{}_{}", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_download_resume() -> ProtResult<()> {
+        let path = temp_path("resume");
+        let etag_path = PathBuf::from(format!("{}.etag", path.display()));
+        let (addr, requests) = run_server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nETag: \"v1\"\r\nAccept-Ranges: bytes\r\n\r\nhello ",
+            "HTTP/1.1 206 Partial Content\r\nContent-Length: 5\r\nContent-Range: bytes 6-10/11\r\nETag: \"v1\"\r\n\r\nworld",
+        ])
+        .await?;
+
+        // 第一次下载中途断开, 保留已下载的部分
+        assert!(download(addr, &path).await.is_err());
+        assert_eq!(tokio::fs::read(&path).await?, b"hello ");
+        assert!(etag_path.exists());
+
+        // 续传剩余部分
+        assert_eq!(download(addr, &path).await?, 11);
+        assert_eq!(tokio::fs::read(&path).await?, b"hello world");
+        assert!(!etag_path.exists());
+        let requests = requests.lock().unwrap().clone();
+        assert!(requests[1].contains("range: bytes=6-\r\n"));
+        assert!(requests[1].contains("if-range: \"v1\"\r\n"));
+
+        let _ = tokio::fs::remove_file(&path).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_identity_weak_etag() -> ProtResult<()> {
+        let path = temp_path("identity");
+        let etag_path = PathBuf::from(format!("{}.etag", path.display()));
+        tokio::fs::write(&path, b"hello ").await?;
+        tokio::fs::write(&etag_path, "W/\"v3\"").await?;
+        let (addr, requests) = run_server(vec![
+            "HTTP/1.1 206 Partial Content\r\nContent-Length: 5\r\nContent-Range: bytes 6-10/11\r\n\r\nworld",
+        ])
+        .await?;
+
+        // 默认的Accept-Encoding不影响续传, 弱ETag不可用于If-Range
+        let url = format!("http://{}/file", addr);
+        let client = Client::builder()
+            .default_header("Accept-Encoding", "gzip")
+            .url(&*url)?
+            .connect()
+            .await?;
+        assert_eq!(client.download_resumable(&url, &path).await?, 11);
+        assert_eq!(tokio::fs::read(&path).await?, b"hello world");
+        let requests = requests.lock().unwrap().clone();
+        assert!(requests[0].contains("accept-encoding: identity\r\n"));
+        assert!(!requests[0].contains("gzip"));
+        assert!(requests[0].contains("range: bytes=6-\r\n"));
+        assert!(!requests[0].contains("if-range"));
+
+        let _ = tokio::fs::remove_file(&path).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_restart() -> ProtResult<()> {
+        let path = temp_path("restart");
+        tokio::fs::write(&path, b"hello ").await?;
+        // 服务端忽略范围请求返回完整内容, 重新下载
+        let (addr, _) = run_server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nETag: \"v2\"\r\n\r\nHELLO WORLD",
+        ])
+        .await?;
+        assert_eq!(download(addr, &path).await?, 11);
+        assert_eq!(tokio::fs::read(&path).await?, b"HELLO WORLD");
+
+        let _ = tokio::fs::remove_file(&path).await;
+        Ok(())
+    }
+}
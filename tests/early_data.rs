@@ -0,0 +1,104 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/18 16:40:12
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use webparse::{Request, Response};
+
+    use wmhttp::{
+        self, Body, Client, ClientHandle, EarlyData, HttpTrait, ProtResult, RecvRequest,
+        RecvResponse, TlsInfo,
+    };
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let early = req.headers().get_str_value(&"Early-Data").unwrap_or_default();
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(format!("early:{}", early)))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    /// 模拟接收0-RTT早期数据的TLS连接, 返回的EarlyData由测试确认握手
+    async fn run_server() -> ProtResult<(SocketAddr, EarlyData)> {
+        let early_data = EarlyData::new();
+        let early = early_data.clone();
+        let addr = common::run_server(
+            || Box::new(Operate),
+            move |server| {
+                server.set_tls_info(Some(TlsInfo::default()));
+                server.set_early_data(Some(early.clone()));
+            },
+        )
+        .await?;
+        Ok((addr, early_data))
+    }
+
+    async fn fetch(handle: &ClientHandle, url: &str, method: &str) -> ProtResult<(u16, String)> {
+        let req = Request::builder()
+            .method(method)
+            .url(url)
+            .body(Body::empty())
+            .unwrap();
+        let mut res = handle.send(req).await?;
+        let mut buffer = BinaryMut::new();
+        res.body_mut().read_all(&mut buffer).await;
+        let text = String::from_utf8_lossy(buffer.chunk()).to_string();
+        Ok((res.status().as_u16(), text))
+    }
+
+    #[tokio::test]
+    async fn test_h2_early_data() -> ProtResult<()> {
+        let (addr, early_data) = run_server().await?;
+        let url = format!("http://{}/", addr);
+        let handle = Client::builder()
+            .http2_only(true)
+            .url(&*url)?
+            .connect()
+            .await?
+            .into_handle()?;
+
+        // 幂等请求正常处理, 并标记来自早期数据
+        let (status, text) = fetch(&handle, &url, "GET").await?;
+        assert_eq!(status, 200);
+        assert_eq!(text, "early:1");
+
+        // 非幂等请求可能被重放, 回复425由客户端在握手后重试
+        let (status, _) = fetch(&handle, &url, "POST").await?;
+        assert_eq!(status, 425);
+
+        // 握手确认后同一连接上的请求正常处理, 不再标记
+        early_data.confirm();
+        let (status, text) = fetch(&handle, &url, "POST").await?;
+        assert_eq!(status, 200);
+        assert_eq!(text, "early:");
+        let (status, text) = fetch(&handle, &url, "GET").await?;
+        assert_eq!(status, 200);
+        assert_eq!(text, "early:");
+        Ok(())
+    }
+}
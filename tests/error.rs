@@ -0,0 +1,66 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/06 14:30:08
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{io, time::Duration};
+
+    use algorithm::buf::BinaryMut;
+    use tokio::sync::mpsc::channel;
+    use tokio_stream::StreamExt;
+    use wmhttp::{Body, ProtError};
+
+    #[tokio::test]
+    async fn test_error_conversion() {
+        let err: ProtError = io::Error::new(io::ErrorKind::Other, "io").into();
+        assert!(err.is_io());
+
+        let (sender, receiver) = channel::<u8>(1);
+        drop(receiver);
+        let err: ProtError = sender.try_send(1).unwrap_err().into();
+        assert!(err.is_channel_closed());
+        assert_eq!(format!("{}", err), "channel closed try send");
+
+        let elapsed = tokio::time::timeout(Duration::from_millis(1), std::future::pending::<()>())
+            .await
+            .unwrap_err();
+        let err: ProtError = elapsed.into();
+        assert!(err.is_timeout().0);
+
+        let err = ProtError::channel_closed("http1 body");
+        assert_eq!(format!("{}", err), "channel closed http1 body");
+    }
+
+    #[tokio::test]
+    async fn test_decompress_error() {
+        // 无效的deflate数据, 读取时返回带压缩方式的解压错误
+        let mut body = Body::new_binary(BinaryMut::from(vec![0xffu8; 16]));
+        body.set_compress_origin_deflate();
+        let err = loop {
+            match body.next().await {
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => break e,
+                None => panic!("decompress error not returned"),
+            }
+        };
+        assert!(matches!(err, ProtError::Decompress("deflate", _)));
+        assert!(err.is_decompress());
+        assert!(!err.is_io());
+        assert!(format!("{}", err).starts_with("deflate decompress error: "));
+
+        // 普通的io错误不受影响
+        let err: ProtError = io::Error::new(io::ErrorKind::Other, "io").into();
+        assert!(err.compress_method().is_none());
+    }
+}
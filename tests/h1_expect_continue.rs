@@ -0,0 +1,111 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 23:47:36
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpHelper, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, mut req: RecvRequest) -> ProtResult<RecvResponse> {
+            let mut buffer = BinaryMut::new();
+            req.body_mut().read_all(&mut buffer).await;
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(format!("len:{}", buffer.remaining())))
+                .unwrap();
+            Ok(response)
+        }
+
+        async fn on_expect_continue(&mut self, req: &RecvRequest) -> bool {
+            // 提前拒绝过大的上传
+            HttpHelper::declared_body_len(req).is_some_and(|len| len <= 1024)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    async fn read_until(stream: &mut TcpStream, buf: &mut Vec<u8>, expect: &str) -> ProtResult<()> {
+        let mut cache = vec![0u8; 1024];
+        while !String::from_utf8_lossy(buf).contains(expect) {
+            let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut cache))
+                .await
+                .expect("response not received")?;
+            assert!(n > 0, "connection closed early");
+            buf.extend_from_slice(&cache[..n]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expect_continue_accept() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(
+                b"POST / HTTP/1.1\r\nHost: localhost\r\nExpect: 100-continue\r\nContent-Length: 11\r\n\r\n",
+            )
+            .await?;
+
+        // 收到100 Continue后才发送包体
+        let mut buf = vec![];
+        read_until(&mut stream, &mut buf, "\r\n\r\n").await?;
+        assert!(buf.starts_with(b"HTTP/1.1 100 Continue\r\n\r\n"));
+        stream.write_all(b"hello world").await?;
+
+        let mut buf = buf[b"HTTP/1.1 100 Continue\r\n\r\n".len()..].to_vec();
+        read_until(&mut stream, &mut buf, "len:11").await?;
+        assert!(buf.starts_with(b"HTTP/1.1 200"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expect_continue_reject() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(
+                b"POST / HTTP/1.1\r\nHost: localhost\r\nExpect: 100-continue\r\nContent-Length: 5000\r\n\r\n",
+            )
+            .await?;
+
+        // 过大的包体直接回复417, 不发送100 Continue, 之后关闭连接
+        let mut buf = vec![];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut buf))
+            .await
+            .expect("rejected connection not closed")?;
+        let text = String::from_utf8_lossy(&buf);
+        assert!(text.starts_with("HTTP/1.1 417"));
+        assert!(!text.contains("100 Continue"));
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 03:42:16
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use algorithm::buf::{Binary, BinaryMut, Bt};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::mpsc::{channel, Receiver},
+    };
+    use webparse::Request;
+
+    use wmhttp::{self, Body, Client, ProtResult};
+
+    /// 只支持HTTP/1.0的服务端, 按Content-Length读取包体,
+    /// 响应不带长度, 以关闭连接表示结束, 收到的请求头及包体发回测试
+    async fn run_legacy_server() -> ProtResult<(SocketAddr, Receiver<(String, Vec<u8>)>)> {
+        let server = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        let (sender, receiver) = channel(10);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = server.accept().await {
+                let mut buf = vec![];
+                while !buf.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0u8; 1];
+                    if stream.read_exact(&mut byte).await.is_err() {
+                        return;
+                    }
+                    buf.push(byte[0]);
+                }
+                let head = String::from_utf8_lossy(&buf).to_string();
+                let len = head
+                    .lines()
+                    .find_map(|l| {
+                        let (name, value) = l.split_once(':')?;
+                        if name.eq_ignore_ascii_case("content-length") {
+                            value.trim().parse::<usize>().ok()
+                        } else {
+                            None
+                        }
+                    })
+                    .unwrap_or(0);
+                let mut body = vec![0u8; len];
+                let _ = stream.read_exact(&mut body).await;
+                let _ = stream.write_all(b"HTTP/1.0 200 OK\r\n\r\nlegacy").await;
+                let _ = stream.shutdown().await;
+                let _ = sender.send((head, body)).await;
+            }
+        });
+        Ok((addr, receiver))
+    }
+
+    fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+        head.lines().find_map(|l| {
+            let (n, v) = l.split_once(':')?;
+            n.eq_ignore_ascii_case(name).then(|| v.trim())
+        })
+    }
+
+    #[tokio::test]
+    async fn test_http10_request() -> ProtResult<()> {
+        let (addr, mut receiver) = run_legacy_server().await?;
+        let url = format!("http://{}/", addr);
+        let client = Client::builder().http_10().url(&*url)?.connect().await?;
+
+        // 流式的包体完整缓存后以Content-Length发送
+        let (sender, body) = Body::channel(4);
+        tokio::spawn(async move {
+            let _ = sender.send((false, Binary::from(b"hello ".to_vec()))).await;
+            let _ = sender.send((true, Binary::from(b"legacy".to_vec()))).await;
+        });
+        let req = Request::builder()
+            .method("POST")
+            .url(&*url)
+            .header("Transfer-Encoding", "chunked")
+            .body(body)
+            .unwrap();
+        let mut res = client.send_now(req).await?;
+        let mut buffer = BinaryMut::new();
+        tokio::time::timeout(Duration::from_secs(5), res.body_mut().read_all(&mut buffer))
+            .await
+            .expect("response not finished");
+        assert_eq!(res.status(), 200);
+        assert_eq!(buffer.chunk(), b"legacy");
+
+        let (head, body) = receiver.recv().await.unwrap();
+        assert!(head.starts_with("POST / HTTP/1.0\r\n"));
+        assert_eq!(header(&head, "Transfer-Encoding"), None);
+        assert_eq!(header(&head, "Content-Length"), Some("12"));
+        assert_eq!(header(&head, "Connection"), Some("close"));
+        assert_eq!(body, b"hello legacy");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_http10_keep_alive() -> ProtResult<()> {
+        let (addr, mut receiver) = run_legacy_server().await?;
+        let url = format!("http://{}/", addr);
+        let client = Client::builder().http_10().url(&*url)?.connect().await?;
+
+        // 显式要求保持连接时不改为短连接, 也不尝试升级HTTP/2
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .header("Connection", "keep-alive")
+            .body(Body::empty())
+            .unwrap();
+        let _ = client.send_now(req).await?;
+        let (head, _) = receiver.recv().await.unwrap();
+        assert!(head.starts_with("GET / HTTP/1.0\r\n"));
+        assert_eq!(header(&head, "Connection"), Some("keep-alive"));
+        assert_eq!(header(&head, "Upgrade"), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_http10_body_error() -> ProtResult<()> {
+        let (addr, mut receiver) = run_legacy_server().await?;
+        let url = format!("http://{}/", addr);
+        let client = Client::builder().http_10().url(&*url)?.connect().await?;
+
+        // 读取包体出错时返回错误, 不以截断的包体发送请求
+        let chunks: Vec<std::io::Result<Vec<u8>>> = vec![
+            Ok(b"hello".to_vec()),
+            Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "upstream reset")),
+        ];
+        let req = Request::builder()
+            .method("POST")
+            .url(&*url)
+            .body(Body::from_stream(tokio_stream::iter(chunks)))
+            .unwrap();
+        assert!(client.send_now(req).await.is_err());
+        let received = tokio::time::timeout(Duration::from_millis(300), receiver.recv()).await;
+        assert!(received.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 21:58:41
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    };
+    use webparse::Response;
+
+    use wmhttp::{
+        self, Body, CloseReason, HttpTrait, ProtResult, RecvRequest, RecvResponse, Server,
+    };
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text("hello".to_string()))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server(sender: UnboundedSender<CloseReason>) -> ProtResult<SocketAddr> {
+        common::serve(
+            move |stream, addr| {
+                let sender = sender.clone();
+                Server::builder()
+                    .addr(addr)
+                    .ka_timeout(Duration::from_millis(200))
+                    .on_disconnect(move |_info, reason| {
+                        let _ = sender.send(reason);
+                    })
+                    .stream(stream)
+            },
+            |server| server.set_callback_http(Box::new(Operate)),
+        )
+        .await
+    }
+
+    /// 连接因空闲超时关闭
+    async fn assert_timeout(receiver: &mut UnboundedReceiver<CloseReason>) {
+        let reason = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("disconnect not notified");
+        assert_eq!(reason, Some(CloseReason::Timeout));
+    }
+
+    /// 读取到连接关闭, 返回读到的数据
+    async fn read_to_close(stream: &mut TcpStream) -> Vec<u8> {
+        let mut buf = vec![];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut buf))
+            .await
+            .expect("idle connection not closed")
+            .unwrap();
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_idle_without_request() -> ProtResult<()> {
+        let (sender, mut receiver) = unbounded_channel();
+        let addr = run_server(sender).await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 建立连接后从不发送数据, 超时后被关闭
+        assert!(read_to_close(&mut stream).await.is_empty());
+        assert_timeout(&mut receiver).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_idle_between_requests() -> ProtResult<()> {
+        let (sender, mut receiver) = unbounded_channel();
+        let addr = run_server(sender).await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        for _ in 0..2 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .await?;
+            let mut buf = vec![];
+            let mut cache = vec![0u8; 1024];
+            while !String::from_utf8_lossy(&buf).ends_with("hello") {
+                let n = stream.read(&mut cache).await?;
+                assert!(n > 0, "connection closed early");
+                buf.extend_from_slice(&cache[..n]);
+            }
+        }
+
+        // 响应完成后空闲超时关闭keep-alive的连接
+        assert!(read_to_close(&mut stream).await.is_empty());
+        assert_timeout(&mut receiver).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 22:52:17
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, mut req: RecvRequest) -> ProtResult<RecvResponse> {
+            let mut buffer = BinaryMut::new();
+            req.body_mut().read_all(&mut buffer).await;
+            // 返回路径及包体, 确认请求的顺序及包体的边界
+            let text = format!(
+                "[{}:{}]",
+                req.path(),
+                String::from_utf8_lossy(buffer.chunk())
+            );
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(text))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    fn pipelined() -> Vec<u8> {
+        let mut data = vec![];
+        data.extend_from_slice(b"GET /a HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        data.extend_from_slice(
+            b"POST /b HTTP/1.1\r\nHost: localhost\r\nContent-Length: 14\r\n\r\nGET /x HTTP/1.",
+        );
+        data.extend_from_slice(
+            b"POST /c HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n",
+        );
+        data.extend_from_slice(b"GET /d HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        data
+    }
+
+    /// 读取直到收到所有响应, 返回各响应包体出现的位置
+    async fn read_responses(stream: &mut TcpStream, expects: &[&str]) -> ProtResult<Vec<usize>> {
+        let mut buf = vec![];
+        let mut cache = vec![0u8; 1024];
+        loop {
+            let text = String::from_utf8_lossy(&buf).to_string();
+            if expects.iter().all(|e| text.contains(e)) {
+                return Ok(expects.iter().map(|e| text.find(e).unwrap()).collect());
+            }
+            let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut cache))
+                .await
+                .expect("pipelined responses not received")?;
+            assert!(n > 0, "connection closed early");
+            buf.extend_from_slice(&cache[..n]);
+        }
+    }
+
+    const EXPECTS: [&str; 4] = ["[/a:]", "[/b:GET /x HTTP/1.]", "[/c:abc]", "[/d:]"];
+
+    #[tokio::test]
+    async fn test_pipelined_requests() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 所有请求一次性发送, 每个请求都得到响应且按请求的顺序返回
+        stream.write_all(&pipelined()).await?;
+        let positions = read_responses(&mut stream, &EXPECTS).await?;
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_then_close() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 发送完毕后关闭写端, 已发送的请求仍全部处理
+        stream.write_all(&pipelined()).await?;
+        stream.shutdown().await?;
+        let positions = read_responses(&mut stream, &EXPECTS).await?;
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 00:52:38
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use algorithm::buf::{Binary, BinaryMut, Bt, BtMut};
+    use async_trait::async_trait;
+    use tokio_stream::StreamExt;
+    use webparse::{HeaderMap, Request, Response};
+
+    use wmhttp::{
+        self, proxy_body, Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse,
+    };
+
+    use crate::common;
+
+    /// 上游服务, 边接收请求包体边原样返回
+    struct Echo;
+
+    #[async_trait]
+    impl HttpTrait for Echo {
+        async fn operate(&mut self, mut req: RecvRequest) -> ProtResult<RecvResponse> {
+            let (sender, body) = Body::channel(4);
+            let src = std::mem::replace(req.body_mut(), Body::empty());
+            tokio::spawn(proxy_body(src, sender));
+            let response = Response::builder()
+                .version(req.version().clone())
+                .header("Transfer-Encoding", "chunked")
+                .body(body)
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    /// 上游服务, 以chunked返回带trailer头的响应
+    struct Trailers;
+
+    #[async_trait]
+    impl HttpTrait for Trailers {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let (sender, mut body) = Body::channel(1);
+            let mut trailers = HeaderMap::new();
+            trailers.insert("X-Checksum", "abc");
+            body.set_trailers(trailers);
+            sender.send((true, Binary::from(b"hello".to_vec()))).await?;
+            let response = Response::builder()
+                .version(req.version().clone())
+                .header("Transfer-Encoding", "chunked")
+                .header("Trailer", "X-Checksum")
+                .body(body)
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    /// 代理服务, 将请求转发到上游
+    struct Proxy {
+        upstream: SocketAddr,
+    }
+
+    #[async_trait]
+    impl HttpTrait for Proxy {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let url = format!("http://{}/", self.upstream);
+            let client = Client::builder()
+                .http2(false)
+                .url(&*url)?
+                .connect()
+                .await?;
+            client.splice(req).await
+        }
+    }
+
+    async fn run_server<F>(build: F) -> ProtResult<SocketAddr>
+    where
+        F: Fn() -> Box<dyn HttpTrait> + Send + 'static,
+    {
+        common::run_server(build, |_| {}).await
+    }
+
+    /// 读取直到收到len个字节
+    async fn read_len(body: &mut Body, buffer: &mut BinaryMut, len: usize) -> ProtResult<()> {
+        while buffer.remaining() < len {
+            let bin = tokio::time::timeout(Duration::from_secs(5), body.next())
+                .await
+                .expect("streamed data not received")
+                .expect("body closed early")?;
+            buffer.put_slice(bin.chunk());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_full_duplex_proxy() -> ProtResult<()> {
+        let upstream = run_server(|| Box::new(Echo)).await?;
+        let proxy = run_server(move || Box::new(Proxy { upstream })).await?;
+
+        let url = format!("http://{}/", proxy);
+        let client = Client::builder()
+            .http2(false)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let (sender, body) = Body::channel(4);
+        let req = Request::builder()
+            .method("POST")
+            .url(&*url)
+            .header("Transfer-Encoding", "chunked")
+            .body(body)
+            .unwrap();
+        let mut res = client.send_now(req).await?;
+        assert_eq!(res.status(), 200);
+
+        // 请求未发送完毕时已收到之前数据的响应
+        let chunk = vec![7u8; 16 * 1024];
+        sender.send((false, Binary::from(chunk.clone()))).await?;
+        let mut result = BinaryMut::new();
+        read_len(res.body_mut(), &mut result, chunk.len()).await?;
+
+        // 大的包体同时上传及下载
+        let total = 4 * 1024 * 1024;
+        tokio::spawn(async move {
+            let mut sent = chunk.len();
+            while sent < total {
+                sent += chunk.len();
+                if sender
+                    .send((sent >= total, Binary::from(chunk.clone())))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        let body = res.body_mut();
+        tokio::time::timeout(Duration::from_secs(10), body.read_all(&mut result))
+            .await
+            .expect("proxied body not finished");
+        assert_eq!(result.remaining(), total);
+        assert!(result.chunk().iter().all(|b| *b == 7));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trailer_forwarded() -> ProtResult<()> {
+        let upstream = run_server(|| Box::new(Trailers)).await?;
+        let proxy = run_server(move || Box::new(Proxy { upstream })).await?;
+
+        let url = format!("http://{}/", proxy);
+        let client = Client::builder()
+            .http2(false)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .body(Body::empty())
+            .unwrap();
+        let mut res = client.send_now(req).await?;
+        // Trailer不是逐跳的头, 与trailer头一起转发
+        assert_eq!(
+            res.headers().get_str_value(&"Trailer").as_deref(),
+            Some("X-Checksum")
+        );
+        let mut result = BinaryMut::new();
+        tokio::time::timeout(Duration::from_secs(5), res.body_mut().read_all(&mut result))
+            .await
+            .expect("proxied body not finished");
+        assert_eq!(result.chunk(), b"hello");
+        let trailers = res.body().trailers().expect("trailers not forwarded");
+        assert_eq!(
+            trailers.get_str_value(&"X-Checksum").as_deref(),
+            Some("abc")
+        );
+        Ok(())
+    }
+}
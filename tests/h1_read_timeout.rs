@@ -0,0 +1,118 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 00:06:21
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, mut req: RecvRequest) -> ProtResult<RecvResponse> {
+            let mut buffer = BinaryMut::new();
+            req.body_mut().read_all(&mut buffer).await;
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(format!("len:{}", buffer.remaining())))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(
+            || Box::new(Operate),
+            |server| server.set_read_timeout(Some(Duration::from_millis(200))),
+        )
+        .await
+    }
+
+    async fn read_to_close(stream: &mut TcpStream) -> ProtResult<String> {
+        let mut buf = vec![];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut buf))
+            .await
+            .expect("timeout connection not closed")?;
+        Ok(String::from_utf8_lossy(&buf).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_header_read_timeout() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 缓慢发送请求头, 虽持续有数据但总时长超出限制
+        for part in [
+            "GET / HTTP/1.1\r\n",
+            "Host: local",
+            "host\r\n",
+            "Accept: */*",
+        ] {
+            stream.write_all(part.as_bytes()).await?;
+            tokio::time::sleep(Duration::from_millis(80)).await;
+        }
+        let text = read_to_close(&mut stream).await?;
+        assert!(text.starts_with("HTTP/1.1 408"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_body_read_timeout() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 包体未在限制时间内发送完毕
+        stream
+            .write_all(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 10\r\n\r\nabc")
+            .await?;
+        let text = read_to_close(&mut stream).await?;
+        assert!(text.starts_with("HTTP/1.1 408"));
+        assert!(!text.contains("len:"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_idle_not_timeout() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 空闲的连接不计入读取时间
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        stream
+            .write_all(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\nabc")
+            .await?;
+        let mut buf = vec![];
+        let mut cache = vec![0u8; 1024];
+        while !String::from_utf8_lossy(&buf).contains("len:3") {
+            let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut cache))
+                .await
+                .expect("response not received")?;
+            assert!(n > 0, "connection closed early");
+            buf.extend_from_slice(&cache[..n]);
+        }
+        assert!(buf.starts_with(b"HTTP/1.1 200"));
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 18:21:37
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use algorithm::buf::Binary;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+    use webparse::{
+        http::http2::frame::{Flag, Frame, FrameHeader, Headers, Kind, StreamIdentifier},
+        HeaderMap, Method,
+    };
+    use wmhttp::{
+        http2::{Builder, Codec},
+        ProtResult, ServerH2Connection,
+    };
+
+    fn headers(id: u32, cookie: &str) -> Frame<Binary> {
+        let id = StreamIdentifier::from(id);
+        let mut flag = Flag::end_headers();
+        flag.set(Flag::end_stream(), true);
+        let header = FrameHeader::new(Kind::Headers, flag, id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "GET");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        fields.insert("cookie", cookie.to_string());
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Get);
+        Frame::Headers(headers)
+    }
+
+    /// 编码出完整的HEADERS帧, 返回帧头及负载
+    async fn encode_headers(frame: Frame<Binary>) -> ProtResult<([u8; 9], Vec<u8>)> {
+        let (io, mut raw) = tokio::io::duplex(65_536);
+        let mut codec = Codec::new(io);
+        codec.send_frame(frame)?;
+        poll_fn(|cx| codec.poll_flush(cx)).await?;
+        let mut head = [0u8; 9];
+        raw.read_exact(&mut head).await?;
+        let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+        let mut payload = vec![0u8; len];
+        raw.read_exact(&mut payload).await?;
+        Ok((head, payload))
+    }
+
+    fn raw_frame(kind: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut data = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        data.push(kind);
+        data.push(flags);
+        data.extend_from_slice(&stream_id.to_be_bytes());
+        data.extend_from_slice(payload);
+        data
+    }
+
+    async fn connect() -> ProtResult<(DuplexStream, ServerH2Connection<DuplexStream>)> {
+        let (mut client, server_io) = tokio::io::duplex(65_536);
+        let server = ServerH2Connection::new(server_io, Builder::new());
+        client.write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n").await?;
+        client.write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        Ok((client, server))
+    }
+
+    #[tokio::test]
+    async fn test_headers_with_continuation() -> ProtResult<()> {
+        let cookie = "a=".to_string() + &"b".repeat(3000);
+        let (head, payload) = encode_headers(headers(1, &cookie)).await?;
+        let (mut client, mut server) = connect().await?;
+
+        // 头部块拆分为HEADERS及两个CONTINUATION帧, 最后一帧才带END_HEADERS
+        let flags = head[4] & !0x4;
+        let (first, rest) = payload.split_at(payload.len() / 3);
+        let (second, third) = rest.split_at(rest.len() / 2);
+        client.write_all(&raw_frame(0x1, flags, 1, first)).await?;
+        client.write_all(&raw_frame(0x9, 0, 1, second)).await?;
+        client.write_all(&raw_frame(0x9, 0x4, 1, third)).await?;
+
+        let req = server.incoming().await?.unwrap();
+        assert_eq!(req.path(), "/");
+        assert_eq!(
+            req.headers().get_str_value(&"cookie").unwrap(),
+            cookie
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_interleaved_continuation() -> ProtResult<()> {
+        let (head, payload) = encode_headers(headers(1, "a=b")).await?;
+        let (mut client, mut server) = connect().await?;
+
+        // 头部块未结束时收到其它流的帧为协议错误
+        let flags = head[4] & !0x4;
+        let (first, second) = payload.split_at(payload.len() / 2);
+        client.write_all(&raw_frame(0x1, flags, 1, first)).await?;
+        client.write_all(&raw_frame(0x9, 0x4, 3, second)).await?;
+
+        assert!(server.incoming().await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 03:08:19
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    const KIND_DATA: u8 = 0x0;
+    const KIND_HEADERS: u8 = 0x1;
+    const FLAG_END_STREAM: u8 = 0x1;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = if req.path() == "/empty" {
+                Response::builder()
+                    .version(req.version().clone())
+                    .header("Content-Length", "0")
+                    .body(Body::empty())
+                    .unwrap()
+            } else {
+                Response::builder()
+                    .version(req.version().clone())
+                    .body(Body::new_text("hello".to_string()))
+                    .unwrap()
+            };
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    /// GET请求的HEADERS帧, 路径以不加入索引的字面量编码
+    fn request(stream_id: u32, path: &str) -> Vec<u8> {
+        // :method GET, :scheme http, :authority localhost
+        let mut block = vec![0x82, 0x86, 0x01, 0x09];
+        block.extend_from_slice(b"localhost");
+        block.extend_from_slice(&[0x04, path.len() as u8]);
+        block.extend_from_slice(path.as_bytes());
+
+        let mut data = (block.len() as u32).to_be_bytes()[1..].to_vec();
+        // END_STREAM | END_HEADERS
+        data.extend_from_slice(&[KIND_HEADERS, 0x5]);
+        data.extend_from_slice(&stream_id.to_be_bytes());
+        data.extend_from_slice(&block);
+        data
+    }
+
+    /// 读取下一帧, 返回(类型, 标志, 流id)
+    async fn read_frame(stream: &mut TcpStream) -> ProtResult<(u8, u8, u32)> {
+        let mut head = [0u8; 9];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_exact(&mut head))
+            .await
+            .expect("frame not received")?;
+        let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+        let mut payload = vec![0u8; len];
+        stream.read_exact(&mut payload).await?;
+        let stream_id = u32::from_be_bytes([head[5] & 0x7F, head[6], head[7], head[8]]);
+        Ok((head[3], head[4], stream_id))
+    }
+
+    /// 读取直到该流结束, 返回该流收到的所有帧
+    async fn read_stream(stream: &mut TcpStream, id: u32) -> ProtResult<Vec<(u8, u8, u32)>> {
+        let mut frames = vec![];
+        loop {
+            let frame = read_frame(stream).await?;
+            if frame.2 != 0 {
+                frames.push(frame);
+            }
+            if frame.2 == id && frame.1 & FLAG_END_STREAM != 0 {
+                return Ok(frames);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_content_length_zero() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n").await?;
+        stream.write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+
+        // 声明为空的包体只发送一个带END_STREAM的HEADERS帧
+        stream.write_all(&request(1, "/empty")).await?;
+        let frames = read_stream(&mut stream, 1).await?;
+        assert_eq!(frames, vec![(KIND_HEADERS, 0x5, 1)]);
+
+        // 之后的请求正常返回包体, 且期间不再收到流1的DATA帧
+        stream.write_all(&request(3, "/text")).await?;
+        let frames = read_stream(&mut stream, 3).await?;
+        assert!(frames.iter().all(|f| f.2 == 3));
+        assert!(frames.iter().any(|f| f.0 == KIND_DATA));
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 17:31:46
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, time::Duration};
+
+    use algorithm::buf::Binary;
+    use tokio::io::AsyncWriteExt;
+    use tokio_stream::StreamExt;
+    use webparse::{
+        http::http2::frame::{Flag, Frame, FrameHeader, Headers, Kind, Reason, StreamIdentifier},
+        HeaderMap, Method, Response,
+    };
+    use wmhttp::{
+        http2::{Builder, Codec},
+        Body, ProtResult, ServerH2Connection,
+    };
+
+    fn headers(id: u32) -> Frame<Binary> {
+        let id = StreamIdentifier::from(id);
+        let mut flag = Flag::end_headers();
+        flag.set(Flag::end_stream(), true);
+        let header = FrameHeader::new(Kind::Headers, flag, id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "GET");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Get);
+        Frame::Headers(headers)
+    }
+
+    #[tokio::test]
+    async fn test_flow_stall_goaway() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let builder = Builder::new().flow_stall_timeout(Duration::from_millis(200));
+        let mut server = ServerH2Connection::new(server_io, builder);
+
+        // 客户端通告初始窗口为0且从不发送WINDOW_UPDATE, 服务端的包体无法发出
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client
+            .get_mut()
+            .write_all(&[0, 0, 6, 4, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0])
+            .await?;
+        client.send_frame(headers(1))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+
+        let mut req = server.incoming().await?.unwrap();
+        let id = req.extensions_mut().remove::<StreamIdentifier>().unwrap();
+        let res = Response::builder()
+            .body(Body::new_text("hello".to_string()))
+            .unwrap();
+        server.send_response(res, id).await?;
+
+        // 无进展超时后以GOAWAY关闭连接, 而不是一直挂起
+        let result = tokio::time::timeout(Duration::from_secs(5), server.incoming())
+            .await
+            .expect("stalled connection not closed");
+        assert!(result.is_err());
+        loop {
+            let frame = client.next().await.unwrap()?;
+            assert!(!frame.is_data());
+            if let Frame::GoAway(goaway) = frame {
+                assert_eq!(goaway.reason(), Reason::FLOW_CONTROL_ERROR);
+                break;
+            }
+        }
+        Ok(())
+    }
+}
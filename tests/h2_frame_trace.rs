@@ -0,0 +1,108 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 01:49:22
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use wmhttp::http2::{format_frame_head, Direction};
+
+    #[test]
+    fn test_format_frame_head() {
+        // DATA帧, END_STREAM, 长度5
+        let head = [0, 0, 5, 0, 1, 0, 0, 0, 1];
+        assert_eq!(
+            format_frame_head(Direction::Outbound, &head),
+            "send DATA type=0x0 stream=1 flags=0x1 len=5"
+        );
+
+        // 忽略流id的保留位, 长度为3字节
+        let head = [1, 0, 0, 9, 4, 0x80, 0, 0, 3];
+        assert_eq!(
+            format_frame_head(Direction::Inbound, &head),
+            "recv CONTINUATION type=0x9 stream=3 flags=0x4 len=65536"
+        );
+
+        // 未知的帧类型
+        let head = [0, 0, 0, 0xa, 0, 0, 0, 0, 0];
+        assert_eq!(
+            format_frame_head(Direction::Inbound, &head),
+            "recv UNKNOWN type=0xa stream=0 flags=0x0 len=0"
+        );
+    }
+}
+
+#[cfg(all(test, feature = "frame-trace"))]
+mod trace_tests {
+    use std::sync::Mutex;
+
+    use log::{LevelFilter, Log, Metadata, Record};
+    use tokio::io::AsyncWriteExt;
+    use tokio_stream::StreamExt;
+    use webparse::http::http2::frame::{Frame, StreamIdentifier, WindowUpdate};
+    use wmhttp::{
+        http2::{Codec, FRAME_TRACE_TARGET},
+        ProtResult,
+    };
+
+    static LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    /// 只收集帧跟踪的日志
+    struct Capture;
+
+    impl Log for Capture {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == FRAME_TRACE_TARGET
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            if self.enabled(record.metadata()) {
+                LINES.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: Capture = Capture;
+
+    #[tokio::test]
+    async fn test_frame_trace() -> ProtResult<()> {
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(LevelFilter::Trace);
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        let mut codec = Codec::new(server);
+        // PING帧, 其后为空的SETTINGS帧
+        client
+            .write_all(&[0, 0, 8, 6, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8])
+            .await?;
+        client.write_all(&[0, 0, 0, 4, 1, 0, 0, 0, 0]).await?;
+        assert!(matches!(codec.next().await.unwrap()?, Frame::Ping(_)));
+        assert!(matches!(codec.next().await.unwrap()?, Frame::Settings(_)));
+
+        codec.send_frame(Frame::WindowUpdate(WindowUpdate::new(
+            StreamIdentifier::from(3),
+            100,
+        )))?;
+
+        assert_eq!(
+            *LINES.lock().unwrap(),
+            vec![
+                "recv PING type=0x6 stream=0 flags=0x0 len=8",
+                "recv SETTINGS type=0x4 stream=0 flags=0x1 len=0",
+                "send WINDOW_UPDATE type=0x8 stream=3 flags=0x0 len=4",
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 20:05:17
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, time::Duration};
+
+    use algorithm::buf::Binary;
+    use tokio::io::{AsyncWriteExt, DuplexStream};
+    use webparse::http::http2::frame::{Frame, GoAway, Reason, StreamIdentifier};
+    use wmhttp::{
+        http2::{Builder, Codec},
+        ProtResult, ServerH2Connection,
+    };
+
+    async fn go_away(
+        reason: Reason,
+        debug_data: &'static [u8],
+    ) -> ProtResult<ServerH2Connection<DuplexStream>> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let server = ServerH2Connection::new(server_io, Builder::new());
+
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client.get_mut().write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        let frame = GoAway::with_debug_data(
+            StreamIdentifier::zero(),
+            reason,
+            Binary::from(debug_data.to_vec()),
+        );
+        client.send_frame(Frame::GoAway(frame))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+        // 保持客户端不被释放, 由服务端主动关闭
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            drop(client);
+        });
+        Ok(server)
+    }
+
+    #[tokio::test]
+    async fn test_recv_goaway_error() -> ProtResult<()> {
+        let mut server = go_away(Reason::ENHANCE_YOUR_CALM, b"too many requests").await?;
+        let err = tokio::time::timeout(Duration::from_secs(5), server.incoming())
+            .await
+            .expect("goaway not handled")
+            .err()
+            .unwrap();
+        assert!(err.is_remote_go_away());
+        assert_eq!(err.go_away_reason(), Some(Reason::ENHANCE_YOUR_CALM));
+        assert_eq!(
+            err.go_away_debug_data().unwrap().as_slice(),
+            b"too many requests"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recv_goaway_no_error() -> ProtResult<()> {
+        let mut server = go_away(Reason::NO_ERROR, b"shutting down").await?;
+        // 正常关闭不返回错误, 调试数据可从连接中获取
+        let req = tokio::time::timeout(Duration::from_secs(5), server.incoming())
+            .await
+            .expect("goaway not handled")?;
+        assert!(req.is_none());
+        let frame = server.remote_go_away().unwrap();
+        assert_eq!(frame.reason(), Reason::NO_ERROR);
+        assert_eq!(frame.debug_data().as_slice(), b"shutting down");
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 20:31:44
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, task::Poll, time::Duration};
+
+    use algorithm::buf::Binary;
+    use tokio::io::AsyncWriteExt;
+    use tokio_stream::StreamExt;
+    use webparse::{
+        http::http2::frame::{Flag, Frame, FrameHeader, Headers, Kind, Reason, StreamIdentifier},
+        HeaderMap, Method, Response,
+    };
+    use wmhttp::{
+        http2::{Builder, Codec},
+        Body, ProtResult, ServerH2Connection,
+    };
+
+    fn headers(id: u32) -> Frame<Binary> {
+        let id = StreamIdentifier::from(id);
+        let mut flag = Flag::end_headers();
+        flag.set(Flag::end_stream(), true);
+        let header = FrameHeader::new(Kind::Headers, flag, id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "GET");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Get);
+        Frame::Headers(headers)
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let mut server = ServerH2Connection::new(server_io, Builder::new());
+
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client.get_mut().write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        client.send_frame(headers(1))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+
+        let mut req = server.incoming().await?.unwrap();
+        let id = req.extensions_mut().remove::<StreamIdentifier>().unwrap();
+
+        // 首个GOAWAY的last_stream_id为最大值, 连接保持
+        server.graceful_shutdown();
+        poll_fn(|cx| server.poll_write(cx)).await?;
+        loop {
+            if let Frame::GoAway(goaway) = client.next().await.unwrap()? {
+                assert_eq!(goaway.reason(), Reason::NO_ERROR);
+                assert_eq!(goaway.last_stream_id(), StreamIdentifier::from(0x7FFF_FFFF));
+                break;
+            }
+        }
+
+        // 关闭中打开的新流被拒绝, 不返回请求
+        client.send_frame(headers(3))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+        let is_pending = poll_fn(|cx| Poll::Ready(server.poll_request(cx).is_pending())).await;
+        assert!(is_pending);
+
+        // 已有的流处理完毕后连接正常关闭
+        let res = Response::builder()
+            .body(Body::new_text("hello".to_string()))
+            .unwrap();
+        server.send_response(res, id).await?;
+        let req = tokio::time::timeout(Duration::from_secs(5), server.incoming())
+            .await
+            .expect("drained connection not closed")?;
+        assert!(req.is_none());
+
+        let (mut is_refused, mut is_response) = (false, false);
+        loop {
+            match client.next().await.unwrap()? {
+                Frame::Reset(reset) => {
+                    assert_eq!(reset.stream_id(), StreamIdentifier::from(3));
+                    assert_eq!(reset.reason(), Reason::REFUSED_STREAM);
+                    is_refused = true;
+                }
+                Frame::Headers(h) => {
+                    assert!(!is_response);
+                    assert_eq!(Frame::Headers(h).stream_id(), id);
+                    is_response = true;
+                }
+                Frame::GoAway(goaway) => {
+                    assert_eq!(goaway.reason(), Reason::NO_ERROR);
+                    assert_eq!(goaway.last_stream_id(), id);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert!(is_refused);
+        assert!(is_response);
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/21 11:20:36
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, net::SocketAddr};
+
+    use algorithm::buf::{Binary, BinaryMut, Bt};
+    use async_trait::async_trait;
+    use tokio::io::AsyncWriteExt;
+    use tokio_stream::StreamExt;
+    use webparse::{
+        http::http2::frame::{Flag, Frame, FrameHeader, Headers, Kind, StreamIdentifier},
+        HeaderMap, Method, Request, Response,
+    };
+    use wmhttp::{
+        http2::{Builder, Codec},
+        Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse, ServerH2Connection,
+    };
+
+    use crate::common;
+
+    /// gRPC的响应: 消息体后跟随带grpc-status的trailer
+    fn grpc_response() -> RecvResponse {
+        let mut body = Body::from(b"\0\0\0\0\x05hello".to_vec());
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", "0");
+        trailers.insert("grpc-message", "OK");
+        body.set_trailers(trailers);
+        Response::builder()
+            .header("content-type", "application/grpc")
+            .body(body)
+            .unwrap()
+    }
+
+    fn request_headers(id: u32) -> Frame<Binary> {
+        let id = StreamIdentifier::from(id);
+        let header = FrameHeader::new(Kind::Headers, Flag::end_headers(), id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "POST");
+        fields.insert(":path", "/helloworld.Greeter/SayHello");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        fields.insert("content-type", "application/grpc");
+        fields.insert("te", "trailers");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Post);
+        headers.flags_mut().set_end_stream();
+        Frame::Headers(headers)
+    }
+
+    #[tokio::test]
+    async fn test_grpc_frame_sequence() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let mut server = ServerH2Connection::new(server_io, Builder::new());
+
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client.get_mut().write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        client.send_frame(request_headers(1))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+
+        let mut req = server.incoming().await?.unwrap();
+        assert_eq!(req.headers().get_str_value(&"te").unwrap(), "trailers");
+        let id = req.extensions_mut().remove::<StreamIdentifier>().unwrap();
+        server.send_response(grpc_response(), id).await?;
+        poll_fn(|cx| server.poll_write(cx)).await?;
+
+        // 依次为HEADERS, DATA, 带END_STREAM的trailer HEADERS
+        let mut frames = vec![];
+        while let Some(frame) = client.next().await {
+            let frame = frame?;
+            if frame.stream_id().is_zero() {
+                continue;
+            }
+            let is_end = frame.is_end_stream();
+            frames.push(frame);
+            if is_end {
+                break;
+            }
+        }
+        assert_eq!(frames.len(), 3);
+        assert!(matches!(&frames[0], Frame::Headers(h) if !h.is_end_stream()));
+        assert!(matches!(&frames[1], Frame::Data(d) if !d.is_end_stream()));
+        let Some(Frame::Headers(trailers)) = frames.pop() else {
+            panic!("trailers must be headers frame");
+        };
+        assert!(trailers.is_end_stream());
+        let mut builder = trailers.into_response(Response::builder())?;
+        let fields = builder.headers_mut().unwrap();
+        assert_eq!(fields.get_str_value(&"grpc-status").unwrap(), "0");
+        assert_eq!(fields.get_str_value(&"grpc-message").unwrap(), "OK");
+        Ok(())
+    }
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, _req: RecvRequest) -> ProtResult<RecvResponse> {
+            Ok(grpc_response())
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    #[tokio::test]
+    async fn test_grpc_client_trailers() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let url = format!("http://{}/helloworld.Greeter/SayHello", addr);
+        let client = Client::builder()
+            .http2_only(true)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("POST")
+            .url(&*url)
+            .header("content-type", "application/grpc")
+            .header("te", "trailers")
+            .body(Body::empty())
+            .unwrap();
+        let mut res = client.send_now(req).await?;
+        let mut buffer = BinaryMut::new();
+        res.body_mut().read_all(&mut buffer).await;
+        assert_eq!(buffer.chunk(), b"\0\0\0\0\x05hello");
+        let trailers = res.body().trailers().unwrap();
+        assert_eq!(trailers.get_str_value(&"grpc-status").unwrap(), "0");
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 21:14:38
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use algorithm::buf::Binary;
+    use tokio::io::AsyncReadExt;
+    use tokio_stream::StreamExt;
+    use webparse::{
+        http::http2::frame::{Flag, Frame, FrameHeader, Headers, Kind, StreamIdentifier},
+        HeaderMap, Method,
+    };
+    use wmhttp::{http2::Codec, ProtResult};
+
+    fn headers(id: u32) -> Frame<Binary> {
+        let id = StreamIdentifier::from(id);
+        let mut flag = Flag::end_headers();
+        flag.set(Flag::end_stream(), true);
+        let header = FrameHeader::new(Kind::Headers, flag, id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "GET");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        fields.insert("x-custom", "value");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Get);
+        Frame::Headers(headers)
+    }
+
+    /// 读取一个完整的帧, 返回头部块的内容
+    async fn read_block(io: &mut tokio::io::DuplexStream) -> ProtResult<Vec<u8>> {
+        let mut head = [0u8; 9];
+        io.read_exact(&mut head).await?;
+        assert_eq!(head[3], 0x1);
+        let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+        let mut block = vec![0u8; len];
+        io.read_exact(&mut block).await?;
+        Ok(block)
+    }
+
+    #[tokio::test]
+    async fn test_table_size_update_bytes() -> ProtResult<()> {
+        let (client_io, mut server_io) = tokio::io::duplex(65_536);
+        let mut client = Codec::new(client_io);
+
+        // 缩小后再扩大, 下个头部块先通知最小值再通知最终值
+        client.set_send_header_table_size(0);
+        client.set_send_header_table_size(4096);
+        client.send_frame(headers(1))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+        let block = read_block(&mut server_io).await?;
+        assert_eq!(&block[..4], &[0x20, 0x3f, 0xe1, 0x1f]);
+
+        // 仅通知一次
+        client.send_frame(headers(3))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+        let block = read_block(&mut server_io).await?;
+        assert_ne!(block[0] & 0xe0, 0x20);
+
+        // 只缩小则仅通知新值
+        client.set_send_header_table_size(100);
+        client.send_frame(headers(5))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+        let block = read_block(&mut server_io).await?;
+        assert_eq!(&block[..2], &[0x3f, 0x45]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_size_update_decode() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let mut client = Codec::new(client_io);
+        let mut server = Codec::new(server_io);
+
+        // 对端按变化后的动态表正确解码
+        for (id, size) in [(1, 4096), (3, 0), (5, 4096), (7, 256)] {
+            client.set_send_header_table_size(size);
+            client.send_frame(headers(id))?;
+            poll_fn(|cx| client.poll_flush(cx)).await?;
+            match server.next().await.unwrap()? {
+                Frame::Headers(mut h) => {
+                    assert_eq!(
+                        h.headers_mut().get_str_value(&"x-custom"),
+                        Some("value".to_string())
+                    );
+                }
+                frame => panic!("unexpected frame {:?}", frame),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/15 09:46:12
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::task::Context;
+
+    use algorithm::buf::{Binary, BinaryMut, Bt};
+    use futures::task::noop_waker_ref;
+    use webparse::{
+        http::http2::frame::{Data, Flag, Frame, FrameHeader, Headers, Kind, StreamIdentifier},
+        HeaderMap, Method,
+    };
+    use wmhttp::{http2::InnerStream, ProtResult};
+
+    fn headers() -> Frame<Binary> {
+        let id = StreamIdentifier::from(1);
+        let header = FrameHeader::new(Kind::Headers, Flag::end_headers(), id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "POST");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Post);
+        Frame::Headers(headers)
+    }
+
+    fn empty_end_data() -> Frame<Binary> {
+        let id = StreamIdentifier::from(1);
+        let header = FrameHeader::new(Kind::Data, Flag::end_stream(), id);
+        Frame::Data(Data::new(header, Binary::new()))
+    }
+
+    #[tokio::test]
+    async fn test_empty_end_stream_data() -> ProtResult<()> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut stream = InnerStream::new(headers());
+        let (is_end, mut req) = stream.build_request()?;
+        assert!(!is_end);
+
+        // 单独的空数据帧结束该流
+        assert!(stream.poll_push(empty_end_data(), &mut cx)?);
+        assert!(stream.is_end());
+        let mut buffer = BinaryMut::new();
+        req.body_mut().read_all(&mut buffer).await;
+        assert_eq!(buffer.remaining(), 0);
+        assert!(req.body().is_end());
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_end_stream_after_body_dropped() -> ProtResult<()> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut stream = InnerStream::new(headers());
+        let (_, req) = stream.build_request()?;
+        drop(req);
+
+        // 包体已被丢弃, 流仍需正常结束
+        assert!(stream.poll_push(empty_end_data(), &mut cx)?);
+        assert!(stream.is_end());
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/14 14:36:09
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use tokio::io::AsyncWriteExt;
+    use tokio_stream::StreamExt;
+    use webparse::http::http2::frame::Frame;
+    use wmhttp::{
+        http2::{Codec, Direction, FrameAction},
+        ProtResult,
+    };
+
+    #[tokio::test]
+    async fn test_interceptor_drop_frame() -> ProtResult<()> {
+        let (client, server) = tokio::io::duplex(1024);
+        let mut codec = Codec::new(server);
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let count = dropped.clone();
+        codec.set_frame_interceptor(Some(Box::new(move |direction, frame: &mut Frame| {
+            // 丢弃收到的PING帧
+            if direction == Direction::Inbound && matches!(frame, Frame::Ping(_)) {
+                count.fetch_add(1, Ordering::Relaxed);
+                return FrameAction::Drop;
+            }
+            FrameAction::Continue
+        })));
+
+        let mut client = client;
+        // PING帧, 其后为空的SETTINGS帧
+        client
+            .write_all(&[0, 0, 8, 6, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8])
+            .await?;
+        client.write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+
+        let frame = codec.next().await.unwrap()?;
+        assert!(matches!(frame, Frame::Settings(_)));
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 19:20:36
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, time::Duration};
+
+    use tokio::io::{AsyncWriteExt, DuplexStream};
+    use tokio_stream::StreamExt;
+    use webparse::http::http2::frame::{Frame, Reason};
+    use wmhttp::{
+        http2::{Builder, Codec},
+        ProtResult, ServerH2Connection,
+    };
+
+    async fn connect() -> ProtResult<(ServerH2Connection<DuplexStream>, Codec<DuplexStream>)> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let builder = Builder::new()
+            .keep_alive_interval(Duration::from_millis(200))
+            .keep_alive_timeout(Duration::from_millis(200));
+        let server = ServerH2Connection::new(server_io, builder);
+
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client.get_mut().write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        Ok((server, client))
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_ping() -> ProtResult<()> {
+        let (mut server, mut client) = connect().await?;
+        tokio::spawn(async move {
+            let _ = server.incoming().await;
+        });
+
+        // 空闲的连接定时收到PING, 回复后连接保持
+        let mut pings = 0;
+        while pings < 3 {
+            let frame = tokio::time::timeout(Duration::from_secs(5), client.next())
+                .await
+                .expect("no keep alive ping")
+                .unwrap()?;
+            assert!(!matches!(frame, Frame::GoAway(_)));
+            if let Frame::Ping(ping) = frame {
+                assert!(!ping.is_ack());
+                pings += 1;
+                client.send_frame(Frame::Ping(ping.ret_pong()))?;
+                poll_fn(|cx| client.poll_flush(cx)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_timeout() -> ProtResult<()> {
+        let (mut server, mut client) = connect().await?;
+
+        // 从不回复PING, 超时后以GOAWAY关闭连接
+        let result = tokio::time::timeout(Duration::from_secs(5), server.incoming())
+            .await
+            .expect("dead connection not closed");
+        // 保活超时以超时错误结束连接
+        assert!(matches!(result, Err(e) if e.is_timeout().0));
+        let mut has_ping = false;
+        loop {
+            match client.next().await.unwrap()? {
+                Frame::Ping(_) => has_ping = true,
+                Frame::GoAway(goaway) => {
+                    assert_eq!(goaway.reason(), Reason::PROTOCOL_ERROR);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert!(has_ping);
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/12 10:18:33
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use algorithm::buf::{Binary, Bt};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use webparse::http::http2::frame::{
+        Data, Flag, Frame, FrameHeader, Kind, Priority, StreamDependency, StreamIdentifier,
+    };
+    use wmhttp::{
+        http2::{Builder, PriorityParam, PriorityQueue},
+        ProtResult, ServerH2Connection,
+    };
+
+    fn priority(id: u32, depend: u32, weight: u8) -> Priority {
+        Priority::new(
+            StreamIdentifier::from(id),
+            StreamDependency::new(StreamIdentifier::from(depend), weight, false),
+        )
+    }
+
+    #[test]
+    fn test_priority_ignored() {
+        let mut queue = PriorityQueue::new(65_535);
+        queue.priority_recv(priority(3, 1, 200));
+        assert_eq!(queue.weight(&StreamIdentifier::from(3)), 200);
+
+        // 不使用RFC7540优先级时忽略PRIORITY帧
+        let mut queue = PriorityQueue::new(65_535);
+        queue.set_no_priorities(true);
+        queue.priority_recv(priority(3, 1, 200));
+        assert!(queue.is_no_priorities());
+        assert_eq!(
+            queue.weight(&StreamIdentifier::from(3)),
+            PriorityParam::default().weight()
+        );
+        assert!(queue.hash_depend.is_empty());
+    }
+
+    #[test]
+    fn test_no_priorities_keeps_connection_weight() {
+        let mut queue = PriorityQueue::new(65_535);
+        queue.priority_recv(priority(3, 0, 200));
+        queue.set_no_priorities(true);
+        // 流0的控制帧仍然优先, 其它未指定的流为默认的紧急程度
+        assert_eq!(queue.weight(&StreamIdentifier::zero()), 255);
+        assert_eq!(
+            queue.weight(&StreamIdentifier::from(3)),
+            PriorityParam::default().weight()
+        );
+    }
+
+    fn first_payload(queue: &mut PriorityQueue) -> Vec<u8> {
+        match queue.send_queue.pop_first().unwrap().0.frame {
+            Frame::Data(d) => d.payload().chunk().to_vec(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_priority_frame_not_scheduled() {
+        // 已有Priority头的流不受PRIORITY帧影响
+        let mut queue = PriorityQueue::new(65_535);
+        queue.priority_header(StreamIdentifier::from(1), PriorityParam::parse("u=5"));
+        queue.priority_header(StreamIdentifier::from(3), PriorityParam::parse("u=1"));
+        queue.priority_recv(priority(1, 0, 255));
+        queue
+            .send_frames(StreamIdentifier::from(1), vec![data(1, b"low")])
+            .unwrap();
+        queue
+            .send_frames(StreamIdentifier::from(3), vec![data(3, b"high")])
+            .unwrap();
+        assert_eq!(first_payload(&mut queue), b"high");
+
+        // 不使用RFC7540优先级时PRIORITY帧不参与调度
+        let mut queue = PriorityQueue::new(65_535);
+        queue.set_no_priorities(true);
+        queue.priority_recv(priority(1, 0, 1));
+        queue.priority_header(StreamIdentifier::from(3), PriorityParam::parse("u=5"));
+        queue
+            .send_frames(StreamIdentifier::from(3), vec![data(3, b"low")])
+            .unwrap();
+        queue
+            .send_frames(StreamIdentifier::from(1), vec![data(1, b"default")])
+            .unwrap();
+        assert_eq!(first_payload(&mut queue), b"default");
+    }
+
+    #[tokio::test]
+    async fn test_no_priorities_advertised() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let builder = Builder::new().no_rfc7540_priorities(true);
+        let mut server = ServerH2Connection::new(server_io, builder);
+        let mut raw = client_io;
+        raw.write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n").await?;
+        raw.write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        let _ = tokio::time::timeout(Duration::from_millis(100), server.incoming()).await;
+
+        // 服务端的SETTINGS帧中带有SETTINGS_NO_RFC7540_PRIORITIES = 1
+        let mut head = [0u8; 9];
+        raw.read_exact(&mut head).await?;
+        assert_eq!(head[3], 4);
+        let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+        let mut payload = vec![0u8; len];
+        raw.read_exact(&mut payload).await?;
+        assert!(payload.chunks(6).any(|p| p == [0, 9, 0, 0, 0, 1]));
+        Ok(())
+    }
+
+    fn data(id: u32, payload: &'static [u8]) -> Frame<Binary> {
+        let header = FrameHeader::new(Kind::Data, Flag::zero(), StreamIdentifier::from(id));
+        Frame::Data(Data::new(header, Binary::from_static(payload)))
+    }
+
+    #[test]
+    fn test_priority_header_urgency() {
+        let param = PriorityParam::parse("u=1, i");
+        assert_eq!(param.urgency, 1);
+        assert!(param.incremental);
+        assert_eq!(PriorityParam::parse("i=?0"), PriorityParam::default());
+
+        let mut queue = PriorityQueue::new(65_535);
+        queue.priority_header(StreamIdentifier::from(1), PriorityParam::parse("u=5"));
+        queue.priority_header(StreamIdentifier::from(3), PriorityParam::parse("u=1"));
+        queue
+            .send_frames(StreamIdentifier::from(1), vec![data(1, b"low")])
+            .unwrap();
+        queue
+            .send_frames(StreamIdentifier::from(3), vec![data(3, b"high")])
+            .unwrap();
+
+        // 紧急程度高的流先发送
+        let first = queue.send_queue.pop_first().unwrap();
+        match first.0.frame {
+            Frame::Data(d) => assert_eq!(d.payload().chunk(), b"high"),
+            _ => unreachable!(),
+        }
+    }
+}
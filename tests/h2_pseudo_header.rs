@@ -0,0 +1,136 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 02:26:41
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, DuplexStream},
+        sync::mpsc::{channel, Receiver},
+    };
+    use wmhttp::{http2::Builder, ProtResult, ServerH2Connection};
+
+    const KIND_RST_STREAM: u8 = 0x3;
+    const PROTOCOL_ERROR: u32 = 0x1;
+
+    /// :method GET
+    const METHOD_GET: u8 = 0x82;
+    /// :scheme http
+    const SCHEME_HTTP: u8 = 0x86;
+    /// :path /
+    const PATH_ROOT: u8 = 0x84;
+
+    /// 不加入索引且头部名称为字面量的头部
+    fn literal(block: &mut Vec<u8>, name: &[u8], value: &[u8]) {
+        block.push(0x00);
+        block.push(name.len() as u8);
+        block.extend_from_slice(name);
+        block.push(value.len() as u8);
+        block.extend_from_slice(value);
+    }
+
+    fn raw_headers(stream_id: u32, block: &[u8]) -> Vec<u8> {
+        let mut data = (block.len() as u32).to_be_bytes()[1..].to_vec();
+        // END_STREAM | END_HEADERS
+        data.extend_from_slice(&[0x1, 0x5]);
+        data.extend_from_slice(&stream_id.to_be_bytes());
+        data.extend_from_slice(block);
+        data
+    }
+
+    async fn connect() -> ProtResult<(DuplexStream, Receiver<String>)> {
+        let (mut client, server_io) = tokio::io::duplex(65_536);
+        let mut server = ServerH2Connection::new(server_io, Builder::new());
+        client.write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n").await?;
+        client.write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        let (sender, receiver) = channel(10);
+        tokio::spawn(async move {
+            while let Ok(Some(req)) = server.incoming().await {
+                let _ = sender.send(req.path().to_string()).await;
+            }
+        });
+        Ok((client, receiver))
+    }
+
+    /// 读取直到收到RST_STREAM帧, 返回(流id, 错误码)
+    async fn read_reset(client: &mut DuplexStream) -> ProtResult<(u32, u32)> {
+        loop {
+            let mut head = [0u8; 9];
+            tokio::time::timeout(Duration::from_secs(5), client.read_exact(&mut head))
+                .await
+                .expect("stream reset not received")?;
+            let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+            let mut payload = vec![0u8; len];
+            client.read_exact(&mut payload).await?;
+            if head[3] == KIND_RST_STREAM {
+                let stream_id = u32::from_be_bytes([head[5], head[6], head[7], head[8]]);
+                let code = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+                return Ok((stream_id, code));
+            }
+        }
+    }
+
+    fn valid_request() -> Vec<u8> {
+        let mut block = vec![METHOD_GET, SCHEME_HTTP];
+        block.extend_from_slice(&[0x04, 0x03]);
+        block.extend_from_slice(b"/ok");
+        block
+    }
+
+    #[tokio::test]
+    async fn test_regular_header_before_path() -> ProtResult<()> {
+        let (mut client, mut receiver) = connect().await?;
+
+        // 普通头部出现在:path之前, 重置该流
+        let mut block = vec![METHOD_GET, SCHEME_HTTP];
+        literal(&mut block, b"x-test", b"1");
+        block.push(PATH_ROOT);
+        client.write_all(&raw_headers(1, &block)).await?;
+        assert_eq!(read_reset(&mut client).await?, (1, PROTOCOL_ERROR));
+
+        // 只重置该流, 连接上的其它请求正常处理
+        client.write_all(&raw_headers(3, &valid_request())).await?;
+        let path = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("request not received");
+        assert_eq!(path.as_deref(), Some("/ok"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_malformed_pseudo_headers() -> ProtResult<()> {
+        let (mut client, mut receiver) = connect().await?;
+
+        // 缺少:path
+        let missing = vec![METHOD_GET, SCHEME_HTTP];
+        // 重复的:path
+        let duplicate = vec![METHOD_GET, SCHEME_HTTP, PATH_ROOT, PATH_ROOT];
+        // 未知的伪头部
+        let mut unknown = vec![METHOD_GET, SCHEME_HTTP, PATH_ROOT];
+        literal(&mut unknown, b":foo", b"bar");
+
+        for (stream_id, block) in [(1, missing), (3, duplicate), (5, unknown)] {
+            client.write_all(&raw_headers(stream_id, &block)).await?;
+            assert_eq!(read_reset(&mut client).await?, (stream_id, PROTOCOL_ERROR));
+        }
+
+        client.write_all(&raw_headers(7, &valid_request())).await?;
+        let path = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("request not received");
+        assert_eq!(path.as_deref(), Some("/ok"));
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 18:46:09
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, time::Duration};
+
+    use algorithm::buf::Binary;
+    use tokio::io::AsyncWriteExt;
+    use tokio_stream::StreamExt;
+    use webparse::{
+        http::http2::frame::{Flag, Frame, FrameHeader, Headers, Kind, StreamIdentifier},
+        HeaderMap, Method, Response,
+    };
+    use wmhttp::{
+        http2::{Builder, Codec},
+        Body, ProtResult, SendControl, ServerH2Connection,
+    };
+
+    fn headers(id: u32) -> Frame<Binary> {
+        let id = StreamIdentifier::from(id);
+        let mut flag = Flag::end_headers();
+        flag.set(Flag::end_stream(), true);
+        let header = FrameHeader::new(Kind::Headers, flag, id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "GET");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Get);
+        Frame::Headers(headers)
+    }
+
+    /// 处理请求时尝试推送资源, 返回客户端收到的所有帧
+    async fn serve_with_push(settings: &[u8], builder: Builder) -> ProtResult<Vec<Frame<Binary>>> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let mut server = ServerH2Connection::new(server_io, builder);
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client.get_mut().write_all(settings).await?;
+        client.send_frame(headers(1))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+
+        let mut req = server.incoming().await?.unwrap();
+        let id = req.extensions_mut().remove::<StreamIdentifier>().unwrap();
+        let mut control = req.extensions_mut().remove::<SendControl>().unwrap();
+        let push = Response::builder()
+            .body(Body::new_text("pushed".to_string()))
+            .unwrap();
+        control.send_response(push).await?;
+        let res = Response::builder()
+            .body(Body::new_text("hello".to_string()))
+            .unwrap();
+        server.send_response(res, id).await?;
+        // 处理推送的响应并写出
+        let _ = tokio::time::timeout(Duration::from_millis(200), server.incoming()).await;
+        poll_fn(|cx| server.poll_write(cx)).await?;
+
+        let mut frames = vec![];
+        while let Ok(Some(frame)) =
+            tokio::time::timeout(Duration::from_millis(200), client.next()).await
+        {
+            frames.push(frame?);
+        }
+        Ok(frames)
+    }
+
+    fn check(frames: &[Frame<Binary>]) {
+        // 没有推送, 正常返回请求的响应
+        assert!(!frames.iter().any(|f| matches!(f, Frame::PushPromise(_))));
+        assert!(frames
+            .iter()
+            .any(|f| matches!(f, Frame::Headers(_)) && f.stream_id() == StreamIdentifier::from(1)));
+    }
+
+    #[tokio::test]
+    async fn test_client_disable_push() -> ProtResult<()> {
+        // SETTINGS_ENABLE_PUSH为0
+        let settings = [0, 0, 6, 4, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0];
+        check(&serve_with_push(&settings, Builder::new()).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_local_disable_push() -> ProtResult<()> {
+        let settings = [0, 0, 0, 4, 0, 0, 0, 0, 0];
+        check(&serve_with_push(&settings, Builder::new().enable_push(false)).await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/18 10:26:41
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, net::SocketAddr, time::Duration};
+
+    use algorithm::buf::Binary;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use tokio_stream::StreamExt;
+    use webparse::{
+        http::http2::frame::{
+            Data, Flag, Frame, FrameHeader, Headers, Kind, Reason, Reset, StreamIdentifier,
+        },
+        HeaderMap, Method, Request, Response,
+    };
+
+    use wmhttp::{
+        http2::{Builder, Codec},
+        Body, Client, Disconnected, ProtResult, ServerH2Connection,
+    };
+
+    /// 收到请求的HEADERS帧后以REFUSED_STREAM重置该流
+    async fn run_server() -> ProtResult<SocketAddr> {
+        let server = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        tokio::spawn(async move {
+            if let Ok((mut stream, _)) = server.accept().await {
+                let mut preface = [0u8; 24];
+                let _ = stream.read_exact(&mut preface).await;
+                // 空的SETTINGS帧及SETTINGS的ACK
+                let _ = stream.write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await;
+                let _ = stream.write_all(&[0, 0, 0, 4, 1, 0, 0, 0, 0]).await;
+                let mut head = [0u8; 9];
+                loop {
+                    if stream.read_exact(&mut head).await.is_err() {
+                        return;
+                    }
+                    let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+                    let mut payload = vec![0u8; len];
+                    let _ = stream.read_exact(&mut payload).await;
+                    if head[3] == 1 {
+                        break;
+                    }
+                }
+                let stream_id = [head[5] & 0x7F, head[6], head[7], head[8]];
+                // RST_STREAM帧, 错误码7为REFUSED_STREAM
+                let mut reset = vec![0, 0, 4, 3, 0];
+                reset.extend_from_slice(&stream_id);
+                reset.extend_from_slice(&[0, 0, 0, 7]);
+                let _ = stream.write_all(&reset).await;
+                let mut buf = vec![0u8; 4096];
+                while let Ok(n) = stream.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(addr)
+    }
+
+    #[tokio::test]
+    async fn test_client_stream_refused() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let url = format!("http://{}/", addr);
+        let client = Client::builder()
+            .http2_only(true)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .body(Body::empty())
+            .unwrap();
+        let err = client.send_now(req).await.unwrap_err();
+        assert_eq!(err.stream_reset_reason(), Some(Reason::REFUSED_STREAM));
+        assert_eq!(err.stream_reset_id(), Some(StreamIdentifier::from(1)));
+        // 服务端未处理该流, 可安全重试
+        assert!(err.is_retryable());
+        Ok(())
+    }
+
+    /// 收到流1及流3的请求后, 重置流3并回复流1
+    async fn run_partial_reset_server() -> ProtResult<SocketAddr> {
+        let server = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        tokio::spawn(async move {
+            if let Ok((mut stream, _)) = server.accept().await {
+                let mut preface = [0u8; 24];
+                let _ = stream.read_exact(&mut preface).await;
+                let _ = stream.write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await;
+                let _ = stream.write_all(&[0, 0, 0, 4, 1, 0, 0, 0, 0]).await;
+                let mut head = [0u8; 9];
+                let mut count = 0;
+                while count < 2 {
+                    if stream.read_exact(&mut head).await.is_err() {
+                        return;
+                    }
+                    let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+                    let mut payload = vec![0u8; len];
+                    let _ = stream.read_exact(&mut payload).await;
+                    if head[3] == 1 {
+                        count += 1;
+                    }
+                }
+                let _ = stream
+                    .write_all(&[0, 0, 4, 3, 0, 0, 0, 0, 3, 0, 0, 0, 7])
+                    .await;
+                // 静态表中的`:status: 200`, 带END_STREAM及END_HEADERS
+                let _ = stream.write_all(&[0, 0, 1, 1, 5, 0, 0, 0, 1, 0x88]).await;
+                let mut buf = vec![0u8; 4096];
+                while let Ok(n) = stream.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(addr)
+    }
+
+    #[tokio::test]
+    async fn test_handle_reset_routed_by_stream() -> ProtResult<()> {
+        let addr = run_partial_reset_server().await?;
+        let url = format!("http://{}/", addr);
+        let handle = Client::builder()
+            .http2_only(true)
+            .url(&*url)?
+            .connect()
+            .await?
+            .into_handle()?;
+        let build = || {
+            Request::builder()
+                .method("GET")
+                .url(&*url)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (a, b) = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(handle.send(build()), handle.send(build()))
+        })
+        .await
+        .expect("responses not received");
+
+        // 只有被重置的流收到错误, 另一个流正常收到响应
+        let (ok, err) = match (a, b) {
+            (Ok(res), Err(err)) | (Err(err), Ok(res)) => (res, err),
+            _ => unreachable!(),
+        };
+        assert_eq!(ok.status(), 200);
+        assert_eq!(err.stream_reset_id(), Some(StreamIdentifier::from(3)));
+        assert_eq!(err.stream_reset_reason(), Some(Reason::REFUSED_STREAM));
+        Ok(())
+    }
+
+    fn headers(id: u32) -> Frame<Binary> {
+        let id = StreamIdentifier::from(id);
+        let header = FrameHeader::new(Kind::Headers, Flag::end_headers(), id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "POST");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Post);
+        Frame::Headers(headers)
+    }
+
+    #[tokio::test]
+    async fn test_server_recv_reset() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let mut server = ServerH2Connection::new(server_io, Builder::new());
+        let metrics = server.stream_metrics();
+
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client.get_mut().write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        client.send_frame(headers(1))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+
+        let mut req = server.incoming().await?.unwrap();
+        let id = req.extensions_mut().remove::<StreamIdentifier>().unwrap();
+        let disconnected = req.extensions().get::<Disconnected>().unwrap().clone();
+        assert!(!disconnected.is_disconnected());
+
+        // 处理中的流被客户端取消, 之后再打开新的流
+        client.send_frame(Frame::Reset(Reset::new(id, Reason::CANCEL)))?;
+        client.send_frame(headers(3))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+        let mut next = server.incoming().await?.unwrap();
+        let next_id = next.extensions_mut().remove::<StreamIdentifier>().unwrap();
+
+        assert!(disconnected.is_disconnected());
+        let err = disconnected.error().unwrap();
+        assert_eq!(err.stream_reset_reason(), Some(Reason::CANCEL));
+        assert_eq!(err.stream_reset_id(), Some(id));
+        assert_eq!(server.remote_reset_count(), 1);
+        assert_eq!(metrics.current(), 1);
+
+        // 被重置的流的响应直接丢弃, 只发送新流的响应
+        for id in [id, next_id] {
+            let res = Response::builder().body(Body::empty()).unwrap();
+            server.send_response(res, id).await?;
+        }
+        poll_fn(|cx| server.poll_write(cx)).await?;
+        loop {
+            let frame = tokio::time::timeout(Duration::from_secs(5), client.next())
+                .await
+                .expect("response not sent")
+                .unwrap()?;
+            if let Frame::Headers(_) = &frame {
+                assert_eq!(frame.stream_id(), next_id);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_late_frames_after_reset() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let builder = Builder::new().stream_timeout(Duration::from_millis(200));
+        let mut server = ServerH2Connection::new(server_io, builder);
+
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client.get_mut().write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        client.send_frame(headers(1))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+        let mut req = server.incoming().await?.unwrap();
+        let id = req.extensions_mut().remove::<StreamIdentifier>().unwrap();
+
+        // 重置后对端仍在发送的DATA及trailer头
+        client.send_frame(Frame::Reset(Reset::new(id, Reason::CANCEL)))?;
+        let header = FrameHeader::new(Kind::Data, Flag::zero(), id);
+        client.send_frame(Frame::Data(Data::new(header, Binary::from_static(b"late"))))?;
+        let mut flag = Flag::end_headers();
+        flag.set(Flag::end_stream(), true);
+        let mut fields = HeaderMap::new();
+        fields.insert("x-trailer", "late");
+        let trailer = Headers::new(FrameHeader::new(Kind::Headers, flag, id), fields);
+        client.send_frame(Frame::Headers(trailer))?;
+        client.send_frame(headers(3))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+
+        // 迟到的trailer头不作为新的请求交给处理函数
+        let mut next = server.incoming().await?.unwrap();
+        let next_id = next.extensions_mut().remove::<StreamIdentifier>().unwrap();
+        assert_eq!(next_id, StreamIdentifier::from(3));
+
+        // 流3超时后被重置, 流1不再被重置
+        let _ = tokio::time::timeout(Duration::from_millis(500), server.incoming()).await;
+        let mut resets = vec![];
+        while let Ok(Some(frame)) =
+            tokio::time::timeout(Duration::from_millis(200), client.next()).await
+        {
+            if let Frame::Reset(v) = frame? {
+                resets.push(v.stream_id());
+            }
+        }
+        assert_eq!(resets, vec![next_id]);
+        Ok(())
+    }
+}
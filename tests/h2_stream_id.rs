@@ -0,0 +1,99 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 04:03:37
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use webparse::{http2::frame::StreamIdentifier, Request, Response};
+
+    use wmhttp::{self, Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(req.url().path.clone()))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    fn build(url: &str) -> RecvRequest {
+        Request::builder()
+            .method("GET")
+            .url(url)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sequential_stream_ids() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let url = format!("http://{}", addr);
+        let client = Client::builder()
+            .http2_only(true)
+            .url(&*url)?
+            .connect()
+            .await?;
+
+        let (mut recv, sender, stream_id) = client.send2(build(&url)).await?;
+        assert_eq!(stream_id, Some(StreamIdentifier::from(1)));
+        sender.send(build(&url)).await?;
+        sender.send(build(&url)).await?;
+
+        // 之后的请求依次分配递增的奇数流id, 响应中带有对应的流id
+        let mut ids = vec![];
+        for _ in 0..3 {
+            let res = tokio::time::timeout(Duration::from_secs(5), recv.recv())
+                .await
+                .unwrap()
+                .unwrap()?;
+            ids.push(res.extensions().get::<StreamIdentifier>().cloned().unwrap());
+        }
+        assert_eq!(ids.len(), 3);
+        for id in [1, 3, 5] {
+            assert!(ids.contains(&StreamIdentifier::from(id)));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_http1_without_stream_id() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let url = format!("http://{}", addr);
+        let client = Client::builder().url(&*url)?.connect().await?;
+
+        let (mut recv, stream_id) = client.send(build(&url)).await?;
+        assert_eq!(stream_id, None);
+        let res = tokio::time::timeout(Duration::from_secs(5), recv.recv())
+            .await
+            .unwrap()
+            .unwrap()?;
+        assert_eq!(res.status(), 200);
+        Ok(())
+    }
+}
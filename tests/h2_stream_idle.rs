@@ -0,0 +1,84 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 20:52:10
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, time::Duration};
+
+    use algorithm::buf::Binary;
+    use tokio::io::AsyncWriteExt;
+    use tokio_stream::StreamExt;
+    use webparse::{
+        http::http2::frame::{Flag, Frame, FrameHeader, Headers, Kind, Reason, StreamIdentifier},
+        HeaderMap, Method,
+    };
+    use wmhttp::{
+        http2::{Builder, Codec},
+        ProtResult, ServerH2Connection,
+    };
+
+    /// 声明有包体的请求头, 之后不再发送DATA
+    fn headers(id: u32) -> Frame<Binary> {
+        let id = StreamIdentifier::from(id);
+        let header = FrameHeader::new(Kind::Headers, Flag::end_headers(), id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "POST");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        fields.insert("content-length", "100");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Post);
+        Frame::Headers(headers)
+    }
+
+    #[tokio::test]
+    async fn test_idle_stream_reset() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let builder = Builder::new().stream_idle_timeout(Duration::from_millis(200));
+        let mut server = ServerH2Connection::new(server_io, builder);
+
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client.get_mut().write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        client.send_frame(headers(1))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+
+        let req = server.incoming().await?.unwrap();
+        let metrics = server.stream_metrics();
+        assert_eq!(metrics.current(), 1);
+        tokio::spawn(async move {
+            while let Ok(Some(_)) = server.incoming().await {}
+        });
+
+        // 包体长时间无数据, 该流被重置并释放并发数
+        let reset = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Frame::Reset(reset) = client.next().await.unwrap().unwrap() {
+                    return reset;
+                }
+            }
+        })
+        .await
+        .expect("idle stream not reset");
+        assert_eq!(reset.stream_id(), StreamIdentifier::from(1));
+        assert_eq!(reset.reason(), Reason::CANCEL);
+        assert_eq!(metrics.current(), 0);
+        drop(req);
+        Ok(())
+    }
+}
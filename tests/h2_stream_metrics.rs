@@ -0,0 +1,80 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/20 10:06:51
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use algorithm::buf::Binary;
+    use tokio::io::AsyncWriteExt;
+    use webparse::{
+        http::http2::frame::{Flag, Frame, FrameHeader, Headers, Kind, StreamIdentifier},
+        HeaderMap, Method, Response,
+    };
+    use wmhttp::{
+        http2::{Builder, Codec},
+        Body, ProtResult, ServerH2Connection,
+    };
+
+    fn headers(id: u32) -> Frame<Binary> {
+        let id = StreamIdentifier::from(id);
+        let header = FrameHeader::new(Kind::Headers, Flag::end_headers(), id);
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "GET");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Get);
+        Frame::Headers(headers)
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_stream_peak() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let mut server = ServerH2Connection::new(server_io, Builder::new());
+        let metrics = server.stream_metrics();
+
+        // 连接前言及空的SETTINGS帧, 之后同时打开3个流
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        client.get_mut().write_all(&[0, 0, 0, 4, 0, 0, 0, 0, 0]).await?;
+        for id in [1, 3, 5] {
+            client.send_frame(headers(id))?;
+        }
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+
+        let mut ids = vec![];
+        for _ in 0..3 {
+            let mut req = server.incoming().await?.unwrap();
+            ids.push(req.extensions_mut().remove::<StreamIdentifier>().unwrap());
+        }
+        assert_eq!(metrics.current(), 3);
+        assert_eq!(metrics.peak(), 3);
+
+        // 响应发送完毕后流结束, 峰值保留
+        for id in ids {
+            let res = Response::builder().body(Body::empty()).unwrap();
+            server.send_response(res, id).await?;
+        }
+        poll_fn(|cx| server.poll_write(cx)).await?;
+        assert_eq!(metrics.current(), 0);
+        assert_eq!(metrics.peak(), 3);
+        assert_eq!(metrics.total(), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/04 10:21:17
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        time::{Duration, Instant},
+    };
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use webparse::{
+        http::http2::frame::{Reason, StreamIdentifier},
+        Request, Response,
+    };
+
+    use wmhttp::{self, Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse, Server};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            if &*req.url().path == "/slow" {
+                tokio::time::sleep(Duration::from_secs(3)).await;
+            }
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(req.url().path.clone()))
+                .unwrap();
+            Ok(response)
+        }
+
+        fn fork(&self) -> Option<Box<dyn HttpTrait>> {
+            Some(Box::new(Operate))
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::serve(
+            |stream, addr| {
+                Server::builder()
+                    .addr(addr)
+                    .stream_timeout(Duration::from_millis(300))
+                    .stream(stream)
+            },
+            |server| server.set_callback_http(Box::new(Operate)),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_stream_timeout() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let url = format!("http://{}", addr);
+        let client = Client::builder()
+            .http2_only(true)
+            .url(&*url)?
+            .connect()
+            .await?;
+
+        let slow = Request::builder()
+            .method("GET")
+            .url(&*format!("{}/slow", url))
+            .body(Body::empty())
+            .unwrap();
+        let fast = Request::builder()
+            .method("GET")
+            .url(&*format!("{}/fast", url))
+            .body(Body::empty())
+            .unwrap();
+        let start = Instant::now();
+        let (mut recv, sender, _) = client.send2(slow).await?;
+        sender.send(fast).await?;
+
+        // 快的流不等待慢的流, 在慢的流超时前返回
+        let mut res = tokio::time::timeout(Duration::from_secs(2), recv.recv())
+            .await
+            .unwrap()
+            .unwrap()?;
+        assert!(start.elapsed() < Duration::from_millis(300));
+        let mut result = BinaryMut::new();
+        res.body_mut().read_all(&mut result).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(result.chunk(), b"/fast");
+
+        // 慢的流超时后被以CANCEL重置
+        let err = tokio::time::timeout(Duration::from_secs(2), recv.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(err.stream_reset_reason(), Some(Reason::CANCEL));
+        assert_eq!(err.stream_reset_id(), Some(StreamIdentifier::from(1)));
+        Ok(())
+    }
+}
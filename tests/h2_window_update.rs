@@ -0,0 +1,300 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/13 09:26:41
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap, future::poll_fn, net::SocketAddr, task::Context, time::Duration,
+    };
+
+    use algorithm::buf::{Binary, BinaryMut, Bt};
+    use async_trait::async_trait;
+    use futures::task::noop_waker_ref;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+    use webparse::{
+        http::http2::frame::{
+            Data, Flag, Frame, FrameHeader, Headers, Kind, StreamIdentifier, WindowUpdate,
+        },
+        HeaderMap, Method, Request, Response,
+    };
+
+    use wmhttp::{
+        http2::{Builder, Codec, FlowControl, PriorityQueue, RecvFlowControl},
+        Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse, ServerH2Connection,
+    };
+
+    use crate::common;
+
+    /// 超过默认窗口65535的包体大小
+    const BODY_SIZE: usize = 200_000;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, mut req: RecvRequest) -> ProtResult<RecvResponse> {
+            // 读取完整的请求包体, 再返回同样大小的响应包体
+            let mut buffer = BinaryMut::new();
+            req.body_mut().read_all(&mut buffer).await;
+            assert_eq!(buffer.remaining(), BODY_SIZE);
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text("b".repeat(BODY_SIZE)))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    #[tokio::test]
+    async fn test_body_larger_than_window() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let url = format!("http://{}/", addr);
+        let client = Client::builder()
+            .http2_only(true)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("POST")
+            .url(&*url)
+            .body(Body::new_text("a".repeat(BODY_SIZE)))
+            .unwrap();
+
+        // 双方都需依赖对端的WINDOW_UPDATE才能发送完超出初始窗口的数据
+        let mut res = tokio::time::timeout(Duration::from_secs(10), client.send_now(req))
+            .await
+            .expect("request body stalled")?;
+        assert_eq!(res.status(), 200);
+        let mut buffer = BinaryMut::new();
+        tokio::time::timeout(Duration::from_secs(10), res.body_mut().read_all(&mut buffer))
+            .await
+            .expect("response body stalled");
+        assert_eq!(buffer.remaining(), BODY_SIZE);
+        Ok(())
+    }
+
+    #[test]
+    fn test_send_window() {
+        let id = StreamIdentifier::from(1);
+        let mut flow = FlowControl::new(65_535);
+        assert_eq!(flow.stream_available(&id), 65_535);
+
+        // 数据同时扣减连接及流的窗口
+        flow.send_data(id, 65_535);
+        assert!(!flow.is_available());
+        assert_eq!(flow.stream_available(&id), 0);
+
+        // 只更新流窗口仍受连接窗口限制
+        assert!(flow.window_update(id, 1_000).is_ok());
+        assert_eq!(flow.stream_available(&id), 0);
+        assert!(flow.window_update(StreamIdentifier::zero(), 500).is_ok());
+        assert_eq!(flow.stream_available(&id), 500);
+
+        // 对端调整初始窗口按差值作用于已有的流
+        assert!(flow.set_initial_window_size(65_535 + 200).is_ok());
+        assert!(flow.window_update(StreamIdentifier::zero(), 10_000).is_ok());
+        assert_eq!(flow.stream_available(&id), 1_200);
+
+        // 窗口超过2^31-1为流量控制错误
+        assert!(flow.window_update(id, i32::MAX as u32).is_err());
+        assert!(flow.set_initial_window_size(i32::MAX as u32).is_err());
+        assert_eq!(flow.stream_available(&id), 1_200);
+    }
+
+    fn data(id: u32, size: usize) -> Frame<Binary> {
+        let header = FrameHeader::new(Kind::Data, Flag::end_stream(), StreamIdentifier::from(id));
+        Frame::Data(Data::new(header, Binary::from(vec![b'a'; size])))
+    }
+
+    /// 发送队列中的数据写出后, 统计每个流收到的DATA大小
+    async fn flush_data(
+        queue: &mut PriorityQueue,
+        codec: &mut Codec<DuplexStream>,
+        raw: &mut DuplexStream,
+    ) -> ProtResult<HashMap<u32, usize>> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let _ = queue.poll_handle(&mut cx, codec);
+        let _ = codec.poll_flush(&mut cx);
+        let mut buf = vec![0u8; 1_000_000];
+        let n = tokio::time::timeout(Duration::from_secs(5), raw.read(&mut buf))
+            .await
+            .expect("no frames")?;
+        let mut result = HashMap::new();
+        let mut data = &buf[..n];
+        while data.len() >= 9 {
+            let len = u32::from_be_bytes([0, data[0], data[1], data[2]]) as usize;
+            let id = u32::from_be_bytes([data[5] & 0x7F, data[6], data[7], data[8]]);
+            if data[3] == 0 {
+                *result.entry(id).or_default() += len;
+            }
+            data = &data[9 + len..];
+        }
+        Ok(result)
+    }
+
+    #[tokio::test]
+    async fn test_blocked_stream_does_not_block_others() -> ProtResult<()> {
+        let (io, mut raw) = tokio::io::duplex(1_000_000);
+        let mut codec = Codec::new(io);
+        let mut queue = PriorityQueue::new(65_535);
+        // 连接窗口足够, 只有流1的窗口不足
+        assert!(queue
+            .window_update(StreamIdentifier::zero(), 100_000)
+            .is_ok());
+        queue.send_frames(StreamIdentifier::from(1), vec![data(1, 70_000)])?;
+        queue.send_frames(StreamIdentifier::from(3), vec![data(3, 100)])?;
+
+        let sent = flush_data(&mut queue, &mut codec, &mut raw).await?;
+        assert_eq!(sent.get(&1), Some(&65_535));
+        assert_eq!(sent.get(&3), Some(&100));
+        assert!(queue.is_blocked());
+
+        // 流1的窗口更新后发送剩余部分
+        assert!(queue
+            .window_update(StreamIdentifier::from(1), 10_000)
+            .is_ok());
+        let sent = flush_data(&mut queue, &mut codec, &mut raw).await?;
+        assert_eq!(sent.get(&1), Some(&4_465));
+        assert!(!queue.is_blocked());
+        assert!(queue.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reset_stream_removed_from_queue() -> ProtResult<()> {
+        let (io, mut raw) = tokio::io::duplex(1_000_000);
+        let mut codec = Codec::new(io);
+        let mut queue = PriorityQueue::new(65_535);
+        queue.send_frames(StreamIdentifier::from(1), vec![data(1, 70_000)])?;
+        let sent = flush_data(&mut queue, &mut codec, &mut raw).await?;
+        assert_eq!(sent.get(&1), Some(&65_535));
+        assert!(queue.is_blocked());
+
+        // 流1被重置后, 等待窗口及排队中的数据均被丢弃
+        assert!(queue
+            .window_update(StreamIdentifier::zero(), 100_000)
+            .is_ok());
+        queue.send_frames(StreamIdentifier::from(1), vec![data(1, 100)])?;
+        queue.send_frames(StreamIdentifier::from(3), vec![data(3, 100)])?;
+        queue.remove_stream(&StreamIdentifier::from(1));
+        assert!(!queue.is_blocked());
+        assert_eq!(queue.buffered_size(), 100);
+
+        let sent = flush_data(&mut queue, &mut codec, &mut raw).await?;
+        assert_eq!(sent.get(&1), None);
+        assert_eq!(sent.get(&3), Some(&100));
+        assert!(queue.is_empty());
+        assert_eq!(queue.buffered_size(), 0);
+        Ok(())
+    }
+
+    fn headers(id: u32) -> Frame<Binary> {
+        let header = FrameHeader::new(
+            Kind::Headers,
+            Flag::end_headers(),
+            StreamIdentifier::from(id),
+        );
+        let mut fields = HeaderMap::new();
+        fields.insert(":method", "POST");
+        fields.insert(":path", "/");
+        fields.insert(":scheme", "http");
+        fields.insert(":authority", "localhost");
+        let mut headers = Headers::new(header, fields);
+        headers.set_method(Method::Post);
+        Frame::Headers(headers)
+    }
+
+    #[tokio::test]
+    async fn test_wait_buffer_ready_reads_window_update() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(1_000_000);
+        let builder = Builder::new().max_buffered_size(1024);
+        let mut server = ServerH2Connection::new(server_io, builder);
+        let mut client = Codec::new(client_io);
+        client
+            .get_mut()
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await?;
+        // SETTINGS_INITIAL_WINDOW_SIZE为0, 服务端的DATA均需等待WINDOW_UPDATE
+        client
+            .get_mut()
+            .write_all(&[0, 0, 6, 4, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0])
+            .await?;
+        client.send_frame(headers(1))?;
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+        let mut req = server.incoming().await?.unwrap();
+        let id = req.extensions_mut().remove::<StreamIdentifier>().unwrap();
+
+        let res = Response::builder()
+            .body(Body::new_text("b".repeat(BODY_SIZE)))
+            .unwrap();
+        server.send_response(res, id).await?;
+        tokio::time::timeout(Duration::from_secs(5), poll_fn(|cx| server.poll_write(cx)))
+            .await
+            .expect("frames not written")?;
+        // 窗口已满, 缓存的数据无法写出
+        let wait = tokio::time::timeout(Duration::from_millis(100), server.wait_buffer_ready());
+        assert!(wait.await.is_err());
+
+        for id in [StreamIdentifier::zero(), id] {
+            let update = WindowUpdate::new(id, BODY_SIZE as u32);
+            client.send_frame(Frame::WindowUpdate(update))?;
+        }
+        poll_fn(|cx| client.poll_flush(cx)).await?;
+        tokio::time::timeout(Duration::from_secs(5), server.wait_buffer_ready())
+            .await
+            .expect("window update not read")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_update_coalesced() {
+        // 默认半个窗口才发送一次更新
+        let mut flow = RecvFlowControl::new(65_535, 0.5);
+        assert_eq!(flow.threshold(), 32_767);
+        let updates: Vec<u32> = (0..10).filter_map(|_| flow.recv_data(16_384)).collect();
+        assert_eq!(updates, vec![32_768, 32_768, 32_768, 32_768, 32_768]);
+
+        // 阈值越小更新越频繁
+        let mut flow = RecvFlowControl::new(65_535, 0.1);
+        let count = (0..10).filter_map(|_| flow.recv_data(16_384)).count();
+        assert_eq!(count, 10);
+
+        let mut flow = RecvFlowControl::new(65_535, 1.0);
+        let updates: Vec<u32> = (0..10).filter_map(|_| flow.recv_data(16_384)).collect();
+        assert_eq!(updates, vec![65_536, 65_536]);
+    }
+
+    #[test]
+    fn test_window_update_threshold_clamped() {
+        let builder = Builder::new().window_update_threshold(0.25);
+        assert_eq!(builder.window_update_threshold, 0.25);
+        let builder = Builder::new().window_update_threshold(2.0);
+        assert_eq!(builder.window_update_threshold, 1.0);
+        let builder = Builder::new().window_update_threshold(f32::NAN);
+        assert_eq!(builder.window_update_threshold, 0.5);
+
+        // 比例为0时每次收到数据都更新
+        let builder = Builder::new().window_update_threshold(-1.0);
+        assert_eq!(builder.window_update_threshold, 0.0);
+        let mut flow = RecvFlowControl::new(65_535, builder.window_update_threshold);
+        assert_eq!(flow.threshold(), 1);
+        assert_eq!(flow.recv_data(1), Some(1));
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/13 15:32:18
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use wmhttp::{self, HttpTrait, ProtError, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate {
+        close_on_error: bool,
+    }
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, _req: RecvRequest) -> ProtResult<RecvResponse> {
+            Err(ProtError::Extension("operate failed"))
+        }
+
+        fn is_close_on_error(&self, _err: &ProtError) -> bool {
+            self.close_on_error
+        }
+    }
+
+    async fn run_server(close_on_error: bool) -> ProtResult<SocketAddr> {
+        common::run_server(move || Box::new(Operate { close_on_error }), |_| {}).await
+    }
+
+    #[tokio::test]
+    async fn test_handler_error_500() -> ProtResult<()> {
+        let addr = run_server(false).await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        let text = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+        assert!(text.starts_with("http/1.1 500"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_handler_error_close() -> ProtResult<()> {
+        let addr = run_server(true).await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await?;
+        let mut buf = vec![0u8; 1024];
+        // 直接关闭连接, 不返回任何数据
+        let n = stream.read(&mut buf).await.unwrap_or(0);
+        assert_eq!(n, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/19 17:20:45
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use webparse::HeaderMap;
+    use wmhttp::HeaderHelper;
+
+    fn pair(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_set_cookie_not_folded() {
+        let headers = vec![
+            pair("set-cookie", "a=1; Expires=Wed, 21 Oct 2026 07:28:00 GMT"),
+            pair("vary", "Accept-Encoding"),
+            pair("Set-Cookie", "b=2; Path=/"),
+            pair("VARY", "accept-encoding, Origin"),
+            pair("set-cookie", "a=1; Expires=Wed, 21 Oct 2026 07:28:00 GMT"),
+        ];
+        let folded = HeaderHelper::fold_headers(headers);
+        // Set-Cookie逐个保留且不拆分值中的逗号, 其它列表头合并去重
+        assert_eq!(
+            folded,
+            vec![
+                pair("Set-Cookie", "a=1; Expires=Wed, 21 Oct 2026 07:28:00 GMT"),
+                pair("Vary", "Accept-Encoding, accept-encoding, Origin"),
+                pair("Set-Cookie", "b=2; Path=/"),
+                pair("Set-Cookie", "a=1; Expires=Wed, 21 Oct 2026 07:28:00 GMT"),
+            ]
+        );
+
+        // 再次处理结果不变
+        assert_eq!(HeaderHelper::fold_headers(folded.clone()), folded);
+        assert!(!HeaderHelper::is_foldable("SET-COOKIE"));
+        assert!(HeaderHelper::is_foldable("Cache-Control"));
+        assert_eq!(
+            HeaderHelper::split_list("a, \"b,c\" ,, d"),
+            vec!["a", "\"b,c\"", "d"]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_singleton() {
+        // 值相同的重复Content-Length只保留一个
+        let same = vec![pair("Content-Length", "5"), pair("content-length", " 5")];
+        assert!(HeaderHelper::check_singletons(&same).is_ok());
+        assert_eq!(
+            HeaderHelper::fold_headers(same),
+            vec![pair("Content-Length", "5")]
+        );
+
+        // 值不同则拒绝
+        let conflict = vec![pair("Content-Length", "5"), pair("Content-Length", "6")];
+        let err = HeaderHelper::check_singletons(&conflict).unwrap_err();
+        assert_eq!(err.status_code(), Some(400));
+        assert!(HeaderHelper::is_singleton("HOST"));
+        assert!(!HeaderHelper::is_singleton("Vary"));
+    }
+
+    #[test]
+    fn test_fold_header_map() {
+        let mut headers = HeaderMap::new();
+        headers.push("Set-Cookie", "a=1; Path=/");
+        headers.push("Set-Cookie", "b=2; Path=/");
+        headers.push("Vary", "Accept-Encoding");
+        headers.push("Vary", "Origin");
+        HeaderHelper::fold_header_map(&mut headers).unwrap();
+        let mut list: Vec<(String, String)> = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        list.sort();
+        assert_eq!(
+            list,
+            vec![
+                pair("Set-Cookie", "a=1; Path=/"),
+                pair("Set-Cookie", "b=2; Path=/"),
+                pair("Vary", "Accept-Encoding, Origin"),
+            ]
+        );
+
+        headers.push("Content-Length", "5");
+        headers.push("Content-Length", "6");
+        assert!(HeaderHelper::fold_header_map(&mut headers).is_err());
+    }
+}
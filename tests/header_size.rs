@@ -0,0 +1,123 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 01:14:57
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+    use webparse::{Request, Response};
+
+    use wmhttp::{self, Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::empty())
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(
+            || Box::new(Operate),
+            |server| server.set_max_header_size(4096),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_request_header_too_large() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 不断发送请求头而不结束
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n").await?;
+        for i in 0..100 {
+            let line = format!("X-Header-{}: {}\r\n", i, "a".repeat(100));
+            if stream.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+        let mut buf = vec![];
+        let _ = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut buf))
+            .await
+            .expect("connection not closed");
+        assert!(buf.starts_with(b"HTTP/1.1 431"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_header_within_limit() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 请求头在限制内, 之后的包体不计入
+        let body = "a".repeat(8192);
+        let req = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(req.as_bytes()).await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_response_header_too_large() -> ProtResult<()> {
+        let server = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        // 上游返回不结束的响应头
+        tokio::spawn(async move {
+            if let Ok((mut stream, _)) = server.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n").await;
+                let line = format!("X-Header: {}\r\n", "a".repeat(1024));
+                while stream.write_all(line.as_bytes()).await.is_ok() {}
+            }
+        });
+
+        let url = format!("http://{}/", addr);
+        let client = Client::builder()
+            .http2(false)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .body(Body::empty())
+            .unwrap();
+        let result = tokio::time::timeout(Duration::from_secs(5), client.send_now(req))
+            .await
+            .expect("client not stopped");
+        assert!(result.is_err());
+        Ok(())
+    }
+}
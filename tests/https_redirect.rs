@@ -0,0 +1,83 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/15 17:42:15
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{
+        self, Body, HttpTrait, HttpsRedirectMiddleware, ProtResult, RecvRequest, RecvResponse,
+    };
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::empty())
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(
+            || Box::new(Operate),
+            |server| server.middle(HttpsRedirectMiddleware::new().port(8443)),
+        )
+        .await
+    }
+
+    async fn request(addr: SocketAddr, data: &[u8]) -> ProtResult<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(data).await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        Ok(String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase())
+    }
+
+    #[tokio::test]
+    async fn test_https_redirect() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let text = request(
+            addr,
+            b"GET /path?a=1 HTTP/1.1\r\nHost: example.com:8080\r\n\r\n",
+        )
+        .await?;
+        assert!(text.starts_with("http/1.1 301"));
+        assert!(text.contains("location: https://example.com:8443/path?a=1"));
+
+        // 非安全方法不做重定向
+        let text = request(
+            addr,
+            b"POST /path HTTP/1.1\r\nHost: example.com\r\nContent-Length: 0\r\n\r\n",
+        )
+        .await?;
+        assert!(text.starts_with("http/1.1 200"));
+        Ok(())
+    }
+}
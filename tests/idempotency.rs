@@ -0,0 +1,186 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 23:24:13
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{
+        self, Body, HttpTrait, IdempotencyMiddleware, ProtResult, RecvRequest, RecvResponse,
+    };
+
+    use crate::common;
+
+    struct Operate {
+        count: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let count = self.count.fetch_add(1, Ordering::SeqCst) + 1;
+            // 处理较慢, 重复的请求在处理期间到达
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let content_type = if req.path() == "/events" {
+                "text/event-stream"
+            } else {
+                "text/plain"
+            };
+            let response = Response::builder()
+                .version(req.version().clone())
+                .header("Content-Type", content_type)
+                .body(Body::new_text(format!("count={}", count)))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server(count: Arc<AtomicUsize>) -> ProtResult<SocketAddr> {
+        run_server_with(count, IdempotencyMiddleware::new(Duration::from_secs(60))).await
+    }
+
+    async fn run_server_with(
+        count: Arc<AtomicUsize>,
+        idempotency: IdempotencyMiddleware,
+    ) -> ProtResult<SocketAddr> {
+        common::run_server(
+            move || {
+                Box::new(Operate {
+                    count: count.clone(),
+                })
+            },
+            move |server| server.middle(idempotency.clone()),
+        )
+        .await
+    }
+
+    async fn send(addr: SocketAddr, key: &str) -> ProtResult<String> {
+        send_body(addr, "/pay", key, "").await
+    }
+
+    async fn send_body(addr: SocketAddr, path: &str, key: &str, body: &str) -> ProtResult<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        let req = format!(
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nIdempotency-Key: {}\r\nContent-Length: {}\r\n\r\n{}",
+            path,
+            key,
+            body.len(),
+            body
+        );
+        stream.write_all(req.as_bytes()).await?;
+        let mut buf = vec![];
+        let mut cache = vec![0u8; 1024];
+        while !String::from_utf8_lossy(&buf).contains("count=")
+            && !String::from_utf8_lossy(&buf).contains("payload")
+        {
+            let n = stream.read(&mut cache).await?;
+            assert!(n > 0, "connection closed early");
+            buf.extend_from_slice(&cache[..n]);
+        }
+        Ok(String::from_utf8_lossy(&buf).to_ascii_lowercase())
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_duplicate_keys() -> ProtResult<()> {
+        let count = Arc::new(AtomicUsize::new(0));
+        let addr = run_server(count.clone()).await?;
+
+        // 并发的重复请求等待首个请求完成, 只处理一次
+        let (first, second) = tokio::join!(send(addr, "order-1"), send(addr, "order-1"));
+        let (first, second) = (first?, second?);
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert!(first.ends_with("count=1"));
+        assert!(second.ends_with("count=1"));
+        assert_eq!(
+            [&first, &second]
+                .iter()
+                .filter(|t| t.contains("idempotent-replayed: true"))
+                .count(),
+            1
+        );
+
+        // 之后的重复请求直接返回保存的响应
+        let third = send(addr, "order-1").await?;
+        assert!(third.ends_with("count=1"));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // 不同的键正常处理
+        let other = send(addr, "order-2").await?;
+        assert!(other.ends_with("count=2"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_key_reused_with_different_payload() -> ProtResult<()> {
+        let count = Arc::new(AtomicUsize::new(0));
+        let addr = run_server(count.clone()).await?;
+
+        let first = send_body(addr, "/pay", "order-1", "amount=10").await?;
+        assert!(first.ends_with("count=1"));
+        let same = send_body(addr, "/pay", "order-1", "amount=10").await?;
+        assert!(same.ends_with("count=1"));
+        assert!(same.contains("idempotent-replayed: true"));
+
+        // 同一个键包体不同, 不处理也不返回保存的响应
+        let other = send_body(addr, "/pay", "order-1", "amount=99").await?;
+        assert!(other.starts_with("http/1.1 422"));
+        assert!(!other.contains("count="));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_not_stored() -> ProtResult<()> {
+        let count = Arc::new(AtomicUsize::new(0));
+        let addr = run_server(count.clone()).await?;
+
+        // 事件流的响应不保存, 重复的请求再次处理
+        let first = send_body(addr, "/events", "stream-1", "").await?;
+        assert!(first.ends_with("count=1"));
+        let second = send_body(addr, "/events", "stream-1", "").await?;
+        assert!(second.ends_with("count=2"));
+        assert!(!second.contains("idempotent-replayed"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_large_body_not_stored() -> ProtResult<()> {
+        let count = Arc::new(AtomicUsize::new(0));
+        let idempotency = IdempotencyMiddleware::new(Duration::from_secs(60)).max_body_size(4);
+        let addr = run_server_with(count.clone(), idempotency).await?;
+
+        // 响应包体超出大小限制, 不保存
+        let first = send(addr, "order-1").await?;
+        assert!(first.ends_with("count=1"));
+        let second = send(addr, "order-1").await?;
+        assert!(second.ends_with("count=2"));
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/18 14:05:37
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpTrait, ProtResult, RecvRequest, RecvResponse, Server};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, _req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .body(Body::new_text("Hello World".to_string()))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::serve(
+            |stream, addr| {
+                Server::builder()
+                    .addr(addr)
+                    .keep_alive(false)
+                    .stream(stream)
+            },
+            |server| server.set_callback_http(Box::new(Operate)),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_disabled() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: keep-alive\r\n\r\n")
+            .await?;
+
+        // 客户端要求keep-alive, 服务端仍在响应后关闭连接
+        let mut buf = vec![];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut buf))
+            .await
+            .expect("connection not closed")?;
+        let text = String::from_utf8_lossy(&buf).to_ascii_lowercase();
+        assert!(text.starts_with("http/1.1 200"));
+        assert!(text.contains("connection: close"));
+        assert!(text.ends_with("hello world"));
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/14 16:32:50
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use algorithm::buf::{Binary, Bt};
+    use tokio::io::AsyncReadExt;
+    use wmhttp::{LengthDelimited, ProtResult};
+
+    #[tokio::test]
+    async fn test_length_delimited_round_trip() -> ProtResult<()> {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let mut writer = LengthDelimited::new(client);
+        let mut reader = LengthDelimited::new(server);
+
+        writer.write_message(Binary::from_static(b"hello")).await?;
+        writer.write_message(Binary::new()).await?;
+        writer.write_message(Binary::from(vec![9u8; 4096])).await?;
+
+        assert_eq!(reader.read_message().await?.unwrap().chunk(), b"hello");
+        assert_eq!(reader.read_message().await?.unwrap().remaining(), 0);
+        assert_eq!(
+            reader.read_message().await?.unwrap().chunk(),
+            &vec![9u8; 4096][..]
+        );
+
+        // 4字节大端的长度前缀
+        writer.write_message(Binary::from_static(b"abc")).await?;
+        let mut raw = [0u8; 7];
+        reader.get_mut().read_exact(&mut raw).await?;
+        assert_eq!(&raw, &[0, 0, 0, 3, b'a', b'b', b'c']);
+
+        drop(writer);
+        assert!(reader.read_message().await?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/19 15:02:33
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpStream;
+    use tokio_stream::StreamExt;
+    use wmhttp::ProtResult;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_listen_reuse_port() -> ProtResult<()> {
+        let first = wmhttp::listen("127.0.0.1:0".parse().unwrap())
+            .reuse_port(true)
+            .backlog(128)
+            .bind()?;
+        let addr = first.local_addr()?;
+        // 开启SO_REUSEPORT后第二个监听可绑定同一地址
+        let second = wmhttp::listen(addr).reuse_port(true).bind()?;
+        assert_eq!(second.local_addr()?, addr);
+
+        // 未开启时绑定失败
+        assert!(wmhttp::listen(addr).bind().is_err());
+
+        let _stream = TcpStream::connect(addr).await?;
+        let mut first = first;
+        let mut second = second;
+        let accepted = tokio::select! {
+            v = first.next() => v,
+            v = second.next() => v,
+        };
+        let (_, peer) = accepted.unwrap()?;
+        assert!(peer.ip().is_loopback());
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 17:58:20
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::{Request, Response};
+
+    use wmhttp::{self, Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            if req.url().path == "/slow" {
+                tokio::time::sleep(Duration::from_millis(400)).await;
+            }
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text("hello".to_string()))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server(age: Duration, grace: Option<Duration>) -> ProtResult<SocketAddr> {
+        common::run_server(
+            || Box::new(Operate),
+            move |server| {
+                server.set_max_connection_age(Some(age));
+                server.set_max_connection_age_grace(grace);
+            },
+        )
+        .await
+    }
+
+    async fn read_text(stream: &mut TcpStream) -> ProtResult<String> {
+        let mut buf = vec![0u8; 1024];
+        let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+            .await
+            .expect("connection not recycled")?;
+        Ok(String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase())
+    }
+
+    #[tokio::test]
+    async fn test_idle_connection_recycled() -> ProtResult<()> {
+        let addr = run_server(Duration::from_millis(200), None).await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await?;
+        let text = read_text(&mut stream).await?;
+        assert!(text.starts_with("http/1.1 200"));
+        assert!(!text.contains("connection: close"));
+
+        // 空闲的keep-alive连接到达存活时间后被关闭
+        assert_eq!(read_text(&mut stream).await?, "");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_after_max_age() -> ProtResult<()> {
+        let addr = run_server(Duration::from_millis(200), Some(Duration::from_secs(2))).await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        // 等待期内的请求仍被处理, 但响应后关闭连接
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await?;
+        let text = read_text(&mut stream).await?;
+        assert!(text.starts_with("http/1.1 200"));
+        assert!(text.contains("connection: close"));
+        assert_eq!(read_text(&mut stream).await?, "");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_h2_stream_finished_in_grace() -> ProtResult<()> {
+        let addr = run_server(Duration::from_millis(200), Some(Duration::from_secs(2))).await?;
+        let url = format!("http://{}/slow", addr);
+        let client = Client::builder()
+            .http2_only(true)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .body(Body::empty())
+            .unwrap();
+
+        // 处理中的流跨过存活时间, 在等待时长内仍正常完成
+        let res = tokio::time::timeout(Duration::from_secs(5), client.send_now(req))
+            .await
+            .expect("response not received")?;
+        assert_eq!(res.status(), 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_handle_recycled() -> ProtResult<()> {
+        // 服务端的存活时间足够长, 由客户端主动关闭
+        let addr = run_server(Duration::from_secs(60), None).await?;
+        let url = format!("http://{}/", addr);
+        let handle = Client::builder()
+            .http2_only(true)
+            .max_connection_age(Duration::from_millis(200))
+            .url(&*url)?
+            .connect()
+            .await?
+            .into_handle()?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .body(Body::empty())
+            .unwrap();
+        let res = handle.send(req).await?;
+        assert_eq!(res.status(), 200);
+
+        // 到达存活时间后句柄关闭, 由调用方重新建立连接
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(handle.is_closed());
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 04:11:52
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{
+        self, Body, HttpTrait, Middleware, OverloadLayer, ProtResult, RecvRequest, RecvResponse,
+        Server,
+    };
+
+    use crate::common;
+
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text("ok".to_string()))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    /// 记录调用顺序的中间件
+    struct Recorder {
+        name: &'static str,
+        log: Log,
+    }
+
+    #[async_trait]
+    impl Middleware for Recorder {
+        async fn process_request(
+            &mut self,
+            _request: &mut RecvRequest,
+        ) -> ProtResult<Option<RecvResponse>> {
+            self.log.lock().unwrap().push(format!("req {}", self.name));
+            Ok(None)
+        }
+
+        async fn process_response(&mut self, _response: &mut RecvResponse) -> ProtResult<()> {
+            self.log.lock().unwrap().push(format!("res {}", self.name));
+            Ok(())
+        }
+    }
+
+    /// 未带Authorization的请求直接回复401
+    struct Auth {
+        log: Log,
+    }
+
+    #[async_trait]
+    impl Middleware for Auth {
+        async fn process_request(
+            &mut self,
+            request: &mut RecvRequest,
+        ) -> ProtResult<Option<RecvResponse>> {
+            self.log.lock().unwrap().push("req auth".to_string());
+            if request.headers().get_str_value(&"Authorization").is_some() {
+                return Ok(None);
+            }
+            let response = Response::builder()
+                .status(401)
+                .header("WWW-Authenticate", "Basic")
+                .body(Body::new_text("unauthorized".to_string()))
+                .unwrap();
+            Ok(Some(response))
+        }
+
+        async fn process_response(&mut self, _response: &mut RecvResponse) -> ProtResult<()> {
+            self.log.lock().unwrap().push("res auth".to_string());
+            Ok(())
+        }
+    }
+
+    async fn run_server(log: Log, overload: Option<OverloadLayer>) -> ProtResult<SocketAddr> {
+        common::serve(
+            move |stream, addr| {
+                Server::builder()
+                    .addr(addr)
+                    .overload_layer(overload.clone())
+                    .stream(stream)
+            },
+            move |server| {
+                server.middle(Recorder {
+                    name: "outer",
+                    log: log.clone(),
+                });
+                server.middle(Auth { log: log.clone() });
+                server.middle(Recorder {
+                    name: "inner",
+                    log: log.clone(),
+                });
+                server.set_callback_http(Box::new(Operate));
+            },
+        )
+        .await
+    }
+
+    async fn request(addr: SocketAddr, data: &[u8]) -> ProtResult<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(data).await?;
+        let mut buf = vec![0u8; 1024];
+        let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+            .await
+            .expect("response not received")?;
+        Ok(String::from_utf8_lossy(&buf[..n]).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_short_circuit() -> ProtResult<()> {
+        let log = Log::default();
+        let addr = run_server(log.clone(), None).await?;
+        let text = request(addr, b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await?;
+        assert!(text.starts_with("HTTP/1.1 401"));
+        assert!(text.ends_with("unauthorized"));
+
+        // 之后的中间件及处理函数不再调用, 已进入的中间件逆序处理响应
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["req outer", "req auth", "res auth", "res outer"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pass_through() -> ProtResult<()> {
+        let log = Log::default();
+        let addr = run_server(log.clone(), None).await?;
+        let text = request(
+            addr,
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nAuthorization: Basic dGVzdA==\r\n\r\n",
+        )
+        .await?;
+        assert!(text.starts_with("HTTP/1.1 200"));
+        assert!(text.ends_with("ok"));
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "req outer",
+                "req auth",
+                "req inner",
+                "res inner",
+                "res auth",
+                "res outer"
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_overload_skips_middlewares() -> ProtResult<()> {
+        let log = Log::default();
+        // 高水位为0, 所有请求均直接回复503
+        let addr = run_server(log.clone(), Some(OverloadLayer::new(0, 0))).await?;
+        let text = request(addr, b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await?;
+        assert!(text.starts_with("HTTP/1.1 503"));
+        // 未进入任何中间件, 也不处理响应
+        assert!(log.lock().unwrap().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/13 17:20:41
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use algorithm::buf::{Binary, BinaryMut, Bt};
+    use webparse::{HeaderMap, HeaderValue};
+    use wmhttp::{Body, MultipartBuilder, ProtResult};
+
+    fn headers(content_type: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", HeaderValue::from_static(content_type));
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_multipart_two_parts() -> ProtResult<()> {
+        let (sender, stream) = Body::channel(2);
+        tokio::spawn(async move {
+            let _ = sender.send((false, Binary::from_static(b"{\"a\":"))).await;
+            let _ = sender.send((true, Binary::from_static(b"1}"))).await;
+        });
+        let builder = MultipartBuilder::with_boundary("wmhttp-boundary".to_string())
+            .part(headers("text/plain"), Body::new_text("hello".to_string()))
+            .part(headers("application/json"), stream);
+        assert_eq!(
+            builder.content_type(),
+            "multipart/mixed; boundary=wmhttp-boundary"
+        );
+        let mut body = builder.build();
+        let mut buffer = BinaryMut::new();
+        body.read_all(&mut buffer).await;
+        let text = String::from_utf8_lossy(buffer.chunk()).to_string();
+
+        // 解析回各个部分
+        assert!(text.ends_with("--wmhttp-boundary--\r\n"));
+        let parts = text
+            .trim_end_matches("--wmhttp-boundary--\r\n")
+            .split("--wmhttp-boundary\r\n")
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let (head, body) = p.split_once("\r\n\r\n").unwrap();
+                (
+                    head.to_ascii_lowercase(),
+                    body.trim_end_matches("\r\n").to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].0, "content-type: text/plain");
+        assert_eq!(parts[0].1, "hello");
+        assert_eq!(parts[1].0, "content-type: application/json");
+        assert_eq!(parts[1].1, "{\"a\":1}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_without_runtime() {
+        // 构建时不启动后台任务, 读取时才写入
+        let _body = MultipartBuilder::new()
+            .part(headers("text/plain"), Body::new_text("hello".to_string()))
+            .build();
+    }
+
+    #[tokio::test]
+    async fn test_part_error() -> ProtResult<()> {
+        let items: Vec<Result<Vec<u8>, io::Error>> = vec![
+            Ok(b"partial".to_vec()),
+            Err(io::Error::new(io::ErrorKind::Other, "part failed")),
+        ];
+        let failed = Body::from_stream(tokio_stream::iter(items));
+        let mut body = MultipartBuilder::with_boundary("wmhttp-boundary".to_string())
+            .part(headers("text/plain"), failed)
+            .part(headers("text/plain"), Body::new_text("never".to_string()))
+            .build();
+
+        // 部分的错误通过包体返回, 不会当作正常结束
+        let mut buffer = BinaryMut::new();
+        assert!(body.read_all(&mut buffer).await.is_none());
+        let text = String::from_utf8_lossy(buffer.chunk()).to_string();
+        assert!(!text.contains("never"));
+        assert!(!text.ends_with("--wmhttp-boundary--\r\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/08 14:20:37
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::empty())
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    #[tokio::test]
+    async fn test_options_asterisk() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"OPTIONS * HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        let text = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+        assert!(text.starts_with("http/1.1 204"));
+        assert!(text.contains("allow: "));
+        assert!(text.contains("options"));
+        Ok(())
+    }
+
+    async fn request(addr: SocketAddr, data: &[u8]) -> ProtResult<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(data).await?;
+        let mut buf = vec![0u8; 1024];
+        let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+            .await
+            .expect("response not received")?;
+        Ok(String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase())
+    }
+
+    #[tokio::test]
+    async fn test_options_origin_form() -> ProtResult<()> {
+        // 指定资源的OPTIONS请求仍由operate处理
+        let addr = run_server().await?;
+        let text = request(addr, b"OPTIONS /index HTTP/1.1\r\nHost: localhost\r\n\r\n").await?;
+        assert!(text.starts_with("http/1.1 200"));
+        assert!(!text.contains("allow: "));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_asterisk_form_other_method() -> ProtResult<()> {
+        // 非OPTIONS请求不允许使用asterisk-form
+        let addr = run_server().await?;
+        let text = request(addr, b"GET * HTTP/1.1\r\nHost: localhost\r\n\r\n").await?;
+        assert!(text.starts_with("http/1.1 400"));
+        Ok(())
+    }
+}
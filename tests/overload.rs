@@ -0,0 +1,116 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/20 16:45:09
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::Semaphore,
+    };
+    use webparse::Response;
+
+    use wmhttp::{
+        self, Body, HttpTrait, OverloadLayer, ProtResult, RecvRequest, RecvResponse, Server,
+    };
+
+    use crate::common;
+
+    struct Operate {
+        release: Arc<Semaphore>,
+    }
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, _req: RecvRequest) -> ProtResult<RecvResponse> {
+            // 模拟耗时的处理, 直到被放行
+            let _ = self.release.acquire().await;
+            let response = Response::builder()
+                .body(Body::new_text("Hello World".to_string()))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server(
+        overload: OverloadLayer,
+        release: Arc<Semaphore>,
+    ) -> ProtResult<SocketAddr> {
+        common::serve(
+            move |stream, addr| {
+                Server::builder()
+                    .addr(addr)
+                    .overload_layer(Some(overload.clone()))
+                    .stream(stream)
+            },
+            move |server| {
+                server.set_callback_http(Box::new(Operate {
+                    release: release.clone(),
+                }))
+            },
+        )
+        .await
+    }
+
+    async fn send(addr: SocketAddr) -> ProtResult<TcpStream> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await?;
+        Ok(stream)
+    }
+
+    async fn read_head(stream: &mut TcpStream) -> ProtResult<String> {
+        let mut buf = vec![0u8; 1024];
+        let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+            .await
+            .expect("no response")?;
+        Ok(String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase())
+    }
+
+    #[tokio::test]
+    async fn test_overload_503() -> ProtResult<()> {
+        let overload = OverloadLayer::new(2, 1);
+        let release = Arc::new(Semaphore::new(0));
+        let addr = run_server(overload.clone(), release.clone()).await?;
+
+        let mut first = send(addr).await?;
+        let mut second = send(addr).await?;
+        while overload.in_flight() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // 达到高水位, 新请求不经处理直接回复503
+        let mut third = send(addr).await?;
+        let text = read_head(&mut third).await?;
+        assert!(text.starts_with("http/1.1 503"));
+        assert!(text.contains("retry-after: 1"));
+        assert!(overload.is_overload());
+
+        // 处理完成后降到低水位以下, 恢复处理
+        release.add_permits(10);
+        assert!(read_head(&mut first).await?.starts_with("http/1.1 200"));
+        assert!(read_head(&mut second).await?.starts_with("http/1.1 200"));
+        let mut fourth = send(addr).await?;
+        assert!(read_head(&mut fourth).await?.starts_with("http/1.1 200"));
+        assert!(!overload.is_overload());
+        assert_eq!(overload.in_flight(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/11 15:02:48
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use algorithm::buf::{Binary, BinaryMut, Bt};
+    use wmhttp::{proxy_body, Body, ProtResult};
+
+    #[tokio::test]
+    async fn test_proxy_large_body() -> ProtResult<()> {
+        let (up_sender, upstream) = Body::channel(4);
+        let (down_sender, mut downstream) = Body::channel(4);
+
+        let total = 4 * 1024 * 1024;
+        tokio::spawn(async move {
+            let chunk = vec![7u8; 16 * 1024];
+            let mut sent = 0;
+            while sent < total {
+                sent += chunk.len();
+                if up_sender
+                    .send((sent >= total, Binary::from(chunk.clone())))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        let proxy = tokio::spawn(proxy_body(upstream, down_sender));
+
+        // 下游缓慢读取, 上游不会无限缓存
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut result = BinaryMut::new();
+        downstream.read_all(&mut result).await;
+        assert_eq!(result.remaining(), total);
+        assert_eq!(proxy.await.unwrap()?, total as u64);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_proxy_backpressure() -> ProtResult<()> {
+        let (up_sender, upstream) = Body::channel(4);
+        let (down_sender, mut downstream) = Body::channel(4);
+
+        let chunks = 256;
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = sent.clone();
+        tokio::spawn(async move {
+            let chunk = vec![7u8; 16 * 1024];
+            for i in 0..chunks {
+                if up_sender
+                    .send((i + 1 == chunks, Binary::from(chunk.clone())))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        let proxy = tokio::spawn(proxy_body(upstream, down_sender));
+
+        // 下游未读取时上游停止发送, 已发送的数据不超过两端通道及转发中的容量
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let high_water = sent.load(Ordering::Relaxed);
+        assert!(high_water <= 32, "buffered {} chunks", high_water);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(sent.load(Ordering::Relaxed), high_water);
+
+        // 下游开始读取后全部数据正常转发
+        let mut result = BinaryMut::new();
+        downstream.read_all(&mut result).await;
+        assert_eq!(result.remaining(), chunks * 16 * 1024);
+        assert_eq!(proxy.await.unwrap()?, (chunks * 16 * 1024) as u64);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_proxy_downstream_closed() -> ProtResult<()> {
+        let (up_sender, upstream) = Body::channel(4);
+        let (down_sender, downstream) = Body::channel(1);
+        drop(downstream);
+
+        up_sender.send((false, Binary::from(vec![1u8; 1024]))).await?;
+        let err = proxy_body(upstream, down_sender).await.unwrap_err();
+        assert!(err.is_channel_closed());
+        Ok(())
+    }
+}
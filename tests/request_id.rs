@@ -0,0 +1,98 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 00:29:05
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use webparse::{Request, Response};
+
+    use wmhttp::{self, Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse, RequestId};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            // 返回路径及请求的标识
+            let id = req
+                .extensions()
+                .get::<RequestId>()
+                .map(|id| id.to_string())
+                .unwrap_or_default();
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(format!("{}:{}", req.url().path, id)))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    /// 同一连接上发送两个请求, 返回排序后的响应包体
+    async fn fetch(http2_only: bool) -> ProtResult<Vec<String>> {
+        let addr = run_server().await?;
+        let url = format!("http://{}", addr);
+        let client = Client::builder()
+            .http2_only(http2_only)
+            .url(&*url)?
+            .connect()
+            .await?;
+        let build = |path: &str| {
+            Request::builder()
+                .method("GET")
+                .url(&*format!("{}{}", url, path))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (mut recv, sender, _) = client.send2(build("/a")).await?;
+        sender.send(build("/b")).await?;
+
+        let mut result = vec![];
+        for _ in 0..2 {
+            let mut res = tokio::time::timeout(Duration::from_secs(5), recv.recv())
+                .await
+                .unwrap()
+                .unwrap()?;
+            let mut buffer = BinaryMut::new();
+            res.body_mut().read_all(&mut buffer).await;
+            result.push(String::from_utf8_lossy(buffer.chunk()).to_string());
+        }
+        result.sort();
+        Ok(result)
+    }
+
+    #[tokio::test]
+    async fn test_request_id_http1() -> ProtResult<()> {
+        // HTTP/1为连接内递增的序号
+        assert_eq!(fetch(false).await?, vec!["/a:1", "/b:2"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_id_http2() -> ProtResult<()> {
+        // HTTP/2为流的id
+        assert_eq!(fetch(true).await?, vec!["/a:1", "/b:3"]);
+        Ok(())
+    }
+}
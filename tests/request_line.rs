@@ -0,0 +1,86 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/11 09:40:15
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::empty())
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(
+            || Box::new(Operate),
+            |server| server.set_max_request_line_bytes(1024),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_request_line_too_long() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 请求行未结束时即超出限制
+        let line = format!("GET /{} HTTP/1.1", "a".repeat(4096));
+        stream.write_all(&line.as_bytes()[..2048]).await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        assert!(buf[..n].starts_with(b"HTTP/1.1 414"));
+
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET /short HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await?;
+        let n = stream.read(&mut buf).await?;
+        assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_obs_fold_rejected() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 以空白开头的续行为已废弃的折行
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Fold: a\r\n b\r\n\r\n")
+            .await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        assert!(buf[..n].starts_with(b"HTTP/1.1 400"));
+        Ok(())
+    }
+}
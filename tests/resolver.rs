@@ -0,0 +1,91 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/21 14:48:19
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use webparse::{Request, Response};
+
+    use wmhttp::{
+        self, Body, Client, HttpTrait, ProtError, ProtResult, RecvRequest, RecvResponse, Resolve,
+    };
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, _req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .body(Body::new_text("Hello World".to_string()))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    /// 将fake.test映射到本地的监听地址, 第一个地址不可连接
+    struct FakeResolver {
+        addr: SocketAddr,
+    }
+
+    #[async_trait]
+    impl Resolve for FakeResolver {
+        async fn resolve(&self, host: &str, _port: u16) -> ProtResult<Vec<SocketAddr>> {
+            if host != "fake.test" {
+                return Err(ProtError::Extension("unknown host"));
+            }
+            let refused = TcpListener::bind("127.0.0.1:0").await?.local_addr()?;
+            Ok(vec![refused, self.addr])
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    async fn fetch(addr: SocketAddr, happy_eyeballs: bool) -> ProtResult<String> {
+        let url = "http://fake.test/";
+        let client = Client::builder()
+            .resolver(Arc::new(FakeResolver { addr }))
+            .happy_eyeballs(happy_eyeballs)
+            .http2(false)
+            .url(url)?
+            .connect()
+            .await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(url)
+            .body(Body::empty())
+            .unwrap();
+        let mut res = client.send_now(req).await?;
+        let mut buffer = BinaryMut::new();
+        res.body_mut().read_all(&mut buffer).await;
+        Ok(String::from_utf8_lossy(buffer.chunk()).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_custom_resolver() -> ProtResult<()> {
+        let addr = run_server().await?;
+        // 按顺序尝试, 第一个地址失败后连接第二个
+        assert_eq!(fetch(addr, false).await?, "Hello World");
+        assert_eq!(fetch(addr, true).await?, "Hello World");
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 19:48:52
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::{Request, Response};
+
+    use wmhttp::{
+        self, Body, HttpTrait, Middleware, ProtResult, RecvRequest, RecvResponse,
+        SecurityHeadersMiddleware, TlsInfo,
+    };
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .header("X-Frame-Options", "DENY")
+                .body(Body::empty())
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(
+            || Box::new(Operate),
+            |server| {
+                server.middle(
+                    SecurityHeadersMiddleware::new()
+                        .content_security_policy(Some("default-src 'self'")),
+                );
+            },
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_security_headers_plaintext() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        let text = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+        assert!(text.starts_with("http/1.1 200"));
+        assert!(text.contains("x-content-type-options: nosniff"));
+        assert!(text.contains("referrer-policy: strict-origin-when-cross-origin"));
+        assert!(text.contains("content-security-policy: default-src 'self'"));
+        // 处理函数已设置的值保持不变
+        assert!(text.contains("x-frame-options: deny"));
+        assert!(!text.contains("sameorigin"));
+        // 明文连接不添加HSTS
+        assert!(!text.contains("strict-transport-security"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_security_headers_tls() -> ProtResult<()> {
+        let mut middle = SecurityHeadersMiddleware::new().referrer_policy(None);
+        let mut req = Request::builder()
+            .method("GET")
+            .url("/")
+            .body(Body::empty())
+            .unwrap();
+        req.extensions_mut().insert(TlsInfo::default());
+        assert!(middle.process_request(&mut req).await?.is_none());
+
+        let mut res = Response::builder().body(Body::empty()).unwrap();
+        middle.process_response(&mut res).await?;
+        assert_eq!(
+            res.headers().get_str_value(&"Strict-Transport-Security"),
+            Some("max-age=31536000; includeSubDomains".to_string())
+        );
+        assert_eq!(
+            res.headers().get_str_value(&"X-Frame-Options"),
+            Some("SAMEORIGIN".to_string())
+        );
+        assert!(res.headers().get_str_value(&"Referrer-Policy").is_none());
+        assert!(res.headers().get_str_value(&"Expect-CT").is_none());
+        Ok(())
+    }
+}
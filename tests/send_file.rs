@@ -0,0 +1,115 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 04:20:15
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    const FILE_SIZE: usize = 1024 * 1024;
+
+    struct Operate {
+        path: PathBuf,
+    }
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            // /file返回文件, 其它返回路径, 确认文件之后的响应顺序正确
+            let body = if req.path() == "/file" {
+                let file = tokio::fs::File::open(&self.path).await?;
+                Body::new_file(file, FILE_SIZE as u64)
+            } else {
+                Body::new_text(req.path().to_string())
+            };
+            let response = Response::builder()
+                .version(req.version().clone())
+                .header("Content-Length", body.size_hint().unwrap_or(0).to_string())
+                .body(body)
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    fn file_data() -> Vec<u8> {
+        (0..FILE_SIZE).map(|i| (i % 251) as u8).collect()
+    }
+
+    async fn run_server(send_file: bool) -> ProtResult<SocketAddr> {
+        let path = std::env::temp_dir().join(format!(
+            "wmhttp_send_file_{}_{}",
+            send_file,
+            std::process::id()
+        ));
+        tokio::fs::write(&path, file_data()).await?;
+        common::run_server(
+            move || Box::new(Operate { path: path.clone() }),
+            move |server| server.set_send_file(send_file),
+        )
+        .await
+    }
+
+    /// 流水线发送文件及普通请求, 读取两个完整的响应
+    async fn request(addr: SocketAddr) -> ProtResult<Vec<u8>> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET /file HTTP/1.1\r\nHost: localhost\r\n\r\nGET /next HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut data = vec![];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut data))
+            .await
+            .expect("response not received")?;
+        Ok(data)
+    }
+
+    fn check_response(data: &[u8]) {
+        let head_end = data.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let head = String::from_utf8_lossy(&data[..head_end]).to_lowercase();
+        assert!(head.starts_with("http/1.1 200"));
+        assert!(head.contains(&format!("content-length: {}", FILE_SIZE)));
+        let body_end = head_end + FILE_SIZE;
+        assert!(&data[head_end..body_end] == &file_data()[..]);
+
+        // 文件发送完毕后, 后续的响应紧随其后
+        let next = String::from_utf8_lossy(&data[body_end..]);
+        assert!(next.starts_with("HTTP/1.1 200"));
+        assert!(next.ends_with("\r\n\r\n/next"));
+    }
+
+    #[tokio::test]
+    async fn test_send_file() -> ProtResult<()> {
+        let addr = run_server(true).await?;
+        check_response(&request(addr).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_buffered_file() -> ProtResult<()> {
+        let addr = run_server(false).await?;
+        check_response(&request(addr).await?);
+        Ok(())
+    }
+}
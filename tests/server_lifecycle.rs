@@ -0,0 +1,116 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/12 15:33:10
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::mpsc::{unbounded_channel, UnboundedSender},
+    };
+    use webparse::Response;
+
+    use wmhttp::{
+        self, Body, CloseReason, HttpTrait, ProtResult, RecvRequest, RecvResponse, Server,
+    };
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::empty())
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    type Event = (&'static str, Option<CloseReason>);
+
+    async fn run_server(sender: UnboundedSender<Event>) -> ProtResult<SocketAddr> {
+        common::serve(
+            move |stream, addr| {
+                let (connect, disconnect) = (sender.clone(), sender.clone());
+                Server::builder()
+                    .addr(addr)
+                    .timeout(Duration::from_millis(300))
+                    .on_connect(move |_info| {
+                        let _ = connect.send(("connect", None));
+                    })
+                    .on_disconnect(move |_info, reason| {
+                        let _ = disconnect.send(("disconnect", Some(reason)));
+                    })
+                    .stream(stream)
+            },
+            |server| server.set_callback_http(Box::new(Operate)),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_connect_lifecycle() -> ProtResult<()> {
+        let (sender, mut receiver) = unbounded_channel();
+        let addr = run_server(sender).await?;
+
+        // 正常请求后客户端关闭连接
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await?;
+        let mut buf = vec![0u8; 1024];
+        let _ = stream.read(&mut buf).await?;
+        drop(stream);
+        assert_eq!(receiver.recv().await.unwrap(), ("connect", None));
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            ("disconnect", Some(CloseReason::Normal))
+        );
+
+        // 连接后不发送数据, 超时关闭
+        let _stream = TcpStream::connect(addr).await?;
+        assert_eq!(receiver.recv().await.unwrap(), ("connect", None));
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            ("disconnect", Some(CloseReason::Timeout))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_error() -> ProtResult<()> {
+        let (sender, mut receiver) = unbounded_channel();
+        let addr = run_server(sender).await?;
+
+        // 请求头未发送完即以RST断开连接, 读取时出错
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(b"GET / HTTP/1.1\r\n").await?;
+        assert_eq!(receiver.recv().await.unwrap(), ("connect", None));
+        stream.set_linger(Some(Duration::ZERO))?;
+        drop(stream);
+        let event = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("disconnect not notified");
+        assert_eq!(event.unwrap(), ("disconnect", Some(CloseReason::ReadError)));
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 10:05:37
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use algorithm::buf::Bt;
+    use async_trait::async_trait;
+    use tokio_stream::StreamExt;
+    use webparse::Request;
+
+    use wmhttp::{
+        self, Body, Client, HttpTrait, ProtResult, RecvRequest, RecvResponse, SseEvent, SseResponse,
+    };
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, _req: RecvRequest) -> ProtResult<RecvResponse> {
+            let (sender, response) = SseResponse::new()
+                .keep_alive(Duration::from_millis(50))
+                .build();
+            tokio::spawn(async move {
+                for i in 0..3 {
+                    let event = SseEvent::new(&format!("line{}\nnext", i))
+                        .event("tick")
+                        .id(&i.to_string());
+                    sender.send(&event).await?;
+                    // 事件之间的空闲期间发送保活注释
+                    tokio::time::sleep(Duration::from_millis(120)).await;
+                }
+                sender.close().await
+            });
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    #[tokio::test]
+    async fn test_sse_events() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let url = format!("http://{}/events", addr);
+        let client = Client::builder().url(&*url)?.connect().await?;
+        let req = Request::builder()
+            .method("GET")
+            .url(&*url)
+            .header("Accept-Encoding", "gzip")
+            .body(Body::empty())
+            .unwrap();
+        let mut res = client.send_now(req).await?;
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            res.headers().get_str_value(&"Content-Type").unwrap(),
+            "text/event-stream"
+        );
+        // 事件流不压缩, 也不设置长度
+        assert!(res.headers().get_str_value(&"Content-Encoding").is_none());
+        assert!(res.headers().get_str_value(&"Content-Length").is_none());
+
+        let mut text = String::new();
+        let body = res.body_mut();
+        // 第一个事件无需等待事件流结束即可读取
+        let first = tokio::time::timeout(Duration::from_secs(5), body.next())
+            .await
+            .expect("event not flushed")
+            .unwrap()?;
+        text += &String::from_utf8_lossy(first.chunk());
+        assert!(text.starts_with("event: tick\nid: 0\ndata: line0\ndata: next\n\n"));
+
+        while let Some(bin) = body.next().await {
+            text += &String::from_utf8_lossy(bin?.chunk());
+        }
+        let events = text
+            .split("\n\n")
+            .filter(|e| e.starts_with("event:"))
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2], "event: tick\nid: 2\ndata: line2\ndata: next");
+        assert!(text.contains(": keep-alive\n\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 18:58:27
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use algorithm::buf::{BinaryMut, Bt};
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpTrait, ProtResult, RecvRequest, RecvResponse};
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, mut req: RecvRequest) -> ProtResult<RecvResponse> {
+            let mut buffer = BinaryMut::new();
+            req.body_mut().read_all(&mut buffer).await;
+            // 返回包体大小及校验和, 确认数据完整且顺序正确
+            let sum = buffer.chunk().iter().fold(0u64, |s, v| s + *v as u64);
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(format!("{}:{}", buffer.remaining(), sum)))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_server(
+            || Box::new(Operate),
+            |server| {
+                server.set_stream_body(true);
+                server.set_read_buf_size(4096);
+                server.set_max_body_size(10 * 1024 * 1024);
+            },
+        )
+        .await
+    }
+
+    fn body_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn expect(data: &[u8]) -> String {
+        let sum = data.iter().fold(0u64, |s, v| s + *v as u64);
+        format!("{}:{}", data.len(), sum)
+    }
+
+    async fn read_response(stream: &mut TcpStream, expect: &str) -> ProtResult<()> {
+        let mut buf = vec![];
+        let mut cache = vec![0u8; 1024];
+        while !String::from_utf8_lossy(&buf).ends_with(expect) {
+            let n = stream.read(&mut cache).await?;
+            assert!(n > 0, "connection closed early");
+            buf.extend_from_slice(&cache[..n]);
+        }
+        assert!(buf.starts_with(b"HTTP/1.1 200"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_large_body() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        let data = body_data(1024 * 1024);
+        let header = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+            data.len()
+        );
+        stream.write_all(header.as_bytes()).await?;
+        for chunk in data.chunks(10_000) {
+            stream.write_all(chunk).await?;
+        }
+        read_response(&mut stream, &expect(&data)).await
+    }
+
+    #[tokio::test]
+    async fn test_stream_chunked_keep_alive() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        let data = body_data(100_000);
+        // 同一连接上连续发送两个chunked请求, 包体结束后继续解析下一个请求
+        for _ in 0..2 {
+            stream
+                .write_all(b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n")
+                .await?;
+            for chunk in data.chunks(30_000) {
+                stream
+                    .write_all(format!("{:x}\r\n", chunk.len()).as_bytes())
+                    .await?;
+                stream.write_all(chunk).await?;
+                stream.write_all(b"\r\n").await?;
+            }
+            stream.write_all(b"0\r\n\r\n").await?;
+            read_response(&mut stream, &expect(&data)).await?;
+        }
+        Ok(())
+    }
+}
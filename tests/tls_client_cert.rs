@@ -0,0 +1,178 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 04:26:38
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+    use async_trait::async_trait;
+    use rcgen::{
+        BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa,
+    };
+    use rustls::{
+        pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName},
+        ClientConfig, RootCertStore,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        sync::mpsc::{unbounded_channel, UnboundedReceiver},
+    };
+    use tokio_rustls::TlsConnector;
+    use webparse::Response;
+
+    use wmhttp::{self, Body, HttpTrait, ProtResult, RecvRequest, RecvResponse, Server, TlsInfo};
+
+    struct Operate;
+
+    #[async_trait]
+    impl HttpTrait for Operate {
+        async fn operate(&mut self, req: RecvRequest) -> ProtResult<RecvResponse> {
+            // 返回客户端证书的数量及首个证书的长度
+            let info = req.extensions().get::<TlsInfo>().cloned().unwrap();
+            let text = format!(
+                "{}:{}",
+                info.peer_certificates.len(),
+                info.peer_certificates.first().map(|c| c.len()).unwrap_or(0)
+            );
+            let response = Response::builder()
+                .version(req.version().clone())
+                .body(Body::new_text(text))
+                .unwrap();
+            Ok(response)
+        }
+    }
+
+    struct Certs {
+        ca: Certificate,
+        server: (CertificateDer<'static>, PrivateKeyDer<'static>),
+        client: (CertificateDer<'static>, PrivateKeyDer<'static>),
+    }
+
+    fn signed(
+        ca: &Certificate,
+        name: &str,
+        usage: ExtendedKeyUsagePurpose,
+    ) -> (CertificateDer<'static>, PrivateKeyDer<'static>) {
+        let mut params = CertificateParams::new(vec![name.to_string()]);
+        params.distinguished_name.push(DnType::CommonName, name);
+        params.extended_key_usages = vec![usage];
+        let cert = Certificate::from_params(params).unwrap();
+        let der = cert.serialize_der_with_signer(ca).unwrap();
+        let key = PrivatePkcs8KeyDer::from(cert.serialize_private_key_der());
+        (CertificateDer::from(der), PrivateKeyDer::Pkcs8(key))
+    }
+
+    fn build_certs() -> Certs {
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "wmhttp test ca");
+        let ca = Certificate::from_params(params).unwrap();
+        let server = signed(&ca, "localhost", ExtendedKeyUsagePurpose::ServerAuth);
+        let client = signed(&ca, "client", ExtendedKeyUsagePurpose::ClientAuth);
+        Certs { ca, server, client }
+    }
+
+    fn ca_roots(certs: &Certs) -> RootCertStore {
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(CertificateDer::from(certs.ca.serialize_der().unwrap()))
+            .unwrap();
+        roots
+    }
+
+    /// 要求客户端证书的服务端, 每个连接的握手结果通过通道返回
+    async fn run_server(certs: &Certs) -> ProtResult<(SocketAddr, UnboundedReceiver<bool>)> {
+        let server = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        let (sender, receiver) = unbounded_channel();
+        let roots = ca_roots(certs);
+        let (cert, key) = (certs.server.0.clone(), certs.server.1.clone_key());
+        tokio::spawn(async move {
+            loop {
+                if let Ok((stream, addr)) = server.accept().await {
+                    let builder = Server::builder()
+                        .addr(addr)
+                        .tls_cert(vec![cert.clone()], key.clone_key(), Some(roots.clone()))
+                        .unwrap();
+                    let sender = sender.clone();
+                    tokio::spawn(async move {
+                        match builder.stream_tls(stream).await {
+                            Ok(mut server) => {
+                                let _ = sender.send(true);
+                                server.set_callback_http(Box::new(Operate));
+                                let _ = server.incoming().await;
+                            }
+                            Err(_) => {
+                                let _ = sender.send(false);
+                            }
+                        }
+                    });
+                }
+            }
+        });
+        Ok((addr, receiver))
+    }
+
+    async fn request(addr: SocketAddr, certs: &Certs, client_cert: bool) -> ProtResult<String> {
+        let config = ClientConfig::builder().with_root_certificates(ca_roots(certs));
+        let config = if client_cert {
+            config
+                .with_client_auth_cert(vec![certs.client.0.clone()], certs.client.1.clone_key())
+                .unwrap()
+        } else {
+            config.with_no_client_auth()
+        };
+        let connector = TlsConnector::from(Arc::new(config));
+        let stream = TcpStream::connect(addr).await?;
+        let domain = ServerName::try_from("localhost").unwrap();
+        let mut stream = connector.connect(domain, stream).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut data = vec![];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut data))
+            .await
+            .expect("response not received")?;
+        Ok(String::from_utf8_lossy(&data).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_client_cert_accepted() -> ProtResult<()> {
+        let certs = build_certs();
+        let (addr, mut handshakes) = run_server(&certs).await?;
+        let text = request(addr, &certs, true).await?;
+        assert_eq!(handshakes.recv().await, Some(true));
+        assert!(text.starts_with("HTTP/1.1 200"));
+        // 处理函数读取到客户端提供的证书
+        assert!(text.ends_with(&format!("1:{}", certs.client.0.len())));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_cert_rejected() -> ProtResult<()> {
+        let certs = build_certs();
+        let (addr, mut handshakes) = run_server(&certs).await?;
+        // TLS1.3中客户端先完成握手, 服务端校验失败后发送alert, 读取时报错
+        let result = request(addr, &certs, false).await;
+        assert!(result.is_err());
+        let is_accepted = tokio::time::timeout(Duration::from_secs(5), handshakes.recv())
+            .await
+            .expect("handshake not finished");
+        assert_eq!(is_accepted, Some(false));
+        Ok(())
+    }
+}
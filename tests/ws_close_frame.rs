@@ -0,0 +1,85 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 04:41:03
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncWriteExt, DuplexStream};
+    use tokio_stream::StreamExt;
+    use webparse::ws::{CloseCode, OwnedMessage};
+    use wmhttp::{ws::ServerWsConnection, ProtResult};
+
+    const MASK: [u8; 4] = [0x12, 0x34, 0x56, 0x78];
+
+    /// 客户端发送的带掩码的帧
+    fn masked_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![first, 0x80 | payload.len() as u8];
+        data.extend_from_slice(&MASK);
+        data.extend(payload.iter().enumerate().map(|(i, b)| b ^ MASK[i % 4]));
+        data
+    }
+
+    /// 读取直到收到关闭消息或连接结束
+    async fn read_until_close(conn: &mut ServerWsConnection<DuplexStream>) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(msg) = conn.next().await {
+                if matches!(msg, Ok(OwnedMessage::Close(_)) | Err(_)) {
+                    break;
+                }
+            }
+        })
+        .await
+        .expect("connection not closed");
+    }
+
+    #[tokio::test]
+    async fn test_close_frame_received() -> ProtResult<()> {
+        let (server_io, mut raw) = tokio::io::duplex(65_536);
+        let mut server = ServerWsConnection::new(server_io);
+        assert!(server.close_frame().is_none());
+
+        // 1000(Normal)及关闭原因
+        let mut payload = 1000u16.to_be_bytes().to_vec();
+        payload.extend_from_slice(b"bye");
+        raw.write_all(&masked_frame(0x88, &payload)).await?;
+        read_until_close(&mut server).await;
+
+        let (code, reason) = server.close_frame().expect("close frame not recorded");
+        assert!(matches!(code, CloseCode::Normal));
+        assert_eq!(reason, "bye");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_close_frame_abnormal() -> ProtResult<()> {
+        let (server_io, mut raw) = tokio::io::duplex(65_536);
+        let mut server = ServerWsConnection::new(server_io);
+        raw.write_all(&masked_frame(0x81, b"hi")).await?;
+        let msg = tokio::time::timeout(Duration::from_secs(5), server.next())
+            .await
+            .expect("message not received");
+        assert!(matches!(msg, Some(Ok(OwnedMessage::Text(v))) if v == "hi"));
+        assert!(server.close_frame().is_none());
+
+        // 未收到Close帧直接断开, 记为1006(Abnormal)且原因为空
+        drop(raw);
+        read_until_close(&mut server).await;
+
+        let (code, reason) = server.close_frame().expect("close frame not recorded");
+        assert!(matches!(code, CloseCode::Abnormal));
+        assert!(reason.is_empty());
+        Ok(())
+    }
+}
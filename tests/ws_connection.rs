@@ -0,0 +1,100 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 02:55:30
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+    use tokio_stream::StreamExt;
+    use wmhttp::{
+        ws::{Message, WsConnection},
+        ProtResult,
+    };
+
+    const MASK: [u8; 4] = [0x12, 0x34, 0x56, 0x78];
+
+    /// 客户端发送的带掩码的帧
+    fn masked_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![first, 0x80 | payload.len() as u8];
+        data.extend_from_slice(&MASK);
+        data.extend(payload.iter().enumerate().map(|(i, b)| b ^ MASK[i % 4]));
+        data
+    }
+
+    async fn next(conn: &mut WsConnection<DuplexStream>) -> Message {
+        tokio::time::timeout(Duration::from_secs(5), conn.next())
+            .await
+            .expect("message not received")
+            .expect("connection closed")
+            .expect("invalid message")
+    }
+
+    #[tokio::test]
+    async fn test_send_and_receive() -> ProtResult<()> {
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let mut client = WsConnection::new(client_io, true);
+        let mut server = WsConnection::new(server_io, false);
+
+        client.send_text("hello".to_string()).await?;
+        assert!(matches!(next(&mut server).await, Message::Text(v) if v == "hello"));
+
+        server.send_binary(vec![1, 2, 3]).await?;
+        assert!(matches!(next(&mut client).await, Message::Binary(v) if v == [1, 2, 3]));
+
+        client.send(Message::Ping(b"ping".to_vec())).await?;
+        assert!(matches!(next(&mut server).await, Message::Ping(v) if v == b"ping"));
+
+        client.close(None).await?;
+        assert!(matches!(next(&mut server).await, Message::Close(_)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_masking() -> ProtResult<()> {
+        // 客户端的帧带掩码
+        let (client_io, mut raw) = tokio::io::duplex(65_536);
+        let mut client = WsConnection::new(client_io, true);
+        client.send_text("abc".to_string()).await?;
+        let mut head = [0u8; 2];
+        raw.read_exact(&mut head).await?;
+        assert_eq!(head, [0x81, 0x80 | 3]);
+
+        // 服务端的帧不带掩码
+        let (server_io, mut raw) = tokio::io::duplex(65_536);
+        let mut server = WsConnection::new(server_io, false);
+        server.send_text("abc".to_string()).await?;
+        let mut frame = [0u8; 5];
+        raw.read_exact(&mut frame).await?;
+        assert_eq!(&frame, b"\x81\x03abc");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fragmented_message() -> ProtResult<()> {
+        let (server_io, mut raw) = tokio::io::duplex(65_536);
+        let mut server = WsConnection::new(server_io, false);
+
+        // 分片的文本消息中间夹着ping, ping先返回, 之后返回合并后的消息
+        let mut data = masked_frame(0x01, b"hel");
+        data.extend(masked_frame(0x89, b"p"));
+        data.extend(masked_frame(0x00, b"lo "));
+        data.extend(masked_frame(0x80, b"world"));
+        raw.write_all(&data).await?;
+
+        assert!(matches!(next(&mut server).await, Message::Ping(v) if v == b"p"));
+        assert!(matches!(next(&mut server).await, Message::Text(v) if v == "hello world"));
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/22 22:31:50
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::mpsc::Sender,
+    };
+    use tokio_stream::StreamExt;
+    use webparse::ws::OwnedMessage;
+    use wmhttp::{
+        ws::{
+            DeflateConfig, DeflateContext, Message, WsConnection, WsHandshake, WsOption, WsTrait,
+        },
+        ProtResult, Server,
+    };
+
+    use crate::common;
+
+    const MASK: [u8; 4] = [0x12, 0x34, 0x56, 0x78];
+
+    fn message() -> Vec<u8> {
+        "hello websocket deflate, ".repeat(40).into_bytes()
+    }
+
+    #[test]
+    fn test_negotiate_default() {
+        // 默认服务端不保留上下文
+        let params = DeflateConfig::new()
+            .negotiate("permessage-deflate; client_max_window_bits")
+            .unwrap();
+        assert!(params.server_no_context_takeover);
+        assert!(!params.client_no_context_takeover);
+        assert_eq!(
+            params.to_header(),
+            "permessage-deflate; server_no_context_takeover; client_max_window_bits=15"
+        );
+    }
+
+    #[test]
+    fn test_negotiate_context_takeover() {
+        let config = DeflateConfig::new()
+            .server_no_context_takeover(false)
+            .client_max_window_bits(10);
+        let params = config.negotiate("permessage-deflate").unwrap();
+        assert_eq!(params.to_header(), "permessage-deflate");
+
+        // 客户端要求不保留上下文时仍需遵守
+        let params = config
+            .negotiate("permessage-deflate; server_no_context_takeover; client_max_window_bits=12")
+            .unwrap();
+        assert_eq!(
+            params.to_header(),
+            "permessage-deflate; server_no_context_takeover; client_max_window_bits=10"
+        );
+    }
+
+    #[test]
+    fn test_negotiate_fallback() {
+        let config = DeflateConfig::new();
+        // 无法满足更小的服务端窗口, 选择下一个方案
+        let params = config
+            .negotiate("permessage-deflate; server_max_window_bits=10, permessage-deflate")
+            .unwrap();
+        assert_eq!(params.server_max_window_bits, None);
+        assert!(config.negotiate("permessage-deflate; unknown_param").is_none());
+        assert!(config
+            .negotiate("permessage-deflate; client_no_context_takeover; client_no_context_takeover")
+            .is_none());
+        assert!(config.negotiate("x-webkit-deflate-frame").is_none());
+    }
+
+    #[test]
+    fn test_no_context_takeover_memory() -> ProtResult<()> {
+        let params = DeflateConfig::new()
+            .negotiate("permessage-deflate")
+            .unwrap();
+        let mut server = DeflateContext::new(params.clone(), true);
+        let mut client = DeflateContext::new(params, false);
+
+        // 不保留上下文, 每条消息结束后即释放压缩上下文
+        let data = message();
+        let first = server.compress_message(&data)?;
+        assert!(!server.has_compress_context());
+        let second = server.compress_message(&data)?;
+        assert!(!server.has_compress_context());
+        assert_eq!(first, second);
+
+        // 客户端解压时同样不保留上下文
+        assert_eq!(
+            client.decompress_message(&first, usize::MAX)?.unwrap(),
+            data
+        );
+        assert!(!client.has_decompress_context());
+        assert_eq!(
+            client.decompress_message(&second, usize::MAX)?.unwrap(),
+            data
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_context_takeover() -> ProtResult<()> {
+        let params = DeflateConfig::new()
+            .server_no_context_takeover(false)
+            .negotiate("permessage-deflate")
+            .unwrap();
+        let mut server = DeflateContext::new(params.clone(), true);
+        let mut client = DeflateContext::new(params, false);
+
+        // 保留上下文, 重复的消息可引用之前的数据, 压缩后更小
+        let data = message();
+        let first = server.compress_message(&data)?;
+        let second = server.compress_message(&data)?;
+        assert!(server.has_compress_context());
+        assert!(second.len() < first.len());
+
+        assert_eq!(
+            client.decompress_message(&first, usize::MAX)?.unwrap(),
+            data
+        );
+        assert_eq!(
+            client.decompress_message(&second, usize::MAX)?.unwrap(),
+            data
+        );
+        assert!(client.has_decompress_context());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompress_limit() -> ProtResult<()> {
+        let params = DeflateConfig::new().negotiate("permessage-deflate").unwrap();
+        let mut server = DeflateContext::new(params.clone(), true);
+        let mut client = DeflateContext::new(params, false);
+
+        // 高压缩比的数据, 解压到超出限制即停止
+        let data = vec![0u8; 10 * 1024 * 1024];
+        let compressed = server.compress_message(&data)?;
+        assert!(compressed.len() < 64 * 1024);
+        assert!(client.decompress_message(&compressed, 1024)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_accept_response() {
+        let config = DeflateConfig::new();
+        assert_eq!(
+            config.to_offer(),
+            "permessage-deflate; client_max_window_bits"
+        );
+        let params = config
+            .accept("permessage-deflate; server_no_context_takeover; server_max_window_bits=10")
+            .unwrap();
+        assert!(params.server_no_context_takeover);
+        assert_eq!(params.server_max_window_bits, Some(10));
+        // 客户端无法以更小的窗口压缩
+        assert!(config
+            .accept("permessage-deflate; client_max_window_bits=10")
+            .is_none());
+        assert!(config.accept("x-webkit-deflate-frame").is_none());
+    }
+
+    /// 原样返回收到的消息
+    struct Operate {
+        sender: Option<Sender<OwnedMessage>>,
+    }
+
+    #[async_trait]
+    impl WsTrait for Operate {
+        async fn on_open(&mut self, shake: WsHandshake) -> ProtResult<Option<WsOption>> {
+            self.sender = Some(shake.sender);
+            Ok(None)
+        }
+
+        async fn on_message(&mut self, msg: OwnedMessage) -> ProtResult<()> {
+            if let Some(sender) = &self.sender {
+                let _ = sender.send(msg).await;
+            }
+            Ok(())
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::serve(
+            |stream, _| {
+                Server::builder()
+                    .enable_per_message_deflate(true)
+                    .stream(stream)
+            },
+            |server| server.set_callback_ws(Box::new(Operate { sender: None })),
+        )
+        .await
+    }
+
+    /// 客户端发送的带掩码的帧, 负载不超过125
+    fn masked_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![first, 0x80 | payload.len() as u8];
+        data.extend_from_slice(&MASK);
+        data.extend(payload.iter().enumerate().map(|(i, b)| b ^ MASK[i % 4]));
+        data
+    }
+
+    /// 读取一个未加掩码的帧, 返回(首字节, 负载)
+    async fn read_frame<T: AsyncRead + Unpin>(stream: &mut T) -> ProtResult<(u8, Vec<u8>)> {
+        let mut head = [0u8; 2];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_exact(&mut head))
+            .await
+            .expect("frame not received")?;
+        let mut payload = vec![0u8; (head[1] & 0x7F) as usize];
+        stream.read_exact(&mut payload).await?;
+        Ok((head[0], payload))
+    }
+
+    async fn handshake(stream: &mut TcpStream, extensions: &str) -> ProtResult<String> {
+        let req = format!(
+            "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n{}\r\n",
+            extensions
+        );
+        stream.write_all(req.as_bytes()).await?;
+        let mut buf = vec![];
+        while !buf.ends_with(b"\r\n\r\n") {
+            let mut byte = [0u8; 1];
+            stream.read_exact(&mut byte).await?;
+            buf.push(byte[0]);
+        }
+        let head = String::from_utf8_lossy(&buf).to_string();
+        assert!(head.starts_with("HTTP/1.1 101"));
+        Ok(head)
+    }
+
+    #[tokio::test]
+    async fn test_server_negotiate() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        let head = handshake(
+            &mut stream,
+            "Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits\r\n",
+        )
+        .await?;
+        let value = "permessage-deflate; server_no_context_takeover; client_max_window_bits=15";
+        assert!(head.contains(&format!("Sec-WebSocket-Extensions: {}\r\n", value)));
+
+        let params = DeflateConfig::new().accept(value).unwrap();
+        let mut client = DeflateContext::new(params, false);
+        let data = b"hello hello hello deflate";
+        let compressed = client.compress_message(data)?;
+        // RSV1 | Text
+        stream.write_all(&masked_frame(0xC1, &compressed)).await?;
+
+        let (first, payload) = read_frame(&mut stream).await?;
+        assert_eq!(first, 0xC1);
+        assert_eq!(
+            client.decompress_message(&payload, usize::MAX)?.unwrap(),
+            data
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_server_without_offer() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 客户端未请求时不启用压缩
+        let head = handshake(&mut stream, "").await?;
+        assert!(!head.contains("Sec-WebSocket-Extensions"));
+        stream.write_all(&masked_frame(0x81, b"plain")).await?;
+        assert_eq!(read_frame(&mut stream).await?, (0x81, b"plain".to_vec()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connection_round_trip() -> ProtResult<()> {
+        let params = DeflateConfig::new()
+            .negotiate("permessage-deflate")
+            .unwrap();
+        let (client_io, server_io) = tokio::io::duplex(65_536);
+        let mut client = WsConnection::new(client_io, true);
+        let mut server = WsConnection::new(server_io, false);
+        client.set_deflate(Some(DeflateContext::new(params.clone(), false)));
+        server.set_deflate(Some(DeflateContext::new(params, true)));
+
+        let text = String::from_utf8(message()).unwrap();
+        for _ in 0..2 {
+            client.send_text(text.clone()).await?;
+            let msg = tokio::time::timeout(Duration::from_secs(5), server.next()).await;
+            assert!(matches!(msg, Ok(Some(Ok(Message::Text(v)))) if v == text));
+        }
+        server.send_binary(message()).await?;
+        let msg = tokio::time::timeout(Duration::from_secs(5), client.next()).await;
+        assert!(matches!(msg, Ok(Some(Ok(Message::Binary(v)))) if v == message()));
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 03:55:08
+
+#![deny(rust_2018_idioms)]
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+    use tokio_stream::StreamExt;
+    use wmhttp::{
+        ws::{Message, WsConnection},
+        ProtResult,
+    };
+
+    const MASK: [u8; 4] = [0x12, 0x34, 0x56, 0x78];
+
+    /// 客户端发送的带掩码的帧
+    fn masked_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![first, 0x80 | payload.len() as u8];
+        data.extend_from_slice(&MASK);
+        data.extend(payload.iter().enumerate().map(|(i, b)| b ^ MASK[i % 4]));
+        data
+    }
+
+    async fn next(conn: &mut WsConnection<DuplexStream>) -> Option<ProtResult<Message>> {
+        tokio::time::timeout(Duration::from_secs(5), conn.next())
+            .await
+            .expect("message not received")
+    }
+
+    /// 读取服务端发出的1009关闭帧
+    async fn read_close_too_big(raw: &mut DuplexStream) -> ProtResult<()> {
+        let mut frame = [0u8; 19];
+        tokio::time::timeout(Duration::from_secs(5), raw.read_exact(&mut frame))
+            .await
+            .expect("close not received")?;
+        assert_eq!(&frame[..4], [0x88, 17, 0x03, 0xF1]);
+        assert_eq!(&frame[4..], b"message too big");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fragments_within_limit() -> ProtResult<()> {
+        let (server_io, mut raw) = tokio::io::duplex(65_536);
+        let mut server = WsConnection::new(server_io, false);
+        server.set_max_message_size(10);
+
+        raw.write_all(&masked_frame(0x01, b"hello")).await?;
+        raw.write_all(&masked_frame(0x89, b"ping")).await?;
+        raw.write_all(&masked_frame(0x80, b"world")).await?;
+        // 控制帧不计入消息大小
+        assert!(matches!(
+            next(&mut server).await,
+            Some(Ok(Message::Ping(_)))
+        ));
+        assert!(matches!(next(&mut server).await, Some(Ok(Message::Text(v))) if v == "helloworld"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fragments_too_big() -> ProtResult<()> {
+        let (server_io, mut raw) = tokio::io::duplex(65_536);
+        let mut server = WsConnection::new(server_io, false);
+        server.set_max_message_size(10);
+
+        // 分片到达时即检查累计大小, 不等待最后一片
+        raw.write_all(&masked_frame(0x01, b"hello")).await?;
+        raw.write_all(&masked_frame(0x00, b"world!")).await?;
+        let err = next(&mut server).await.unwrap().unwrap_err();
+        assert!(format!("{:?}", err).contains("too big"));
+        read_close_too_big(&mut raw).await?;
+        assert!(next(&mut server).await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frame_header_too_big() -> ProtResult<()> {
+        let (server_io, mut raw) = tokio::io::duplex(65_536);
+        let mut server = WsConnection::new(server_io, false);
+        server.set_max_message_size(100);
+
+        // 帧头声明1000字节, 负载未发送即关闭
+        raw.write_all(&[0x82, 0x80 | 126, 0x03, 0xE8]).await?;
+        let err = next(&mut server).await.unwrap().unwrap_err();
+        assert!(format!("{:?}", err).contains("too big"));
+        read_close_too_big(&mut raw).await?;
+        Ok(())
+    }
+}
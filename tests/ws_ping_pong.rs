@@ -0,0 +1,142 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 03:34:07
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::mpsc::{channel, Sender},
+    };
+    use tokio_stream::StreamExt;
+    use webparse::ws::OwnedMessage;
+
+    use wmhttp::{
+        self,
+        ws::{Message, WsConnection, WsHandshake, WsOption, WsTrait},
+        ProtResult,
+    };
+
+    use crate::common;
+
+    const MASK: [u8; 4] = [0x12, 0x34, 0x56, 0x78];
+
+    /// 记录收到的ping, 不自行回复
+    struct Operate {
+        pings: Sender<Vec<u8>>,
+    }
+
+    #[async_trait]
+    impl WsTrait for Operate {
+        async fn on_open(&mut self, _shake: WsHandshake) -> ProtResult<Option<WsOption>> {
+            Ok(None)
+        }
+
+        async fn on_ping(&mut self, val: Vec<u8>) -> ProtResult<Option<OwnedMessage>> {
+            let _ = self.pings.send(val).await;
+            Ok(None)
+        }
+
+        async fn on_message(&mut self, _msg: OwnedMessage) -> ProtResult<()> {
+            Ok(())
+        }
+    }
+
+    async fn run_server(pings: Sender<Vec<u8>>) -> ProtResult<SocketAddr> {
+        common::run_ws_server(
+            move || {
+                Box::new(Operate {
+                    pings: pings.clone(),
+                })
+            },
+            |_| {},
+        )
+        .await
+    }
+
+    /// 客户端发送的带掩码的帧, 负载不超过125
+    fn masked_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![first, 0x80 | payload.len() as u8];
+        data.extend_from_slice(&MASK);
+        data.extend(payload.iter().enumerate().map(|(i, b)| b ^ MASK[i % 4]));
+        data
+    }
+
+    /// 读取一个服务端发送的未加掩码的帧, 返回(首字节, 负载)
+    async fn read_frame<T: AsyncRead + Unpin>(stream: &mut T) -> ProtResult<(u8, Vec<u8>)> {
+        let mut head = [0u8; 2];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_exact(&mut head))
+            .await
+            .expect("frame not received")?;
+        assert_eq!(head[1] & 0x80, 0);
+        let mut payload = vec![0u8; (head[1] & 0x7F) as usize];
+        stream.read_exact(&mut payload).await?;
+        Ok((head[0], payload))
+    }
+
+    #[tokio::test]
+    async fn test_auto_pong() -> ProtResult<()> {
+        let (sender, mut pings) = channel(10);
+        let addr = run_server(sender).await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .await?;
+        let mut buf = vec![];
+        while !buf.ends_with(b"\r\n\r\n") {
+            let mut byte = [0u8; 1];
+            stream.read_exact(&mut byte).await?;
+            buf.push(byte[0]);
+        }
+        assert!(buf.starts_with(b"HTTP/1.1 101"));
+
+        // 控制帧负载最大的125字节原样返回
+        let payload: Vec<u8> = (0..125u8).collect();
+        stream.write_all(&masked_frame(0x89, &payload)).await?;
+        assert_eq!(read_frame(&mut stream).await?, (0x8A, payload.clone()));
+
+        // 应用仍能收到ping
+        let observed = tokio::time::timeout(Duration::from_secs(5), pings.recv())
+            .await
+            .expect("ping not observed");
+        assert_eq!(observed, Some(payload));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_auto_pong_disabled() -> ProtResult<()> {
+        let (server_io, mut raw) = tokio::io::duplex(65_536);
+        let mut server = WsConnection::new(server_io, false);
+        server.set_auto_pong(false);
+
+        raw.write_all(&masked_frame(0x89, b"ping")).await?;
+        let msg = tokio::time::timeout(Duration::from_secs(5), server.next())
+            .await
+            .expect("ping not received");
+        assert!(matches!(msg, Some(Ok(Message::Ping(v))) if v == b"ping"));
+
+        // 不自动回复, 之后收到的首帧为应用发送的消息
+        server.send_text("done".to_string()).await?;
+        assert_eq!(read_frame(&mut raw).await?, (0x81, b"done".to_vec()));
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/08 16:45:03
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::sync::mpsc::{channel, Sender};
+    use webparse::ws::{CloseCode, CloseData, OwnedMessage};
+
+    use wmhttp::{
+        self,
+        ws::{WsHandshake, WsOption, WsTrait},
+        Client, ProtResult, Rate,
+    };
+
+    use crate::common;
+
+    struct ServerOperate;
+
+    #[async_trait]
+    impl WsTrait for ServerOperate {
+        async fn on_open(&mut self, _shake: WsHandshake) -> ProtResult<Option<WsOption>> {
+            let mut option = WsOption::new();
+            option.set_msg_rate(Rate::new(5, Duration::from_secs(1)));
+            Ok(Some(option))
+        }
+
+        async fn on_message(&mut self, _msg: OwnedMessage) -> ProtResult<()> {
+            Ok(())
+        }
+    }
+
+    struct ClientOperate {
+        closed: Sender<Option<CloseData>>,
+    }
+
+    #[async_trait]
+    impl WsTrait for ClientOperate {
+        async fn on_open(&mut self, shake: WsHandshake) -> ProtResult<Option<WsOption>> {
+            let sender = shake.sender;
+            tokio::spawn(async move {
+                for i in 0..20 {
+                    if sender.send(OwnedMessage::Text(format!("flood {}", i))).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            Ok(None)
+        }
+
+        async fn on_message(&mut self, _msg: OwnedMessage) -> ProtResult<()> {
+            Ok(())
+        }
+
+        async fn on_close(&mut self, reason: &Option<CloseData>) {
+            let _ = self.closed.send(reason.clone()).await;
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_ws_server(|| Box::new(ServerOperate), |_| {}).await
+    }
+
+    #[tokio::test]
+    async fn test_ws_msg_rate_limit() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let (sender, mut receiver) = channel(1);
+        let mut client = Client::builder()
+            .url(&*format!("ws://{}", addr))?
+            .connect()
+            .await?;
+        client.set_callback_ws(Box::new(ClientOperate { closed: sender }));
+        tokio::spawn(async move {
+            let _ = client.wait_ws_operate().await;
+        });
+
+        let reason = tokio::time::timeout(Duration::from_secs(2), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(reason.status_code, CloseCode::Policy);
+        Ok(())
+    }
+}
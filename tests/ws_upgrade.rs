@@ -0,0 +1,122 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/23 02:07:45
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::mpsc::Sender,
+    };
+    use webparse::ws::OwnedMessage;
+
+    use wmhttp::{
+        self,
+        ws::{WsHandshake, WsOption, WsTrait},
+        ProtResult,
+    };
+
+    use crate::common;
+
+    /// 原样返回收到的消息
+    struct Operate {
+        sender: Option<Sender<OwnedMessage>>,
+    }
+
+    #[async_trait]
+    impl WsTrait for Operate {
+        async fn on_open(&mut self, shake: WsHandshake) -> ProtResult<Option<WsOption>> {
+            self.sender = Some(shake.sender);
+            Ok(None)
+        }
+
+        async fn on_message(&mut self, msg: OwnedMessage) -> ProtResult<()> {
+            if let Some(sender) = &self.sender {
+                let _ = sender.send(msg).await;
+            }
+            Ok(())
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_ws_server(|| Box::new(Operate { sender: None }), |_| {}).await
+    }
+
+    async fn read_until(
+        stream: &mut TcpStream,
+        buf: &mut Vec<u8>,
+        expect: &[u8],
+    ) -> ProtResult<()> {
+        let mut cache = vec![0u8; 1024];
+        while !buf.windows(expect.len()).any(|w| w == expect) {
+            let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut cache))
+                .await
+                .expect("data not received")?;
+            assert!(n > 0, "connection closed early");
+            buf.extend_from_slice(&cache[..n]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ws_upgrade_with_leftover() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        // 握手请求与首个消息帧一起发送, 消息帧须留给websocket连接处理
+        let mut data = b"GET /chat HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+            .to_vec();
+        // 掩码为0的文本帧"hello"
+        data.extend_from_slice(&[0x81, 0x85, 0, 0, 0, 0]);
+        data.extend_from_slice(b"hello");
+        stream.write_all(&data).await?;
+
+        let mut buf = vec![];
+        read_until(&mut stream, &mut buf, b"hello").await?;
+        let text = String::from_utf8_lossy(&buf).to_ascii_lowercase();
+        assert!(text.starts_with("http/1.1 101"));
+        assert!(text.contains("sec-websocket-accept: s3pplmbitxaq9kygzzhzrbk+xoo="));
+        // 客户端未提供子协议时不返回
+        assert!(!text.contains("sec-websocket-protocol"));
+
+        let pos = buf.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        assert_eq!(&buf[pos..], &[0x81, 0x05, b'h', b'e', b'l', b'l', b'o']);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ws_upgrade_protocol() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\
+                Sec-WebSocket-Protocol: superchat, chat\r\n\r\n",
+            )
+            .await?;
+        let mut buf = vec![];
+        read_until(&mut stream, &mut buf, b"\r\n\r\n").await?;
+        let text = String::from_utf8_lossy(&buf).to_ascii_lowercase();
+        assert!(text.starts_with("http/1.1 101"));
+        // 选择客户端提供的首个子协议
+        assert!(text.contains("sec-websocket-protocol: superchat\r\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright 2022 - 2023 Wenmeng See the COPYRIGHT
+// file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// Author: tickbh
+// -----
+// Created Date: 2024/03/13 14:05:52
+
+#![deny(rust_2018_idioms)]
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use async_trait::async_trait;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use webparse::ws::OwnedMessage;
+
+    use wmhttp::{
+        self,
+        ws::{WsHandshake, WsOption, WsTrait},
+        ProtResult,
+    };
+
+    use crate::common;
+
+    struct Operate;
+
+    #[async_trait]
+    impl WsTrait for Operate {
+        async fn on_open(&mut self, _shake: WsHandshake) -> ProtResult<Option<WsOption>> {
+            Ok(None)
+        }
+
+        async fn on_message(&mut self, _msg: OwnedMessage) -> ProtResult<()> {
+            Ok(())
+        }
+    }
+
+    async fn run_server() -> ProtResult<SocketAddr> {
+        common::run_ws_server(|| Box::new(Operate), |_| {}).await
+    }
+
+    #[tokio::test]
+    async fn test_ws_unsupported_version() -> ProtResult<()> {
+        let addr = run_server().await?;
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 8\r\n\r\n",
+            )
+            .await?;
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await?;
+        let text = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+        assert!(text.starts_with("http/1.1 426"));
+        assert!(text.contains("sec-websocket-version: 13"));
+        Ok(())
+    }
+}